@@ -0,0 +1,156 @@
+//! Accessibility lint.
+//!
+//! With `a11y_lint = true`, every post's markdown is checked for images
+//! without alt text, headings that skip a level (`#` then `###`), `#`
+//! headings besides the title and links without text, and on a
+//! multilingual or non-English site every post page for a `<html lang>`
+//! matching its language. Findings are recoverable
+//! `AccessibilityIssue` warnings at `file:line:column`; pages are written
+//! unchanged. With `require_alt_text`, alt text is left to that check.
+
+use std::path::Path;
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::config::Config;
+use crate::error::{BuildError, SourcePos};
+use crate::image_meta::ImageMeta;
+use crate::parser::markdown_options;
+
+/// Problems in `markdown`, the body of `source`, at positions in
+/// `markdown` (see `BuildError::below_lines`).
+pub fn lint_markdown(markdown: &str, source: &Path, config: &Config) -> Vec<BuildError> {
+    let issue = |offset: usize, message: String| BuildError::AccessibilityIssue {
+        path: source.to_path_buf(),
+        position: SourcePos::of_offset(markdown, offset),
+        message,
+    };
+
+    let mut issues = Vec::new();
+    // The page title is the h1
+    let mut level = 1;
+    let mut seen_title = false;
+    // Open image: start offset, URL and alt text so far
+    let mut image: Option<(usize, String, String)> = None;
+    // Open link: start offset and whether it has text
+    let mut link: Option<(usize, bool)> = None;
+    for (event, range) in Parser::new_ext(markdown, markdown_options(config)).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level: heading, .. }) => {
+                let heading = heading as usize;
+                if heading > level + 1 {
+                    issues.push(issue(range.start, format!("heading level jumps from h{level} to h{heading}")));
+                }
+                if heading == 1 && seen_title && !config.demote_headings {
+                    issues.push(issue(range.start, "second h1 below the title (use ## or demote_headings)".to_string()));
+                }
+                seen_title |= heading == 1;
+                level = heading;
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => image = Some((range.start, dest_url.to_string(), String::new())),
+            Event::End(TagEnd::Image) => {
+                if let Some((start, url, alt)) = image.take() {
+                    // `require_alt_text` reports these itself; a sidecar
+                    // can supply the alt text
+                    let sidecar_alt = || ImageMeta::load(&url, &config.content_dir).ok().flatten().is_some_and(|m| m.alt().is_some());
+                    if alt.trim().is_empty() && !config.require_alt_text && !sidecar_alt() {
+                        issues.push(issue(start, "image has no alt text".to_string()));
+                    } else if let Some((_, text)) = &mut link {
+                        *text = true;
+                    }
+                }
+            }
+            Event::Start(Tag::Link { .. }) => link = Some((range.start, false)),
+            Event::End(TagEnd::Link) => {
+                if let Some((start, false)) = link.take() {
+                    issues.push(issue(start, "link has no text".to_string()));
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, alt)) = &mut image {
+                    alt.push_str(&text);
+                } else if let Some((_, has_text)) = &mut link {
+                    *has_text |= !text.trim().is_empty();
+                }
+            }
+            _ => {}
+        }
+    }
+    issues
+}
+
+/// Language `<html lang>` should name on a tree's pages: the tree's
+/// language code, else `lang`, else the locale's language unless that is
+/// English. None for single-language English sites, which aren't checked.
+pub fn expected_lang(config: &Config, language: Option<&str>) -> Option<String> {
+    if let Some(code) = language {
+        return Some(code.to_string());
+    }
+    if let Some(lang) = &config.lang {
+        return Some(lang.clone());
+    }
+    let locale = format!("{:?}", config.locale);
+    let lang = locale.split('_').next().unwrap_or_default();
+    (lang != "en").then(|| lang.to_string())
+}
+
+/// A missing or wrong `lang` on the `<html>` tag of the page `html`
+/// written to `output`.
+pub fn lint_lang(html: &str, output: &Path, expected: &str) -> Option<BuildError> {
+    let start = html.find("<html")?;
+    let tag = &html[start..start + html[start..].find('>')?];
+    let lang = tag.split_once(" lang=\"").and_then(|(_, rest)| rest.split('"').next());
+    let message = match lang {
+        Some(lang) if lang.split('-').next() == expected.split('-').next() => return None,
+        Some(lang) => format!("<html lang=\"{lang}\"> but the page language is \"{expected}\""),
+        None => format!("<html> has no lang attribute (expected \"{expected}\")"),
+    };
+    Some(BuildError::AccessibilityIssue {
+        path: output.to_path_buf(),
+        position: SourcePos::of_offset(html, start),
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Locale;
+
+    use super::*;
+
+    fn lint(markdown: &str) -> Vec<(usize, String)> {
+        lint_markdown(markdown, Path::new("post.md"), &Config::new())
+            .into_iter()
+            .map(|issue| match issue.below_lines(2) {
+                BuildError::AccessibilityIssue { position, message, .. } => (position.line, message),
+                other => panic!("unexpected {other}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flags_alt_text_heading_jumps_and_empty_links() {
+        let markdown = "# Title\n\n![](a.png) ![A duck](b.png)\n\n### Skipped\n\n## Fine\n\n[](https://duck.dev) [![Logo](logo.png)](/) [`code`](x.md)\n";
+        assert_eq!(lint(markdown), [
+            (5, "image has no alt text".to_string()),
+            (7, "heading level jumps from h1 to h3".to_string()),
+            (11, "link has no text".to_string()),
+        ]);
+        assert!(lint("## Intro\n\n### Details\n\n# Again\n").is_empty());
+        assert_eq!(lint("# Title\n\n# Section\n"), [(5, "second h1 below the title (use ## or demote_headings)".to_string())]);
+    }
+
+    #[test]
+    fn checks_lang_on_non_english_sites() {
+        let config = Config::new();
+        assert_eq!(expected_lang(&config, None), None);
+        assert_eq!(expected_lang(&config, Some("de")).as_deref(), Some("de"));
+        assert_eq!(expected_lang(&config.locale(Locale::fr_FR), None).as_deref(), Some("fr"));
+
+        let page = Path::new("public/de/index.html");
+        assert!(lint_lang("<!DOCTYPE html>\n<html lang=\"de-AT\">", page, "de").is_none());
+        let issue = lint_lang("<!DOCTYPE html>\n<html lang=\"en\">", page, "de").unwrap();
+        assert_eq!(issue.to_string(), "Accessibility issue in public/de/index.html:2:1: <html lang=\"en\"> but the page language is \"de\"");
+        assert!(lint_lang("<html>", page, "de").unwrap().to_string().contains("no lang attribute"));
+    }
+}
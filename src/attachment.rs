@@ -0,0 +1,122 @@
+//! Downloads linked from posts.
+//!
+//! A link to a file in the content directory that isn't a post
+//! (`[Slides](talks/slides.pdf)`) is a download: the file is copied to
+//! `public/files/` under the same relative path and the link points there,
+//! marked up as a download with the file's size. Copies are recorded as
+//! outputs like every other file, so removed attachments are pruned.
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::BuildError;
+use crate::output::OutputSet;
+use crate::types::RelPath;
+
+/// Output directory of attachments, relative to the public root.
+pub const FILES_DIR: &str = "files";
+
+/// A file copied for download.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// Path relative to the output root (`files/talks/slides.pdf`).
+    pub src: RelPath,
+    /// Size in bytes.
+    pub length: u64,
+}
+
+/// Copy the file `path` (relative to `content_dir`, without query or
+/// fragment) into `public_dir/files/`; None if it isn't a regular file.
+pub fn attach(path: &str, content_dir: &Path, public_dir: &Path, outputs: &OutputSet) -> Result<Option<Attachment>, BuildError> {
+    let source = content_dir.join(path);
+    let Ok(metadata) = fs::metadata(&source) else {
+        return Ok(None);
+    };
+    if !metadata.is_file() {
+        return Ok(None);
+    }
+
+    // `a/../../x` may leave the content directory, and its copy `files/`
+    let src = RelPath::default().join(FILES_DIR).join(path);
+    if !in_content_dir(&source, content_dir) || !src.as_str().starts_with(&format!("{FILES_DIR}/")) {
+        return Ok(None);
+    }
+    let dest = public_dir.join(src.as_str());
+    if let Some(dir) = dest.parent() {
+        outputs.create_dir(dir)?;
+    }
+    outputs.copy(&source, &dest)?;
+    Ok(Some(Attachment { src, length: metadata.len() }))
+}
+
+/// Whether `file` is in `content_dir` once `..` and symlinks are
+/// resolved; false if it doesn't exist.
+pub fn in_content_dir(file: &Path, content_dir: &Path) -> bool {
+    match (fs::canonicalize(file), fs::canonicalize(content_dir)) {
+        (Ok(file), Ok(root)) => file.starts_with(root),
+        _ => false,
+    }
+}
+
+/// Size for people: `512 B`, `1.5 KB`, `12 MB`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size < 10.0 {
+        format!("{:.1} {}", size, UNITS[unit])
+    } else {
+        format!("{:.0} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_for_people() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536), "1.5 KB");
+        assert_eq!(human_size(12 * 1024 * 1024 + 1), "12 MB");
+        assert_eq!(human_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn copies_files_keeping_their_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let public = dir.path().join("public");
+        fs::create_dir(dir.path().join("talks")).unwrap();
+        fs::write(dir.path().join("talks/slides.pdf"), b"%PDF").unwrap();
+        let outputs = OutputSet::new();
+
+        let attachment = attach("talks/slides.pdf", dir.path(), &public, &outputs).unwrap().unwrap();
+        assert_eq!((attachment.src.as_str(), attachment.length), ("files/talks/slides.pdf", 4));
+        assert!(outputs.contains(&public.join("files/talks/slides.pdf")));
+        assert_eq!(attach("talks", dir.path(), &public, &outputs).unwrap(), None);
+        assert_eq!(attach("missing.zip", dir.path(), &public, &outputs).unwrap(), None);
+    }
+
+    #[test]
+    fn files_outside_the_content_directory_are_not_copied() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public) = (dir.path().join("site/content"), dir.path().join("site/public"));
+        fs::create_dir_all(content.join("a")).unwrap();
+        fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        fs::write(dir.path().join("site/notes.txt"), "notes").unwrap();
+        let outputs = OutputSet::new();
+
+        assert!(content.join("a/../../../secret.txt").is_file());
+        assert_eq!(attach("a/../../../secret.txt", &content, &public, &outputs).unwrap(), None);
+        assert_eq!(attach("a/../../notes.txt", &content, &public, &outputs).unwrap(), None);
+        assert!(!dir.path().join("site/secret.txt").exists());
+        assert!(outputs.paths().is_empty());
+    }
+}
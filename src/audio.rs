@@ -0,0 +1,113 @@
+//! Audio attachments for podcast-style posts.
+//!
+//! `audio: episodes/01.mp3` front matter names a file relative to the
+//! content directory (like images); it is copied to `public/audio/`,
+//! embedded as an `<audio>` player above the post body and announced as
+//! an enclosure in the feeds. External `https://` URLs are embedded as-is,
+//! with an unknown (zero) length.
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::BuildError;
+use crate::output::OutputSet;
+use crate::types::EscapeHtml;
+
+/// An audio file attached to a post.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Audio {
+    /// Path relative to the output root (`audio/01.mp3`), or an external URL.
+    pub src: String,
+    /// Size in bytes (0 if unknown).
+    pub length: u64,
+    /// MIME type from the file extension.
+    pub mime: &'static str,
+}
+
+impl Audio {
+    pub fn is_external(&self) -> bool {
+        self.src.starts_with("http://") || self.src.starts_with("https://")
+    }
+
+    /// `<audio>` player for a page whose links are relative to `relative_root`.
+    pub fn render_player(&self, relative_root: &str) -> String {
+        let href = if self.is_external() {
+            self.src.clone()
+        } else {
+            format!("{relative_root}{}", self.src)
+        };
+        let href = href.escape_html();
+        format!(
+            r#"<audio class="audio-player" controls preload="metadata" src="{href}"><a href="{href}">{href}</a></audio>"#
+        )
+    }
+}
+
+/// Copy the audio file `src` (as written in front matter) into
+/// `public_dir/audio/`.
+pub fn attach(src: &str, content_dir: &Path, public_dir: &Path, outputs: &OutputSet) -> Result<Audio, BuildError> {
+    let mime = mime_type(src);
+    if src.starts_with("http://") || src.starts_with("https://") {
+        return Ok(Audio { src: src.to_string(), length: 0, mime });
+    }
+
+    let path = content_dir.join(src);
+    let (Some(file_name), Ok(metadata)) = (path.file_name(), fs::metadata(&path)) else {
+        return Err(BuildError::MediaNotFound { path });
+    };
+    if !metadata.is_file() {
+        return Err(BuildError::MediaNotFound { path });
+    }
+
+    let dir = public_dir.join("audio");
+    outputs.create_dir(&dir)?;
+    outputs.copy(&path, &dir.join(file_name))?;
+    Ok(Audio {
+        src: format!("audio/{}", file_name.to_string_lossy()),
+        length: metadata.len(),
+        mime,
+    })
+}
+
+/// MIME type of an audio file by extension.
+pub fn mime_type(src: &str) -> &'static str {
+    let extension = Path::new(src)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "mp3" => "audio/mpeg",
+        "m4a" | "mp4" => "audio/mp4",
+        "aac" => "audio/aac",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_local_files_with_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = dir.path().join("content");
+        let public = dir.path().join("public");
+        fs::create_dir_all(content.join("episodes")).unwrap();
+        fs::write(content.join("episodes/01.MP3"), b"ID3data").unwrap();
+
+        let outputs = OutputSet::new();
+        let audio = attach("episodes/01.MP3", &content, &public, &outputs).unwrap();
+        assert_eq!(audio, Audio { src: "audio/01.MP3".to_string(), length: 7, mime: "audio/mpeg" });
+        assert!(public.join("audio/01.MP3").exists());
+        assert!(audio.render_player("../").contains(r#"src="../audio/01.MP3""#));
+
+        let missing = attach("nope.mp3", &content, &public, &outputs).unwrap_err();
+        assert_eq!(missing.category(), "media_not_found");
+        assert!(attach("https://cdn.dev/a.ogg", &content, &public, &outputs).unwrap().is_external());
+    }
+}
@@ -0,0 +1,144 @@
+//! Blogroll page and OPML export from `data/blogroll.toml`.
+//!
+//! ```toml
+//! [[blogs]]
+//! name = "Duck Notes"
+//! url = "https://duck.example"
+//! description = "Rubber duck debugging, daily"
+//! feed = "https://duck.example/atom.xml"
+//! ```
+//!
+//! Each tree gets a `links.html` listing the blogs (as `h-card`s) and a
+//! `links.opml` with their feeds for import into a feed reader.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::BuildError;
+use crate::types::{EscapeHtml, HtmlSafe};
+
+/// Data file name in the data directory.
+pub const BLOGROLL_FILE: &str = "blogroll.toml";
+
+/// Blogroll page written to each tree's output root.
+pub const LINKS_PAGE: &str = "links.html";
+
+/// OPML export next to the page.
+pub const OPML_FILE: &str = "links.opml";
+
+/// Contents of `blogroll.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Blogroll {
+    #[serde(default)]
+    pub blogs: Vec<Blog>,
+}
+
+/// One blogroll entry.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Blog {
+    pub name: String,
+    pub url: String,
+    pub description: Option<String>,
+    /// Feed URL, exported to OPML.
+    pub feed: Option<String>,
+}
+
+impl Blogroll {
+    /// Read `blogroll.toml` from `data_dir`; None if there is none.
+    pub fn load(data_dir: &Path) -> Result<Option<Self>, BuildError> {
+        let path = data_dir.join(BLOGROLL_FILE);
+        let invalid = |message: String| BuildError::DataInvalid { path: path.clone(), message };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(invalid(e.to_string())),
+        };
+        toml::from_str(&text).map(Some).map_err(|e| invalid(e.message().to_string()))
+    }
+
+    /// Blog list for the links page.
+    pub fn render_list(&self) -> String {
+        let mut html = String::from(r#"<ul class="blogroll">"#);
+        for blog in &self.blogs {
+            let feed = blog
+                .feed
+                .as_ref()
+                .map(|feed| format!(r#" <a class="feed-link" href="{}">feed</a>"#, feed.escape_html()))
+                .unwrap_or_default();
+            let description = blog
+                .description
+                .as_ref()
+                .map(|d| format!(r#"<p class="p-note">{}</p>"#, d.escape_html()))
+                .unwrap_or_default();
+            html.push_str(&format!(
+                r#"<li class="h-card"><a class="p-name u-url" href="{}">{}</a>{feed}{description}</li>"#,
+                blog.url.escape_html(),
+                blog.name.escape_html(),
+            ));
+        }
+        html.push_str("</ul>");
+        html.push_str(&format!(r#"<p class="opml-link"><a href="{OPML_FILE}" type="text/x-opml">OPML</a></p>"#));
+        html
+    }
+
+    /// OPML 2.0 document titled `title`.
+    pub fn render_opml(&self, title: &str) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+        xml.push_str(&format!("  <head><title>{}</title></head>\n  <body>\n", HtmlSafe::escape(title)));
+        for blog in &self.blogs {
+            let (name, url) = (HtmlSafe::escape(&blog.name), HtmlSafe::escape(&blog.url));
+            let description = blog
+                .description
+                .as_ref()
+                .map(|d| format!(" description=\"{}\"", HtmlSafe::escape(d)))
+                .unwrap_or_default();
+            match &blog.feed {
+                Some(feed) => xml.push_str(&format!(
+                    "    <outline type=\"rss\" text=\"{name}\" title=\"{name}\" xmlUrl=\"{}\" htmlUrl=\"{url}\"{description}/>\n",
+                    HtmlSafe::escape(feed)
+                )),
+                None => xml.push_str(&format!(
+                    "    <outline type=\"link\" text=\"{name}\" url=\"{url}\"{description}/>\n"
+                )),
+            }
+        }
+        xml.push_str("  </body>\n</opml>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_and_renders_blogroll() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Blogroll::load(dir.path()).unwrap(), None);
+
+        fs::write(
+            dir.path().join(BLOGROLL_FILE),
+            "[[blogs]]\nname = \"Duck & Co\"\nurl = \"https://duck.example\"\nfeed = \"https://duck.example/atom.xml\"\n\n\
+             [[blogs]]\nname = \"Goose\"\nurl = \"https://goose.example\"\ndescription = \"Honk\"\n",
+        )
+        .unwrap();
+        let blogroll = Blogroll::load(dir.path()).unwrap().unwrap();
+        assert_eq!(blogroll.blogs.len(), 2);
+
+        let html = blogroll.render_list();
+        assert!(html.contains(r#"<li class="h-card"><a class="p-name u-url" href="https://duck.example">Duck &amp; Co</a> <a class="feed-link""#));
+        assert!(html.contains(r#"<p class="p-note">Honk</p>"#));
+
+        let opml = blogroll.render_opml("Links");
+        assert!(opml.contains(r#"<outline type="rss" text="Duck &amp; Co" title="Duck &amp; Co" xmlUrl="https://duck.example/atom.xml" htmlUrl="https://duck.example"/>"#));
+        assert!(opml.contains(r#"<outline type="link" text="Goose" url="https://goose.example" description="Honk"/>"#));
+
+        fs::write(dir.path().join(BLOGROLL_FILE), "[[blogs]]\nname = \"No URL\"\n").unwrap();
+        assert_eq!(Blogroll::load(dir.path()).unwrap_err().category(), "data_invalid");
+    }
+}
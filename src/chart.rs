@@ -0,0 +1,207 @@
+//! Small bar and line charts drawn as inline SVG.
+//!
+//! ```text
+//! {{< chart "bar" values="Jan: 3, Feb: 5, Mar: 2" title="Posts per month" >}}
+//! {{< chart "line" data="stats.visits" x="month" y="count" >}}
+//! ```
+//!
+//! Values are written inline as `label: value` pairs, or taken from an
+//! array of tables in the data directory, with the label and value under
+//! the `x` and `y` keys (`label` and `value` by default). Charts are drawn
+//! in `currentColor`, so they follow the text color and dark mode; each
+//! bar or point has a tooltip with its value.
+
+use serde_json::Value;
+
+use crate::data::SiteData;
+use crate::shortcode::Shortcode;
+use crate::types::EscapeHtml;
+
+/// Size of the drawing (the `viewBox`); it scales with the page.
+const WIDTH: f64 = 600.0;
+const HEIGHT: f64 = 240.0;
+
+/// Space for the value labels (left), and the bar labels (bottom).
+const LEFT: f64 = 40.0;
+const RIGHT: f64 = 10.0;
+const TOP: f64 = 15.0;
+const BOTTOM: f64 = 30.0;
+
+/// How values are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartKind {
+    Bar,
+    Line,
+}
+
+impl ChartKind {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "bar" => Some(Self::Bar),
+            "line" => Some(Self::Line),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Bar => "bar",
+            Self::Line => "line",
+        }
+    }
+}
+
+/// A labelled value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    pub label: String,
+    pub value: f64,
+}
+
+/// HTML for a `{{< chart "bar" ... >}}` shortcode.
+pub fn render_shortcode(code: &Shortcode, data: &SiteData) -> Result<String, String> {
+    let kind = code.arg().ok_or("missing chart type")?;
+    let kind = ChartKind::parse(kind).ok_or_else(|| format!("unknown chart type {kind:?} (bar or line)"))?;
+    let points = match (code.named.get("values"), code.named.get("data")) {
+        (Some(values), None) => parse_values(values)?,
+        (None, Some(path)) => {
+            let value = data.get(path).ok_or_else(|| format!("no data at {path:?}"))?;
+            let key = |name: &str, default| code.named.get(name).map_or(default, String::as_str);
+            points_from_data(value, key("x", "label"), key("y", "value"))?
+        }
+        (Some(_), Some(_)) => return Err("both values and data given".to_string()),
+        (None, None) => return Err("missing values or data".to_string()),
+    };
+    if points.is_empty() {
+        return Err("no values".to_string());
+    }
+    Ok(render(kind, &points, code.named.get("title").map(String::as_str)))
+}
+
+/// `Jan: 3, Feb: 5` as points.
+fn parse_values(values: &str) -> Result<Vec<Point>, String> {
+    values
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (label, value) = pair.split_once(':').ok_or_else(|| format!("{:?} is not label: value", pair.trim()))?;
+            let value = value.trim().parse::<f64>().ok().filter(|v| v.is_finite())
+                .ok_or_else(|| format!("{:?} is not a number", value.trim()))?;
+            Ok(Point { label: label.trim().to_string(), value })
+        })
+        .collect()
+}
+
+/// Points of an array of tables, from the `x` and `y` keys of each.
+fn points_from_data(value: &Value, x: &str, y: &str) -> Result<Vec<Point>, String> {
+    let rows = value.as_array().filter(|rows| rows.iter().all(Value::is_object)).ok_or("data is not an array of tables")?;
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let label = match row.get(x) {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(other) => other.to_string(),
+            };
+            let value = row.get(y).and_then(Value::as_f64).ok_or_else(|| format!("row {i} has no number {y:?}"))?;
+            Ok(Point { label, value })
+        })
+        .collect()
+}
+
+/// `<figure>` with the chart of `points`, non-empty.
+pub fn render(kind: ChartKind, points: &[Point], title: Option<&str>) -> String {
+    let lo = points.iter().map(|p| p.value).fold(0.0, f64::min);
+    let hi = points.iter().map(|p| p.value).fold(0.0, f64::max);
+    let range = if hi > lo { hi - lo } else { 1.0 };
+    let plot_height = HEIGHT - TOP - BOTTOM;
+    let y = |value: f64| TOP + (hi - value) / range * plot_height;
+    let slot = (WIDTH - LEFT - RIGHT) / points.len() as f64;
+    let center = |i: usize| LEFT + slot * (i as f64 + 0.5);
+    let tooltip = |p: &Point| format!("<title>{}: {}</title>", p.label.escape_html(), p.value);
+
+    let label = title.unwrap_or(kind.name()).escape_html();
+    let mut svg = format!(
+        r#"<svg class="chart chart-{}" viewBox="0 0 {WIDTH} {HEIGHT}" role="img" aria-label="{label}" fill="currentColor" font-size="12">"#,
+        kind.name()
+    );
+    // Scale: the highest and lowest values, and the zero line
+    svg.push_str(&format!(r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{hi}</text>"#, LEFT - 6.0, y(hi) + 4.0));
+    if lo < 0.0 {
+        svg.push_str(&format!(r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{lo}</text>"#, LEFT - 6.0, y(lo) + 4.0));
+    }
+    svg.push_str(&format!(
+        r#"<line x1="{LEFT}" y1="{0:.1}" x2="{1}" y2="{0:.1}" stroke="currentColor" stroke-opacity="0.4"/>"#,
+        y(0.0), WIDTH - RIGHT
+    ));
+
+    match kind {
+        ChartKind::Bar => {
+            for (i, p) in points.iter().enumerate() {
+                let (top, bottom) = (y(p.value.max(0.0)), y(p.value.min(0.0)));
+                svg.push_str(&format!(
+                    r#"<rect x="{:.1}" y="{top:.1}" width="{:.1}" height="{:.1}">{}</rect>"#,
+                    center(i) - slot * 0.35, slot * 0.7, bottom - top, tooltip(p)
+                ));
+            }
+        }
+        ChartKind::Line => {
+            let line: Vec<String> = points.iter().enumerate().map(|(i, p)| format!("{:.1},{:.1}", center(i), y(p.value))).collect();
+            svg.push_str(&format!(r#"<polyline points="{}" fill="none" stroke="currentColor" stroke-width="2"/>"#, line.join(" ")));
+            for (i, p) in points.iter().enumerate() {
+                svg.push_str(&format!(r#"<circle cx="{:.1}" cy="{:.1}" r="3">{}</circle>"#, center(i), y(p.value), tooltip(p)));
+            }
+        }
+    }
+    for (i, p) in points.iter().enumerate() {
+        svg.push_str(&format!(
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+            center(i), HEIGHT - 10.0, p.label.escape_html()
+        ));
+    }
+    svg.push_str("</svg>");
+
+    let caption = title.map(|t| format!("<figcaption>{}</figcaption>", t.escape_html())).unwrap_or_default();
+    format!(r#"<figure class="chart">{svg}{caption}</figure>"#)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn draws_bars_from_inline_values() {
+        let code = Shortcode::parse(r#"{{< chart "bar" values="Jan: 3, Feb: 6, Mar: 0" title="Posts & notes" >}}"#).unwrap();
+        let html = render_shortcode(&code, &SiteData::default()).unwrap();
+        assert!(html.starts_with(r#"<figure class="chart"><svg class="chart chart-bar" viewBox="0 0 600 240" role="img" aria-label="Posts &amp; notes""#));
+        assert_eq!(html.matches("<rect ").count(), 3);
+        // The tallest bar spans the plot, from the top to the zero line
+        assert!(html.contains(r#"<rect x="250.8" y="15.0" width="128.3" height="195.0"><title>Feb: 6</title></rect>"#));
+        assert!(html.contains(r#"<text x="498.3" y="230.0" text-anchor="middle">Mar</text>"#));
+        assert!(html.ends_with("</svg><figcaption>Posts &amp; notes</figcaption></figure>"));
+    }
+
+    #[test]
+    fn draws_lines_from_data_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("stats.toml"), "[[visits]]\nmonth = \"May\"\ncount = -2\n\n[[visits]]\nmonth = \"Jun\"\ncount = 2\n").unwrap();
+        let (data, _) = SiteData::load(dir.path());
+        let code = Shortcode::parse(r#"{{< chart "line" data="stats.visits" x="month" y="count" >}}"#).unwrap();
+        let html = render_shortcode(&code, &data).unwrap();
+        assert!(html.contains(r#"<polyline points="177.5,210.0 452.5,15.0""#));
+        assert!(html.contains(r#"<text x="34.0" y="214.0" text-anchor="end">-2</text>"#));
+        assert!(html.contains("<title>May: -2</title>"));
+    }
+
+    #[test]
+    fn reports_bad_arguments() {
+        let error = |line: &str| render_shortcode(&Shortcode::parse(line).unwrap(), &SiteData::default()).unwrap_err();
+        assert_eq!(error(r#"{{< chart "pie" values="a: 1" >}}"#), r#"unknown chart type "pie" (bar or line)"#);
+        assert_eq!(error(r#"{{< chart "bar" values="a: x" >}}"#), r#""x" is not a number"#);
+        assert_eq!(error(r#"{{< chart "bar" values="a" >}}"#), r#""a" is not label: value"#);
+        assert_eq!(error(r#"{{< chart "bar" >}}"#), "missing values or data");
+        assert_eq!(error(r#"{{< chart "bar" data="nope" >}}"#), r#"no data at "nope""#);
+    }
+}
@@ -0,0 +1,365 @@
+//! Command-line argument parsing.
+
+use std::path::PathBuf;
+
+use crate::error::BuildError;
+use crate::import::Generator;
+use crate::logging::Verbosity;
+
+/// Usage text printed for `--help` and on argument errors.
+pub const USAGE: &str = "\
+Usage: ssg [COMMAND] [OPTIONS]
+
+Commands:
+  build              Build the site (default)
+  check              Build, then check every page for well-formed HTML
+  clean              Remove all generated outputs
+  daemon             Keep the site warm and serve commands over a socket
+  deploy             Upload changed outputs to the [deploy] target in ssg.toml
+  export epub        Bundle the posts into an EPUB book
+  import GEN PATH    Import posts from a Jekyll, Hugo or Zola site at PATH,
+                     or a WordPress export file (GEN: wordpress)
+  init [DIR]         Create a new site (ssg.toml, content/, static/) in DIR
+
+Build options:
+  --no-prune         Keep stale outputs from previous builds
+  --dry-run          Run the full pipeline but write nothing; report changes
+  --strict           Fail on recoverable problems (missing images, invalid
+                     tags, broken links)
+  --report PATH      Write a JSON build report to PATH
+  --format FORMAT    Console output: human (default) or json
+  --timings          Print wall/CPU time per phase and the slowest posts
+                     and images
+  -j, --jobs N       Use N worker threads (default: one per core)
+
+Clean options:
+  --dry-run          List files that would be removed without deleting them
+
+Deploy options:
+  --dry-run          List files that would be uploaded or deleted
+
+Export options:
+  --tag TAG          Only posts with this tag
+  --cover PATH       Cover image of the book
+  -o, --output PATH  Book file to write (default: book.epub)
+
+Import options:
+  --dry-run          List posts that would be imported without writing
+
+Daemon options:
+  --socket PATH      Unix socket to listen on (default: ssg.sock)
+
+Global options:
+  -c, --config PATH  Config file (default: ssg.toml if present)
+  -q, --quiet        Only print warnings and errors
+  -v, -vv            Verbose output with phase timings (-vv: trace)
+  -h, --help         Print this help
+
+Exit codes: 0 success, 1 total failure, 2 partial failure (posts skipped,
+or warnings in strict mode), 64 invalid arguments";
+
+/// Parsed command line: a command plus global options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cli {
+    pub command: Command,
+    pub verbosity: Verbosity,
+    /// Explicit config file, if given.
+    pub config: Option<PathBuf>,
+}
+
+/// Subcommand selected on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Build the site.
+    Build(BuildArgs),
+
+    /// Build, then validate the written pages.
+    Check,
+
+    /// Remove generated outputs.
+    Clean { dry_run: bool },
+
+    /// Serve build commands over a Unix socket.
+    Daemon { socket: PathBuf },
+
+    /// Upload the built site.
+    Deploy { dry_run: bool },
+
+    /// Export posts as an EPUB book.
+    Export(ExportArgs),
+
+    /// Import posts from another generator.
+    Import { generator: Generator, source: PathBuf, dry_run: bool },
+
+    /// Scaffold a new site.
+    Init { dir: PathBuf },
+
+    /// Print usage and exit.
+    Help,
+}
+
+/// Console output format for build results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Options for the `build` command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildArgs {
+    /// Skip removal of stale outputs.
+    pub no_prune: bool,
+
+    /// Plan the build and report changes without writing.
+    pub dry_run: bool,
+
+    /// Treat recoverable problems as build failures.
+    pub strict: bool,
+
+    /// Where to write the JSON build report, if anywhere.
+    pub report: Option<PathBuf>,
+
+    /// Console output format.
+    pub format: OutputFormat,
+
+    /// Print a per-phase profile after the build.
+    pub timings: bool,
+
+    /// Worker thread count (None: one per core).
+    pub jobs: Option<usize>,
+}
+
+/// Options for the `export epub` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportArgs {
+    /// Only posts with this tag.
+    pub tag: Option<String>,
+
+    /// Cover image.
+    pub cover: Option<PathBuf>,
+
+    /// Book file to write.
+    pub output: PathBuf,
+}
+
+impl Default for ExportArgs {
+    fn default() -> Self {
+        Self { tag: None, cover: None, output: PathBuf::from("book.epub") }
+    }
+}
+
+/// Parse arguments (without the program name).
+pub fn parse<I>(args: I) -> Result<Cli, BuildError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter().peekable();
+
+    let command = match args.peek().map(String::as_str) {
+        Some("build" | "check" | "clean" | "daemon" | "deploy" | "export" | "import" | "init") => args.next(),
+        _ => None,
+    };
+    let command = command.as_deref().unwrap_or("build");
+    if command == "export" {
+        match args.next().as_deref() {
+            Some("epub") => {}
+            Some(other) => return Err(BuildError::InvalidArgs(format!("unknown export format '{other}'"))),
+            None => return Err(BuildError::InvalidArgs("export requires a format (epub)".to_string())),
+        }
+    }
+    let generator = if command == "import" {
+        let name = args.next().ok_or_else(|| BuildError::InvalidArgs("import requires a generator (jekyll, hugo, zola, wordpress)".to_string()))?;
+        let generator = Generator::parse(&name).ok_or_else(|| BuildError::InvalidArgs(format!("unknown generator '{name}'")))?;
+        Some(generator)
+    } else {
+        None
+    };
+
+    let mut build = BuildArgs::default();
+    let mut export = ExportArgs::default();
+    let mut dry_run = false;
+    let mut socket = PathBuf::from("ssg.sock");
+    let mut dir = None;
+    let mut verbosity = Verbosity::Normal;
+    let mut config = None;
+
+    while let Some(arg) = args.next() {
+        match (command, arg.as_str()) {
+            (_, "-h" | "--help") => {
+                return Ok(Cli { command: Command::Help, verbosity, config });
+            }
+            (_, "-q" | "--quiet") => verbosity = Verbosity::Quiet,
+            (_, "-v" | "--verbose") => {
+                verbosity = if verbosity >= Verbosity::Verbose { Verbosity::Trace } else { Verbosity::Verbose };
+            }
+            (_, "-vv") => verbosity = Verbosity::Trace,
+            (_, "-c" | "--config") => config = Some(PathBuf::from(value(&mut args, "--config")?)),
+            ("build", "--no-prune") => build.no_prune = true,
+            ("build", "--dry-run") => build.dry_run = true,
+            ("build", "--strict") => build.strict = true,
+            ("build", "--timings") => build.timings = true,
+            ("build", "-j" | "--jobs") => {
+                build.jobs = match value(&mut args, "--jobs")?.parse() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => return Err(BuildError::InvalidArgs("--jobs requires a positive number".to_string())),
+                };
+            }
+            ("build", "--report") => build.report = Some(PathBuf::from(value(&mut args, "--report")?)),
+            ("build", "--format") => {
+                build.format = match value(&mut args, "--format")?.as_str() {
+                    "human" => OutputFormat::Human,
+                    "json" => OutputFormat::Json,
+                    other => return Err(BuildError::InvalidArgs(format!("unknown format '{other}'"))),
+                };
+            }
+            ("clean" | "deploy" | "import", "--dry-run") => dry_run = true,
+            ("export", "--tag") => export.tag = Some(value(&mut args, "--tag")?),
+            ("export", "--cover") => export.cover = Some(PathBuf::from(value(&mut args, "--cover")?)),
+            ("export", "-o" | "--output") => export.output = PathBuf::from(value(&mut args, "--output")?),
+            ("daemon", "--socket") => socket = PathBuf::from(value(&mut args, "--socket")?),
+            ("import" | "init", path) if dir.is_none() && !path.starts_with('-') => dir = Some(PathBuf::from(path)),
+            (_, other) => return Err(BuildError::InvalidArgs(format!("unexpected argument '{other}'"))),
+        }
+    }
+
+    let command = match command {
+        "check" => Command::Check,
+        "clean" => Command::Clean { dry_run },
+        "daemon" => Command::Daemon { socket },
+        "deploy" => Command::Deploy { dry_run },
+        "export" => Command::Export(export),
+        "import" => match (generator, dir) {
+            (Some(generator), Some(source)) => Command::Import { generator, source, dry_run },
+            _ => return Err(BuildError::InvalidArgs("import requires the site directory or export file".to_string())),
+        },
+        "init" => Command::Init { dir: dir.unwrap_or_else(|| PathBuf::from(".")) },
+        _ => Command::Build(build),
+    };
+    Ok(Cli { command, verbosity, config })
+}
+
+/// Take the value following a flag.
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, BuildError> {
+    args.next()
+        .ok_or_else(|| BuildError::InvalidArgs(format!("{flag} requires a value")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(args: &[&str]) -> Result<Command, BuildError> {
+        parse(args.iter().map(|s| s.to_string())).map(|cli| cli.command)
+    }
+
+    #[test]
+    fn defaults_to_build() {
+        assert_eq!(parse_str(&[]).unwrap(), Command::Build(BuildArgs::default()));
+    }
+
+    #[test]
+    fn build_dry_run() {
+        let expected = BuildArgs { dry_run: true, ..BuildArgs::default() };
+        assert_eq!(parse_str(&["--dry-run"]).unwrap(), Command::Build(expected));
+    }
+
+    #[test]
+    fn build_report_options() {
+        let Command::Build(args) = parse_str(&["--format", "json", "--report", "r.json"]).unwrap() else {
+            panic!("expected build");
+        };
+        assert_eq!(args.format, OutputFormat::Json);
+        assert_eq!(args.report, Some(PathBuf::from("r.json")));
+        assert!(!args.timings);
+        assert!(parse_str(&["--format", "xml"]).is_err());
+    }
+
+    #[test]
+    fn build_jobs() {
+        let Command::Build(args) = parse_str(&["-j", "2"]).unwrap() else {
+            panic!("expected build");
+        };
+        assert_eq!(args.jobs, Some(2));
+        assert!(parse_str(&["--jobs", "0"]).is_err());
+        assert!(parse_str(&["--jobs", "many"]).is_err());
+    }
+
+    #[test]
+    fn check_command() {
+        assert_eq!(parse_str(&["check"]).unwrap(), Command::Check);
+        assert!(parse_str(&["check", "--dry-run"]).is_err());
+    }
+
+    #[test]
+    fn clean_dry_run() {
+        assert_eq!(parse_str(&["clean", "--dry-run"]).unwrap(), Command::Clean { dry_run: true });
+        assert_eq!(parse_str(&["deploy", "--dry-run"]).unwrap(), Command::Deploy { dry_run: true });
+    }
+
+    #[test]
+    fn daemon_socket_path() {
+        assert_eq!(
+            parse_str(&["daemon", "--socket", "/tmp/s.sock"]).unwrap(),
+            Command::Daemon { socket: PathBuf::from("/tmp/s.sock") }
+        );
+        assert!(parse_str(&["daemon", "--socket"]).is_err());
+    }
+
+    #[test]
+    fn export_epub_options() {
+        assert_eq!(parse_str(&["export", "epub"]).unwrap(), Command::Export(ExportArgs::default()));
+        let Command::Export(args) = parse_str(&["export", "epub", "--tag", "rust", "-o", "rust.epub"]).unwrap() else {
+            panic!("expected export");
+        };
+        assert_eq!(args.tag.as_deref(), Some("rust"));
+        assert_eq!(args.output, PathBuf::from("rust.epub"));
+        assert!(parse_str(&["export"]).is_err());
+        assert!(parse_str(&["export", "pdf"]).is_err());
+    }
+
+    #[test]
+    fn import_options() {
+        assert_eq!(
+            parse_str(&["import", "hugo", "../old-blog", "--dry-run"]).unwrap(),
+            Command::Import { generator: Generator::Hugo, source: PathBuf::from("../old-blog"), dry_run: true }
+        );
+        assert!(parse_str(&["import"]).is_err());
+        assert!(parse_str(&["import", "ghost", "site"]).is_err());
+        assert!(parse_str(&["import", "zola"]).is_err());
+        let Command::Import { generator, .. } = parse_str(&["import", "wordpress", "export.xml"]).unwrap() else {
+            panic!("expected import");
+        };
+        assert_eq!(generator, Generator::WordPress);
+    }
+
+    #[test]
+    fn init_directory() {
+        assert_eq!(parse_str(&["init"]).unwrap(), Command::Init { dir: PathBuf::from(".") });
+        assert_eq!(parse_str(&["init", "blog"]).unwrap(), Command::Init { dir: PathBuf::from("blog") });
+        assert!(parse_str(&["init", "a", "b"]).is_err());
+    }
+
+    #[test]
+    fn config_path() {
+        let cli = parse(["build", "-c", "site/ssg.toml"].map(String::from)).unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("site/ssg.toml")));
+    }
+
+    #[test]
+    fn verbosity_flags() {
+        let verbosity = |args: &[&str]| parse(args.iter().map(|s| s.to_string())).unwrap().verbosity;
+        assert_eq!(verbosity(&[]), Verbosity::Normal);
+        assert_eq!(verbosity(&["clean", "-q"]), Verbosity::Quiet);
+        assert_eq!(verbosity(&["-v"]), Verbosity::Verbose);
+        assert_eq!(verbosity(&["-vv"]), Verbosity::Trace);
+        assert_eq!(verbosity(&["-v", "-v"]), Verbosity::Trace);
+    }
+
+    #[test]
+    fn rejects_unknown_flags() {
+        assert!(parse_str(&["--bogus"]).is_err());
+        assert!(parse_str(&["clean", "--no-prune"]).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Comment threads below posts.
+//!
+//! The `[comments]` table picks a provider whose embed script is added at
+//! the end of every post (list pages get none):
+//!
+//! ```toml
+//! [comments]
+//! provider = "giscus"          # or "utterances", "isso"
+//! repo = "duck/blog"
+//! repo_id = "R_kgDO..."
+//! category = "Comments"
+//! category_id = "DIC_kwDO..."
+//! ```
+//!
+//! `comments: false` front matter turns the thread off for one post.
+
+use serde::Deserialize;
+
+use crate::csp::ScriptTargets;
+use crate::types::EscapeHtml;
+
+/// Comment provider settings (the `[comments]` table).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase", deny_unknown_fields)]
+pub enum CommentsConfig {
+    /// GitHub Discussions via giscus.app.
+    Giscus {
+        repo: String,
+        repo_id: String,
+        category: String,
+        category_id: String,
+        /// How pages map to discussions.
+        #[serde(default = "default_mapping")]
+        mapping: String,
+        #[serde(default = "default_giscus_theme")]
+        theme: String,
+        /// Widget language; giscus picks English if unset.
+        lang: Option<String>,
+    },
+    /// GitHub issues via utteranc.es.
+    Utterances {
+        repo: String,
+        #[serde(default = "default_mapping")]
+        issue_term: String,
+        #[serde(default = "default_utterances_theme")]
+        theme: String,
+        /// Label added to created issues.
+        label: Option<String>,
+    },
+    /// A self-hosted Isso server.
+    Isso {
+        /// Base URL of the server (`https://comments.example.com`).
+        url: String,
+    },
+}
+
+fn default_mapping() -> String {
+    "pathname".to_string()
+}
+
+fn default_giscus_theme() -> String {
+    "preferred_color_scheme".to_string()
+}
+
+fn default_utterances_theme() -> String {
+    "preferred-color-scheme".to_string()
+}
+
+impl CommentsConfig {
+    /// What the widget's script reaches: the frame it opens (giscus,
+    /// utterances) or the server it talks to (Isso).
+    pub fn script_targets(&self) -> ScriptTargets {
+        match self {
+            Self::Giscus { .. } => ScriptTargets { frames: vec!["https://giscus.app".to_string()], ..Default::default() },
+            Self::Utterances { .. } => ScriptTargets { frames: vec!["https://utteranc.es".to_string()], ..Default::default() },
+            Self::Isso { url } => ScriptTargets { connect: vec![url.clone()], ..Default::default() },
+        }
+    }
+
+    /// Embed markup for the end of a post.
+    pub fn render(&self) -> String {
+        let embed = match self {
+            Self::Giscus { repo, repo_id, category, category_id, mapping, theme, lang } => {
+                let lang = lang
+                    .as_ref()
+                    .map(|lang| format!(r#" data-lang="{}""#, lang.escape_html()))
+                    .unwrap_or_default();
+                format!(
+                    r#"<script src="https://giscus.app/client.js" data-repo="{}" data-repo-id="{}" data-category="{}" data-category-id="{}" data-mapping="{}" data-reactions-enabled="1" data-input-position="bottom" data-theme="{}"{lang} data-loading="lazy" crossorigin="anonymous" async></script>"#,
+                    repo.escape_html(),
+                    repo_id.escape_html(),
+                    category.escape_html(),
+                    category_id.escape_html(),
+                    mapping.escape_html(),
+                    theme.escape_html(),
+                )
+            }
+            Self::Utterances { repo, issue_term, theme, label } => {
+                let label = label
+                    .as_ref()
+                    .map(|label| format!(r#" label="{}""#, label.escape_html()))
+                    .unwrap_or_default();
+                format!(
+                    r#"<script src="https://utteranc.es/client.js" repo="{}" issue-term="{}" theme="{}"{label} crossorigin="anonymous" async></script>"#,
+                    repo.escape_html(),
+                    issue_term.escape_html(),
+                    theme.escape_html(),
+                )
+            }
+            Self::Isso { url } => {
+                let url = url.trim_end_matches('/').escape_html();
+                format!(r#"<script data-isso="{url}/" src="{url}/js/embed.min.js" async></script><section id="isso-thread"></section>"#)
+            }
+        };
+        format!(r#"<section class="comments">{embed}</section>"#)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn providers_from_toml() {
+        let giscus: CommentsConfig = toml::from_str(
+            "provider = \"giscus\"\nrepo = \"duck/blog\"\nrepo_id = \"R_1\"\ncategory = \"Q & A\"\ncategory_id = \"C_1\"\n",
+        )
+        .unwrap();
+        let html = giscus.render();
+        assert!(html.starts_with(r#"<section class="comments"><script src="https://giscus.app/client.js" data-repo="duck/blog""#));
+        assert!(html.contains(r#"data-category="Q &amp; A""#));
+        assert!(html.contains(r#"data-mapping="pathname""#));
+
+        let isso: CommentsConfig = toml::from_str("provider = \"isso\"\nurl = \"https://c.example/\"\n").unwrap();
+        assert!(isso.render().contains(r#"data-isso="https://c.example/" src="https://c.example/js/embed.min.js""#));
+
+        assert!(toml::from_str::<CommentsConfig>("provider = \"disqus\"\n").is_err());
+        assert!(toml::from_str::<CommentsConfig>("provider = \"utterances\"\n").is_err());
+        assert!(toml::from_str::<CommentsConfig>("provider = \"isso\"\nurl = \"x\"\nrepo = \"y\"\n").is_err());
+    }
+}
@@ -1,7 +1,71 @@
 //! Build configuration with typed defaults.
+//!
+//! A config starts from `Config::default()` and is adjusted with builder
+//! methods, or loaded from an `ssg.toml` whose keys mirror the field names.
+//! Paths in `ssg.toml` are relative to the file itself.
 
+use std::cmp::Ordering;
+use std::fmt::Write;
+use std::fs;
 use std::path::{Path, PathBuf};
 
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Locale, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::comments::CommentsConfig;
+use crate::deploy::DeployConfig;
+use crate::diagram::DiagramConfig;
+use crate::error::{BuildError, Suppression};
+use crate::feed::FeedConfig;
+use crate::fonts::FontsConfig;
+use crate::hosting::HeaderRules;
+use crate::i18n::{self, Language, StringOverrides, Strings};
+use crate::image::{ImageBounds, ImageFit};
+use crate::indieweb::IndieWebConfig;
+use crate::lightbox::Lightbox;
+use crate::parser::ExternalLinks;
+use crate::purge::PurgeConfig;
+use crate::search::SearchConfig;
+use crate::sitemap::RobotsConfig;
+use crate::source::PostSource;
+use crate::sri::ResourcesConfig;
+use crate::timezone::SiteTimezone;
+use crate::types::AbsoluteUrl;
+
+/// Config file looked up in the working directory.
+pub const CONFIG_FILE: &str = "ssg.toml";
+
+/// Default `date_format`.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y.%m.%d %H:%M";
+
+/// Default `iso_date_format` (RFC 3339).
+pub const DEFAULT_ISO_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
+
+/// Order of posts in lists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Newest first.
+    #[default]
+    Desc,
+    /// Oldest first.
+    Asc,
+}
+
+impl SortOrder {
+    /// Order two posts by date, then title (always A to Z) for posts
+    /// published at the same time.
+    pub fn compare(self, a: (&DateTime<FixedOffset>, &str), b: (&DateTime<FixedOffset>, &str)) -> Ordering {
+        let by_date = match self {
+            Self::Desc => b.0.cmp(a.0),
+            Self::Asc => a.0.cmp(b.0),
+        };
+        by_date.then_with(|| a.1.cmp(b.1))
+    }
+}
+
 /// Configuration for the blog generator.
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -10,18 +74,200 @@ pub struct Config {
     
     /// Directory for generated output.
     pub public_dir: PathBuf,
+
+    /// Directory whose files are copied verbatim into the output root.
+    pub static_dir: PathBuf,
+
+    /// Data files such as `blogroll.toml` (default: `data/` next to
+    /// `ssg.toml`, `../data` without one).
+    pub data_dir: PathBuf,
+
+    /// Page layouts selected with `layout:` front matter (default:
+    /// `templates/` next to `ssg.toml`, `../templates` without one).
+    pub templates_dir: PathBuf,
+
+    /// Gemtext mirror of the posts (`public_gemini/`); None: not written.
+    pub gemini_dir: Option<PathBuf>,
     
     /// Maximum image width (images larger will be resized).
     pub max_image_width: u32,
+
+    /// Source image of the favicons and app icons.
+    pub icon: Option<PathBuf>,
+
+    /// Maximum image height (None: unlimited).
+    pub max_image_height: Option<u32>,
+
+    /// How images over both limits are fitted.
+    pub image_fit: ImageFit,
     
     /// Timezone offset in hours (for display dates).
     pub timezone_offset_hours: i32,
+
+    /// IANA timezone (`Europe/Berlin`); overrides `timezone_offset_hours`
+    /// and follows daylight saving time.
+    pub timezone: Option<Tz>,
+
+    /// chrono format for dates shown on pages.
+    pub date_format: String,
+
+    /// chrono format for machine-readable dates (JSON-LD, sitemap).
+    pub iso_date_format: String,
+
+    /// Language of UI strings and month names in `date_format`.
+    pub locale: Locale,
+
+    /// `<html lang>` of pages and language of the feeds (default: from
+    /// `locale`; a language's code in its subtree).
+    pub lang: Option<String>,
+
+    /// Custom UI strings (the `[strings]` table).
+    pub strings: StringOverrides,
+
+    /// Languages of a multilingual site (`[[languages]]`); the first is
+    /// the default. Empty for a single-language site.
+    pub languages: Vec<Language>,
     
     /// Site brand name shown in header.
     pub brand_name: String,
+
+    /// Meta description of the index and tag pages.
+    pub description: Option<String>,
     
     /// Whether to inline CSS into HTML (eliminates render-blocking).
     pub inline_css: bool,
+
+    /// Whether to remove stale outputs not produced by the current build.
+    pub prune: bool,
+
+    /// Whether recoverable problems (missing images, invalid tags, broken
+    /// links) fail the build.
+    pub strict: bool,
+
+    /// Warnings to drop (the `[[suppress]]` tables).
+    pub suppress: Vec<Suppression>,
+
+    /// Worker threads for parsing, rendering and image encoding
+    /// (None: one per core).
+    pub threads: Option<usize>,
+
+    /// Maximum images decoded and encoded at once (None: one per worker).
+    pub image_jobs: Option<usize>,
+
+    /// Target for `ssg deploy` (the `[deploy]` table).
+    pub deploy: Option<DeployConfig>,
+
+    /// Custom response headers by path pattern, written as `_headers`
+    /// and `vercel.json` (the `[headers]` table).
+    pub headers: HeaderRules,
+
+    /// Add `Cache-Control` rules to `headers`: immutable for fingerprinted
+    /// images, revalidated for pages.
+    pub cache_headers: bool,
+
+    /// Public URL of the site root (`https://example.com`), needed for
+    /// absolute URLs such as the sitemap.
+    pub base_url: Option<AbsoluteUrl>,
+
+    /// `robots.txt` settings (the `[robots]` table).
+    pub robots: RobotsConfig,
+
+    /// Atom feed settings (the `[feed]` table).
+    pub feed: FeedConfig,
+
+    /// Search index settings (the `[search]` table).
+    pub search: SearchConfig,
+
+    /// Unused stylesheet rule removal (the `[purge]` table).
+    pub purge: PurgeConfig,
+
+    /// Webfont subsetting and preloads (the `[fonts]` table).
+    pub fonts: FontsConfig,
+
+    /// Markup for links leaving the site (the `[external_links]` table).
+    pub external_links: ExternalLinks,
+
+    /// Mermaid and Graphviz rendering (the `[diagrams]` table).
+    pub diagrams: DiagramConfig,
+
+    /// Render straight quotes, `--`, `---` and `...` as typographic
+    /// punctuation.
+    pub smart_punctuation: bool,
+
+    /// Demote headings of posts that use `#` below the title, so the title
+    /// stays the page's only `<h1>`.
+    pub demote_headings: bool,
+
+    /// Leave the `# Title` heading out of the rendered body; the post
+    /// header shows the title as the page's `<h1>` instead.
+    pub strip_title: bool,
+
+    /// Show the author's name in the post meta line.
+    pub show_author: bool,
+
+    /// Show an estimated reading time in the post meta line.
+    pub reading_time: bool,
+
+    /// How images open in full size.
+    pub lightbox: Lightbox,
+
+    /// External scripts and stylesheets (the `[resources]` table).
+    pub resources: ResourcesConfig,
+
+    /// Author h-card and Webmention/pingback endpoints (the `[indieweb]`
+    /// table).
+    pub indieweb: IndieWebConfig,
+
+    /// Comment thread provider for posts (the `[comments]` table).
+    pub comments: Option<CommentsConfig>,
+
+    /// HTML inserted verbatim at the end of every page's `<head>`.
+    pub head_html: Option<String>,
+
+    /// HTML inserted verbatim at the end of every page's `<body>`.
+    pub body_end_html: Option<String>,
+
+    /// Write a web app manifest and a service worker that keeps the
+    /// index, recent posts and stylesheet readable offline.
+    pub pwa: bool,
+
+    /// Add the print stylesheet to every page.
+    pub print_css: bool,
+
+    /// Check posts for accessibility problems (missing alt text, skipped
+    /// heading levels, empty links, missing `lang`) and report them as
+    /// warnings.
+    pub a11y_lint: bool,
+
+    /// Warn about images without alt text (failing strict builds).
+    pub require_alt_text: bool,
+
+    /// Write each post's markdown next to its page (`posts/{stem}.txt`)
+    /// and list them in `llms.txt`.
+    pub post_source: Option<PostSource>,
+
+    /// Whether to take post dates from git history when available.
+    pub git_dates: bool,
+
+    /// Fixed time (`SOURCE_DATE_EPOCH`) standing in for file mtimes and
+    /// the clock, so two builds of the same sources are byte-identical.
+    pub source_date_epoch: Option<DateTime<Utc>>,
+
+    /// Order of posts on the index and tag pages.
+    pub sort_order: SortOrder,
+
+    /// Show only the latest this many posts on the index, linking to an
+    /// archive page with all of them.
+    pub index_posts: Option<usize>,
+
+    /// List up to this many tags that share posts with a tag on its page.
+    pub related_tags: Option<usize>,
+
+    /// Show only this many tags, those with the most posts, in the nav.
+    pub nav_tags: Option<usize>,
+
+    /// Fold the nav's tag links into a `<details>` section.
+    pub collapse_nav_tags: bool,
 }
 
 impl Config {
@@ -30,6 +276,37 @@ impl Config {
         Self::default()
     }
 
+    /// Load an `ssg.toml`; missing keys keep their defaults.
+    pub fn load(path: &Path) -> Result<Self, BuildError> {
+        let invalid = |message: String| BuildError::ConfigInvalid {
+            path: path.to_path_buf(),
+            message,
+        };
+        let text = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let mut file: ConfigFile = toml::from_str(&text).map_err(|e| invalid(e.message().to_string()))?;
+
+        let root = path.parent().unwrap_or(Path::new(""));
+        let head_html = file.head_html.take().map(|s| s.read(root)).transpose().map_err(invalid)?;
+        let body_end_html = file.body_end_html.take().map(|s| s.read(root)).transpose().map_err(invalid)?;
+        let mut config = file.apply(Self::default(), root);
+        config.head_html = head_html.or(config.head_html);
+        config.body_end_html = body_end_html.or(config.body_end_html);
+        for format in [&config.date_format, &config.iso_date_format] {
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(invalid(format!("invalid date format {format:?}")));
+            }
+        }
+        for (i, language) in config.languages.iter().enumerate() {
+            if !Language::is_valid_code(&language.code) {
+                return Err(invalid(format!("invalid language code {:?}", language.code)));
+            }
+            if config.languages[..i].iter().any(|l| l.code == language.code) {
+                return Err(invalid(format!("duplicate language {:?}", language.code)));
+            }
+        }
+        Ok(config)
+    }
+
     /// Builder: set content directory.
     pub fn content_dir(mut self, path: impl AsRef<Path>) -> Self {
         self.content_dir = path.as_ref().to_path_buf();
@@ -42,24 +319,330 @@ impl Config {
         self
     }
 
+    /// Builder: set static assets directory.
+    pub fn static_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.static_dir = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Builder: set data files directory.
+    pub fn data_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.data_dir = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Builder: set page layouts directory.
+    pub fn templates_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.templates_dir = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Builder: write a Gemtext mirror of the posts to `path`.
+    pub fn gemini_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.gemini_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Builder: add a custom response header for paths matching `pattern`.
+    pub fn header(mut self, pattern: impl Into<String>, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.entry(pattern.into()).or_default().insert(name.into(), value.into());
+        self
+    }
+
+    /// Builder: set the public URL of the site root.
+    pub fn base_url(mut self, url: AbsoluteUrl) -> Self {
+        self.base_url = Some(url);
+        self
+    }
+
+    /// Builder: set the site description.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Builder: set the feed settings.
+    pub fn feed(mut self, feed: FeedConfig) -> Self {
+        self.feed = feed;
+        self
+    }
+
+    /// Builder: set the search index settings.
+    pub fn search(mut self, search: SearchConfig) -> Self {
+        self.search = search;
+        self
+    }
+
+    /// Builder: set the removal of unused stylesheet rules.
+    pub fn purge(mut self, purge: PurgeConfig) -> Self {
+        self.purge = purge;
+        self
+    }
+
+    /// Builder: set the webfont subsetting and preloads.
+    pub fn fonts(mut self, fonts: FontsConfig) -> Self {
+        self.fonts = fonts;
+        self
+    }
+
+    /// Builder: set the markup of external links.
+    pub fn external_links(mut self, links: ExternalLinks) -> Self {
+        self.external_links = links;
+        self
+    }
+
+    /// Builder: set how diagrams are rendered.
+    pub fn diagrams(mut self, diagrams: DiagramConfig) -> Self {
+        self.diagrams = diagrams;
+        self
+    }
+
+    /// Builder: enable/disable smart punctuation.
+    pub fn smart_punctuation(mut self, enabled: bool) -> Self {
+        self.smart_punctuation = enabled;
+        self
+    }
+
+    /// Builder: enable/disable heading demotion.
+    pub fn demote_headings(mut self, enabled: bool) -> Self {
+        self.demote_headings = enabled;
+        self
+    }
+
+    /// Builder: enable/disable moving the title out of the body.
+    pub fn strip_title(mut self, enabled: bool) -> Self {
+        self.strip_title = enabled;
+        self
+    }
+
+    /// Builder: show/hide the author in the post meta line.
+    pub fn show_author(mut self, enabled: bool) -> Self {
+        self.show_author = enabled;
+        self
+    }
+
+    /// Builder: show/hide the reading time in the post meta line.
+    pub fn reading_time(mut self, enabled: bool) -> Self {
+        self.reading_time = enabled;
+        self
+    }
+
+    /// Builder: set the image lightbox.
+    pub fn lightbox(mut self, lightbox: Lightbox) -> Self {
+        self.lightbox = lightbox;
+        self
+    }
+
+    /// Builder: set the external scripts and stylesheets.
+    pub fn resources(mut self, resources: ResourcesConfig) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// Builder: set the IndieWeb author and endpoints.
+    pub fn indieweb(mut self, indieweb: IndieWebConfig) -> Self {
+        self.indieweb = indieweb;
+        self
+    }
+
+    /// Builder: set the comment provider.
+    pub fn comments(mut self, comments: CommentsConfig) -> Self {
+        self.comments = Some(comments);
+        self
+    }
+
+    /// Builder: set HTML for the end of every `<head>`.
+    pub fn head_html(mut self, html: impl Into<String>) -> Self {
+        self.head_html = Some(html.into());
+        self
+    }
+
+    /// Builder: set HTML for the end of every `<body>`.
+    pub fn body_end_html(mut self, html: impl Into<String>) -> Self {
+        self.body_end_html = Some(html.into());
+        self
+    }
+
+    /// Builder: enable/disable the manifest and offline service worker.
+    pub fn pwa(mut self, enabled: bool) -> Self {
+        self.pwa = enabled;
+        self
+    }
+
+    /// Builder: enable/disable the print stylesheet.
+    pub fn print_css(mut self, enabled: bool) -> Self {
+        self.print_css = enabled;
+        self
+    }
+
+    /// Builder: enable/disable the accessibility lint.
+    pub fn a11y_lint(mut self, enabled: bool) -> Self {
+        self.a11y_lint = enabled;
+        self
+    }
+
+    /// Builder: require alt text on every image.
+    pub fn require_alt_text(mut self, enabled: bool) -> Self {
+        self.require_alt_text = enabled;
+        self
+    }
+
+    /// Builder: write post sources in `format` next to the pages.
+    pub fn post_source(mut self, format: PostSource) -> Self {
+        self.post_source = Some(format);
+        self
+    }
+
+    /// Builder: enable/disable dates from git history.
+    pub fn git_dates(mut self, enabled: bool) -> Self {
+        self.git_dates = enabled;
+        self
+    }
+
+    /// Builder: use `time` instead of file mtimes and the clock.
+    pub fn source_date_epoch(mut self, time: DateTime<Utc>) -> Self {
+        self.source_date_epoch = Some(time);
+        self
+    }
+
+    /// Builder: set the order of posts in lists.
+    pub fn sort_order(mut self, order: SortOrder) -> Self {
+        self.sort_order = order;
+        self
+    }
+
+    /// Builder: list only the latest `count` posts on the index.
+    pub fn index_posts(mut self, count: usize) -> Self {
+        self.index_posts = Some(count);
+        self
+    }
+
+    /// Builder: list up to `count` related tags on tag pages.
+    pub fn related_tags(mut self, count: usize) -> Self {
+        self.related_tags = Some(count);
+        self
+    }
+
+    /// Builder: show only the `count` most used tags in the nav.
+    pub fn nav_tags(mut self, count: usize) -> Self {
+        self.nav_tags = Some(count);
+        self
+    }
+
+    /// Builder: enable/disable folding the nav's tags.
+    pub fn collapse_nav_tags(mut self, enabled: bool) -> Self {
+        self.collapse_nav_tags = enabled;
+        self
+    }
+
     /// Builder: set max image width.
     pub fn max_image_width(mut self, width: u32) -> Self {
         self.max_image_width = width;
         self
     }
 
+    /// Builder: set the source image of the site icons.
+    pub fn icon(mut self, path: impl AsRef<Path>) -> Self {
+        self.icon = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Builder: set max image height.
+    pub fn max_image_height(mut self, height: Option<u32>) -> Self {
+        self.max_image_height = height;
+        self
+    }
+
+    /// Builder: set how images over both limits are fitted.
+    pub fn image_fit(mut self, fit: ImageFit) -> Self {
+        self.image_fit = fit;
+        self
+    }
+
     /// Builder: set timezone offset.
     pub fn timezone_offset(mut self, hours: i32) -> Self {
         self.timezone_offset_hours = hours;
         self
     }
 
+    /// Builder: set an IANA timezone.
+    pub fn timezone(mut self, zone: Tz) -> Self {
+        self.timezone = Some(zone);
+        self
+    }
+
+    /// Builder: set the format of dates shown on pages.
+    pub fn date_format(mut self, format: impl Into<String>) -> Self {
+        self.date_format = format.into();
+        self
+    }
+
+    /// Builder: set the format of machine-readable dates.
+    pub fn iso_date_format(mut self, format: impl Into<String>) -> Self {
+        self.iso_date_format = format.into();
+        self
+    }
+
+    /// Builder: set the locale of UI strings and dates.
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Builder: set the `<html lang>` of pages.
+    pub fn lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// Builder: override UI strings.
+    pub fn strings(mut self, strings: StringOverrides) -> Self {
+        self.strings = strings;
+        self
+    }
+
+    /// Builder: add a language (the first one added is the default).
+    pub fn language(mut self, language: Language) -> Self {
+        self.languages.push(language);
+        self
+    }
+
     /// Builder: set brand name.
     pub fn brand_name(mut self, name: impl Into<String>) -> Self {
         self.brand_name = name.into();
         self
     }
 
+    /// Builder: enable/disable pruning of stale outputs.
+    pub fn prune(mut self, enabled: bool) -> Self {
+        self.prune = enabled;
+        self
+    }
+
+    /// Builder: enable/disable strict mode.
+    pub fn strict(mut self, enabled: bool) -> Self {
+        self.strict = enabled;
+        self
+    }
+
+    /// Builder: drop the warnings `rule` matches.
+    pub fn suppress(mut self, rule: Suppression) -> Self {
+        self.suppress.push(rule);
+        self
+    }
+
+    /// Builder: limit the number of worker threads.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Builder: limit concurrent image decoding to bound memory use.
+    pub fn image_jobs(mut self, jobs: usize) -> Self {
+        self.image_jobs = Some(jobs);
+        self
+    }
+
     /// Get the posts output directory.
     pub fn posts_dir(&self) -> PathBuf {
         self.public_dir.join("posts")
@@ -74,6 +657,251 @@ impl Config {
     pub fn images_dir(&self) -> PathBuf {
         self.public_dir.join("images")
     }
+
+    /// Size limits of optimized images.
+    pub fn image_bounds(&self) -> ImageBounds {
+        ImageBounds {
+            max_width: self.max_image_width,
+            max_height: self.max_image_height,
+            fit: self.image_fit,
+        }
+    }
+
+    /// Get the audio output directory.
+    pub fn audio_dir(&self) -> PathBuf {
+        self.public_dir.join("audio")
+    }
+
+    /// Get the timezone dates are shown in.
+    pub fn site_timezone(&self) -> Result<SiteTimezone, BuildError> {
+        if let Some(zone) = self.timezone {
+            return Ok(SiteTimezone::Zone(zone));
+        }
+        FixedOffset::east_opt(self.timezone_offset_hours * 3600)
+            .map(SiteTimezone::Offset)
+            .ok_or_else(|| BuildError::Internal("Invalid timezone offset".to_string()))
+    }
+
+    /// Language tag of pages and feeds: `lang`, else the locale's
+    /// (`de_DE` → `de-DE`).
+    pub fn html_lang(&self) -> String {
+        self.lang.clone().unwrap_or_else(|| format!("{:?}", self.locale).replace('_', "-"))
+    }
+
+    /// Get the UI strings for the locale, with overrides applied.
+    pub fn ui_strings(&self) -> Strings {
+        Strings::for_locale(self.locale).with_overrides(&self.strings)
+    }
+
+    /// Format a date for display, in the configured locale.
+    pub fn format_date(&self, date: &DateTime<FixedOffset>) -> String {
+        format_or_default(date, &self.date_format, DEFAULT_DATE_FORMAT, self.locale)
+    }
+
+    /// Format a date for metadata (never localized).
+    pub fn format_iso_date(&self, date: &DateTime<FixedOffset>) -> String {
+        format_or_default(date, &self.iso_date_format, DEFAULT_ISO_DATE_FORMAT, Locale::POSIX)
+    }
+
+    /// Whether builds write a feed (enabled and `base_url` set).
+    pub fn feed_enabled(&self) -> bool {
+        self.feed.enabled && self.base_url.is_some()
+    }
+
+    /// Config for building one language: its content and output
+    /// subdirectories, locale, brand name, description and strings.
+    pub fn for_language(&self, language: &Language) -> Config {
+        let mut config = self.clone();
+        config.content_dir = self.content_dir.join(&language.code);
+        config.public_dir = self.public_dir.join(&language.code);
+        config.gemini_dir = self.gemini_dir.as_ref().map(|dir| dir.join(&language.code));
+        config.locale = language.locale.unwrap_or(self.locale);
+        config.lang = Some(language.code.clone());
+        config.brand_name = language.brand_name.clone().unwrap_or_else(|| self.brand_name.clone());
+        config.description = language.description.clone().or_else(|| self.description.clone());
+        config.strings = language.strings.clone().unwrap_or_else(|| self.strings.clone());
+        config.languages = Vec::new();
+        config
+    }
+
+    /// Output directories fully owned by the generator (safe to prune).
+    pub fn owned_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![self.posts_dir(), self.tags_dir(), self.images_dir(), self.audio_dir()];
+        dirs.extend(self.gemini_dir.clone());
+        for language in &self.languages {
+            dirs.extend(self.for_language(language).owned_dirs());
+        }
+        dirs
+    }
+}
+
+/// Format `date`, falling back to `default` if `format` is invalid (only
+/// possible through the builder; `Config::load` rejects bad formats).
+fn format_or_default(date: &DateTime<FixedOffset>, format: &str, default: &str, locale: Locale) -> String {
+    let mut out = String::new();
+    if write!(out, "{}", date.format_localized(format, locale)).is_err() {
+        out = date.format_localized(default, locale).to_string();
+    }
+    out
+}
+
+/// Verbatim HTML in the config file, or a file (relative to it) holding
+/// it: `head_html = { file = "analytics.html" }`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Snippet {
+    Html(String),
+    File { file: PathBuf },
+}
+
+impl Snippet {
+    fn read(self, root: &Path) -> Result<String, String> {
+        match self {
+            Self::Html(html) => Ok(html),
+            Self::File { file } => fs::read_to_string(root.join(&file))
+                .map_err(|e| format!("cannot read {}: {e}", file.display())),
+        }
+    }
+}
+
+/// On-disk form of `Config`: every key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    content_dir: Option<PathBuf>,
+    public_dir: Option<PathBuf>,
+    static_dir: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    templates_dir: Option<PathBuf>,
+    gemini_dir: Option<PathBuf>,
+    max_image_width: Option<u32>,
+    max_image_height: Option<u32>,
+    icon: Option<PathBuf>,
+    image_fit: Option<ImageFit>,
+    timezone_offset_hours: Option<i32>,
+    timezone: Option<Tz>,
+    date_format: Option<String>,
+    iso_date_format: Option<String>,
+    #[serde(deserialize_with = "i18n::deserialize_locale")]
+    locale: Option<Locale>,
+    lang: Option<String>,
+    strings: Option<StringOverrides>,
+    languages: Option<Vec<Language>>,
+    brand_name: Option<String>,
+    description: Option<String>,
+    inline_css: Option<bool>,
+    prune: Option<bool>,
+    strict: Option<bool>,
+    suppress: Option<Vec<Suppression>>,
+    threads: Option<usize>,
+    image_jobs: Option<usize>,
+    deploy: Option<DeployConfig>,
+    headers: Option<HeaderRules>,
+    cache_headers: Option<bool>,
+    base_url: Option<AbsoluteUrl>,
+    robots: Option<RobotsConfig>,
+    feed: Option<FeedConfig>,
+    search: Option<SearchConfig>,
+    purge: Option<PurgeConfig>,
+    fonts: Option<FontsConfig>,
+    external_links: Option<ExternalLinks>,
+    smart_punctuation: Option<bool>,
+    demote_headings: Option<bool>,
+    strip_title: Option<bool>,
+    show_author: Option<bool>,
+    reading_time: Option<bool>,
+    lightbox: Option<Lightbox>,
+    resources: Option<ResourcesConfig>,
+    indieweb: Option<IndieWebConfig>,
+    comments: Option<CommentsConfig>,
+    /// Read by `Config::load`, which can fail on a missing file.
+    head_html: Option<Snippet>,
+    body_end_html: Option<Snippet>,
+    pwa: Option<bool>,
+    print_css: Option<bool>,
+    a11y_lint: Option<bool>,
+    require_alt_text: Option<bool>,
+    post_source: Option<PostSource>,
+    diagrams: Option<DiagramConfig>,
+    git_dates: Option<bool>,
+    sort_order: Option<SortOrder>,
+    index_posts: Option<usize>,
+    related_tags: Option<usize>,
+    nav_tags: Option<usize>,
+    collapse_nav_tags: Option<bool>,
+}
+
+impl ConfigFile {
+    /// Overlay the keys that are set onto `config`.
+    fn apply(self, mut config: Config, root: &Path) -> Config {
+        if let Some(dir) = self.content_dir {
+            config.content_dir = root.join(dir);
+        }
+        if let Some(dir) = self.public_dir {
+            config.public_dir = root.join(dir);
+        }
+        if let Some(dir) = self.static_dir {
+            config.static_dir = root.join(dir);
+        }
+        // Added after sites had config files: default to `data/` next to it
+        config.data_dir = root.join(self.data_dir.unwrap_or_else(|| PathBuf::from("data")));
+        config.templates_dir = root.join(self.templates_dir.unwrap_or_else(|| PathBuf::from("templates")));
+        config.gemini_dir = self.gemini_dir.map(|dir| root.join(dir)).or(config.gemini_dir);
+        config.max_image_width = self.max_image_width.unwrap_or(config.max_image_width);
+        config.max_image_height = self.max_image_height.or(config.max_image_height);
+        config.icon = self.icon.map(|path| root.join(path)).or(config.icon);
+        config.image_fit = self.image_fit.unwrap_or(config.image_fit);
+        config.timezone_offset_hours = self.timezone_offset_hours.unwrap_or(config.timezone_offset_hours);
+        config.timezone = self.timezone.or(config.timezone);
+        config.date_format = self.date_format.unwrap_or(config.date_format);
+        config.iso_date_format = self.iso_date_format.unwrap_or(config.iso_date_format);
+        config.locale = self.locale.unwrap_or(config.locale);
+        config.lang = self.lang.or(config.lang);
+        config.strings = self.strings.unwrap_or(config.strings);
+        config.languages = self.languages.unwrap_or(config.languages);
+        config.brand_name = self.brand_name.unwrap_or(config.brand_name);
+        config.description = self.description.or(config.description);
+        config.inline_css = self.inline_css.unwrap_or(config.inline_css);
+        config.prune = self.prune.unwrap_or(config.prune);
+        config.strict = self.strict.unwrap_or(config.strict);
+        if let Some(rules) = self.suppress {
+            config.suppress = rules.into_iter().map(|rule| rule.relative_to(root)).collect();
+        }
+        config.threads = self.threads.or(config.threads);
+        config.image_jobs = self.image_jobs.or(config.image_jobs);
+        config.deploy = self.deploy.map(|d| d.relative_to(root)).or(config.deploy);
+        config.headers = self.headers.unwrap_or(config.headers);
+        config.cache_headers = self.cache_headers.unwrap_or(config.cache_headers);
+        config.base_url = self.base_url.or(config.base_url);
+        config.robots = self.robots.unwrap_or(config.robots);
+        config.feed = self.feed.unwrap_or(config.feed);
+        config.search = self.search.unwrap_or(config.search);
+        config.purge = self.purge.unwrap_or(config.purge);
+        config.fonts = self.fonts.unwrap_or(config.fonts);
+        config.external_links = self.external_links.unwrap_or(config.external_links);
+        config.smart_punctuation = self.smart_punctuation.unwrap_or(config.smart_punctuation);
+        config.demote_headings = self.demote_headings.unwrap_or(config.demote_headings);
+        config.strip_title = self.strip_title.unwrap_or(config.strip_title);
+        config.show_author = self.show_author.unwrap_or(config.show_author);
+        config.reading_time = self.reading_time.unwrap_or(config.reading_time);
+        config.lightbox = self.lightbox.unwrap_or(config.lightbox);
+        config.resources = self.resources.unwrap_or(config.resources);
+        config.indieweb = self.indieweb.unwrap_or(config.indieweb);
+        config.comments = self.comments.or(config.comments);
+        config.pwa = self.pwa.unwrap_or(config.pwa);
+        config.print_css = self.print_css.unwrap_or(config.print_css);
+        config.a11y_lint = self.a11y_lint.unwrap_or(config.a11y_lint);
+        config.require_alt_text = self.require_alt_text.unwrap_or(config.require_alt_text);
+        config.post_source = self.post_source.or(config.post_source);
+        config.diagrams = self.diagrams.unwrap_or(config.diagrams);
+        config.git_dates = self.git_dates.unwrap_or(config.git_dates);
+        config.sort_order = self.sort_order.unwrap_or(config.sort_order);
+        config.index_posts = self.index_posts.or(config.index_posts);
+        config.related_tags = self.related_tags.or(config.related_tags);
+        config.nav_tags = self.nav_tags.or(config.nav_tags);
+        config.collapse_nav_tags = self.collapse_nav_tags.unwrap_or(config.collapse_nav_tags);
+        config
+    }
 }
 
 impl Default for Config {
@@ -81,10 +909,64 @@ impl Default for Config {
         Self {
             content_dir: PathBuf::from("../content"),
             public_dir: PathBuf::from("../public"),
+            static_dir: PathBuf::from("../static"),
+            data_dir: PathBuf::from("../data"),
+            templates_dir: PathBuf::from("../templates"),
+            gemini_dir: None,
             max_image_width: 1200,
+            max_image_height: None,
+            icon: None,
+            image_fit: ImageFit::Contain,
             timezone_offset_hours: 8, // GMT+8
+            timezone: None,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            iso_date_format: DEFAULT_ISO_DATE_FORMAT.to_string(),
+            locale: Locale::en_US,
+            lang: None,
+            strings: StringOverrides::default(),
+            languages: Vec::new(),
             brand_name: String::from("CODE A DUCK"),
+            description: None,
             inline_css: true, // Eliminate render-blocking CSS
+            prune: true,
+            strict: false,
+            suppress: Vec::new(),
+            threads: None,
+            image_jobs: None,
+            deploy: None,
+            headers: HeaderRules::new(),
+            cache_headers: false,
+            base_url: None,
+            robots: RobotsConfig::default(),
+            feed: FeedConfig::default(),
+            search: SearchConfig::default(),
+            purge: PurgeConfig::default(),
+            fonts: FontsConfig::default(),
+            external_links: ExternalLinks::default(),
+            smart_punctuation: false,
+            demote_headings: false,
+            strip_title: false,
+            show_author: false,
+            reading_time: false,
+            lightbox: Lightbox::None,
+            resources: ResourcesConfig::default(),
+            indieweb: IndieWebConfig::default(),
+            comments: None,
+            head_html: None,
+            body_end_html: None,
+            pwa: false,
+            print_css: true,
+            a11y_lint: false,
+            require_alt_text: false,
+            post_source: None,
+            diagrams: DiagramConfig::default(),
+            git_dates: true,
+            source_date_epoch: None,
+            sort_order: SortOrder::Desc,
+            index_posts: None,
+            related_tags: None,
+            nav_tags: None,
+            collapse_nav_tags: false,
         }
     }
 }
@@ -92,17 +974,118 @@ impl Default for Config {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::feed::FeedContent;
 
     #[test]
     fn builder_pattern() {
         let config = Config::new()
             .content_dir("./src")
             .max_image_width(800)
-            .brand_name("My Blog");
+            .brand_name("My Blog")
+            .threads(2);
         
         assert_eq!(config.content_dir, PathBuf::from("./src"));
         assert_eq!(config.max_image_width, 800);
         assert_eq!(config.brand_name, "My Blog");
+        assert_eq!(config.threads, Some(2));
+    }
+
+    #[test]
+    fn load_resolves_paths_against_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(
+            &path,
+            "content_dir = \"posts\"\nbrand_name = \"Quack\"\nthreads = 2\ntimezone = \"Europe/Berlin\"\nlocale = \"de_DE\"\n\n[strings]\nindex = \"Start\"\n\n[feed]\ncontent = \"summary\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.content_dir, dir.path().join("posts"));
+        assert_eq!(config.brand_name, "Quack");
+        assert_eq!(config.threads, Some(2));
+        assert_eq!(config.max_image_width, 1200);
+        assert_eq!(config.site_timezone().unwrap(), SiteTimezone::Zone(chrono_tz::Europe::Berlin));
+        assert_eq!(config.ui_strings().index, "Start");
+        assert_eq!(config.ui_strings().filter, "Filter");
+        assert_eq!(config.feed.content, FeedContent::Summary);
+        assert_eq!(config.feed.max_items, 20);
+    }
+
+    #[test]
+    fn load_rejects_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(&path, "brand = \"typo\"\n").unwrap();
+
+        let err = Config::load(&path).unwrap_err();
+        assert_eq!(err.category(), "config_invalid");
+
+        fs::write(&path, "timezone = \"Mars/Olympus\"\n").unwrap();
+        assert_eq!(Config::load(&path).unwrap_err().category(), "config_invalid");
+
+        fs::write(&path, "locale = \"xx_XX\"\n").unwrap();
+        assert_eq!(Config::load(&path).unwrap_err().category(), "config_invalid");
+
+        fs::write(&path, "[[languages]]\ncode = \"en\"\n[[languages]]\ncode = \"en\"\n").unwrap();
+        assert_eq!(Config::load(&path).unwrap_err().category(), "config_invalid");
+
+        fs::write(&path, "date_format = \"%Y %Q\"\n").unwrap();
+        assert_eq!(Config::load(&path).unwrap_err().category(), "config_invalid");
+    }
+
+    #[test]
+    fn load_reads_html_snippets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(dir.path().join("analytics.html"), "<script src=\"/a.js\"></script>").unwrap();
+        fs::write(&path, "head_html = \"<meta name=x>\"\nbody_end_html = { file = \"analytics.html\" }\n").unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.head_html.as_deref(), Some("<meta name=x>"));
+        assert_eq!(config.body_end_html.as_deref(), Some("<script src=\"/a.js\"></script>"));
+
+        fs::write(&path, "head_html = { file = \"missing.html\" }\n").unwrap();
+        assert_eq!(Config::load(&path).unwrap_err().category(), "config_invalid");
+    }
+
+    #[test]
+    fn load_resolves_suppression_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(&path, "[[suppress]]\ncategory = \"image_not_found\"\npath = \"content/legacy\"\n").unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.suppress, [Suppression::new("image_not_found").below(dir.path().join("content/legacy"))]);
+
+        fs::write(&path, "[[suppress]]\ncategory = \"image_not_found\"\ndirectory = \"x\"\n").unwrap();
+        assert_eq!(Config::load(&path).unwrap_err().category(), "config_invalid");
+    }
+
+    #[test]
+    fn date_formats() {
+        let date = DateTime::parse_from_rfc3339("2024-05-04T09:30:00+08:00").unwrap();
+        let config = Config::new();
+        assert_eq!(config.format_date(&date), "2024.05.04 09:30");
+        assert_eq!(config.format_iso_date(&date), "2024-05-04T09:30:00+08:00");
+
+        let config = config.date_format("%d %B %Y").iso_date_format("%Q");
+        assert_eq!(config.format_date(&date), "04 May 2024");
+        assert_eq!(config.format_iso_date(&date), "2024-05-04T09:30:00+08:00");
+
+        let config = config.locale(Locale::de_DE);
+        assert_eq!(config.format_date(&date), "04 Mai 2024");
+        assert_eq!(config.format_iso_date(&date), "2024-05-04T09:30:00+08:00");
+    }
+
+    #[test]
+    fn sort_order_by_date_then_title() {
+        let early = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap();
+        let late = DateTime::parse_from_rfc3339("2024-02-01T00:00:00Z").unwrap();
+        assert_eq!(SortOrder::Desc.compare((&late, "B"), (&early, "A")), Ordering::Less);
+        assert_eq!(SortOrder::Asc.compare((&late, "B"), (&early, "A")), Ordering::Greater);
+        assert_eq!(SortOrder::Desc.compare((&late, "A"), (&late, "B")), Ordering::Less);
+        assert_eq!(SortOrder::Asc.compare((&late, "A"), (&late, "B")), Ordering::Less);
     }
 
     #[test]
@@ -110,5 +1093,21 @@ mod tests {
         let config = Config::new().public_dir("./out");
         assert_eq!(config.posts_dir(), PathBuf::from("./out/posts"));
         assert_eq!(config.images_dir(), PathBuf::from("./out/images"));
+
+        let config = config.content_dir("./in").language(Language::new("de", Locale::de_DE));
+        let german = config.for_language(&config.languages[0]);
+        assert_eq!(german.content_dir, PathBuf::from("./in/de"));
+        assert_eq!(german.posts_dir(), PathBuf::from("./out/de/posts"));
+        assert_eq!(german.ui_strings().index, "Übersicht");
+        assert!(config.owned_dirs().contains(&PathBuf::from("./out/de/tags")));
+    }
+
+    #[test]
+    fn html_lang_from_locale_or_language() {
+        assert_eq!(Config::new().html_lang(), "en-US");
+        assert_eq!(Config::new().locale(Locale::pt_BR).html_lang(), "pt-BR");
+        let config = Config::new().lang("en").language(Language::new("de", Locale::de_DE));
+        assert_eq!(config.html_lang(), "en");
+        assert_eq!(config.for_language(&config.languages[0]).html_lang(), "de");
     }
 }
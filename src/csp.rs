@@ -0,0 +1,324 @@
+//! Content-Security-Policy meta tags.
+//!
+//! With `csp = true` in `[resources]`, every page gets a policy that allows
+//! only what its markup references: the site's own origin, the origins of
+//! its scripts, stylesheets, images, media and frames (including the
+//! players behind click-to-load embeds), the font origins of linked
+//! external stylesheets, and SHA-384 hashes of its inline scripts and
+//! styles. What scripts fetch and the frames they open don't show in the
+//! markup: only the origins listed for them ([`ScriptTargets`]) are
+//! allowed. Anything else, such as an injected script, is blocked.
+
+use std::collections::BTreeSet;
+
+use crate::sri::{self, Integrity};
+use crate::types::EscapeHtml;
+
+/// Sources one page loads from, by directive.
+#[derive(Debug, Default)]
+struct Sources {
+    script: BTreeSet<String>,
+    style: BTreeSet<String>,
+    img: BTreeSet<String>,
+    font: BTreeSet<String>,
+    media: BTreeSet<String>,
+    frame: BTreeSet<String>,
+    connect: BTreeSet<String>,
+}
+
+/// What a page's scripts reach without the markup showing it: the URLs
+/// they fetch from (`connect-src`) and open frames from (`frame-src`).
+/// Opt-in: the `[resources]` lists and the comments widget.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptTargets {
+    pub connect: Vec<String>,
+    pub frames: Vec<String>,
+}
+
+/// `html` with a CSP meta tag derived from it, right after the charset.
+pub fn with_meta(html: String, integrity: Option<&Integrity>, targets: &ScriptTargets) -> String {
+    let meta = format!(
+        r#"<meta http-equiv="Content-Security-Policy" content="{}">"#,
+        policy(&html, integrity, targets).escape_html()
+    );
+    let at = html
+        .find("<meta charset")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1))
+        .or_else(|| html.find("<head>").map(|start| start + "<head>".len()));
+    match at {
+        Some(at) => format!("{}\n    {meta}{}", &html[..at], &html[at..]),
+        None => html,
+    }
+}
+
+/// Policy for the page `html`; `integrity` knows what the linked external
+/// stylesheets load.
+pub fn policy(html: &str, integrity: Option<&Integrity>, targets: &ScriptTargets) -> String {
+    let mut sources = Sources::default();
+    scan(html, integrity, false, &mut sources);
+    targets.connect.iter().for_each(|url| add(&mut sources.connect, url));
+    targets.frames.iter().for_each(|url| add(&mut sources.frame, url));
+
+    let mut directives = vec!["default-src 'self'".to_string()];
+    for (name, set) in [
+        ("script-src", &sources.script),
+        ("style-src", &sources.style),
+        ("img-src", &sources.img),
+        ("font-src", &sources.font),
+        ("media-src", &sources.media),
+        ("frame-src", &sources.frame),
+        ("connect-src", &sources.connect),
+    ] {
+        if !set.is_empty() {
+            let list: Vec<&str> = set.iter().map(String::as_str).collect();
+            directives.push(format!("{name} 'self' {}", list.join(" ")));
+        }
+    }
+    directives.push("object-src 'none'".to_string());
+    directives.push("base-uri 'self'".to_string());
+    directives.join("; ")
+}
+
+/// Origins a stylesheet loads from with `url(…)`.
+pub fn css_origins(css: &str) -> Vec<String> {
+    let mut origins: Vec<String> = css
+        .split("url(")
+        .skip(1)
+        .filter_map(|rest| source(rest.trim_start().trim_start_matches(['"', '\''])))
+        .collect();
+    origins.sort();
+    origins.dedup();
+    origins
+}
+
+/// Collect the sources of the tags in `html`; `nested` is a `srcdoc`
+/// document, which inherits the page's policy.
+fn scan(html: &str, integrity: Option<&Integrity>, nested: bool, sources: &mut Sources) {
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let name_len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+        // Closing tags and the doctype
+        if name_len == 0 {
+            continue;
+        }
+        let name = rest[..name_len].to_ascii_lowercase();
+        let (attrs, after) = parse_attributes(&rest[name_len..]);
+        rest = after;
+        let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+        match name.as_str() {
+            "script" => {
+                let (text, after) = raw_text(rest, "</script");
+                rest = after;
+                if let Some(src) = attr("src") {
+                    add(&mut sources.script, src);
+                } else if attr("type").is_none_or(|t| matches!(t, "module" | "text/javascript")) && !text.is_empty() {
+                    sources.script.insert(format!("'{}'", sri::digest(text.as_bytes())));
+                }
+            }
+            "style" => {
+                let (text, after) = raw_text(rest, "</style");
+                rest = after;
+                sources.style.insert(format!("'{}'", sri::digest(text.as_bytes())));
+            }
+            "link" => {
+                let rel = attr("rel").unwrap_or_default();
+                let Some(href) = attr("href") else { continue };
+                if rel == "stylesheet" {
+                    add(&mut sources.style, href);
+                    if let Some(integrity) = integrity {
+                        sources.font.extend(integrity.origins(href).iter().cloned());
+                    }
+                } else if rel == "preload" && attr("as") == Some("image") {
+                    add(&mut sources.img, href);
+                }
+            }
+            "img" => {
+                if let Some(src) = attr("src") {
+                    add(&mut sources.img, src);
+                }
+                add_srcset(&mut sources.img, attr("srcset"));
+            }
+            "source" => {
+                add_srcset(&mut sources.img, attr("srcset"));
+                if let Some(src) = attr("src") {
+                    add(&mut sources.media, src);
+                }
+            }
+            "audio" | "video" => {
+                if let Some(src) = attr("src") {
+                    add(&mut sources.media, src);
+                }
+                if let Some(poster) = attr("poster") {
+                    add(&mut sources.img, poster);
+                }
+            }
+            "iframe" => {
+                if let Some(src) = attr("src") {
+                    add(&mut sources.frame, src);
+                }
+                if let Some(doc) = attr("srcdoc") {
+                    scan(doc, integrity, true, sources);
+                }
+            }
+            // A placeholder link navigates its frame to the player
+            "a" if nested => {
+                if let Some(href) = attr("href") {
+                    add(&mut sources.frame, href);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Attributes of a tag (names lowercased, values decoded) and the input
+/// after its `>`.
+pub(crate) fn parse_attributes(mut s: &str) -> (Vec<(String, String)>, &str) {
+    let mut attrs = Vec::new();
+    loop {
+        s = s.trim_start();
+        if let Some(rest) = s.strip_prefix('>').or_else(|| s.strip_prefix("/>")) {
+            return (attrs, rest);
+        }
+        if s.is_empty() {
+            return (attrs, s);
+        }
+        let name_end = s.find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/')).unwrap_or(s.len());
+        // Stray `/` or `=`
+        if name_end == 0 {
+            s = &s[1..];
+            continue;
+        }
+        let name = s[..name_end].to_ascii_lowercase();
+        s = s[name_end..].trim_start();
+        let mut value = String::new();
+        if let Some(rest) = s.strip_prefix('=') {
+            let rest = rest.trim_start();
+            let (raw, after) = match rest.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let body = &rest[1..];
+                    let end = body.find(quote).unwrap_or(body.len());
+                    (&body[..end], body.get(end + 1..).unwrap_or_default())
+                }
+                _ => {
+                    let end = rest.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(rest.len());
+                    rest.split_at(end)
+                }
+            };
+            value = decode_entities(raw);
+            s = after;
+        }
+        attrs.push((name, value));
+    }
+}
+
+/// Text of a raw-text element up to `end_tag`, and the input after it.
+fn raw_text<'a>(s: &'a str, end_tag: &str) -> (&'a str, &'a str) {
+    match s.find(end_tag) {
+        Some(end) => (&s[..end], &s[end..]),
+        None => (s, ""),
+    }
+}
+
+/// Undo `escape_html` in an attribute value.
+fn decode_entities(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+    value
+        .replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn add(set: &mut BTreeSet<String>, url: &str) {
+    set.extend(source(url));
+}
+
+fn add_srcset(set: &mut BTreeSet<String>, srcset: Option<&str>) {
+    for candidate in srcset.unwrap_or_default().split(',') {
+        if let Some(url) = candidate.split_whitespace().next() {
+            add(set, url);
+        }
+    }
+}
+
+/// CSP source of an external URL (`https://host`), None for same-origin
+/// (relative) URLs.
+fn source(url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.starts_with("data:") {
+        return Some("data:".to_string());
+    }
+    let (scheme, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        ("https://", rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        ("http://", rest)
+    } else if let Some(rest) = url.strip_prefix("//") {
+        ("", rest)
+    } else {
+        return None;
+    };
+    let host = rest.split(['/', '?', '#', ')', '"', '\'']).next().filter(|host| !host.is_empty())?;
+    Some(format!("{scheme}{host}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_lists_referenced_origins_and_inline_hashes() {
+        let html = concat!(
+            r#"<html><head><meta charset="UTF-8"><style>body{}</style>"#,
+            r#"<script type="application/ld+json">{}</script></head><body>"#,
+            r#"<img src="../images/a.webp" srcset="https://cdn.example/a.webp 2x, b.webp 1x">"#,
+            r#"<iframe srcdoc="&lt;style&gt;a{}&lt;/style&gt;&lt;a href=&quot;https://www.youtube-nocookie.com/embed/x&quot;&gt;&lt;img src=&quot;https://img.youtube.com/vi/x.jpg&quot;&gt;&lt;/a&gt;"></iframe>"#,
+            r#"<!-- <script src="https://ignored.example/x.js"></script> -->"#,
+            r#"<script src="https://plausible.io/js/script.js" defer></script></body></html>"#,
+        );
+        let none = ScriptTargets::default();
+        let policy = policy(html, None, &none);
+        assert!(policy.starts_with("default-src 'self'; script-src 'self' https://plausible.io; style-src 'self' 'sha384-"));
+        assert!(policy.contains("img-src 'self' https://cdn.example https://img.youtube.com;"));
+        assert!(policy.contains("frame-src 'self' https://www.youtube-nocookie.com;"));
+        assert!(!policy.contains("connect-src"));
+        assert!(!policy.contains("ignored"));
+        assert_eq!(policy.matches("'sha384-").count(), 2);
+
+        let page = with_meta(html.to_string(), None, &none);
+        assert!(page.starts_with(r#"<html><head><meta charset="UTF-8">"#));
+        assert!(page.contains(r#"<meta http-equiv="Content-Security-Policy" content="default-src &#x27;self&#x27;;"#));
+    }
+
+    #[test]
+    fn script_targets_are_opt_in() {
+        let html = r#"<script src="https://plausible.io/js/script.js" defer></script><script src="https://giscus.app/client.js" async></script>"#;
+        let targets = ScriptTargets {
+            connect: vec!["https://plausible.io/api/event".to_string()],
+            frames: vec!["https://giscus.app".to_string()],
+        };
+        let policy = policy(html, None, &targets);
+        assert!(policy.contains("script-src 'self' https://giscus.app https://plausible.io;"));
+        assert!(policy.contains("frame-src 'self' https://giscus.app;"));
+        assert!(policy.contains("connect-src 'self' https://plausible.io;"));
+    }
+
+    #[test]
+    fn stylesheet_font_origins() {
+        let css = "@font-face { src: url(https://fonts.gstatic.com/s/inter.woff2) format('woff2'); }\n\
+                   .a { background: url('/local.png'); } .b { background: url(\"https://fonts.gstatic.com/x\") }";
+        assert_eq!(css_origins(css), ["https://fonts.gstatic.com"]);
+        assert_eq!(source("//cdn.example/x.js").as_deref(), Some("cdn.example"));
+        assert_eq!(source("posts/a.html"), None);
+    }
+}
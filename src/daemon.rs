@@ -0,0 +1,197 @@
+//! Long-running build daemon over a Unix socket.
+//!
+//! The daemon keeps a `Site` (and its parse cache) warm in memory and
+//! accepts one command per connection. Protocol: the client sends a single
+//! line, the daemon replies with `ok` or `error: <message>` on the first
+//! line followed by an optional body, then closes the connection.
+//!
+//! Commands:
+//! - `build` — build into the configured public directory
+//! - `rebuild <markdown path>` — rebuild after one post changed
+//! - `preview <markdown path>` — reply with the rendered post page
+//! - `export <dir>` — build into another directory
+//! - `status` — report cache size
+//! - `shutdown` — stop the daemon
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+use tracing::{info, warn};
+
+use crate::error::{BuildError, BuildSummary, EXIT_SUCCESS};
+use crate::site::Site;
+
+/// A parsed daemon request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    Build,
+    Rebuild(String),
+    Preview(String),
+    Export(String),
+    Status,
+    Shutdown,
+}
+
+impl Request {
+    /// Parse a single request line.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (cmd, arg) = match line.split_once(' ') {
+            Some((cmd, arg)) => (cmd, arg.trim()),
+            None => (line, ""),
+        };
+
+        match (cmd, arg.is_empty()) {
+            ("build", true) => Ok(Self::Build),
+            ("rebuild", false) => Ok(Self::Rebuild(arg.to_string())),
+            ("preview", false) => Ok(Self::Preview(arg.to_string())),
+            ("export", false) => Ok(Self::Export(arg.to_string())),
+            ("status", true) => Ok(Self::Status),
+            ("shutdown", true) => Ok(Self::Shutdown),
+            _ => Err(format!("unknown command '{line}'")),
+        }
+    }
+}
+
+/// Serve requests on `socket` until a `shutdown` command arrives.
+pub fn serve(mut site: Site, socket: &Path) -> Result<(), BuildError> {
+    remove_stale(socket)?;
+    let listener = UnixListener::bind(socket).map_err(|e| BuildError::SocketFailed {
+        path: socket.to_path_buf(),
+        source: e,
+    })?;
+    let bound = socket_id(socket);
+    info!("Daemon listening on {}", socket.display());
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("Connection failed: {}", e);
+                continue;
+            }
+        };
+
+        match handle(&mut site, stream) {
+            Ok(true) => break,
+            Ok(false) => {}
+            Err(e) => warn!("Client error: {}", e),
+        }
+    }
+
+    // Unless another daemon has replaced it since
+    if bound.is_some() && socket_id(socket) == bound {
+        let _ = fs::remove_file(socket);
+    }
+    info!("Daemon stopped.");
+    Ok(())
+}
+
+/// Remove the socket a crashed daemon left at `socket`, which would make
+/// bind fail. Anything but a socket there is an error, and left alone.
+fn remove_stale(socket: &Path) -> Result<(), BuildError> {
+    let failed = |source| BuildError::SocketFailed { path: socket.to_path_buf(), source };
+    match fs::symlink_metadata(socket) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(socket).map_err(failed),
+        Ok(_) => Err(failed(io::Error::new(io::ErrorKind::AlreadyExists, "not a socket"))),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Device and inode of the socket at `path`; None for anything else.
+fn socket_id(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    metadata.file_type().is_socket().then(|| (metadata.dev(), metadata.ino()))
+}
+
+/// Handle one connection. Returns true when the daemon should stop.
+fn handle(site: &mut Site, stream: UnixStream) -> std::io::Result<bool> {
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let mut stream = stream;
+
+    let request = match Request::parse(&line) {
+        Ok(req) => req,
+        Err(msg) => {
+            writeln!(stream, "error: {msg}")?;
+            return Ok(false);
+        }
+    };
+
+    let strict = site.config().strict;
+    let reply = match &request {
+        Request::Build => site.build().map(|s| summary_line(&s, strict)),
+        Request::Rebuild(path) => site.rebuild_post(Path::new(path)).map(|s| summary_line(&s, strict)),
+        Request::Preview(path) => site.preview(Path::new(path)).map(Ok),
+        Request::Export(dir) => site.build_into(Path::new(dir)).map(|s| summary_line(&s, strict)),
+        Request::Status => Ok(Ok(format!("{} posts cached", site.cached_posts()))),
+        Request::Shutdown => Ok(Ok(String::from("shutting down"))),
+    };
+
+    match reply {
+        Ok(Ok(body)) => writeln!(stream, "ok\n{body}")?,
+        Ok(Err(msg)) => writeln!(stream, "error: {msg}")?,
+        Err(e) => writeln!(stream, "error: {e}")?,
+    }
+
+    Ok(request == Request::Shutdown)
+}
+
+/// One-line build result; skipped posts, and in strict mode any problem,
+/// make it an error reply.
+fn summary_line(summary: &BuildSummary, strict: bool) -> Result<String, String> {
+    let line = format!(
+        "built {} posts, skipped {}, {} warnings",
+        summary.posts_built,
+        summary.posts_skipped,
+        summary.warnings.len(),
+    );
+    if summary.exit_code(strict) == EXIT_SUCCESS {
+        Ok(line)
+    } else if summary.posts_skipped > 0 {
+        Err(line)
+    } else {
+        Err(format!("strict mode: {line}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(Request::parse("build\n"), Ok(Request::Build));
+        assert_eq!(Request::parse("rebuild a.md"), Ok(Request::Rebuild("a.md".to_string())));
+        assert_eq!(Request::parse("preview a.md"), Ok(Request::Preview("a.md".to_string())));
+        assert_eq!(Request::parse("export  /tmp/out "), Ok(Request::Export("/tmp/out".to_string())));
+    }
+
+    #[test]
+    fn only_stale_sockets_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("ssg.toml");
+        fs::write(&config, "brand_name = \"Duck\"\n").unwrap();
+        let err = remove_stale(&config).unwrap_err();
+        assert!(matches!(err, BuildError::SocketFailed { .. }), "{err}");
+        assert!(config.exists());
+
+        let socket = dir.path().join("ssg.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+        assert!(socket_id(&socket).is_some() && socket_id(&config).is_none());
+        remove_stale(&socket).unwrap();
+        assert!(!socket.exists());
+        remove_stale(&socket).unwrap();
+    }
+
+    #[test]
+    fn rejects_missing_or_extra_arguments() {
+        assert!(Request::parse("preview").is_err());
+        assert!(Request::parse("rebuild").is_err());
+        assert!(Request::parse("build now").is_err());
+        assert!(Request::parse("deploy").is_err());
+    }
+}
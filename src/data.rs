@@ -0,0 +1,248 @@
+//! Site data from the `data/` directory.
+//!
+//! Every `.toml`, `.json` and `.yaml` (`.yml`) file in the data directory
+//! is parsed and made available under its file stem, so
+//! `data/projects.toml` becomes `data.projects`. Pages reach the values with a shortcode or inline:
+//!
+//! ```text
+//! {{< data "projects" columns="name,url" >}}
+//! Current release: {{ data.site.release }}
+//! ```
+//!
+//! The shortcode renders an array of tables as a table, any other array
+//! as a list, a table as a definition list and a scalar as a paragraph;
+//! the inline form takes scalars only.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::error::{BuildError, SourcePos};
+use crate::parser::lines_outside_code;
+use crate::shortcode::Shortcode;
+use crate::types::EscapeHtml;
+
+/// Parsed data files keyed by file stem.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SiteData {
+    values: Map<String, Value>,
+}
+
+impl SiteData {
+    /// Parse the data files in `data_dir`. A file that can't be read or
+    /// parsed is left out and reported.
+    pub fn load(data_dir: &Path) -> (Self, Vec<BuildError>) {
+        let mut data = Self::default();
+        let mut errors = Vec::new();
+        let entries = match fs::read_dir(data_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return (data, errors),
+            Err(e) => {
+                errors.push(BuildError::DataInvalid { path: data_dir.to_path_buf(), message: e.to_string() });
+                return (data, errors);
+            }
+        };
+        let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok().map(|e| e.path())).collect();
+        paths.sort();
+        for path in paths {
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            let format = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+            let invalid = |message: String| BuildError::DataInvalid { path: path.clone(), message };
+            if !matches!(format, "toml" | "json" | "yaml" | "yml") {
+                continue;
+            }
+            let parsed = fs::read_to_string(&path).map_err(|e| invalid(e.to_string())).and_then(|text| match format {
+                "toml" => toml::from_str(&text).map_err(|e| invalid(e.message().to_string())),
+                "json" => serde_json::from_str(&text).map_err(|e| invalid(e.to_string())),
+                _ => serde_yaml_ng::from_str(&text).map_err(|e| invalid(e.to_string())),
+            });
+            match parsed {
+                Ok(value) => {
+                    data.values.insert(stem.to_string(), value);
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        (data, errors)
+    }
+
+    /// Value at a dotted `path` (`projects.0.name`); array items are
+    /// addressed by index.
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let mut keys = path.split('.');
+        let mut value = self.values.get(keys.next()?)?;
+        for key in keys {
+            value = match value {
+                Value::Object(map) => map.get(key)?,
+                Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// True without any data files.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// HTML for a `{{< data "path" >}}` shortcode; `columns="a,b"` picks
+    /// and orders the columns of a table.
+    pub fn render_shortcode(&self, code: &Shortcode) -> Result<String, String> {
+        let path = code.arg().ok_or("missing data path")?;
+        let value = self.get(path).ok_or_else(|| format!("no data at {path:?}"))?;
+        let columns: Option<Vec<&str>> =
+            code.named.get("columns").map(|list| list.split(',').map(str::trim).collect());
+        Ok(match value {
+            Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => {
+                render_table(items, columns)
+            }
+            Value::Array(items) => {
+                let list: String = items.iter().map(|item| format!("<li>{}</li>", render_inline(item))).collect();
+                format!(r#"<ul class="data">{list}</ul>"#)
+            }
+            Value::Object(map) => {
+                let list: String = map
+                    .iter()
+                    .map(|(key, item)| format!("<dt>{}</dt><dd>{}</dd>", key.escape_html(), render_inline(item)))
+                    .collect();
+                format!(r#"<dl class="data">{list}</dl>"#)
+            }
+            scalar => format!("<p>{}</p>", render_inline(scalar)),
+        })
+    }
+
+    /// Replace `{{ data.path }}` outside code blocks with the escaped
+    /// scalar at `path`; unknown paths and non-scalars are left in place
+    /// and reported with their position.
+    pub fn substitute(&self, markdown: &str) -> (String, Vec<(SourcePos, String)>) {
+        let mut out = String::with_capacity(markdown.len());
+        let mut errors = Vec::new();
+        for (index, (line, in_code)) in lines_outside_code(markdown).enumerate() {
+            if in_code || !line.contains("{{") {
+                out.push_str(line);
+                continue;
+            }
+            let mut rest = line;
+            while let Some(start) = rest.find("{{") {
+                let Some(len) = rest[start..].find("}}") else { break };
+                let inner = rest[start + 2..start + len].trim();
+                out.push_str(&rest[..start]);
+                let placeholder = &rest[start..start + len + 2];
+                let position = SourcePos::new(index + 1, line[..line.len() - rest.len() + start].chars().count() + 1);
+                rest = &rest[start + len + 2..];
+                let Some(path) = inner.strip_prefix("data.") else {
+                    out.push_str(placeholder);
+                    continue;
+                };
+                match self.get(path) {
+                    Some(value @ (Value::String(_) | Value::Number(_) | Value::Bool(_))) => {
+                        out.push_str(&render_inline(value));
+                    }
+                    Some(_) => {
+                        errors.push((position, format!("{placeholder}: not a single value")));
+                        out.push_str(placeholder);
+                    }
+                    None => {
+                        errors.push((position, format!("{placeholder}: no such data")));
+                        out.push_str(placeholder);
+                    }
+                }
+            }
+            out.push_str(rest);
+        }
+        (out, errors)
+    }
+}
+
+/// Table with a column per key, in order of first appearance unless
+/// `columns` names them.
+fn render_table(rows: &[Value], columns: Option<Vec<&str>>) -> String {
+    let columns = columns.unwrap_or_else(|| {
+        let mut keys: Vec<&str> = Vec::new();
+        for key in rows.iter().filter_map(Value::as_object).flat_map(|row| row.keys()) {
+            if !keys.contains(&key.as_str()) {
+                keys.push(key);
+            }
+        }
+        keys
+    });
+    let head: String = columns.iter().map(|c| format!("<th>{}</th>", c.escape_html())).collect();
+    let mut html = format!(r#"<table class="data"><thead><tr>{head}</tr></thead><tbody>"#);
+    for row in rows {
+        html.push_str("<tr>");
+        for column in &columns {
+            let cell = row.get(column).map(render_inline).unwrap_or_default();
+            html.push_str(&format!("<td>{cell}</td>"));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</tbody></table>");
+    html
+}
+
+/// Escaped text of a value; URLs become links and nested values are
+/// joined with commas.
+fn render_inline(value: &Value) -> String {
+    match value {
+        Value::String(s) if s.starts_with("https://") || s.starts_with("http://") => {
+            let url = s.escape_html();
+            format!(r#"<a href="{url}">{url}</a>"#)
+        }
+        Value::String(s) => s.escape_html().to_string(),
+        Value::Null => String::new(),
+        Value::Array(items) => items.iter().map(render_inline).collect::<Vec<_>>().join(", "),
+        Value::Object(map) => map.values().map(render_inline).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_files_and_renders_values() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("projects.toml"),
+            "[[items]]\nname = \"ssg & co\"\nurl = \"https://duck.example\"\n\n[[items]]\nname = \"quack\"\nstars = 3\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("site.json"), r#"{"release": "1.2", "tags": ["a", "b"]}"#).unwrap();
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+        fs::write(dir.path().join("team.yaml"), "lead:\n  name: Duck\n  since: 2019\nmembers: [Goose, Swan]\n").unwrap();
+        fs::write(dir.path().join("broken.yml"), "a: [1").unwrap();
+        fs::write(dir.path().join("README.md"), "ignored").unwrap();
+
+        let (data, errors) = SiteData::load(dir.path());
+        assert_eq!(errors.len(), 2);
+        assert_eq!(data.get("team.lead.since"), Some(&Value::from(2019)));
+        assert_eq!(data.get("team.members.1"), Some(&Value::from("Swan")));
+        assert!(errors.iter().all(|e| e.category() == "data_invalid"));
+        assert_eq!(data.get("projects.items.1.stars"), Some(&Value::from(3)));
+        assert_eq!(data.get("site.missing"), None);
+
+        let code = Shortcode::parse(r#"{{< data "projects.items" >}}"#).unwrap();
+        assert_eq!(
+            data.render_shortcode(&code).unwrap(),
+            concat!(
+                r#"<table class="data"><thead><tr><th>name</th><th>url</th><th>stars</th></tr></thead><tbody>"#,
+                r#"<tr><td>ssg &amp; co</td><td><a href="https://duck.example">https://duck.example</a></td><td></td></tr>"#,
+                "<tr><td>quack</td><td></td><td>3</td></tr></tbody></table>",
+            )
+        );
+        let code = Shortcode::parse(r#"{{< data "projects.items" columns="stars, name" >}}"#).unwrap();
+        assert!(data.render_shortcode(&code).unwrap().contains("<tr><td></td><td>ssg &amp; co</td></tr>"));
+        let code = Shortcode::parse(r#"{{< data "site.tags" >}}"#).unwrap();
+        assert_eq!(data.render_shortcode(&code).unwrap(), r#"<ul class="data"><li>a</li><li>b</li></ul>"#);
+        let code = Shortcode::parse(r#"{{< data "nope" >}}"#).unwrap();
+        assert_eq!(data.render_shortcode(&code).unwrap_err(), r#"no data at "nope""#);
+
+        let (text, errors) = data.substitute("Release {{ data.site.release }}, {{data.site.tags}}\n```\n{{ data.site.release }}\n```\n");
+        assert_eq!(text, "Release 1.2, {{data.site.tags}}\n```\n{{ data.site.release }}\n```\n");
+        assert_eq!(errors, [(SourcePos::new(1, 34), "{{data.site.tags}}: not a single value".to_string())]);
+    }
+}
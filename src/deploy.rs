@@ -0,0 +1,426 @@
+//! `ssg deploy`: upload the built site.
+//!
+//! Deploys are incremental. The manifest of the last build lists every
+//! output, and `.ssg-deployed` in the public directory records the content
+//! hash of each file at the last successful deploy. Only new or changed
+//! files are uploaded, and files that dropped out of the manifest are
+//! deleted from the target.
+//!
+//! Backends shell out to the usual tools: `rsync` (over ssh), the `aws`
+//! CLI (any S3-compatible endpoint) and `git` (push to a gh-pages branch).
+//! The target is configured in the `[deploy]` table of `ssg.toml`:
+//!
+//! ```toml
+//! [deploy]
+//! target = "rsync"
+//! destination = "me@example.com:/var/www/blog"
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::output::{self, content_hash};
+
+/// Hashes of the files uploaded by the last successful deploy.
+pub const DEPLOY_STATE_FILE: &str = ".ssg-deployed";
+
+/// Where and how to deploy, from the `[deploy]` table.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "target", rename_all = "kebab-case")]
+pub enum DeployConfig {
+    /// `rsync` over ssh to `destination` (`user@host:/path`).
+    Rsync {
+        destination: String,
+        identity_file: Option<PathBuf>,
+        port: Option<u16>,
+    },
+
+    /// Sync to an S3-compatible bucket with the `aws` CLI. Keys fall back
+    /// to the usual AWS environment/profile when not set here.
+    S3 {
+        bucket: String,
+        #[serde(default)]
+        prefix: String,
+        endpoint: Option<String>,
+        region: Option<String>,
+        access_key_id: Option<String>,
+        secret_access_key: Option<String>,
+    },
+
+    /// Commit to a branch of `repository` and push it.
+    GhPages {
+        repository: String,
+        #[serde(default = "default_branch")]
+        branch: String,
+        /// Local checkout of the branch, reused between deploys.
+        #[serde(default = "default_work_dir")]
+        work_dir: PathBuf,
+    },
+}
+
+impl DeployConfig {
+    /// Resolve local paths against the config file's directory.
+    pub(crate) fn relative_to(mut self, root: &Path) -> Self {
+        match self {
+            Self::Rsync { identity_file: Some(ref mut key), .. } => *key = root.join(&*key),
+            Self::GhPages { ref mut work_dir, .. } => *work_dir = root.join(&*work_dir),
+            _ => {}
+        }
+        self
+    }
+}
+
+fn default_branch() -> String {
+    String::from("gh-pages")
+}
+
+fn default_work_dir() -> PathBuf {
+    PathBuf::from(".ssg-gh-pages")
+}
+
+/// Files to upload and delete, relative to the public directory.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DeployPlan {
+    pub upload: Vec<PathBuf>,
+    pub delete: Vec<PathBuf>,
+    pub unchanged: usize,
+    /// Hash of every current output, saved as the new state on success.
+    hashes: BTreeMap<PathBuf, String>,
+}
+
+impl DeployPlan {
+    pub fn is_empty(&self) -> bool {
+        self.upload.is_empty() && self.delete.is_empty()
+    }
+}
+
+/// A deploy target.
+pub trait Backend {
+    /// Short name used in messages.
+    fn name(&self) -> &'static str;
+
+    /// Apply `plan` to the target; paths are relative to `public_dir`.
+    fn deploy(&self, public_dir: &Path, plan: &DeployPlan) -> Result<(), BuildError>;
+}
+
+/// Backend for a `[deploy]` table.
+pub fn backend(config: &DeployConfig) -> Box<dyn Backend + '_> {
+    match config {
+        DeployConfig::Rsync { .. } => Box::new(Rsync(config)),
+        DeployConfig::S3 { .. } => Box::new(S3(config)),
+        DeployConfig::GhPages { .. } => Box::new(GhPages(config)),
+    }
+}
+
+/// Compare the last build's manifest with the last deploy.
+pub fn plan(public_dir: &Path) -> Result<DeployPlan, BuildError> {
+    let config = Config::new().public_dir(public_dir);
+    let outputs = output::read_manifest(&config);
+    if outputs.is_empty() {
+        return Err(BuildError::DeployFailed {
+            target: "deploy",
+            message: format!("no build manifest in {}; run `ssg build` first", public_dir.display()),
+        });
+    }
+
+    let previous = read_state(public_dir);
+    let mut plan = DeployPlan::default();
+    for path in outputs {
+        let Ok(rel) = path.strip_prefix(public_dir) else {
+            continue;
+        };
+        // Generator bookkeeping (sidecars, manifests) stays local
+        if is_internal(rel) {
+            continue;
+        }
+        let Some(hash) = content_hash(&path) else {
+            continue;
+        };
+
+        if previous.get(rel) == Some(&hash) {
+            plan.unchanged += 1;
+        } else {
+            plan.upload.push(rel.to_path_buf());
+        }
+        plan.hashes.insert(rel.to_path_buf(), hash);
+    }
+    plan.delete = previous
+        .into_keys()
+        .filter(|rel| !plan.hashes.contains_key(rel))
+        .collect();
+    Ok(plan)
+}
+
+/// Plan and (unless `dry_run`) apply a deploy, then record the new state.
+pub fn deploy(config: &Config, dry_run: bool) -> Result<DeployPlan, BuildError> {
+    let Some(ref target) = config.deploy else {
+        return Err(BuildError::InvalidArgs("no [deploy] target configured in ssg.toml".to_string()));
+    };
+
+    let plan = plan(&config.public_dir)?;
+    if dry_run || plan.is_empty() {
+        return Ok(plan);
+    }
+
+    let backend = backend(target);
+    info!("Deploying {} files ({} deletions) via {}...", plan.upload.len(), plan.delete.len(), backend.name());
+    backend.deploy(&config.public_dir, &plan)?;
+    write_state(&config.public_dir, &plan)?;
+    Ok(plan)
+}
+
+fn is_internal(rel: &Path) -> bool {
+    rel.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with(".ssg-"))
+}
+
+/// Read `.ssg-deployed` (`<hash>  <path>` per line; empty if missing).
+fn read_state(public_dir: &Path) -> BTreeMap<PathBuf, String> {
+    fs::read_to_string(public_dir.join(DEPLOY_STATE_FILE))
+        .map(|state| {
+            state
+                .lines()
+                .filter_map(|l| l.split_once("  "))
+                .map(|(hash, path)| (PathBuf::from(path), hash.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn write_state(public_dir: &Path, plan: &DeployPlan) -> Result<(), BuildError> {
+    let mut state = String::new();
+    for (path, hash) in &plan.hashes {
+        state.push_str(&format!("{}  {}\n", hash, path.display()));
+    }
+    let path = public_dir.join(DEPLOY_STATE_FILE);
+    fs::write(&path, state).map_err(|e| BuildError::OutputNotWritable { path, source: e })
+}
+
+/// Run a command; a spawn failure or non-zero exit is a deploy failure.
+fn run(target: &'static str, cmd: &mut Command) -> Result<(), BuildError> {
+    // Not `{cmd:?}`: that would include credentials passed via env
+    debug!("Running {:?} {:?}", cmd.get_program(), cmd.get_args().collect::<Vec<_>>());
+    let status = cmd.status().map_err(|e| BuildError::DeployFailed {
+        target,
+        message: format!("failed to run {:?}: {}", cmd.get_program(), e),
+    })?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(BuildError::DeployFailed {
+            target,
+            message: format!("{:?} exited with {}", cmd.get_program(), status),
+        })
+    }
+}
+
+struct Rsync<'a>(&'a DeployConfig);
+
+impl Backend for Rsync<'_> {
+    fn name(&self) -> &'static str {
+        "rsync"
+    }
+
+    fn deploy(&self, public_dir: &Path, plan: &DeployPlan) -> Result<(), BuildError> {
+        let DeployConfig::Rsync { destination, identity_file, port } = self.0 else {
+            unreachable!("rsync backend built from rsync config");
+        };
+
+        // Deleted paths are passed too: --delete-missing-args removes them remotely
+        let mut cmd = Command::new("rsync");
+        cmd.args(["-az", "--files-from=-", "--delete-missing-args"]);
+        if identity_file.is_some() || port.is_some() {
+            let mut ssh = String::from("ssh");
+            if let Some(key) = identity_file {
+                ssh.push_str(&format!(" -i {}", key.display()));
+            }
+            if let Some(port) = port {
+                ssh.push_str(&format!(" -p {port}"));
+            }
+            cmd.args(["-e", &ssh]);
+        }
+        cmd.arg(format!("{}/", public_dir.display())).arg(destination);
+
+        let failed = |message: String| BuildError::DeployFailed { target: "rsync", message };
+        let mut child = cmd.stdin(Stdio::piped()).spawn()
+            .map_err(|e| failed(format!("failed to run rsync: {e}")))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            for path in plan.upload.iter().chain(&plan.delete) {
+                writeln!(stdin, "{}", path.display()).map_err(|e| failed(e.to_string()))?;
+            }
+        }
+        let status = child.wait().map_err(|e| failed(e.to_string()))?;
+        if !status.success() {
+            return Err(failed(format!("rsync exited with {status}")));
+        }
+        Ok(())
+    }
+}
+
+struct S3<'a>(&'a DeployConfig);
+
+impl Backend for S3<'_> {
+    fn name(&self) -> &'static str {
+        "s3"
+    }
+
+    fn deploy(&self, public_dir: &Path, plan: &DeployPlan) -> Result<(), BuildError> {
+        let DeployConfig::S3 { bucket, prefix, endpoint, region, access_key_id, secret_access_key } = self.0 else {
+            unreachable!("s3 backend built from s3 config");
+        };
+
+        let aws = |args: &[&str]| {
+            let mut cmd = Command::new("aws");
+            cmd.args(["s3"]).args(args).arg("--only-show-errors");
+            if let Some(endpoint) = endpoint {
+                cmd.args(["--endpoint-url", endpoint]);
+            }
+            if let Some(region) = region {
+                cmd.args(["--region", region]);
+            }
+            if let (Some(id), Some(secret)) = (access_key_id, secret_access_key) {
+                cmd.env("AWS_ACCESS_KEY_ID", id).env("AWS_SECRET_ACCESS_KEY", secret);
+            }
+            cmd
+        };
+        let url = |rel: &Path| {
+            let prefix = prefix.trim_matches('/');
+            let key = rel.to_string_lossy().replace('\\', "/");
+            if prefix.is_empty() {
+                format!("s3://{bucket}/{key}")
+            } else {
+                format!("s3://{bucket}/{prefix}/{key}")
+            }
+        };
+
+        for rel in &plan.upload {
+            let local = public_dir.join(rel);
+            run("s3", &mut aws(&["cp", &local.to_string_lossy(), &url(rel)]))?;
+        }
+        for rel in &plan.delete {
+            run("s3", &mut aws(&["rm", &url(rel)]))?;
+        }
+        Ok(())
+    }
+}
+
+struct GhPages<'a>(&'a DeployConfig);
+
+impl Backend for GhPages<'_> {
+    fn name(&self) -> &'static str {
+        "gh-pages"
+    }
+
+    fn deploy(&self, public_dir: &Path, plan: &DeployPlan) -> Result<(), BuildError> {
+        let DeployConfig::GhPages { repository, branch, work_dir } = self.0 else {
+            unreachable!("gh-pages backend built from gh-pages config");
+        };
+        let git = |args: &[&str]| {
+            let mut cmd = Command::new("git");
+            cmd.arg("-C").arg(work_dir).args(args);
+            cmd
+        };
+        let failed = |path: &Path, e: std::io::Error| BuildError::DeployFailed {
+            target: "gh-pages",
+            message: format!("{}: {}", path.display(), e),
+        };
+
+        if work_dir.join(".git").exists() {
+            // Best effort: the branch may not exist on the remote yet
+            if run("gh-pages", &mut git(&["fetch", "origin", branch])).is_ok() {
+                run("gh-pages", &mut git(&["reset", "--hard", &format!("origin/{branch}")]))?;
+            }
+        } else {
+            let cloned = run("gh-pages", Command::new("git")
+                .args(["clone", "--branch", branch, "--single-branch", repository])
+                .arg(work_dir));
+            if cloned.is_err() {
+                fs::create_dir_all(work_dir).map_err(|e| failed(work_dir, e))?;
+                run("gh-pages", &mut git(&["init"]))?;
+                run("gh-pages", &mut git(&["checkout", "--orphan", branch]))?;
+                run("gh-pages", &mut git(&["remote", "add", "origin", repository]))?;
+            }
+        }
+
+        for rel in &plan.upload {
+            let dest = work_dir.join(rel);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent).map_err(|e| failed(parent, e))?;
+            }
+            fs::copy(public_dir.join(rel), &dest).map_err(|e| failed(&dest, e))?;
+        }
+        for rel in &plan.delete {
+            let dest = work_dir.join(rel);
+            if dest.exists() {
+                fs::remove_file(&dest).map_err(|e| failed(&dest, e))?;
+            }
+        }
+        // Pages would otherwise run Jekyll and hide files starting with `_`
+        let nojekyll = work_dir.join(".nojekyll");
+        if !nojekyll.exists() {
+            fs::write(&nojekyll, "").map_err(|e| failed(&nojekyll, e))?;
+        }
+
+        run("gh-pages", &mut git(&["add", "-A"]))?;
+        let message = format!("Deploy {} files, delete {}", plan.upload.len(), plan.delete.len());
+        run("gh-pages", &mut git(&["commit", "-q", "-m", &message]))?;
+        run("gh-pages", &mut git(&["push", "origin", branch]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CONFIG_FILE;
+
+    #[test]
+    fn plan_uploads_changes_and_deletes_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let public = dir.path();
+        fs::write(public.join("index.html"), "v1").unwrap();
+        fs::write(public.join("old.html"), "old").unwrap();
+        fs::write(public.join(output::MANIFEST_FILE), "index.html\nold.html\n").unwrap();
+
+        let first = plan(public).unwrap();
+        assert_eq!(first.upload, vec![PathBuf::from("index.html"), PathBuf::from("old.html")]);
+        write_state(public, &first).unwrap();
+
+        fs::write(public.join("index.html"), "v2").unwrap();
+        fs::write(public.join("new.html"), "new").unwrap();
+        fs::write(public.join(output::MANIFEST_FILE), "index.html\nnew.html\n").unwrap();
+
+        let second = plan(public).unwrap();
+        assert_eq!(second.upload, vec![PathBuf::from("index.html"), PathBuf::from("new.html")]);
+        assert_eq!(second.delete, vec![PathBuf::from("old.html")]);
+        assert_eq!(second.unchanged, 0);
+    }
+
+    #[test]
+    fn plan_requires_a_build() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(plan(dir.path()).is_err());
+    }
+
+    #[test]
+    fn deploy_table_in_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(&path, "[deploy]\ntarget = \"gh-pages\"\nrepository = \"git@github.com:me/blog.git\"\n").unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.deploy, Some(DeployConfig::GhPages {
+            repository: "git@github.com:me/blog.git".to_string(),
+            branch: "gh-pages".to_string(),
+            work_dir: dir.path().join(".ssg-gh-pages"),
+        }));
+    }
+}
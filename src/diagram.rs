@@ -0,0 +1,186 @@
+//! Mermaid and Graphviz diagrams in fenced code blocks.
+//!
+//! Blocks tagged `mermaid` or `dot` are piped through `mmdc` (mermaid-cli)
+//! or `dot` at build time and inlined as SVG. Without the tool, or with
+//! `prerender = false`, they are emitted as source in wrapper markup
+//! instead: `<pre class="mermaid">` is picked up by the Mermaid script when
+//! `mermaid_script` is set, Graphviz source stays a styled code block.
+//!
+//! ```toml
+//! [diagrams]
+//! prerender = true
+//! mermaid_script = "https://cdn.jsdelivr.net/npm/mermaid@11/dist/mermaid.min.js"
+//! ```
+
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::BuildError;
+use crate::types::EscapeHtml;
+
+/// Diagram settings (the `[diagrams]` table).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiagramConfig {
+    /// Render to SVG at build time when the tool is installed.
+    pub prerender: bool,
+    /// Script included on pages with client-side Mermaid diagrams.
+    pub mermaid_script: Option<String>,
+}
+
+impl Default for DiagramConfig {
+    fn default() -> Self {
+        Self {
+            prerender: true,
+            mermaid_script: None,
+        }
+    }
+}
+
+/// Diagram language of a fenced block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagramKind {
+    Mermaid,
+    Dot,
+}
+
+impl DiagramKind {
+    /// Kind for a fence info string (`mermaid`, `dot {.wide}`).
+    pub fn from_info(info: &str) -> Option<Self> {
+        match info.split_whitespace().next()? {
+            "mermaid" => Some(Self::Mermaid),
+            "dot" | "graphviz" => Some(Self::Dot),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Mermaid => "mermaid",
+            Self::Dot => "dot",
+        }
+    }
+
+    fn command(self) -> Command {
+        match self {
+            Self::Mermaid => {
+                let mut cmd = Command::new("mmdc");
+                cmd.args(["--quiet", "--input", "-", "--output", "-", "--outputFormat", "svg"]);
+                cmd
+            }
+            Self::Dot => {
+                let mut cmd = Command::new("dot");
+                cmd.arg("-Tsvg");
+                cmd
+            }
+        }
+    }
+}
+
+/// HTML for one diagram block.
+#[derive(Debug)]
+pub struct DiagramHtml {
+    pub html: String,
+    /// Rendering failed; the source is shown instead.
+    pub warning: Option<BuildError>,
+    /// The page needs the Mermaid script.
+    pub needs_script: bool,
+}
+
+/// Render the diagram `source` found in `path`.
+pub fn render(kind: DiagramKind, source: &str, config: &DiagramConfig, path: &Path) -> DiagramHtml {
+    let mut warning = None;
+    if config.prerender {
+        match prerender(kind, source) {
+            Ok(Some(svg)) => {
+                return DiagramHtml {
+                    html: format!(r#"<figure class="diagram diagram-{}">{}</figure>"#, kind.name(), svg),
+                    warning: None,
+                    needs_script: false,
+                };
+            }
+            Ok(None) => {}
+            Err(message) => {
+                warning = Some(BuildError::DiagramFailed {
+                    path: path.to_path_buf(),
+                    message,
+                });
+            }
+        }
+    }
+
+    let escaped = source.escape_html();
+    let html = match kind {
+        DiagramKind::Mermaid => format!(r#"<pre class="mermaid">{escaped}</pre>"#),
+        DiagramKind::Dot => format!(r#"<pre class="diagram diagram-dot"><code class="language-dot">{escaped}</code></pre>"#),
+    };
+    DiagramHtml {
+        html,
+        warning,
+        needs_script: kind == DiagramKind::Mermaid && config.mermaid_script.is_some(),
+    }
+}
+
+/// SVG from the diagram tool; None if it isn't installed.
+fn prerender(kind: DiagramKind, source: &str) -> Result<Option<String>, String> {
+    let mut cmd = kind.command();
+    let child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            debug!("{:?} not installed, emitting {} source", cmd.get_program(), kind.name());
+            return Ok(None);
+        }
+        Err(e) => return Err(format!("failed to run {:?}: {}", cmd.get_program(), e)),
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(source.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "{:?} exited with {}: {}",
+            cmd.get_program(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(Some(strip_prolog(&String::from_utf8_lossy(&output.stdout)).to_string()))
+}
+
+/// The `<svg>` element without the XML declaration and doctype before it.
+fn strip_prolog(svg: &str) -> &str {
+    svg.find("<svg").map_or(svg, |start| svg[start..].trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallback_markup_without_prerender() {
+        assert_eq!(DiagramKind::from_info("mermaid"), Some(DiagramKind::Mermaid));
+        assert_eq!(DiagramKind::from_info("dot {.wide}"), Some(DiagramKind::Dot));
+        assert_eq!(DiagramKind::from_info("rust"), None);
+
+        let config = DiagramConfig {
+            prerender: false,
+            mermaid_script: Some("/mermaid.js".to_string()),
+        };
+        let mermaid = render(DiagramKind::Mermaid, "graph TD; A-->B", &config, Path::new("a.md"));
+        assert_eq!(mermaid.html, r#"<pre class="mermaid">graph TD; A--&gt;B</pre>"#);
+        assert!(mermaid.needs_script && mermaid.warning.is_none());
+        assert!(!render(DiagramKind::Dot, "digraph {}", &config, Path::new("a.md")).needs_script);
+    }
+
+    #[test]
+    fn prolog_is_stripped() {
+        let svg = "<?xml version=\"1.0\"?>\n<!DOCTYPE svg>\n<svg width=\"1\"></svg>\n";
+        assert_eq!(strip_prolog(svg), "<svg width=\"1\"></svg>");
+    }
+}
@@ -0,0 +1,152 @@
+//! YouTube, Vimeo and Mastodon embeds.
+//!
+//! Embeds are click-to-load: the page carries a lazy `<iframe>` whose
+//! `srcdoc` is a local placeholder (the video thumbnail, or a label), and
+//! only following its link loads the third-party player. YouTube uses the
+//! no-cookie domain. Nothing is fetched from the provider at build time.
+
+use crate::shortcode::Shortcode;
+use crate::types::EscapeHtml;
+
+/// HTML for an embed shortcode, or an error for unknown names and
+/// unusable arguments.
+pub fn render(code: &Shortcode) -> Result<String, String> {
+    let arg = || code.arg().ok_or("missing URL or ID");
+    let title = code.named.get("title").map(String::as_str);
+    match code.name.as_str() {
+        "youtube" => {
+            let arg = arg()?;
+            let id = youtube_id(arg).ok_or_else(|| format!("not a YouTube video: {arg}"))?;
+            let thumbnail = format!("https://img.youtube.com/vi/{id}/hqdefault.jpg");
+            Ok(frame(
+                "youtube",
+                &format!("https://www.youtube-nocookie.com/embed/{id}?autoplay=1"),
+                title.unwrap_or("YouTube video"),
+                Some(&thumbnail),
+            ))
+        }
+        "vimeo" => {
+            let arg = arg()?;
+            let id = vimeo_id(arg).ok_or_else(|| format!("not a Vimeo video: {arg}"))?;
+            Ok(frame(
+                "vimeo",
+                &format!("https://player.vimeo.com/video/{id}?autoplay=1&dnt=1"),
+                title.unwrap_or("Vimeo video"),
+                None,
+            ))
+        }
+        "mastodon" => {
+            let arg = arg()?;
+            let url = mastodon_url(arg).ok_or_else(|| format!("not a Mastodon post: {arg}"))?;
+            Ok(frame("mastodon", &format!("{url}/embed"), title.unwrap_or("Mastodon post"), None))
+        }
+        name => Err(format!("unknown shortcode {name:?}")),
+    }
+}
+
+/// Lazy iframe showing a placeholder that links to `player`.
+fn frame(provider: &str, player: &str, title: &str, thumbnail: Option<&str>) -> String {
+    let title = title.escape_html();
+    let player = player.escape_html();
+    let preview = thumbnail.map_or_else(String::new, |src| format!(r#"<img src="{}" alt="">"#, src.escape_html()));
+    let placeholder = format!(
+        "<style>{PLACEHOLDER_CSS}</style><a href=\"{player}\">{preview}<span>&#9654; {title}</span></a>"
+    );
+    format!(
+        r#"<div class="embed embed-{provider}"><iframe title="{title}" loading="lazy" allow="autoplay; fullscreen; picture-in-picture" allowfullscreen srcdoc="{}"></iframe></div>"#,
+        placeholder.escape_html()
+    )
+}
+
+const PLACEHOLDER_CSS: &str = "*{margin:0}html,body,a{height:100%}a{display:flex;align-items:center;justify-content:center;background:#111;color:#fff;font:1.2em sans-serif;text-decoration:none}img{position:absolute;inset:0;width:100%;height:100%;object-fit:cover;opacity:.7}span{position:relative;padding:.5em 1em;background:#000a;border-radius:.3em}";
+
+/// Video ID from a YouTube URL (`watch?v=`, `youtu.be/`, `/embed/`,
+/// `/shorts/`) or a bare ID.
+fn youtube_id(arg: &str) -> Option<&str> {
+    let rest = strip_scheme(arg);
+    let id = if let Some(path) = rest.strip_prefix("youtu.be/") {
+        path
+    } else if let Some(path) = rest.strip_prefix("youtube.com/").or_else(|| rest.strip_prefix("m.youtube.com/")) {
+        if let Some(query) = path.strip_prefix("watch?") {
+            query.split('&').find_map(|pair| pair.strip_prefix("v="))?
+        } else {
+            path.strip_prefix("embed/").or_else(|| path.strip_prefix("shorts/"))?
+        }
+    } else if rest == arg {
+        arg
+    } else {
+        return None;
+    };
+    let id = id.split(['?', '&', '#', '/']).next()?;
+    let valid = id.len() == 11 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then_some(id)
+}
+
+/// Numeric video ID from a `vimeo.com/ID` URL or a bare ID.
+fn vimeo_id(arg: &str) -> Option<&str> {
+    let rest = strip_scheme(arg);
+    let id = match rest.strip_prefix("vimeo.com/") {
+        Some(path) => path.split(['?', '#', '/']).next()?,
+        None if rest == arg => arg,
+        None => return None,
+    };
+    (!id.is_empty() && id.chars().all(|c| c.is_ascii_digit())).then_some(id)
+}
+
+/// A `https://host/@user/123` post URL, without a trailing slash.
+fn mastodon_url(arg: &str) -> Option<&str> {
+    let url = arg.trim_end_matches('/');
+    let (host, path) = url.strip_prefix("https://")?.split_once('/')?;
+    let (user, id) = path.strip_prefix('@')?.split_once('/')?;
+    let valid = !host.is_empty()
+        && !user.is_empty()
+        && !id.is_empty()
+        && id.chars().all(|c| c.is_ascii_digit());
+    valid.then_some(url)
+}
+
+/// `arg` without `http(s)://` and `www.`.
+fn strip_scheme(arg: &str) -> &str {
+    let rest = arg
+        .strip_prefix("https://")
+        .or_else(|| arg.strip_prefix("http://"))
+        .unwrap_or(arg);
+    rest.strip_prefix("www.").unwrap_or(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn video_ids_from_urls() {
+        for url in [
+            "dQw4w9WgXcQ",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42",
+            "https://youtu.be/dQw4w9WgXcQ?t=1",
+            "https://www.youtube.com/embed/dQw4w9WgXcQ",
+            "youtube.com/shorts/dQw4w9WgXcQ",
+        ] {
+            assert_eq!(youtube_id(url), Some("dQw4w9WgXcQ"), "{url}");
+        }
+        assert_eq!(youtube_id("https://example.com/watch?v=dQw4w9WgXcQ"), None);
+        assert_eq!(vimeo_id("https://vimeo.com/76979871"), Some("76979871"));
+        assert_eq!(vimeo_id("vimeo"), None);
+        assert_eq!(mastodon_url("https://mastodon.social/@duck/1099/"), Some("https://mastodon.social/@duck/1099"));
+        assert_eq!(mastodon_url("https://mastodon.social/about"), None);
+    }
+
+    #[test]
+    fn embeds_are_click_to_load() {
+        let code = Shortcode::parse(r#"{{< youtube "https://youtu.be/dQw4w9WgXcQ" title="Tom & Jerry" >}}"#).unwrap();
+        let html = render(&code).unwrap();
+        assert!(html.starts_with(r#"<div class="embed embed-youtube"><iframe title="Tom &amp; Jerry" loading="lazy""#));
+        assert!(html.contains("https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?autoplay=1"));
+        assert!(html.contains("img.youtube.com/vi/dQw4w9WgXcQ/hqdefault.jpg"));
+        assert!(!html.contains(r#" src=""#), "player must not load up front: {html}");
+        assert!(!html.contains('\n'));
+
+        let unknown = Shortcode::parse(r#"{{< tiktok "x" >}}"#).unwrap();
+        assert_eq!(render(&unknown), Err("unknown shortcode \"tiktok\"".to_string()));
+    }
+}
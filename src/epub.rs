@@ -0,0 +1,386 @@
+//! EPUB export of posts (`ssg export epub`).
+//!
+//! Posts are rendered by the markdown renderer in an EPUB profile (no
+//! lightbox, no new-tab links), then converted to XHTML: void elements are
+//! closed, scripts dropped and frames replaced by a link to their source.
+//! The book gets a cover page, a navigation document listing the posts,
+//! and the optimized images the posts embed.
+
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::config::Config;
+use crate::csp::parse_attributes;
+use crate::error::BuildError;
+use crate::lightbox::Lightbox;
+use crate::output::StableHasher;
+use crate::types::EscapeHtml;
+
+/// Elements without content, self-closed in XHTML.
+const VOID_ELEMENTS: &[&str] =
+    &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr"];
+
+/// Stylesheet of the book.
+const STYLE: &str = "body { font-family: serif; line-height: 1.5; }\n\
+                     img { max-width: 100%; height: auto; }\n\
+                     pre { white-space: pre-wrap; font-size: 0.85em; }\n\
+                     figure { margin: 1em 0; }\n\
+                     .cover { text-align: center; margin-top: 20%; }\n";
+
+/// One post of the book.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub stem: String,
+    pub title: String,
+    /// Body HTML, with images relative to the posts directory (`../`).
+    pub html: String,
+}
+
+/// An EPUB 3 book.
+#[derive(Debug, Clone)]
+pub struct Book {
+    pub title: String,
+    pub author: String,
+    /// BCP 47 language tag.
+    pub language: String,
+    pub chapters: Vec<Chapter>,
+    /// Public directory holding the images the chapters reference.
+    pub assets: PathBuf,
+    /// Cover image file.
+    pub cover: Option<PathBuf>,
+    /// `dcterms:modified` date: the site's `SOURCE_DATE_EPOCH`, else now.
+    pub modified: DateTime<Utc>,
+}
+
+/// `config` for rendering book chapters.
+pub fn profile(config: &Config) -> Config {
+    let mut config = config.clone();
+    config.lightbox = Lightbox::None;
+    config.external_links.new_tab = false;
+    config
+}
+
+impl Book {
+    /// Book of `chapters` titled after the site (and `tag`).
+    pub fn new(config: &Config, tag: Option<&str>, chapters: Vec<Chapter>) -> Self {
+        let title = match tag {
+            Some(tag) => format!("{} – {tag}", config.brand_name),
+            None => config.brand_name.clone(),
+        };
+        Self {
+            title,
+            author: config.indieweb.author.clone().unwrap_or_else(|| config.brand_name.clone()),
+            language: format!("{:?}", config.locale).replace('_', "-"),
+            chapters,
+            assets: config.public_dir.clone(),
+            cover: None,
+            modified: config.source_date_epoch.unwrap_or_else(Utc::now),
+        }
+    }
+
+    /// Builder: use `path` as the cover image.
+    pub fn cover(mut self, path: impl AsRef<Path>) -> Self {
+        self.cover = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Write the book to `path`.
+    pub fn write(&self, path: &Path) -> Result<(), BuildError> {
+        let bytes = self.to_bytes()?;
+        fs::write(path, bytes).map_err(|e| BuildError::OutputNotWritable { path: path.to_path_buf(), source: e })
+    }
+
+    /// The EPUB (ZIP) file contents.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BuildError> {
+        let public_dir = &self.assets;
+        let stems: Vec<&str> = self.chapters.iter().map(|c| c.stem.as_str()).collect();
+        let mut files: Vec<(String, Vec<u8>)> = vec![
+            ("mimetype".to_string(), b"application/epub+zip".to_vec()),
+            ("META-INF/container.xml".to_string(), CONTAINER.as_bytes().to_vec()),
+            ("OEBPS/style.css".to_string(), STYLE.as_bytes().to_vec()),
+        ];
+        let mut manifest = vec![
+            r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#.to_string(),
+            r#"<item id="style" href="style.css" media-type="text/css"/>"#.to_string(),
+            r#"<item id="cover" href="cover.xhtml" media-type="application/xhtml+xml"/>"#.to_string(),
+        ];
+        let mut spine = vec![r#"<itemref idref="cover"/>"#.to_string(), r#"<itemref idref="nav"/>"#.to_string()];
+
+        let mut cover_img = String::new();
+        if let Some(cover) = &self.cover {
+            let ext = cover.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+            let media_type = media_type(&ext).ok_or_else(|| BuildError::ImageNotFound { path: cover.clone() })?;
+            let data = fs::read(cover).map_err(|_| BuildError::ImageNotFound { path: cover.clone() })?;
+            let href = format!("cover.{ext}");
+            manifest.push(format!(r#"<item id="cover-image" href="{href}" media-type="{media_type}" properties="cover-image"/>"#));
+            cover_img = format!(r#"<img src="{href}" alt=""/>"#);
+            files.push((format!("OEBPS/{href}"), data));
+        }
+        let cover_body = format!(
+            r#"<section class="cover" epub:type="cover">{cover_img}<h1>{}</h1><p>{}</p></section>"#,
+            self.title.escape_html(),
+            self.author.escape_html()
+        );
+        files.push(("OEBPS/cover.xhtml".to_string(), self.page(&self.title, "", &cover_body).into_bytes()));
+
+        let mut toc = String::new();
+        let mut images: Vec<String> = Vec::new();
+        for (i, chapter) in self.chapters.iter().enumerate() {
+            let (body, refs) = xhtml(&chapter.html, &stems);
+            for image in refs {
+                if !images.contains(&image) && public_dir.join(&image).is_file() {
+                    images.push(image);
+                }
+            }
+            let href = format!("text/{}.xhtml", chapter.stem);
+            manifest.push(format!(r#"<item id="c{i}" href="{href}" media-type="application/xhtml+xml"/>"#));
+            spine.push(format!(r#"<itemref idref="c{i}"/>"#));
+            toc.push_str(&format!(r#"<li><a href="{href}">{}</a></li>"#, chapter.title.escape_html()));
+            let body = format!(r#"<section epub:type="chapter">{body}</section>"#);
+            files.push((format!("OEBPS/{href}"), self.page(&chapter.title, "../", &body).into_bytes()));
+        }
+        for (i, image) in images.iter().enumerate() {
+            let ext = image.rsplit('.').next().unwrap_or_default().to_ascii_lowercase();
+            let media_type = media_type(&ext).unwrap_or("application/octet-stream");
+            manifest.push(format!(r#"<item id="img{i}" href="{}" media-type="{media_type}"/>"#, image.escape_html()));
+            let path = public_dir.join(image);
+            let data = fs::read(&path).map_err(|source| BuildError::ContentNotReadable { path, source })?;
+            files.push((format!("OEBPS/{image}"), data));
+        }
+        let nav = format!(r#"<nav epub:type="toc" id="toc"><h1>{}</h1><ol>{toc}</ol></nav>"#, self.title.escape_html());
+        files.push(("OEBPS/nav.xhtml".to_string(), self.page(&self.title, "", &nav).into_bytes()));
+        files.push(("OEBPS/content.opf".to_string(), self.package(&manifest, &spine).into_bytes()));
+        zip(&files)
+    }
+
+    /// XHTML document with `body`, its stylesheet at `root`.
+    fn page(&self, title: &str, root: &str, body: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+             <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" xml:lang=\"{lang}\" lang=\"{lang}\">\n\
+             <head><meta charset=\"UTF-8\"/><title>{}</title><link rel=\"stylesheet\" type=\"text/css\" href=\"{root}style.css\"/></head>\n\
+             <body>{body}</body>\n</html>\n",
+            title.escape_html(),
+            lang = self.language.escape_html(),
+        )
+    }
+
+    /// `content.opf`, identified by a hash of the title and posts.
+    fn package(&self, manifest: &[String], spine: &[String]) -> String {
+        let mut hasher = StableHasher::default();
+        self.title.hash(&mut hasher);
+        self.chapters.iter().for_each(|c| c.stem.hash(&mut hasher));
+        let cover_meta = if self.cover.is_some() { "\n    <meta name=\"cover\" content=\"cover-image\"/>" } else { "" };
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\n  \
+             <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n    \
+             <dc:identifier id=\"book-id\">urn:ssg:{:016x}</dc:identifier>\n    \
+             <dc:title>{}</dc:title>\n    \
+             <dc:creator>{}</dc:creator>\n    \
+             <dc:language>{}</dc:language>\n    \
+             <meta property=\"dcterms:modified\">{}</meta>{cover_meta}\n  \
+             </metadata>\n  <manifest>\n    {}\n  </manifest>\n  <spine>\n    {}\n  </spine>\n</package>\n",
+            hasher.finish(),
+            self.title.escape_html(),
+            self.author.escape_html(),
+            self.language.escape_html(),
+            self.modified.format("%Y-%m-%dT%H:%M:%SZ"),
+            manifest.join("\n    "),
+            spine.join("\n    "),
+        )
+    }
+}
+
+const CONTAINER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n  \
+<rootfiles><rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/></rootfiles>\n\
+</container>\n";
+
+/// Media type of an image extension EPUB readers support.
+fn media_type(ext: &str) -> Option<&'static str> {
+    Some(match ext {
+        "webp" => "image/webp",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    })
+}
+
+/// XHTML for a chapter body plus the local images it references
+/// (relative to the public directory). Links to posts in `stems` point at
+/// their chapters.
+pub fn xhtml(html: &str, stems: &[&str]) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(html.len());
+    let mut images = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start].replace("&nbsp;", "&#160;"));
+        rest = &rest[start + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if let Some(closing) = rest.strip_prefix('/') {
+            let end = closing.find('>').map_or(closing.len(), |end| end + 1);
+            out.push_str("</");
+            out.push_str(&closing[..end]);
+            rest = &closing[end..];
+            continue;
+        }
+        let name_len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+        if name_len == 0 {
+            // Doctype or a stray `<`
+            match rest.find('>') {
+                Some(end) if rest.starts_with('!') => rest = &rest[end + 1..],
+                _ => out.push_str("&lt;"),
+            }
+            continue;
+        }
+        let name = rest[..name_len].to_ascii_lowercase();
+        let (mut attrs, after) = parse_attributes(&rest[name_len..]);
+        rest = after;
+        let attr = |attrs: &[(String, String)], key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+
+        match name.as_str() {
+            "script" => {
+                rest = rest.find("</script>").map_or("", |end| &rest[end + "</script>".len()..]);
+                continue;
+            }
+            "iframe" => {
+                rest = rest.find("</iframe>").map_or("", |end| &rest[end + "</iframe>".len()..]);
+                if let Some(src) = attr(&attrs, "src") {
+                    out.push_str(&format!(r#"<a href="{0}">{0}</a>"#, src.escape_html()));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        attrs.retain(|(key, _)| !key.starts_with("on"));
+        for (key, value) in attrs.iter_mut() {
+            match key.as_str() {
+                "src" | "poster" => images.extend(local_image(value)),
+                "srcset" => {
+                    images.extend(value.split(',').filter_map(|c| c.split_whitespace().next()).filter_map(local_image));
+                }
+                "href" if name == "a" => {
+                    if let Some(image) = local_image(value) {
+                        images.push(image);
+                    } else if let Some(chapter) = chapter_href(value, stems) {
+                        *value = chapter;
+                    }
+                }
+                _ => {}
+            }
+        }
+        out.push('<');
+        out.push_str(&name);
+        for (key, value) in &attrs {
+            out.push_str(&format!(r#" {key}="{}""#, value.escape_html()));
+        }
+        out.push_str(if VOID_ELEMENTS.contains(&name.as_str()) { "/>" } else { ">" });
+    }
+    out.push_str(&rest.replace("&nbsp;", "&#160;"));
+    (out, images)
+}
+
+/// Public-dir path of an image referenced from a chapter (`../images/a.webp`).
+fn local_image(url: &str) -> Option<String> {
+    let path = url.strip_prefix("../")?;
+    let ext = path.rsplit('.').next()?.to_ascii_lowercase();
+    (media_type(&ext).is_some() && !path.split('/').any(|part| part == "..")).then(|| path.to_string())
+}
+
+/// A ZIP archive of `files` stored uncompressed, as EPUB requires for
+/// `mimetype` (the first entry).
+fn zip(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>, BuildError> {
+    let failed = |e: ZipError| BuildError::Internal(format!("cannot write the EPUB archive: {e}"));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, data) in files {
+        archive.start_file(name.as_str(), options).map_err(failed)?;
+        archive.write_all(data).map_err(|e| failed(e.into()))?;
+    }
+    Ok(archive.finish().map_err(failed)?.into_inner())
+}
+
+/// Chapter of a link to a post page (`other.html` or
+/// `../posts/other.html`, fragment kept).
+fn chapter_href(href: &str, stems: &[&str]) -> Option<String> {
+    let (page, fragment) = href.split_at(href.find('#').unwrap_or(href.len()));
+    let page = page.strip_prefix("../posts/").unwrap_or(page);
+    let stem = page.strip_suffix(".html").filter(|stem| stems.contains(stem))?;
+    Some(format!("{stem}.xhtml{fragment}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn converts_html_to_xhtml() {
+        let html = concat!(
+            r#"<p>a&nbsp;b<br><img src="../images/a.webp" alt="A &amp; B" loading="lazy" onload="x()"></p>"#,
+            r#"<script>alert(1)</script><iframe src="https://www.youtube-nocookie.com/embed/x"></iframe>"#,
+            r#"<a href="other.html">next</a><a href="../posts/other.html#end">end</a><a href="missing.html">gone</a><details open><summary>s</summary></details>"#,
+        );
+        let (xhtml, images) = xhtml(html, &["other"]);
+        assert_eq!(
+            xhtml,
+            concat!(
+                r#"<p>a&#160;b<br/><img src="../images/a.webp" alt="A &amp; B" loading="lazy"/></p>"#,
+                r#"<a href="https://www.youtube-nocookie.com/embed/x">https://www.youtube-nocookie.com/embed/x</a>"#,
+                r#"<a href="other.xhtml">next</a><a href="other.xhtml#end">end</a><a href="missing.html">gone</a><details open=""><summary>s</summary></details>"#,
+            )
+        );
+        assert_eq!(images, ["images/a.webp"]);
+        assert_eq!(local_image("../../etc/a.png"), None);
+    }
+
+    #[test]
+    fn writes_epub_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("images")).unwrap();
+        fs::write(dir.path().join("images/a.webp"), "webp").unwrap();
+        let config = Config::new().brand_name("Duck & Co").public_dir(dir.path());
+        let chapter = Chapter {
+            stem: "hello".to_string(),
+            title: "Hello".to_string(),
+            html: r#"<h1>Hello</h1><img src="../images/a.webp" alt="">"#.to_string(),
+        };
+        let book = Book::new(&config, Some("rust"), vec![chapter]);
+        assert_eq!(book.title, "Duck & Co – rust");
+        let bytes = book.to_bytes().unwrap();
+
+        // `mimetype` comes first, uncompressed
+        assert_eq!(&bytes[..4], b"PK\x03\x04");
+        assert_eq!(&bytes[30..38], b"mimetype");
+        assert_eq!(&bytes[38..58], b"application/epub+zip");
+
+        // Read back, every entry stored
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let read = |archive: &mut zip::ZipArchive<_>, name: &str| {
+            let mut file = archive.by_name(name).unwrap();
+            assert_eq!(file.compression(), CompressionMethod::Stored);
+            let mut text = String::new();
+            file.read_to_string(&mut text).unwrap();
+            text
+        };
+        assert_eq!(read(&mut archive, "OEBPS/images/a.webp"), "webp");
+        let package = read(&mut archive, "OEBPS/content.opf");
+        assert!(package.contains(r#"<item id="img0" href="images/a.webp" media-type="image/webp"/>"#));
+        assert!(package.contains("<dc:title>Duck &amp; Co – rust</dc:title>"));
+        assert!(read(&mut archive, "OEBPS/nav.xhtml").contains(r#"<li><a href="text/hello.xhtml">Hello</a></li>"#));
+    }
+}
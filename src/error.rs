@@ -1,7 +1,15 @@
 //! Error types with semantic recovery strategies.
 
+use std::fmt;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::image::OptimizedImage;
+use crate::report::{ImageStats, PostReport};
+use crate::timings::PhaseTiming;
+use crate::types::Tag;
 
 /// All possible errors during blog generation.
 #[derive(Debug, thiserror::Error)]
@@ -11,17 +19,28 @@ pub enum BuildError {
     // ══════════════════════════════════════════════════════════════════════
     
     /// A single post failed to parse. Skip it, continue others.
-    #[error("Parse failed for {path:?}: {message}")]
+    #[error("Parse failed for {}: {message}", located(path, position))]
     ParseFailed {
         path: PathBuf,
+        position: Option<SourcePos>,
         message: String,
     },
 
-    /// Tag validation failed. Use fallback or skip tag.
-    #[error("Invalid tag '{tag}': {reason}")]
+    /// Tag validation failed. Use fallback or skip tag. The post is known
+    /// once the tag was read from one.
+    #[error("Invalid tag '{tag}'{}: {reason}", in_post(path, position))]
     InvalidTag {
         tag: String,
         reason: &'static str,
+        path: Option<PathBuf>,
+        position: Option<SourcePos>,
+    },
+
+    /// A URL or path failed validation. Leave it out.
+    #[error("Invalid URL '{url}': {reason}")]
+    InvalidUrl {
+        url: String,
+        reason: &'static str,
     },
 
     /// Image optimization failed. Use original image instead.
@@ -32,6 +51,149 @@ pub enum BuildError {
         source: image::ImageError,
     },
 
+    /// A referenced local image does not exist. Keep the original src.
+    #[error("Image not found: {path:?}")]
+    ImageNotFound {
+        path: PathBuf,
+    },
+
+    /// A referenced local media file (audio) does not exist. Skip it.
+    #[error("Media file not found: {path:?}")]
+    MediaNotFound {
+        path: PathBuf,
+    },
+
+    /// `ffmpeg` failed to convert an animated GIF or to extract a video's
+    /// poster. Use the GIF, or no poster, instead.
+    #[error("Video conversion failed for {path:?}: {message}")]
+    VideoFailed {
+        path: PathBuf,
+        message: String,
+    },
+
+    /// `pyftsubset` failed to subset a webfont. Use the whole font instead.
+    #[error("Font subsetting failed for {path:?}: {message}")]
+    FontFailed {
+        path: PathBuf,
+        message: String,
+    },
+
+    /// A diagram tool rejected a diagram. Show its source instead.
+    #[error("Diagram failed in {path:?}: {message}")]
+    DiagramFailed {
+        path: PathBuf,
+        message: String,
+    },
+
+    /// An image URL has a bad processing query. Keep the original URL.
+    #[error("Invalid image options in {path:?}: {message}")]
+    InvalidImageOptions {
+        path: PathBuf,
+        message: String,
+    },
+
+    /// A front matter override of a rendering option has a bad value.
+    /// Keep the site setting for the post.
+    #[error("Invalid {key} override in {path:?}: {message}")]
+    InvalidOverride {
+        path: PathBuf,
+        key: &'static str,
+        message: String,
+    },
+
+    /// A layout file is unusable, or a post names a layout that doesn't
+    /// exist. Use the default layout.
+    #[error("Layout failed in {path:?}: {message}")]
+    InvalidLayout {
+        path: PathBuf,
+        message: String,
+    },
+
+    /// A post from another generator can't be imported as is (`ssg
+    /// import`). Skip the post or the value.
+    #[error("Import failed for {path:?}: {message}")]
+    ImportFailed {
+        path: PathBuf,
+        message: String,
+    },
+
+    /// A shortcode is unknown or has bad arguments. Leave it as text.
+    #[error("Shortcode failed in {}: {message}", located(path, position))]
+    ShortcodeFailed {
+        path: PathBuf,
+        position: Option<SourcePos>,
+        message: String,
+    },
+
+    /// An external resource could not be fetched for hashing. Include it
+    /// without `integrity`.
+    #[error("Integrity hash failed for {url}: {message}")]
+    IntegrityFailed {
+        url: String,
+        message: String,
+    },
+
+    /// A data file could not be read or parsed. Skip what it generates.
+    #[error("Invalid data file {path:?}: {message}")]
+    DataInvalid {
+        path: PathBuf,
+        message: String,
+    },
+
+    /// A relative link points at nothing in the content tree. Keep the link.
+    #[error("Broken link in {path:?}: {target}")]
+    BrokenLink {
+        path: PathBuf,
+        target: String,
+    },
+
+    /// The accessibility lint found a problem. The page is written as is.
+    #[error("Accessibility issue in {}: {message}", located(path, &Some(*position)))]
+    AccessibilityIssue {
+        path: PathBuf,
+        position: SourcePos,
+        message: String,
+    },
+
+    /// An image has no alt text and `require_alt_text` is set. Render it
+    /// without.
+    #[error("Image without alt text in {path:?}: {src}")]
+    MissingAltText {
+        path: PathBuf,
+        src: String,
+    },
+
+    /// A generated page is not well-formed HTML (`ssg check`).
+    #[error("Invalid HTML in {}: {message}", located(path, &Some(*position)))]
+    InvalidHtml {
+        path: PathBuf,
+        position: SourcePos,
+        message: String,
+    },
+
+    /// A post's social preview is incomplete (`ssg check`). The page is
+    /// written as is.
+    #[error("Social preview of {path:?}: {message}")]
+    SocialPreview {
+        path: PathBuf,
+        message: String,
+    },
+
+    /// Two posts claim the same alias. The first one keeps it.
+    #[error("Alias {alias} in {path:?} is already used by another post")]
+    AliasConflict {
+        path: PathBuf,
+        alias: String,
+    },
+
+    /// A stale output could not be removed. Leave it in place.
+    #[error("Failed to remove stale output {path:?}")]
+    PruneFailed {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
     // ══════════════════════════════════════════════════════════════════════
     // NON-RECOVERABLE: Must abort entire build
     // ══════════════════════════════════════════════════════════════════════
@@ -52,6 +214,32 @@ pub enum BuildError {
         source: io::Error,
     },
 
+    /// The config file could not be read or has invalid keys/values.
+    #[error("Invalid config {path:?}: {message}")]
+    ConfigInvalid {
+        path: PathBuf,
+        message: String,
+    },
+
+    /// Daemon socket could not be bound.
+    #[error("Socket not available: {path:?}")]
+    SocketFailed {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// An external deploy tool failed or the deploy could not be planned.
+    #[error("Deploy via {target} failed: {message}")]
+    DeployFailed {
+        target: &'static str,
+        message: String,
+    },
+
+    /// Command-line arguments could not be understood.
+    #[error("Invalid arguments: {0}")]
+    InvalidArgs(String),
+
     /// No valid posts found to build.
     #[error("No valid posts found in {path:?}")]
     NoValidPosts {
@@ -73,21 +261,278 @@ impl BuildError {
             self,
             Self::ParseFailed { .. } 
             | Self::InvalidTag { .. } 
+            | Self::InvalidUrl { .. }
             | Self::ImageOptFailed { .. }
+            | Self::ImageNotFound { .. }
+            | Self::MediaNotFound { .. }
+            | Self::VideoFailed { .. }
+            | Self::FontFailed { .. }
+            | Self::DiagramFailed { .. }
+            | Self::ShortcodeFailed { .. }
+            | Self::InvalidImageOptions { .. }
+            | Self::InvalidOverride { .. }
+            | Self::InvalidLayout { .. }
+            | Self::ImportFailed { .. }
+            | Self::IntegrityFailed { .. }
+            | Self::DataInvalid { .. }
+            | Self::BrokenLink { .. }
+            | Self::AccessibilityIssue { .. }
+            | Self::InvalidHtml { .. }
+            | Self::MissingAltText { .. }
+            | Self::SocialPreview { .. }
+            | Self::AliasConflict { .. }
+            | Self::PruneFailed { .. }
         )
     }
 
+    /// How much this matters: errors skip posts or abort the build,
+    /// warnings leave something missing in the output, infos are noise.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::InvalidTag { reason, .. } if *reason == Tag::EMPTY => Severity::Info,
+            Self::ParseFailed { .. } => Severity::Error,
+            error if error.is_recoverable() => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
     /// Returns true if this indicates a bug in the generator.
     pub fn is_internal(&self) -> bool {
         matches!(self, Self::Internal(_))
     }
+
+    /// Stable machine-readable category (used in build reports).
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::ParseFailed { .. } => "parse_failed",
+            Self::InvalidTag { .. } => "invalid_tag",
+            Self::InvalidUrl { .. } => "invalid_url",
+            Self::ImageOptFailed { .. } => "image_opt_failed",
+            Self::ImageNotFound { .. } => "image_not_found",
+            Self::MediaNotFound { .. } => "media_not_found",
+            Self::VideoFailed { .. } => "video_failed",
+            Self::FontFailed { .. } => "font_failed",
+            Self::DiagramFailed { .. } => "diagram_failed",
+            Self::ShortcodeFailed { .. } => "shortcode_failed",
+            Self::InvalidImageOptions { .. } => "invalid_image_options",
+            Self::InvalidOverride { .. } => "invalid_override",
+            Self::InvalidLayout { .. } => "invalid_layout",
+            Self::ImportFailed { .. } => "import_failed",
+            Self::IntegrityFailed { .. } => "integrity_failed",
+            Self::DataInvalid { .. } => "data_invalid",
+            Self::BrokenLink { .. } => "broken_link",
+            Self::AccessibilityIssue { .. } => "accessibility_issue",
+            Self::InvalidHtml { .. } => "invalid_html",
+            Self::MissingAltText { .. } => "missing_alt_text",
+            Self::SocialPreview { .. } => "social_preview",
+            Self::AliasConflict { .. } => "alias_conflict",
+            Self::PruneFailed { .. } => "prune_failed",
+            Self::ContentNotReadable { .. } => "content_not_readable",
+            Self::OutputNotWritable { .. } => "output_not_writable",
+            Self::ConfigInvalid { .. } => "config_invalid",
+            Self::SocketFailed { .. } => "socket_failed",
+            Self::DeployFailed { .. } => "deploy_failed",
+            Self::InvalidArgs(_) => "invalid_args",
+            Self::NoValidPosts { .. } => "no_valid_posts",
+            Self::Internal(_) => "internal",
+        }
+    }
+
+    /// File the error refers to, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::ParseFailed { path, .. }
+            | Self::ImageOptFailed { path, .. }
+            | Self::ImageNotFound { path }
+            | Self::MediaNotFound { path }
+            | Self::VideoFailed { path, .. }
+            | Self::FontFailed { path, .. }
+            | Self::DiagramFailed { path, .. }
+            | Self::ShortcodeFailed { path, .. }
+            | Self::InvalidImageOptions { path, .. }
+            | Self::InvalidOverride { path, .. }
+            | Self::InvalidLayout { path, .. }
+            | Self::ImportFailed { path, .. }
+            | Self::DataInvalid { path, .. }
+            | Self::BrokenLink { path, .. }
+            | Self::AccessibilityIssue { path, .. }
+            | Self::InvalidHtml { path, .. }
+            | Self::MissingAltText { path, .. }
+            | Self::SocialPreview { path, .. }
+            | Self::AliasConflict { path, .. }
+            | Self::PruneFailed { path, .. }
+            | Self::ContentNotReadable { path, .. }
+            | Self::OutputNotWritable { path, .. }
+            | Self::ConfigInvalid { path, .. }
+            | Self::SocketFailed { path, .. }
+            | Self::NoValidPosts { path } => Some(path),
+            Self::InvalidTag { path, .. } => path.as_deref(),
+            Self::InvalidUrl { .. }
+            | Self::IntegrityFailed { .. }
+            | Self::DeployFailed { .. }
+            | Self::InvalidArgs(_)
+            | Self::Internal(_) => None,
+        }
+    }
+
+    /// Line and column in `path()` the error refers to, if known.
+    pub fn position(&self) -> Option<SourcePos> {
+        match self {
+            Self::ParseFailed { position, .. }
+            | Self::InvalidTag { position, .. }
+            | Self::ShortcodeFailed { position, .. } => *position,
+            Self::AccessibilityIssue { position, .. } | Self::InvalidHtml { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+
+    /// Move the position `lines` lines down, for an error found in text
+    /// that starts on line `lines + 1` of its file (a post body below its
+    /// front matter).
+    pub fn below_lines(mut self, lines: usize) -> Self {
+        match &mut self {
+            Self::ParseFailed { position, .. }
+            | Self::InvalidTag { position, .. }
+            | Self::ShortcodeFailed { position, .. } => {
+                if let Some(position) = position {
+                    position.line += lines;
+                }
+            }
+            Self::AccessibilityIssue { position, .. } | Self::InvalidHtml { position, .. } => position.line += lines,
+            _ => {}
+        }
+        self
+    }
+
+    /// Process exit code when this error aborts the program.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::InvalidArgs(_) => EXIT_USAGE,
+            _ => EXIT_TOTAL_FAILURE,
+        }
+    }
+}
+
+/// How much a problem matters, least first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Problems by severity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SeverityCounts {
+    pub error: usize,
+    pub warning: usize,
+    pub info: usize,
+}
+
+/// A `[[suppress]]` rule: drop warnings and infos of one category,
+/// optionally only for files below a path. Errors are never suppressed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suppression {
+    /// A `BuildError::category`, such as `image_not_found`.
+    pub category: String,
+    /// Only problems in files below this path.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+impl Suppression {
+    pub fn new(category: impl Into<String>) -> Self {
+        Self { category: category.into(), path: None }
+    }
+
+    /// Builder: only suppress problems in files below `path`.
+    pub fn below(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Resolve the path against the config file's directory.
+    pub(crate) fn relative_to(mut self, root: &Path) -> Self {
+        self.path = self.path.map(|path| root.join(path));
+        self
+    }
+
+    pub fn matches(&self, error: &BuildError) -> bool {
+        error.severity() != Severity::Error
+            && error.category() == self.category
+            && self.path.as_ref().is_none_or(|below| error.path().is_some_and(|path| path.starts_with(below)))
+    }
+}
+
+/// 1-based line and column in a source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourcePos {
+    pub line: usize,
+    /// In characters.
+    pub column: usize,
+}
+
+impl SourcePos {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    /// Position of byte `offset` in `text`.
+    pub fn of_offset(text: &str, offset: usize) -> Self {
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self::new(1 + before.matches('\n').count(), 1 + before[line_start..].chars().count())
+    }
+}
+
+impl fmt::Display for SourcePos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// `path:line:column` as editors and terminals link it, else the quoted
+/// path.
+fn located(path: &Path, position: &Option<SourcePos>) -> String {
+    match position {
+        Some(position) => format!("{}:{position}", path.display()),
+        None => format!("{path:?}"),
+    }
+}
+
+/// ` in path:line:column` after a tag, if it came from a post.
+fn in_post(path: &Option<PathBuf>, position: &Option<SourcePos>) -> String {
+    path.as_ref().map(|path| format!(" in {}", located(path, position))).unwrap_or_default()
 }
 
+/// Exit code: build finished (warnings allowed unless strict).
+pub const EXIT_SUCCESS: u8 = 0;
+
+/// Exit code: build aborted or produced nothing.
+pub const EXIT_TOTAL_FAILURE: u8 = 1;
+
+/// Exit code: some posts failed, or (strict mode) raised warnings.
+pub const EXIT_PARTIAL_FAILURE: u8 = 2;
+
+/// Exit code: command-line arguments could not be understood.
+pub const EXIT_USAGE: u8 = 64;
+
 /// Result of a build that may have partial failures.
+///
+/// `failures` are items that could not be built at all; `warnings` are
+/// recoverable problems in items that were still built.
 #[derive(Debug)]
 pub struct BuildResult {
     pub successes: usize,
     pub failures: Vec<BuildError>,
+    pub warnings: Vec<BuildError>,
+    pub posts: Vec<PostReport>,
+    pub images: ImageStats,
+    pub phases: Vec<PhaseTiming>,
+    /// Warnings dropped by `[[suppress]]` rules.
+    pub suppressed: usize,
 }
 
 impl BuildResult {
@@ -95,6 +540,33 @@ impl BuildResult {
         Self {
             successes: 0,
             failures: Vec::new(),
+            warnings: Vec::new(),
+            suppressed: 0,
+            posts: Vec::new(),
+            images: ImageStats::default(),
+            phases: Vec::new(),
+        }
+    }
+
+    /// Record per-post status and timings for the build report.
+    pub fn record_post(&mut self, post: PostReport) {
+        self.posts.push(post);
+    }
+
+    /// Record an optimized image for the savings statistics.
+    pub fn record_image(&mut self, image: &OptimizedImage) {
+        self.images.add(image);
+    }
+
+    /// Record the timing of a finished build phase; repeated phases (one
+    /// per language) add up.
+    pub fn record_phase(&mut self, phase: PhaseTiming) {
+        match self.phases.iter_mut().find(|p| p.name == phase.name) {
+            Some(total) => {
+                total.wall_ms += phase.wall_ms;
+                total.cpu_ms += phase.cpu_ms;
+            }
+            None => self.phases.push(phase),
         }
     }
 
@@ -106,6 +578,17 @@ impl BuildResult {
         self.failures.push(error);
     }
 
+    pub fn record_warning(&mut self, error: BuildError) {
+        self.warnings.push(error);
+    }
+
+    /// Drop the warnings a rule in `rules` matches.
+    pub fn suppress(&mut self, rules: &[Suppression]) {
+        let before = self.warnings.len();
+        self.warnings.retain(|warning| !rules.iter().any(|rule| rule.matches(warning)));
+        self.suppressed += before - self.warnings.len();
+    }
+
     /// Returns Err if no posts succeeded or if any non-recoverable error occurred.
     pub fn finalize(self) -> Result<BuildSummary, BuildError> {
         // Check for non-recoverable errors
@@ -127,7 +610,12 @@ impl BuildResult {
         Ok(BuildSummary {
             posts_built: self.successes,
             posts_skipped: self.failures.len(),
-            warnings: self.failures,
+            failures: self.failures,
+            warnings: self.warnings,
+            posts: self.posts,
+            images: self.images,
+            phases: self.phases,
+            suppressed: self.suppressed,
         })
     }
 }
@@ -143,17 +631,165 @@ impl Default for BuildResult {
 pub struct BuildSummary {
     pub posts_built: usize,
     pub posts_skipped: usize,
+    pub failures: Vec<BuildError>,
     pub warnings: Vec<BuildError>,
+    pub posts: Vec<PostReport>,
+    pub images: ImageStats,
+    pub phases: Vec<PhaseTiming>,
+    /// Warnings dropped by `[[suppress]]` rules.
+    pub suppressed: usize,
 }
 
 impl BuildSummary {
+    /// Returns true if anything failed or raised a warning; infos don't
+    /// count.
+    pub fn has_problems(&self) -> bool {
+        !self.failures.is_empty() || self.warnings.iter().any(|w| w.severity() > Severity::Info)
+    }
+
+    /// Failures and warnings by severity.
+    pub fn counts(&self) -> SeverityCounts {
+        let mut counts = SeverityCounts::default();
+        for problem in self.failures.iter().chain(&self.warnings) {
+            match problem.severity() {
+                Severity::Error => counts.error += 1,
+                Severity::Warning => counts.warning += 1,
+                Severity::Info => counts.info += 1,
+            }
+        }
+        counts
+    }
+
+    /// Exit code for this build. Skipped posts always fail it; warnings
+    /// only in strict mode.
+    pub fn exit_code(&self, strict: bool) -> u8 {
+        if self.posts_skipped > 0 || (strict && self.has_problems()) {
+            EXIT_PARTIAL_FAILURE
+        } else {
+            EXIT_SUCCESS
+        }
+    }
+
     pub fn print_report(&self) {
-        println!("✓ Built {} posts", self.posts_built);
+        tracing::info!("✓ Built {} posts", self.posts_built);
         if self.posts_skipped > 0 {
-            eprintln!("⚠ Skipped {} posts:", self.posts_skipped);
-            for warn in &self.warnings {
-                eprintln!("  - {}", warn);
+            tracing::warn!("Skipped {} posts:", self.posts_skipped);
+            for err in &self.failures {
+                tracing::warn!("  - {}", err);
             }
         }
+        let (warnings, infos): (Vec<_>, Vec<_>) = self.warnings.iter().partition(|w| w.severity() > Severity::Info);
+        if !warnings.is_empty() {
+            tracing::warn!("{} warnings:", warnings.len());
+            for warn in &warnings {
+                tracing::warn!("  - {}", warn);
+            }
+        }
+        if !infos.is_empty() {
+            tracing::info!("{} notes:", infos.len());
+            for info in &infos {
+                tracing::info!("  - {}", info);
+            }
+        }
+        if self.suppressed > 0 {
+            tracing::info!("{} warnings suppressed", self.suppressed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn missing_image() -> BuildError {
+        BuildError::ImageNotFound { path: PathBuf::from("a.png") }
+    }
+
+    #[test]
+    fn warnings_only_fail_in_strict_mode() {
+        let mut result = BuildResult::new();
+        result.record_success();
+        result.record_warning(missing_image());
+
+        let summary = result.finalize().unwrap();
+        assert_eq!(summary.posts_skipped, 0);
+        assert_eq!(summary.exit_code(false), EXIT_SUCCESS);
+        assert_eq!(summary.exit_code(true), EXIT_PARTIAL_FAILURE);
+    }
+
+    #[test]
+    fn failed_posts_fail_the_build_in_any_mode() {
+        let mut result = BuildResult::new();
+        result.record_success();
+        result.record_failure(BuildError::ParseFailed {
+            path: PathBuf::from("a.md"),
+            position: None,
+            message: "bad".to_string(),
+        });
+
+        let summary = result.finalize().unwrap();
+        assert_eq!(summary.posts_skipped, 1);
+        assert_eq!(summary.exit_code(false), EXIT_PARTIAL_FAILURE);
+        assert_eq!(summary.exit_code(true), EXIT_PARTIAL_FAILURE);
+    }
+
+    #[test]
+    fn clean_build_succeeds_in_strict_mode() {
+        let mut result = BuildResult::new();
+        result.record_success();
+        assert_eq!(result.finalize().unwrap().exit_code(true), EXIT_SUCCESS);
+    }
+
+    #[test]
+    fn total_failure_when_nothing_built() {
+        let mut result = BuildResult::new();
+        result.record_failure(BuildError::ParseFailed {
+            path: PathBuf::from("a.md"),
+            position: None,
+            message: "bad".to_string(),
+        });
+
+        let err = result.finalize().unwrap_err();
+        assert!(matches!(err, BuildError::NoValidPosts { .. }));
+        assert_eq!(err.exit_code(), EXIT_TOTAL_FAILURE);
+    }
+
+    #[test]
+    fn infos_are_counted_but_pass_strict_mode() {
+        let mut result = BuildResult::new();
+        result.record_success();
+        result.record_warning(BuildError::InvalidTag {
+            tag: String::new(),
+            reason: Tag::EMPTY,
+            path: Some(PathBuf::from("a.md")),
+            position: None,
+        });
+
+        let summary = result.finalize().unwrap();
+        assert_eq!(summary.counts(), SeverityCounts { error: 0, warning: 0, info: 1 });
+        assert_eq!(summary.exit_code(true), EXIT_SUCCESS);
+    }
+
+    #[test]
+    fn suppression_by_category_and_path() {
+        let legacy = BuildError::ImageNotFound { path: PathBuf::from("content/legacy/a.png") };
+        let rule = Suppression::new("image_not_found").below("content/legacy");
+        assert!(rule.matches(&legacy));
+        assert!(!rule.matches(&missing_image()));
+        assert!(Suppression::new("image_not_found").matches(&missing_image()));
+        assert!(!Suppression::new("parse_failed").matches(&BuildError::ParseFailed {
+            path: PathBuf::from("a.md"),
+            position: None,
+            message: "bad".to_string(),
+        }));
+
+        let mut result = BuildResult::new();
+        result.record_success();
+        result.record_warning(legacy);
+        result.record_warning(missing_image());
+        result.suppress(&[rule]);
+        let summary = result.finalize().unwrap();
+        assert_eq!(summary.suppressed, 1);
+        assert_eq!(summary.counts().warning, 1);
     }
 }
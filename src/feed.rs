@@ -0,0 +1,365 @@
+//! Atom (`atom.xml`) and RSS 2.0 (`rss.xml`) feeds.
+//!
+//! Written next to the index when `Config::base_url` is set, since feed
+//! readers need absolute URLs. Entries carry either the full post HTML or
+//! only its summary (the `[feed]` table), plus the post's audio file as an
+//! enclosure, which makes the RSS feed subscribable in podcast apps:
+//!
+//! ```toml
+//! [feed]
+//! content = "summary"   # or "full"
+//! max_items = 20        # 0 for every post
+//! ```
+//!
+//! Full content is rendered for pages under `posts/`, so its relative
+//! links and image paths (`../images/...`) are resolved against the post
+//! URL before they go into the feed.
+//!
+//! Feed readers tell entries apart by their id (`<id>`, `<guid>`) and show
+//! them again when it or `<updated>` changes. Ids therefore survive
+//! renames: a post's front matter `id:`, else its first alias that was a
+//! permalink of the post, else the permalink. Entries are only updated by
+//! front matter `updated:`, not by every commit touching the post.
+
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+use serde::Deserialize;
+
+use crate::types::{AbsoluteUrl, HtmlSafe};
+
+/// Atom feed file name, relative to the index.
+pub const FEED_FILE: &str = "atom.xml";
+
+/// RSS feed file name, relative to the index.
+pub const RSS_FILE: &str = "rss.xml";
+
+/// What a feed entry contains.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedContent {
+    /// The whole post body, plus its summary.
+    #[default]
+    Full,
+    /// Only the summary.
+    Summary,
+}
+
+/// Feed settings (the `[feed]` table).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeedConfig {
+    /// Write `atom.xml` and `rss.xml` (only happens with a `base_url`).
+    pub enabled: bool,
+    pub content: FeedContent,
+    /// Newest posts included; 0 for all.
+    pub max_items: usize,
+    /// Link the feed from the nav.
+    pub nav_link: bool,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            content: FeedContent::Full,
+            max_items: 20,
+            nav_link: true,
+        }
+    }
+}
+
+/// Feed-level data.
+#[derive(Debug, Clone)]
+pub struct FeedMeta<'a> {
+    pub title: &'a str,
+    pub subtitle: Option<&'a str>,
+    pub base_url: &'a AbsoluteUrl,
+    /// Directory of the feed and its index below the site root (`de/`,
+    /// empty for the root).
+    pub prefix: &'a str,
+    /// Language tag (`Config::html_lang`).
+    pub lang: &'a str,
+}
+
+impl FeedMeta<'_> {
+    fn url(&self, file: &str) -> String {
+        self.base_url.join(format!("{}{file}", self.prefix)).to_string()
+    }
+}
+
+/// A media file attached to an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enclosure {
+    /// Absolute URL.
+    pub url: String,
+    /// Size in bytes (0 if unknown).
+    pub length: u64,
+    pub mime: String,
+}
+
+/// One post in the feed.
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    /// Plain-text title.
+    pub title: String,
+    /// Entry id if not the permalink (`stable_id`).
+    pub id: Option<String>,
+    /// Language tag if not the feed's (`lang:` front matter).
+    pub lang: Option<String>,
+    /// Path relative to the site root (`posts/a.html`).
+    pub path: String,
+    pub published: DateTime<FixedOffset>,
+    /// Last announced update; also dates the feed itself.
+    pub updated: DateTime<FixedOffset>,
+    /// Plain text.
+    pub summary: Option<String>,
+    /// Post body HTML with absolute URLs (full content mode).
+    pub content: Option<String>,
+    pub enclosure: Option<Enclosure>,
+}
+
+/// Id of a post's entries other than its permalink: front matter `id:`
+/// (used as is, so it should be a URI such as `urn:uuid:...`), else the
+/// first alias under the tree's `posts/` (`/de/posts/old-name.html`),
+/// which was the permalink before the post was renamed.
+pub fn stable_id(id: Option<&str>, aliases: &[String], prefix: &str, base_url: &AbsoluteUrl) -> Option<String> {
+    if let Some(id) = id.map(str::trim).filter(|id| !id.is_empty()) {
+        return Some(id.to_string());
+    }
+    let posts = format!("/{prefix}posts/");
+    aliases.iter()
+        .map(|alias| alias.trim())
+        .find(|alias| alias.starts_with(&posts) && alias.ends_with(".html"))
+        .map(|alias| base_url.join(&alias[1..]).to_string())
+}
+
+/// Atom document for `entries`, newest first.
+pub fn render_atom(meta: &FeedMeta<'_>, entries: &[FeedEntry]) -> String {
+    let feed_url = meta.url(FEED_FILE);
+    let index_url = meta.url("index.html");
+    let updated = entries.iter().map(|e| e.updated).max();
+
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\" xml:lang=\"{}\">\n",
+        HtmlSafe::escape(meta.lang)
+    );
+    xml.push_str(&format!("  <title>{}</title>\n", HtmlSafe::escape(meta.title)));
+    if let Some(subtitle) = meta.subtitle {
+        xml.push_str(&format!("  <subtitle>{}</subtitle>\n", HtmlSafe::escape(subtitle)));
+    }
+    xml.push_str(&format!("  <id>{}</id>\n", HtmlSafe::escape(&feed_url)));
+    xml.push_str(&format!("  <link rel=\"self\" href=\"{}\"/>\n", HtmlSafe::escape(&feed_url)));
+    xml.push_str(&format!("  <link rel=\"alternate\" href=\"{}\"/>\n", HtmlSafe::escape(&index_url)));
+    if let Some(updated) = updated {
+        xml.push_str(&format!("  <updated>{}</updated>\n", updated.to_rfc3339_opts(SecondsFormat::Secs, false)));
+    }
+    xml.push_str(&format!("  <author><name>{}</name></author>\n", HtmlSafe::escape(meta.title)));
+
+    for entry in entries {
+        let url = HtmlSafe::escape(&meta.base_url.join(&entry.path));
+        let id = entry.id.as_deref().map_or_else(|| url.clone(), HtmlSafe::escape);
+        match &entry.lang {
+            Some(lang) => xml.push_str(&format!("  <entry xml:lang=\"{}\">\n", HtmlSafe::escape(lang))),
+            None => xml.push_str("  <entry>\n"),
+        }
+        xml.push_str(&format!("    <title>{}</title>\n", HtmlSafe::escape(&entry.title)));
+        xml.push_str(&format!("    <id>{}</id>\n", id));
+        xml.push_str(&format!("    <link rel=\"alternate\" href=\"{}\"/>\n", url));
+        xml.push_str(&format!("    <published>{}</published>\n", entry.published.to_rfc3339_opts(SecondsFormat::Secs, false)));
+        xml.push_str(&format!("    <updated>{}</updated>\n", entry.updated.to_rfc3339_opts(SecondsFormat::Secs, false)));
+        if let Some(summary) = &entry.summary {
+            xml.push_str(&format!("    <summary>{}</summary>\n", HtmlSafe::escape(summary)));
+        }
+        if let Some(content) = &entry.content {
+            xml.push_str(&format!("    <content type=\"html\">{}</content>\n", HtmlSafe::escape(content)));
+        }
+        if let Some(enclosure) = &entry.enclosure {
+            xml.push_str(&format!(
+                "    <link rel=\"enclosure\" type=\"{}\" length=\"{}\" href=\"{}\"/>\n",
+                HtmlSafe::escape(&enclosure.mime), enclosure.length, HtmlSafe::escape(&enclosure.url)
+            ));
+        }
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// RSS 2.0 document for `entries`, newest first. Items carry the full
+/// content, else the summary, as their description.
+pub fn render_rss(meta: &FeedMeta<'_>, entries: &[FeedEntry]) -> String {
+    let feed_url = HtmlSafe::escape(&meta.url(RSS_FILE));
+    let index_url = HtmlSafe::escape(&meta.url("index.html"));
+    let updated = entries.iter().map(|e| e.updated).max();
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n",
+    );
+    xml.push_str(&format!("  <title>{}</title>\n", HtmlSafe::escape(meta.title)));
+    xml.push_str(&format!("  <link>{}</link>\n", index_url));
+    xml.push_str(&format!("  <description>{}</description>\n", HtmlSafe::escape(meta.subtitle.unwrap_or(meta.title))));
+    xml.push_str(&format!("  <language>{}</language>\n", HtmlSafe::escape(meta.lang)));
+    xml.push_str(&format!("  <atom:link rel=\"self\" type=\"application/rss+xml\" href=\"{}\"/>\n", feed_url));
+    if let Some(updated) = updated {
+        xml.push_str(&format!("  <lastBuildDate>{}</lastBuildDate>\n", updated.to_rfc2822()));
+    }
+
+    for entry in entries {
+        let url = HtmlSafe::escape(&meta.base_url.join(&entry.path));
+        xml.push_str("  <item>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", HtmlSafe::escape(&entry.title)));
+        xml.push_str(&format!("    <link>{}</link>\n", url));
+        match &entry.id {
+            Some(id) => xml.push_str(&format!("    <guid isPermaLink=\"false\">{}</guid>\n", HtmlSafe::escape(id))),
+            None => xml.push_str(&format!("    <guid isPermaLink=\"true\">{}</guid>\n", url)),
+        }
+        xml.push_str(&format!("    <pubDate>{}</pubDate>\n", entry.published.to_rfc2822()));
+        if let Some(description) = entry.content.as_ref().or(entry.summary.as_ref()) {
+            xml.push_str(&format!("    <description>{}</description>\n", HtmlSafe::escape(description)));
+        }
+        if let Some(enclosure) = &entry.enclosure {
+            xml.push_str(&format!(
+                "    <enclosure url=\"{}\" length=\"{}\" type=\"{}\"/>\n",
+                HtmlSafe::escape(&enclosure.url), enclosure.length, HtmlSafe::escape(&enclosure.mime)
+            ));
+        }
+        xml.push_str("  </item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// Rewrite relative `src` and `href` attributes in `html` to absolute URLs
+/// as seen from `page_url`.
+pub fn absolutize_urls(html: &str, page_url: &str) -> String {
+    const ATTRIBUTES: [&str; 2] = ["src=\"", "href=\""];
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = ATTRIBUTES.iter().filter_map(|a| rest.find(a).map(|i| i + a.len())).min() {
+        let Some(len) = rest[start..].find('"') else { break };
+        out.push_str(&rest[..start]);
+        out.push_str(&resolve_url(page_url, &rest[start..start + len]));
+        rest = &rest[start + len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `url` resolved against the absolute `page_url`.
+pub fn resolve_url(page_url: &str, url: &str) -> String {
+    let has_scheme = url.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+    });
+    if has_scheme || url.starts_with("//") {
+        return url.to_string();
+    }
+    if url.is_empty() || url.starts_with('#') {
+        return format!("{page_url}{url}");
+    }
+
+    let path_start = page_url.find("://").map_or(0, |i| i + 3);
+    let (origin, path) = page_url.split_at(page_url[path_start..].find('/').map_or(page_url.len(), |i| path_start + i));
+    if url.starts_with('/') {
+        return format!("{origin}{url}");
+    }
+    let path = if path.is_empty() { "/" } else { path };
+
+    let mut segments: Vec<&str> = path.split('/').collect();
+    segments.pop();
+    for segment in url.split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                if segments.len() > 1 {
+                    segments.pop();
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+    if segments.len() == 1 {
+        segments.push("");
+    }
+    format!("{origin}{}", segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_relative_urls_against_post() {
+        let page = "https://duck.dev/blog/posts/a.html";
+        assert_eq!(resolve_url(page, "../images/a.webp"), "https://duck.dev/blog/images/a.webp");
+        assert_eq!(resolve_url(page, "b.html#top"), "https://duck.dev/blog/posts/b.html#top");
+        assert_eq!(resolve_url(page, "/about.html"), "https://duck.dev/about.html");
+        assert_eq!(resolve_url(page, "#note"), "https://duck.dev/blog/posts/a.html#note");
+        assert_eq!(resolve_url(page, "mailto:me@duck.dev"), "mailto:me@duck.dev");
+        assert_eq!(resolve_url(page, "//cdn.dev/x.js"), "//cdn.dev/x.js");
+        assert_eq!(resolve_url("https://duck.dev/posts/a.html", "../../x.png"), "https://duck.dev/x.png");
+
+        let html = r#"<img src="../images/a.webp" alt="a"><a href="https://x.dev/">x</a>"#;
+        assert_eq!(
+            absolutize_urls(html, page),
+            r#"<img src="https://duck.dev/blog/images/a.webp" alt="a"><a href="https://x.dev/">x</a>"#
+        );
+    }
+
+    fn entry() -> FeedEntry {
+        let date = DateTime::parse_from_rfc3339("2024-05-04T09:30:00+08:00").unwrap();
+        FeedEntry {
+            title: "A <post>".to_string(),
+            id: None,
+            lang: Some("de-AT".to_string()),
+            path: "de/posts/a.html".to_string(),
+            published: date,
+            updated: date,
+            summary: Some("Short".to_string()),
+            content: Some("<p>Hi</p>".to_string()),
+            enclosure: Some(Enclosure {
+                url: "https://duck.dev/de/audio/a.mp3".to_string(),
+                length: 1234,
+                mime: "audio/mpeg".to_string(),
+            }),
+        }
+    }
+
+    fn meta(base_url: &AbsoluteUrl) -> FeedMeta<'_> {
+        FeedMeta { title: "Duck & Co", subtitle: None, base_url, prefix: "de/", lang: "de" }
+    }
+
+    #[test]
+    fn atom_escapes_and_dates_entries() {
+        let xml = render_atom(&meta(&AbsoluteUrl::new("https://duck.dev").unwrap()), &[entry()]);
+        assert!(xml.contains("<title>Duck &amp; Co</title>"));
+        assert!(xml.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\" xml:lang=\"de\">"));
+        assert!(xml.contains("<id>https://duck.dev/de/atom.xml</id>"));
+        assert!(xml.contains("  <entry xml:lang=\"de-AT\">"));
+        assert!(xml.contains("  <updated>2024-05-04T09:30:00+08:00</updated>"));
+        assert!(xml.contains("<title>A &lt;post&gt;</title>"));
+        assert!(xml.contains("<content type=\"html\">&lt;p&gt;Hi&lt;/p&gt;</content>"));
+        assert!(xml.contains(r#"<link rel="enclosure" type="audio/mpeg" length="1234" href="https://duck.dev/de/audio/a.mp3"/>"#));
+    }
+
+    #[test]
+    fn stable_ids_prefer_front_matter_then_old_permalinks() {
+        let base_url = AbsoluteUrl::new("https://duck.dev").unwrap();
+        let aliases = vec!["/old".to_string(), "/de/posts/old-name.html".to_string(), "/de/posts/older.html".to_string()];
+        assert_eq!(stable_id(Some("urn:uuid:1234"), &aliases, "de/", &base_url).as_deref(), Some("urn:uuid:1234"));
+        assert_eq!(stable_id(None, &aliases, "de/", &base_url).as_deref(), Some("https://duck.dev/de/posts/old-name.html"));
+        assert_eq!(stable_id(Some(" "), &aliases[..1], "de/", &base_url), None);
+        assert_eq!(stable_id(None, &aliases, "", &base_url), None);
+    }
+
+    #[test]
+    fn rss_items_have_enclosures() {
+        let xml = render_rss(&meta(&AbsoluteUrl::new("https://duck.dev").unwrap()), &[entry()]);
+        assert!(xml.contains("<link>https://duck.dev/de/index.html</link>"));
+        assert!(xml.contains("<description>Duck &amp; Co</description>"));
+        assert!(xml.contains("<language>de</language>"));
+        assert!(xml.contains("<lastBuildDate>Sat, 4 May 2024 09:30:00 +0800</lastBuildDate>"));
+        assert!(xml.contains("<guid isPermaLink=\"true\">https://duck.dev/de/posts/a.html</guid>"));
+        assert!(xml.contains("<description>&lt;p&gt;Hi&lt;/p&gt;</description>"));
+        assert!(xml.contains(r#"<enclosure url="https://duck.dev/de/audio/a.mp3" length="1234" type="audio/mpeg"/>"#));
+    }
+}
@@ -0,0 +1,247 @@
+//! Webfont subsetting and preloading (the `[fonts]` table).
+//!
+//! Webfonts are the `.woff2`, `.woff`, `.ttf` and `.otf` files of
+//! `static/fonts/`, copied to `public/fonts/` with the rest of the static
+//! directory. With `subset`, `pyftsubset` (fonttools, when installed) cuts
+//! each copy down to the characters the site's pages use, or to the
+//! `unicodes` range, keeping its name and format: a CJK font shrinks from
+//! megabytes to the glyphs the site shows. If it fails, the font is
+//! copied whole. Pages of protected posts, decrypted in the browser, show
+//! characters no page has, so a site with protected posts subsets only to
+//! `unicodes`.
+//!
+//! With `preload`, every page preloads the fonts (`<link rel="preload"
+//! as="font">`), only the `.woff2` one of a font in several formats.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::BuildError;
+use crate::output::OutputSet;
+use crate::renderer::Preload;
+use crate::types::{Href, RelPath};
+
+/// Directory of the webfonts, in the static and the public directory.
+pub const FONTS_DIR: &str = "fonts";
+
+/// Webfont settings (the `[fonts]` table).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FontsConfig {
+    /// Cut the fonts down to the characters in use.
+    pub subset: bool,
+    /// Characters to keep instead of those of the pages, as a unicode
+    /// range (`U+0000-00FF, U+4E00-9FFF`).
+    pub unicodes: Option<String>,
+    /// Preload the fonts on every page.
+    pub preload: bool,
+}
+
+impl FontsConfig {
+    /// The subset depends on the text of every page.
+    pub fn follows_pages(&self) -> bool {
+        self.subset && self.unicodes.is_none()
+    }
+}
+
+/// MIME type of a webfont by extension; None for anything else.
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "woff2" => Some("font/woff2"),
+        "woff" => Some("font/woff"),
+        "ttf" => Some("font/ttf"),
+        "otf" => Some("font/otf"),
+        _ => None,
+    }
+}
+
+/// File names of the webfonts in `static_dir/fonts/`, sorted.
+pub fn find(static_dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(static_dir.join(FONTS_DIR)) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file() && mime_type(&e.path()).is_some())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Preloads of the webfonts in `static_dir/fonts/`: the `.woff2` file of
+/// a font that has one, else each of its files.
+pub fn preloads(static_dir: &Path) -> Vec<Preload> {
+    let names = find(static_dir);
+    let has_woff2 = |stem: &str| names.iter().any(|name| name.strip_suffix(".woff2") == Some(stem));
+    names
+        .iter()
+        .filter(|name| {
+            let path = Path::new(name);
+            mime_type(path) == Some("font/woff2") || !path.file_stem().and_then(|s| s.to_str()).is_some_and(has_woff2)
+        })
+        .filter_map(|name| {
+            let href = RelPath::new(&format!("{FONTS_DIR}/{name}")).ok()?;
+            let media_type = mime_type(Path::new(name)).map(str::to_string);
+            Some(Preload { media_type, ..Preload::new(Href::Relative(href), "font") })
+        })
+        .collect()
+}
+
+/// Characters of one or more pages.
+#[derive(Debug, Clone)]
+pub struct UsedChars {
+    chars: BTreeSet<char>,
+}
+
+impl Default for UsedChars {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsedChars {
+    /// Printable ASCII, which scripts and form controls show too.
+    pub fn new() -> Self {
+        Self { chars: (' '..='~').collect() }
+    }
+
+    /// Add the characters of `html`, markup included (only ASCII).
+    pub fn scan(&mut self, html: &str) {
+        self.chars.extend(html.chars().filter(|c| !c.is_control()));
+    }
+
+    /// The characters as `pyftsubset --unicodes`: hex code points and
+    /// ranges of them (`20-7e,e9,4e00-4e01`).
+    pub fn ranges(&self) -> String {
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for c in self.chars.iter().map(|&c| u32::from(c)) {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == c => *end = c,
+                _ => ranges.push((c, c)),
+            }
+        }
+        ranges
+            .iter()
+            .map(|&(start, end)| if start == end { format!("{start:x}") } else { format!("{start:x}-{end:x}") })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Subset the webfonts copied from `static_dir/fonts/` to `unicodes` (a
+/// `pyftsubset --unicodes` list). A font that fails is left whole.
+pub fn subset(static_dir: &Path, public_dir: &Path, unicodes: &str, outputs: &OutputSet) -> Vec<BuildError> {
+    let mut errors = Vec::new();
+    for name in find(static_dir) {
+        let src = static_dir.join(FONTS_DIR).join(&name);
+        let dest = public_dir.join(FONTS_DIR).join(&name);
+        if !outputs.contains(&dest) {
+            continue;
+        }
+        let before = fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+        match run(&src, &dest, unicodes) {
+            Ok(true) => {
+                let after = fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+                debug!("Subset {} from {} to {} bytes", name, before, after);
+            }
+            // Without pyftsubset, no font is subset
+            Ok(false) => break,
+            Err(message) => {
+                let message = restore(&src, &dest, message);
+                errors.push(BuildError::FontFailed { path: src, message });
+            }
+        }
+    }
+    errors
+}
+
+/// Copy `src` whole over the partial font at `dest`, adding to `message`
+/// if that fails too.
+fn restore(src: &Path, dest: &Path, mut message: String) -> String {
+    if let Err(e) = fs::copy(src, dest) {
+        message.push_str(&format!("; restoring {} failed: {e}", dest.display()));
+    }
+    message
+}
+
+/// Run `pyftsubset` on `src`, writing `dest` in the same format. Ok(false)
+/// when it isn't installed.
+fn run(src: &Path, dest: &Path, unicodes: &str) -> Result<bool, String> {
+    let flavor = match mime_type(src) {
+        Some("font/woff2") => Some("woff2"),
+        Some("font/woff") => Some("woff"),
+        _ => None,
+    };
+    let mut cmd = Command::new("pyftsubset");
+    cmd.arg(src)
+        .arg(format!("--unicodes={unicodes}"))
+        .arg(format!("--output-file={}", dest.display()))
+        .arg("--layout-features=*");
+    if let Some(flavor) = flavor {
+        cmd.arg(format!("--flavor={flavor}"));
+    }
+    let output = match cmd.output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            debug!("pyftsubset not installed, skipping {}", src.display());
+            return Ok(false);
+        }
+        Err(e) => return Err(format!("failed to run pyftsubset: {e}")),
+    };
+    if !output.status.success() {
+        return Err(format!("pyftsubset exited with {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn characters_become_code_point_ranges() {
+        let mut used = UsedChars::new();
+        used.scan("<p>Café 中文</p>\n");
+        assert_eq!(used.ranges(), "20-7e,e9,4e2d,6587");
+    }
+
+    #[test]
+    fn failed_restores_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dest) = (dir.path().join("body.woff2"), dir.path().join("out.woff2"));
+        fs::write(&dest, "partial").unwrap();
+        let message = restore(&src, &dest, "pyftsubset exited with 1".to_string());
+        assert!(message.starts_with("pyftsubset exited with 1; restoring "));
+
+        fs::write(&src, "font").unwrap();
+        assert_eq!(restore(&src, &dest, "pyftsubset exited with 1".to_string()), "pyftsubset exited with 1");
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "font");
+    }
+
+    #[test]
+    fn preloads_prefer_woff2() {
+        let dir = tempfile::tempdir().unwrap();
+        let fonts = dir.path().join(FONTS_DIR);
+        fs::create_dir(&fonts).unwrap();
+        for name in ["body.woff", "body.woff2", "mono.ttf", "notes.txt"] {
+            fs::write(fonts.join(name), "").unwrap();
+        }
+        assert_eq!(find(dir.path()), ["body.woff", "body.woff2", "mono.ttf"]);
+        let preloads: Vec<(String, Option<String>)> = preloads(dir.path())
+            .into_iter()
+            .map(|p| (p.href.to_string(), p.media_type))
+            .collect();
+        assert_eq!(preloads, [
+            ("fonts/body.woff2".to_string(), Some("font/woff2".to_string())),
+            ("fonts/mono.ttf".to_string(), Some("font/ttf".to_string())),
+        ]);
+    }
+}
@@ -0,0 +1,198 @@
+//! Post front matter.
+//!
+//! An optional block at the very top of a post, between `---` lines:
+//!
+//! ```text
+//! ---
+//! date: 2019-05-04
+//! aliases: [/2019/old-name.html, /old]
+//! draft: false
+//! ---
+//! # Title
+//! ```
+//!
+//! Values are scalars (`key: value`), inline lists (`key: [a, b]`) or
+//! block lists (`key:` followed by `- item` lines). The legacy `Tags:` line
+//! in the body keeps working alongside it.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+
+use crate::timezone::SiteTimezone;
+
+/// A front matter value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Scalar(String),
+    List(Vec<String>),
+}
+
+/// Parsed front matter keys (lowercased).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrontMatter {
+    values: BTreeMap<String, Value>,
+}
+
+impl FrontMatter {
+    /// Scalar value of `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        match self.values.get(key)? {
+            Value::Scalar(s) => Some(s),
+            Value::List(_) => None,
+        }
+    }
+
+    /// List value of `key`; a scalar counts as a one-item list.
+    pub fn list(&self, key: &str) -> Vec<String> {
+        match self.values.get(key) {
+            Some(Value::List(items)) => items.clone(),
+            Some(Value::Scalar(s)) if !s.is_empty() => vec![s.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Boolean flag: `true`/`yes` (any case) is set, anything else is not.
+    pub fn flag(&self, key: &str) -> bool {
+        self.get(key)
+            .is_some_and(|v| v.eq_ignore_ascii_case("true") || v.eq_ignore_ascii_case("yes"))
+    }
+
+    /// Explicitly turned off: `false`/`no` (any case).
+    pub fn disabled(&self, key: &str) -> bool {
+        self.get(key)
+            .is_some_and(|v| v.eq_ignore_ascii_case("false") || v.eq_ignore_ascii_case("no"))
+    }
+
+    /// Date value of `key`: RFC 3339, or `YYYY-MM-DD[ HH:MM]` in the site
+    /// timezone. None if missing or unparsable.
+    pub fn date(&self, key: &str, timezone: &SiteTimezone) -> Option<DateTime<FixedOffset>> {
+        let value = self.get(key)?;
+        if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+            return Some(date);
+        }
+        let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+            .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|d| d.and_time(Default::default())))
+            .ok()?;
+        timezone.from_local(&naive)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// Split a post into front matter and body. Posts without a leading `---`
+/// block are returned unchanged with empty front matter.
+pub fn split(markdown: &str) -> (FrontMatter, &str) {
+    let Some(rest) = markdown.strip_prefix("---\n").or_else(|| markdown.strip_prefix("---\r\n")) else {
+        return (FrontMatter::default(), markdown);
+    };
+
+    // Find the closing fence on its own line
+    let mut offset = 0;
+    let mut block = None;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            block = Some((&rest[..offset], &rest[offset + line.len()..]));
+            break;
+        }
+        offset += line.len();
+    }
+    let Some((block, body)) = block else {
+        return (FrontMatter::default(), markdown);
+    };
+
+    (parse_block(block), body)
+}
+
+fn parse_block(block: &str) -> FrontMatter {
+    let mut values = BTreeMap::new();
+    let mut open_list: Option<(String, Vec<String>)> = None;
+
+    for line in block.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(item) = trimmed.strip_prefix("- ")
+            && let Some((_, ref mut items)) = open_list
+        {
+            items.push(unquote(item).to_string());
+            continue;
+        }
+        if let Some((key, items)) = open_list.take() {
+            values.insert(key, Value::List(items));
+        }
+
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+
+        if value.is_empty() {
+            open_list = Some((key, Vec::new()));
+        } else if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            let items = inner
+                .split(',')
+                .map(|i| unquote(i.trim()).to_string())
+                .filter(|i| !i.is_empty())
+                .collect();
+            values.insert(key, Value::List(items));
+        } else {
+            values.insert(key, Value::Scalar(unquote(value).to_string()));
+        }
+    }
+    if let Some((key, items)) = open_list {
+        values.insert(key, Value::List(items));
+    }
+
+    FrontMatter { values }
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_scalars_and_lists() {
+        let post = "---\ntitle: \"Hi: there\"\naliases: [/a, '/b']\nextra:\n  - one\n  - two\n---\n# Body\n";
+        let (front, body) = split(post);
+
+        assert_eq!(body, "# Body\n");
+        assert_eq!(front.get("title"), Some("Hi: there"));
+        assert_eq!(front.list("aliases"), vec!["/a", "/b"]);
+        assert_eq!(front.list("extra"), vec!["one", "two"]);
+        assert_eq!(front.list("title"), vec!["Hi: there"]);
+        assert!(!front.flag("title"));
+        assert!(split("---\nnoindex: Yes\n---\n").0.flag("noindex"));
+    }
+
+    #[test]
+    fn dates_in_site_timezone() {
+        let gmt8 = SiteTimezone::Offset(FixedOffset::east_opt(8 * 3600).unwrap());
+        let (front, _) = split("---\na: 2024-05-04\nb: 2024-05-04 09:30\nc: 2024-05-04T09:30:00Z\nd: May 4\n---\n");
+        assert_eq!(front.date("a", &gmt8).unwrap().to_rfc3339(), "2024-05-04T00:00:00+08:00");
+        assert_eq!(front.date("b", &gmt8).unwrap().to_rfc3339(), "2024-05-04T09:30:00+08:00");
+        assert_eq!(front.date("c", &gmt8).unwrap().to_rfc3339(), "2024-05-04T09:30:00+00:00");
+        assert_eq!(front.date("d", &gmt8), None);
+    }
+
+    #[test]
+    fn no_front_matter_is_untouched() {
+        let post = "# Title\n---\nnot front matter\n";
+        assert_eq!(split(post), (FrontMatter::default(), post));
+        // Unterminated block
+        assert_eq!(split("---\na: b\n").1, "---\na: b\n");
+    }
+}
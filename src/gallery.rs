@@ -0,0 +1,180 @@
+//! `{{< gallery "trip/*.jpg" >}}` image grids.
+//!
+//! The pattern is relative to the content directory (like images), with
+//! `*` and `?` wildcards in the file name. Every match is optimized like
+//! an inline image and gets a thumbnail; the grid shows the thumbnails,
+//! each linking to its full-size version. Files are ordered by name;
+//! dark-mode variants (`*.dark.jpg`) are left out.
+
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::image::{ImageQuery, OptimizedImage, is_dark_variant, optimize_image, optimize_thumbnail};
+use crate::image_meta::ImageMeta;
+use crate::lightbox::LightboxPage;
+use crate::parser::MarkdownEnv;
+use crate::shortcode::Shortcode;
+use crate::types::EscapeHtml;
+
+/// Maximum thumbnail width in pixels.
+const THUMBNAIL_WIDTH: u32 = 480;
+
+/// A rendered gallery.
+#[derive(Debug)]
+pub struct Gallery {
+    pub html: String,
+    /// Full-size images and thumbnails written for the gallery.
+    pub images: Vec<OptimizedImage>,
+    /// Images that could not be optimized (and are left out).
+    pub warnings: Vec<BuildError>,
+}
+
+/// Render a gallery shortcode in a page whose links are relative to
+/// `relative_root`; thumbnails open through the page's `lightbox` if
+/// enabled.
+pub fn render(
+    code: &Shortcode,
+    config: &Config,
+    relative_root: &str,
+    lightbox: &mut LightboxPage,
+    env: &MarkdownEnv<'_>,
+    image_defaults: &ImageQuery,
+) -> Result<Gallery, String> {
+    let pattern = code.arg().ok_or("missing file pattern")?;
+    let sources = matching_files(&config.content_dir, pattern)?;
+    if sources.is_empty() {
+        return Err(format!("no files match {pattern:?}"));
+    }
+
+    let mut gallery = Gallery {
+        html: String::from(r#"<div class="gallery">"#),
+        images: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let MarkdownEnv { outputs, decode_limit: limit, image_cache: cache, .. } = *env;
+    let (content, public) = (&config.content_dir, &config.public_dir);
+    let thumb_width = THUMBNAIL_WIDTH.min(image_defaults.width.unwrap_or(config.max_image_width));
+    for src in &sources {
+        let sized = image_defaults.apply_to(src);
+        let full = match optimize_image(&sized, content, public, config.image_bounds(), outputs, limit, cache) {
+            Ok(full) => full,
+            Err(e) => {
+                gallery.warnings.push(e);
+                continue;
+            }
+        };
+        let thumb = optimize_thumbnail(src, content, public, thumb_width, outputs, limit, cache)
+            .unwrap_or_else(|e| {
+                gallery.warnings.push(e);
+                full.clone()
+            });
+
+        let meta = ImageMeta::load(src, content).unwrap_or_else(|e| {
+            gallery.warnings.push(e);
+            None
+        });
+        let alt = meta.as_ref().and_then(ImageMeta::alt).map_or_else(|| alt_text(src), str::to_string);
+        let full_src = full.src.href(relative_root).escape_html();
+        let img = format!(
+            r#"<img src="{}" alt="{}" width="{}" height="{}" loading="lazy" decoding="async">"#,
+            thumb.src.href(relative_root).escape_html(),
+            alt.escape_html(),
+            thumb.width,
+            thumb.height,
+        );
+        let html = lightbox
+            .wrap(&img, &full_src, &alt)
+            .unwrap_or_else(|| format!(r#"<a href="{full_src}">{img}</a>"#));
+        gallery.html.push_str(&html);
+        let is_thumb = thumb.src != full.src;
+        gallery.images.push(full);
+        if is_thumb {
+            gallery.images.push(thumb);
+        }
+    }
+    gallery.html.push_str("</div>");
+    Ok(gallery)
+}
+
+/// Files under `content_dir` matching `pattern`, as content-relative
+/// paths sorted by name.
+fn matching_files(content_dir: &Path, pattern: &str) -> Result<Vec<String>, String> {
+    let (dir, name) = pattern.rsplit_once('/').unwrap_or(("", pattern));
+    if dir.contains(['*', '?']) {
+        return Err(format!("wildcards are only allowed in file names: {pattern:?}"));
+    }
+    let entries = fs::read_dir(content_dir.join(dir)).map_err(|e| format!("cannot read {dir:?}: {e}"))?;
+
+    let mut files: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|file| wildcard_match(name, file) && !is_dark_variant(file))
+        .map(|file| if dir.is_empty() { file } else { format!("{dir}/{file}") })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `name` matches `pattern` with `*` (any run) and `?` (any one
+/// character).
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it is matched to
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            star = Some((p, n));
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p;
+            n = star_n + 1;
+            star = Some((star_p, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Alt text from a file name: `beach-at_dawn.jpg` → "beach at dawn".
+fn alt_text(src: &str) -> String {
+    Path::new(src)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().replace(['-', '_'], " "))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_file_names() {
+        assert!(wildcard_match("*.jpg", "beach.jpg"));
+        assert!(wildcard_match("img-??.*", "img-01.png"));
+        assert!(wildcard_match("a*b*c", "aXbYbZc"));
+        assert!(!wildcard_match("*.jpg", "beach.jpeg"));
+        assert!(!wildcard_match("img-??.*", "img-1.png"));
+    }
+
+    #[test]
+    fn matches_files_in_one_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("trip/nested")).unwrap();
+        for file in ["b.jpg", "a.jpg", "a.dark.jpg", "notes.txt"] {
+            fs::write(dir.path().join("trip").join(file), b"").unwrap();
+        }
+
+        assert_eq!(matching_files(dir.path(), "trip/*.jpg").unwrap(), ["trip/a.jpg", "trip/b.jpg"]);
+        assert!(matching_files(dir.path(), "*/a.jpg").is_err());
+        assert_eq!(alt_text("trip/beach-at_dawn.jpg"), "beach at dawn");
+    }
+}
@@ -0,0 +1,271 @@
+//! Gemtext (Gemini) mirror of the posts.
+//!
+//! With `gemini_dir = "public_gemini"`, every post is also written as
+//! `{stem}.gmi` from the same markdown events the HTML renderer parses,
+//! next to an `index.gmi` listing them. Gemtext has no inline markup:
+//! links and images become `=>` lines after their paragraph, local images
+//! are copied alongside, and raw HTML is dropped.
+
+use std::path::{Component, Path};
+
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
+
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::output::OutputSet;
+use crate::parser::markdown_options;
+use crate::shortcode;
+
+/// Index page of the mirror.
+pub const INDEX_FILE: &str = "index.gmi";
+
+/// A post to mirror.
+#[derive(Debug, Clone, Copy)]
+pub struct GeminiPost<'a> {
+    pub stem: &'a str,
+    pub title: &'a str,
+    /// `YYYY-MM-DD`, shown in the index.
+    pub date: &'a str,
+    /// Markdown body.
+    pub content: &'a str,
+}
+
+/// Gemtext of one document plus the local images it links to.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Gemtext {
+    pub text: String,
+    pub images: Vec<String>,
+}
+
+/// Write `posts` (newest first) and the index to `config.gemini_dir`.
+/// Images that can't be copied are returned as warnings.
+pub fn write(config: &Config, posts: &[GeminiPost<'_>], outputs: &OutputSet) -> Result<Vec<BuildError>, BuildError> {
+    let Some(dir) = &config.gemini_dir else { return Ok(Vec::new()) };
+    outputs.create_dir(dir)?;
+    let mut warnings = Vec::new();
+    let mut index = format!("# {}\n\n", config.brand_name);
+    if let Some(description) = &config.description {
+        index.push_str(&format!("{description}\n\n"));
+    }
+    for post in posts {
+        let gemtext = render_markdown(post.content, config);
+        let text = format!("{}\n=> {INDEX_FILE} {}\n", gemtext.text, config.brand_name);
+        outputs.write(&dir.join(format!("{}.gmi", post.stem)), text)?;
+        for image in &gemtext.images {
+            let target = dir.join(image);
+            let copied = match target.parent() {
+                Some(parent) => outputs.create_dir(parent),
+                None => Ok(()),
+            }
+            .and_then(|()| outputs.copy(&config.content_dir.join(image), &target));
+            warnings.extend(copied.err());
+        }
+        index.push_str(&format!("=> {}.gmi {} {}\n", post.stem, post.date, post.title));
+    }
+    outputs.write(&dir.join(INDEX_FILE), index)?;
+    Ok(warnings)
+}
+
+/// Gemtext for a markdown post. Shortcodes with a URL become links.
+pub fn render_markdown(markdown: &str, config: &Config) -> Gemtext {
+    let (markdown, _) = shortcode::expand(markdown, |code| {
+        Ok(code
+            .arg()
+            .filter(|arg| arg.starts_with("https://") || arg.starts_with("http://"))
+            .map(|url| format!("<{url}>"))
+            .unwrap_or_default())
+    });
+    render(Parser::new_ext(&markdown, markdown_options(config)))
+}
+
+/// Gemtext for a stream of markdown events.
+pub fn render<'a>(events: impl IntoIterator<Item = Event<'a>>) -> Gemtext {
+    let mut out = Gemtext::default();
+    // Text of the line being built and its line-type prefix
+    let mut line = String::new();
+    let mut prefix = String::new();
+    let mut links: Vec<(String, String)> = Vec::new();
+    // Open link: (url, where its text starts in `line`)
+    let mut link: Option<(String, usize)> = None;
+    let mut image: Option<(String, String)> = None;
+    // Whether the paragraph has text outside links
+    let mut bare = false;
+    let (mut lists, mut quotes, mut in_code) = (0, 0, false);
+
+    let flush = |line: &mut String, prefix: &str, quotes: usize, text: &mut String| {
+        let content = line.trim();
+        if !content.is_empty() {
+            if quotes > 0 {
+                text.push_str("> ");
+            }
+            text.push_str(prefix);
+            text.push_str(content);
+            text.push('\n');
+        }
+        line.clear();
+    };
+    let end_block = |links: &mut Vec<(String, String)>, lists: usize, quotes: usize, text: &mut String| {
+        if lists > 0 || quotes > 0 {
+            return;
+        }
+        for (url, label) in links.drain(..) {
+            let label = label.trim();
+            if label.is_empty() || label == url {
+                text.push_str(&format!("=> {url}\n"));
+            } else {
+                text.push_str(&format!("=> {url} {label}\n"));
+            }
+        }
+        if !text.is_empty() && !text.ends_with("\n\n") {
+            text.push('\n');
+        }
+    };
+
+    for event in events {
+        if let Some((_, alt)) = image.as_mut() {
+            match event {
+                Event::End(TagEnd::Image) => {
+                    let Some((url, alt)) = image.take() else { continue };
+                    if is_local(&url) && !out.images.contains(&url) {
+                        out.images.push(url.clone());
+                    }
+                    links.push((url, alt));
+                }
+                Event::Text(text) | Event::Code(text) => alt.push_str(&text),
+                _ => {}
+            }
+            continue;
+        }
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                prefix = format!("{} ", "#".repeat((level as usize).min(3)));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                flush(&mut line, &prefix, quotes, &mut out.text);
+                prefix.clear();
+                end_block(&mut links, lists, quotes, &mut out.text);
+            }
+            Event::End(TagEnd::Paragraph) => {
+                // A paragraph of links is just its link lines
+                if lists == 0 && !bare && !links.is_empty() {
+                    line.clear();
+                }
+                bare = false;
+                if lists == 0 {
+                    flush(&mut line, &prefix, quotes, &mut out.text);
+                    end_block(&mut links, lists, quotes, &mut out.text);
+                } else {
+                    line.push(' ');
+                }
+            }
+            Event::Start(Tag::List(_)) => {
+                flush(&mut line, &prefix, quotes, &mut out.text);
+                lists += 1;
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists -= 1;
+                end_block(&mut links, lists, quotes, &mut out.text);
+            }
+            Event::Start(Tag::Item) => {
+                flush(&mut line, &prefix, quotes, &mut out.text);
+                prefix = "* ".to_string();
+            }
+            Event::End(TagEnd::Item) => {
+                flush(&mut line, &prefix, quotes, &mut out.text);
+                prefix.clear();
+            }
+            Event::Start(Tag::BlockQuote(_)) => quotes += 1,
+            Event::End(TagEnd::BlockQuote(_)) => {
+                quotes -= 1;
+                end_block(&mut links, lists, quotes, &mut out.text);
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                flush(&mut line, &prefix, quotes, &mut out.text);
+                let alt = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or_default().to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                out.text.push_str(&format!("```{alt}\n"));
+                in_code = true;
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if !out.text.ends_with('\n') {
+                    out.text.push('\n');
+                }
+                out.text.push_str("```\n");
+                in_code = false;
+                end_block(&mut links, lists, quotes, &mut out.text);
+            }
+            Event::Text(text) if in_code => out.text.push_str(&text),
+            Event::Text(text) | Event::Code(text) => {
+                bare |= link.is_none() && !text.trim().is_empty();
+                line.push_str(&text);
+            }
+            Event::SoftBreak => line.push(' '),
+            Event::HardBreak => flush(&mut line, &prefix, quotes, &mut out.text),
+            Event::Start(Tag::Link { dest_url, .. }) => link = Some((dest_url.to_string(), line.len())),
+            Event::End(TagEnd::Link) => {
+                if let Some((url, start)) = link.take() {
+                    links.push((url, line[start..].to_string()));
+                }
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => image = Some((dest_url.to_string(), String::new())),
+            _ => {}
+        }
+    }
+    flush(&mut line, &prefix, quotes, &mut out.text);
+    end_block(&mut links, lists, quotes, &mut out.text);
+    out.text.truncate(out.text.trim_end().len());
+    out.text.push('\n');
+    out
+}
+
+/// A relative image path inside the content directory.
+fn is_local(url: &str) -> bool {
+    !url.contains(':')
+        && !url.starts_with("//")
+        && Path::new(url).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_gemtext() {
+        let markdown = "# Title\n\nSee [the docs](https://docs.example) and\n[more](https://more.example).\n\n\
+                        ## Steps\n\n- one\n- two [x](https://x.example)\n\n> quoted\n\n\
+                        ```rust\nfn main() {}\n```\n\n![A duck](images/duck.png)\n\n![remote](https://cdn.example/a.png)\n\n\
+                        <div>raw</div>\n\n{{< youtube \"https://youtu.be/x\" >}}\n";
+        let gemtext = render_markdown(markdown, &Config::new());
+        assert_eq!(
+            gemtext.text,
+            "# Title\n\nSee the docs and more.\n=> https://docs.example the docs\n=> https://more.example more\n\n\
+             ## Steps\n\n* one\n* two x\n=> https://x.example x\n\n> quoted\n\n```rust\nfn main() {}\n```\n\n\
+             => images/duck.png A duck\n\n=> https://cdn.example/a.png remote\n\n=> https://youtu.be/x\n"
+        );
+        assert_eq!(gemtext.images, ["images/duck.png"]);
+        assert!(!is_local("../secret.png"));
+    }
+
+    #[test]
+    fn writes_posts_and_index() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("duck.png"), "png").unwrap();
+        let config = Config::new()
+            .content_dir(dir.path())
+            .gemini_dir(dir.path().join("gemini"))
+            .brand_name("Duck");
+        let post = GeminiPost { stem: "hello", title: "Hello", date: "2024-01-01", content: "# Hello\n\n![](duck.png)\n" };
+        let warnings = write(&config, &[post], &OutputSet::new()).unwrap();
+        assert!(warnings.is_empty());
+
+        let gemini = dir.path().join("gemini");
+        assert_eq!(std::fs::read_to_string(gemini.join(INDEX_FILE)).unwrap(), "# Duck\n\n=> hello.gmi 2024-01-01 Hello\n");
+        assert_eq!(
+            std::fs::read_to_string(gemini.join("hello.gmi")).unwrap(),
+            "# Hello\n\n=> duck.png\n\n=> index.gmi Duck\n"
+        );
+        assert!(gemini.join("duck.png").exists());
+    }
+}
@@ -0,0 +1,102 @@
+//! Publish and modified dates from git history.
+//!
+//! A single `git log` over the content directory gives, for every file,
+//! the author date of the first commit touching it (published) and of the
+//! last one (modified). Unlike mtimes these survive fresh clones and CI
+//! checkouts. Outside a repository, without `git`, or for untracked files
+//! there is no history and callers fall back to mtime; front matter
+//! dates win over both.
+//!
+//! Shallow clones only see the commits they fetched, so CI should check
+//! out the full history (`fetch-depth: 0` on GitHub Actions).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::{DateTime, Utc};
+use tracing::debug;
+
+/// Publish and last-modified dates of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileDates {
+    pub published: DateTime<Utc>,
+    pub modified: DateTime<Utc>,
+}
+
+/// Commit dates for the files under a directory.
+#[derive(Debug, Default)]
+pub struct GitHistory {
+    files: HashMap<PathBuf, FileDates>,
+}
+
+impl GitHistory {
+    /// Read the history of `dir`. None if it is not in a git repository
+    /// or `git` is not installed.
+    pub fn load(dir: &Path) -> Option<Self> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "core.quotepath=false", "log", "--format=%x00%aI", "--name-only", "--relative", "--", "."])
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                Some(Self::parse(dir, &String::from_utf8_lossy(&output.stdout)))
+            }
+            Ok(output) => {
+                debug!("No git history for {}: {}", dir.display(), String::from_utf8_lossy(&output.stderr).trim());
+                None
+            }
+            Err(e) => {
+                debug!("git unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Dates of `path` (as found under the loaded directory), if committed.
+    pub fn dates(&self, path: &Path) -> Option<FileDates> {
+        self.files.get(path).copied()
+    }
+
+    /// Parse `git log` output: a NUL-prefixed date line per commit, newest
+    /// first, followed by the files it touched.
+    fn parse(dir: &Path, log: &str) -> Self {
+        let mut files: HashMap<PathBuf, FileDates> = HashMap::new();
+        let mut date = None;
+        for line in log.lines() {
+            if let Some(stamp) = line.strip_prefix('\0') {
+                date = DateTime::parse_from_rfc3339(stamp.trim()).ok().map(|d| d.with_timezone(&Utc));
+                continue;
+            }
+            let Some(date) = date else { continue };
+            if line.is_empty() {
+                continue;
+            }
+            files
+                .entry(dir.join(line))
+                .and_modify(|dates| dates.published = date)
+                .or_insert(FileDates { published: date, modified: date });
+        }
+        Self { files }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_and_last_commit_per_file() {
+        let log = "\x002024-03-01T10:00:00+08:00\n\na.md\n\x002024-02-01T10:00:00Z\n\na.md\nb.md\n\x002024-01-01T10:00:00Z\n\na.md\n";
+        let history = GitHistory::parse(Path::new("content"), log);
+
+        let a = history.dates(Path::new("content/a.md")).unwrap();
+        assert_eq!(a.published.to_rfc3339(), "2024-01-01T10:00:00+00:00");
+        assert_eq!(a.modified.to_rfc3339(), "2024-03-01T02:00:00+00:00");
+
+        let b = history.dates(Path::new("content/b.md")).unwrap();
+        assert_eq!(b.published, b.modified);
+        assert!(history.dates(Path::new("content/c.md")).is_none());
+    }
+}
@@ -0,0 +1,160 @@
+//! Redirect and header files for static hosts.
+//!
+//! Post `aliases` (old URLs) become permanent redirects to the post's
+//! permalink, and `[headers]` from `ssg.toml` become custom response
+//! headers. Both are written in Netlify format (`_redirects`, `_headers`)
+//! and as `vercel.json`; nothing is written when there is nothing to say.
+//!
+//! With `cache_headers`, `Cache-Control` rules are added for what the build
+//! knows about its outputs: everything in `images/` is named after a hash
+//! of its source, so a changed image gets a new URL and the old one can be
+//! cached forever, while pages keep their URL and must be revalidated.
+
+use std::collections::BTreeMap;
+
+use serde_json::json;
+
+/// Netlify redirect rules.
+pub const REDIRECTS_FILE: &str = "_redirects";
+
+/// Netlify header rules.
+pub const HEADERS_FILE: &str = "_headers";
+
+/// Vercel project config (redirects and headers).
+pub const VERCEL_FILE: &str = "vercel.json";
+
+/// Header rules: path pattern (`/images/*`) to header name and value.
+pub type HeaderRules = BTreeMap<String, BTreeMap<String, String>>;
+
+/// `Cache-Control` of fingerprinted files, whose contents never change.
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// `Cache-Control` of pages, which change under the same URL.
+pub const REVALIDATE: &str = "public, max-age=0, must-revalidate";
+
+/// Add `Cache-Control` rules for the output trees at `prefixes` (`""`,
+/// `de/`), unless `headers` already sets one for the pattern.
+pub fn add_cache_rules(headers: &mut HeaderRules, prefixes: &[&str]) {
+    for prefix in prefixes {
+        let rules = [
+            (format!("/{prefix}images/*"), IMMUTABLE),
+            (format!("/{prefix}"), REVALIDATE),
+            (format!("/{prefix}index.html"), REVALIDATE),
+            (format!("/{prefix}posts/*"), REVALIDATE),
+            (format!("/{prefix}tags/*"), REVALIDATE),
+        ];
+        for (pattern, value) in rules {
+            headers.entry(pattern).or_default()
+                .entry("Cache-Control".to_string())
+                .or_insert_with(|| value.to_string());
+        }
+    }
+}
+
+/// A permanent redirect from an old URL to a permalink.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Redirect {
+    pub from: String,
+    pub to: String,
+}
+
+impl Redirect {
+    /// Redirect `alias` to `to`; the alias gets a leading `/` if missing.
+    pub fn new(alias: &str, to: impl Into<String>) -> Self {
+        let alias = alias.trim();
+        let from = if alias.starts_with('/') {
+            alias.to_string()
+        } else {
+            format!("/{alias}")
+        };
+        Self { from, to: to.into() }
+    }
+}
+
+/// Netlify `_redirects` contents.
+pub fn render_redirects(redirects: &[Redirect]) -> String {
+    redirects
+        .iter()
+        .map(|r| format!("{}  {}  301\n", r.from, r.to))
+        .collect()
+}
+
+/// Netlify `_headers` contents.
+pub fn render_headers(headers: &HeaderRules) -> String {
+    let mut out = String::new();
+    for (pattern, values) in headers {
+        out.push_str(pattern);
+        out.push('\n');
+        for (name, value) in values {
+            out.push_str(&format!("  {name}: {value}\n"));
+        }
+    }
+    out
+}
+
+/// `vercel.json` with the same redirects and headers.
+pub fn render_vercel(redirects: &[Redirect], headers: &HeaderRules) -> String {
+    let redirects: Vec<_> = redirects
+        .iter()
+        .map(|r| json!({ "source": r.from, "destination": r.to, "permanent": true }))
+        .collect();
+    let headers: Vec<_> = headers
+        .iter()
+        .map(|(pattern, values)| {
+            let values: Vec<_> = values
+                .iter()
+                .map(|(key, value)| json!({ "key": key, "value": value }))
+                .collect();
+            json!({ "source": vercel_pattern(pattern), "headers": values })
+        })
+        .collect();
+
+    let mut config = serde_json::Map::new();
+    if !redirects.is_empty() {
+        config.insert("redirects".to_string(), redirects.into());
+    }
+    if !headers.is_empty() {
+        config.insert("headers".to_string(), headers.into());
+    }
+    serde_json::to_string_pretty(&config).expect("vercel config is always serializable")
+}
+
+/// Netlify splat (`/images/*`) to Vercel path pattern (`/images/(.*)`).
+fn vercel_pattern(pattern: &str) -> String {
+    pattern.replace('*', "(.*)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn netlify_and_vercel_formats() {
+        let redirects = vec![Redirect::new("old.html", "/posts/new.html")];
+        let mut headers = HeaderRules::new();
+        headers.entry("/images/*".to_string()).or_default()
+            .insert("Cache-Control".to_string(), "max-age=31536000".to_string());
+
+        assert_eq!(render_redirects(&redirects), "/old.html  /posts/new.html  301\n");
+        assert_eq!(render_headers(&headers), "/images/*\n  Cache-Control: max-age=31536000\n");
+
+        let vercel: serde_json::Value = serde_json::from_str(&render_vercel(&redirects, &headers)).unwrap();
+        assert_eq!(vercel["redirects"][0]["source"], "/old.html");
+        assert_eq!(vercel["headers"][0]["source"], "/images/(.*)");
+        assert_eq!(vercel["headers"][0]["headers"][0]["key"], "Cache-Control");
+    }
+
+    #[test]
+    fn cache_rules_keep_configured_headers() {
+        let mut headers = HeaderRules::new();
+        headers.entry("/posts/*".to_string()).or_default()
+            .insert("Cache-Control".to_string(), "max-age=60".to_string());
+        add_cache_rules(&mut headers, &["", "de/"]);
+
+        assert_eq!(headers["/images/*"]["Cache-Control"], IMMUTABLE);
+        assert_eq!(headers["/de/images/*"]["Cache-Control"], IMMUTABLE);
+        assert_eq!(headers["/de/"]["Cache-Control"], REVALIDATE);
+        assert_eq!(headers["/posts/*"]["Cache-Control"], "max-age=60");
+        assert!(render_headers(&headers).contains("/tags/*\n  Cache-Control: public, max-age=0, must-revalidate\n"));
+    }
+}
@@ -0,0 +1,245 @@
+//! Localized UI strings and month names.
+//!
+//! `locale = "de_DE"` picks the built-in strings for the language (English
+//! when there are none) and localizes month and weekday names in
+//! `date_format`. Single strings are overridden in the `[strings]` table:
+//!
+//! ```toml
+//! locale = "de_DE"
+//!
+//! [strings]
+//! upload = "GEPOSTET"
+//! tag_page = "Schlagwort: {tag}"
+//! ```
+//!
+//! Multilingual sites list their languages instead; each has its own
+//! content subtree (`content/de/`) and output directory (`public/de/`):
+//!
+//! ```toml
+//! [[languages]]
+//! code = "en"
+//!
+//! [[languages]]
+//! code = "de"
+//! locale = "de_DE"
+//! ```
+
+use chrono::Locale;
+use serde::{Deserialize, Deserializer};
+
+/// UI strings for one language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Strings {
+    /// Post meta label for the publish date.
+    pub upload: String,
+    /// Post meta label for the last-updated date.
+    pub updated: String,
+    /// Index page title and nav link.
+    pub index: String,
+    /// Nav label before the tag links.
+    pub filter: String,
+    /// Heading of the pinned posts block.
+    pub pinned: String,
+    /// Link to an image's original file.
+    pub download_full_size: String,
+    /// Tag page title; `{tag}` is replaced by the tag.
+    pub tag_page: String,
+    /// Title of the built-in 404 page.
+    pub not_found: String,
+    /// Text of the built-in 404 page.
+    pub not_found_text: String,
+    /// Link back to the index on the built-in 404 page.
+    pub back_to_index: String,
+    /// Title of the blogroll page.
+    pub links: String,
+    /// Title of the tags index.
+    pub tags: String,
+    /// Link to the page content, for keyboard and screen reader users.
+    pub skip_to_content: String,
+    /// Label of the site navigation.
+    pub navigation: String,
+    /// Title of the page with all posts, and the index's link to it.
+    pub archive: String,
+    /// Label of the password field of a protected post.
+    pub protected: String,
+    /// Button decrypting a protected post.
+    pub unlock: String,
+    pub wrong_password: String,
+    /// Link to the archived copy of an external page.
+    pub archived: String,
+    /// Post meta label before the author's name.
+    pub by: String,
+    /// Estimated reading time of a post, with `{minutes}`.
+    pub reading_time: String,
+    /// Heading of the tags sharing posts with a tag, on its page.
+    pub related_tags: String,
+    /// Nav link to the tags index, when the nav shows only some tags.
+    pub all_tags: String,
+    /// Nav link to the site feed.
+    pub feed: String,
+    /// Title of the search page, its nav link and button.
+    pub search: String,
+    /// Shown on the search page when nothing matches.
+    pub no_results: String,
+}
+
+impl Strings {
+    /// Built-in strings for `locale`'s language.
+    pub fn for_locale(locale: Locale) -> Self {
+        let name = format!("{locale:?}");
+        let table = match name.split('_').next().unwrap_or_default() {
+            "de" => ["VERÖFFENTLICHT", "AKTUALISIERT", "Übersicht", "Filter", "Angeheftet", "Originalgröße herunterladen", "Schlagwort: {tag}", "Nicht gefunden", "Diese Seite existiert nicht.", "Zurück zur Übersicht", "Links", "Schlagwörter", "Zum Inhalt springen", "Navigation", "Archiv", "Dieser Beitrag ist geschützt. Passwort:", "Entsperren", "Falsches Passwort.", "archiviert", "von", "{minutes} Min. Lesezeit", "Verwandte Schlagwörter", "Alle Schlagwörter", "Feed", "Suche", "Keine Beiträge gefunden."],
+            "fr" => ["PUBLIÉ", "MIS À JOUR", "Index", "Filtrer", "Épinglé", "Télécharger en taille réelle", "Étiquette : {tag}", "Introuvable", "Cette page n'existe pas.", "Retour à l'index", "Liens", "Étiquettes", "Aller au contenu", "Navigation", "Archives", "Cet article est protégé. Mot de passe :", "Déverrouiller", "Mot de passe incorrect.", "archivé", "par", "{minutes} min de lecture", "Étiquettes associées", "Toutes les étiquettes", "Flux", "Rechercher", "Aucun article trouvé."],
+            "es" => ["PUBLICADO", "ACTUALIZADO", "Índice", "Filtrar", "Fijado", "Descargar tamaño completo", "Etiqueta: {tag}", "No encontrado", "Esta página no existe.", "Volver al índice", "Enlaces", "Etiquetas", "Saltar al contenido", "Navegación", "Archivo", "Esta entrada está protegida. Contraseña:", "Desbloquear", "Contraseña incorrecta.", "archivado", "por", "{minutes} min de lectura", "Etiquetas relacionadas", "Todas las etiquetas", "Feed", "Buscar", "No se encontraron entradas."],
+            "ja" => ["投稿", "更新", "一覧", "絞り込み", "固定", "フルサイズをダウンロード", "タグ: {tag}", "見つかりません", "このページは存在しません。", "一覧に戻る", "リンク", "タグ一覧", "本文へスキップ", "ナビゲーション", "アーカイブ", "この記事は保護されています。パスワード:", "表示", "パスワードが違います。", "アーカイブ", "著者", "{minutes}分で読めます", "関連タグ", "すべてのタグ", "フィード", "検索", "記事が見つかりません。"],
+            "zh" => ["发布", "更新", "目录", "筛选", "置顶", "下载原图", "标签: {tag}", "未找到", "此页面不存在。", "返回目录", "链接", "全部标签", "跳到正文", "导航", "归档", "此文章受密码保护。密码:", "解锁", "密码错误。", "存档", "作者", "阅读约 {minutes} 分钟", "相关标签", "所有标签", "订阅", "搜索", "未找到文章。"],
+            _ => ["UPLOAD", "UPDATE", "Index", "Filter", "Pinned", "Download Full Size", "Tag: {tag}", "Not Found", "This page does not exist.", "Back to the index", "Links", "Tags", "Skip to content", "Navigation", "Archive", "This post is protected. Password:", "Unlock", "Wrong password.", "archived", "by", "{minutes} min read", "Related tags", "All tags", "Feed", "Search", "No posts found."],
+        };
+        let [upload, updated, index, filter, pinned, download_full_size, tag_page, not_found, not_found_text, back_to_index, links, tags, skip_to_content, navigation, archive, protected, unlock, wrong_password, archived, by, reading_time, related_tags, all_tags, feed, search, no_results] =
+            table.map(String::from);
+        Self { upload, updated, index, filter, pinned, download_full_size, tag_page, not_found, not_found_text, back_to_index, links, tags, skip_to_content, navigation, archive, protected, unlock, wrong_password, archived, by, reading_time, related_tags, all_tags, feed, search, no_results }
+    }
+
+    /// Replace the strings set in `overrides`.
+    pub fn with_overrides(mut self, overrides: &StringOverrides) -> Self {
+        let fields = [
+            (&mut self.upload, &overrides.upload),
+            (&mut self.updated, &overrides.updated),
+            (&mut self.index, &overrides.index),
+            (&mut self.filter, &overrides.filter),
+            (&mut self.pinned, &overrides.pinned),
+            (&mut self.download_full_size, &overrides.download_full_size),
+            (&mut self.tag_page, &overrides.tag_page),
+            (&mut self.not_found, &overrides.not_found),
+            (&mut self.not_found_text, &overrides.not_found_text),
+            (&mut self.back_to_index, &overrides.back_to_index),
+            (&mut self.links, &overrides.links),
+            (&mut self.tags, &overrides.tags),
+            (&mut self.skip_to_content, &overrides.skip_to_content),
+            (&mut self.navigation, &overrides.navigation),
+            (&mut self.archive, &overrides.archive),
+            (&mut self.protected, &overrides.protected),
+            (&mut self.unlock, &overrides.unlock),
+            (&mut self.wrong_password, &overrides.wrong_password),
+            (&mut self.archived, &overrides.archived),
+            (&mut self.by, &overrides.by),
+            (&mut self.reading_time, &overrides.reading_time),
+            (&mut self.related_tags, &overrides.related_tags),
+            (&mut self.all_tags, &overrides.all_tags),
+            (&mut self.feed, &overrides.feed),
+            (&mut self.search, &overrides.search),
+            (&mut self.no_results, &overrides.no_results),
+        ];
+        for (field, value) in fields {
+            if let Some(value) = value {
+                field.clone_from(value);
+            }
+        }
+        self
+    }
+
+    /// Title of the page for `tag`.
+    pub fn tag_title(&self, tag: &str) -> String {
+        self.tag_page.replace("{tag}", tag)
+    }
+
+    /// Reading time of `minutes`.
+    pub fn reading_time(&self, minutes: usize) -> String {
+        self.reading_time.replace("{minutes}", &minutes.to_string())
+    }
+}
+
+/// User overrides for `Strings` (the `[strings]` table).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StringOverrides {
+    pub upload: Option<String>,
+    pub updated: Option<String>,
+    pub index: Option<String>,
+    pub filter: Option<String>,
+    pub pinned: Option<String>,
+    pub download_full_size: Option<String>,
+    pub tag_page: Option<String>,
+    pub not_found: Option<String>,
+    pub not_found_text: Option<String>,
+    pub back_to_index: Option<String>,
+    pub links: Option<String>,
+    pub tags: Option<String>,
+    pub skip_to_content: Option<String>,
+    pub navigation: Option<String>,
+    pub archive: Option<String>,
+    pub protected: Option<String>,
+    pub unlock: Option<String>,
+    pub wrong_password: Option<String>,
+    pub archived: Option<String>,
+    pub by: Option<String>,
+    pub reading_time: Option<String>,
+    pub related_tags: Option<String>,
+    pub all_tags: Option<String>,
+    pub feed: Option<String>,
+    pub search: Option<String>,
+    pub no_results: Option<String>,
+}
+
+/// One language of a multilingual site (a `[[languages]]` entry).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Language {
+    /// Subdirectory of content and output (`de`), also the `hreflang`.
+    pub code: String,
+    /// Locale of this language (default: the site locale).
+    #[serde(default, deserialize_with = "deserialize_locale")]
+    pub locale: Option<Locale>,
+    /// Brand name in this language (default: the site brand name).
+    #[serde(default)]
+    pub brand_name: Option<String>,
+    /// Site description in this language (default: the site one).
+    #[serde(default)]
+    pub description: Option<String>,
+    /// UI string overrides for this language (default: the site ones).
+    #[serde(default)]
+    pub strings: Option<StringOverrides>,
+}
+
+impl Language {
+    pub fn new(code: impl Into<String>, locale: Locale) -> Self {
+        Self {
+            code: code.into(),
+            locale: Some(locale),
+            brand_name: None,
+            description: None,
+            strings: None,
+        }
+    }
+
+    /// Whether `code` can be used as a directory name.
+    pub fn is_valid_code(code: &str) -> bool {
+        !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    }
+}
+
+/// Deserialize a locale name such as `de_DE`.
+pub(crate) fn deserialize_locale<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Locale>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    name.parse()
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("unknown locale {name:?}, expected e.g. \"de_DE\"")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_defaults_and_overrides() {
+        let german = Strings::for_locale(Locale::de_AT);
+        assert_eq!(german.index, "Übersicht");
+        assert_eq!(german.tag_title("Rust"), "Schlagwort: Rust");
+        assert_eq!(Strings::for_locale(Locale::nl_NL).upload, "UPLOAD");
+
+        let overrides = StringOverrides { upload: Some("GEPOSTET".to_string()), ..Default::default() };
+        let custom = german.clone().with_overrides(&overrides);
+        assert_eq!(custom.upload, "GEPOSTET");
+        assert_eq!(custom.filter, german.filter);
+    }
+}
@@ -0,0 +1,133 @@
+//! Favicons and app icons from one source image.
+//!
+//! `icon = "icon.png"` (relative to `ssg.toml`, ideally square and at
+//! least 512px) is turned into `favicon.ico` (16, 32 and 48px),
+//! `favicon-32x32.png`, `apple-touch-icon.png` (180px), `icon-192.png`,
+//! `icon-512.png` at the output root, the large ones also listed in the web
+//! manifest (see `pwa`). Files of the same name in `static/` win. Icons
+//! are re-encoded only when the source is newer than them.
+
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, ImageFormat};
+use serde_json::{Value, json};
+
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::output::OutputSet;
+
+/// PNG icons written: file name and edge length.
+const PNG_ICONS: [(&str, u32); 4] = [
+    ("favicon-32x32.png", 32),
+    ("apple-touch-icon.png", 180),
+    ("icon-192.png", 192),
+    ("icon-512.png", 512),
+];
+
+/// Sizes packed into `favicon.ico`.
+const ICO_SIZES: [u32; 3] = [16, 32, 48];
+
+/// Head tags for the generated icons at `asset_root`.
+pub fn head_links(asset_root: &str) -> String {
+    format!(
+        r#"<link rel="icon" href="{asset_root}favicon.ico" sizes="32x32">
+    <link rel="icon" href="{asset_root}favicon-32x32.png" type="image/png">
+    <link rel="apple-touch-icon" href="{asset_root}apple-touch-icon.png">"#
+    )
+}
+
+/// Write the icons of `config.icon` to the output root.
+pub fn generate(icon: &Path, config: &Config, outputs: &OutputSet) -> Result<(), BuildError> {
+    let source_modified = fs::metadata(icon)
+        .and_then(|m| m.modified())
+        .map_err(|_| BuildError::ImageNotFound { path: icon.to_path_buf() })?;
+
+    let public = &config.public_dir;
+    let mut files: Vec<&str> = PNG_ICONS.iter().map(|(name, _)| *name).collect();
+    files.push("favicon.ico");
+    // Static files of the same name were already copied and take precedence
+    let pending: Vec<&str> = files.into_iter().filter(|f| !outputs.contains(&public.join(f))).collect();
+
+    let fresh = |file: &&str| {
+        fs::metadata(public.join(file))
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified >= source_modified)
+    };
+    if pending.iter().all(fresh) {
+        pending.iter().for_each(|file| outputs.record(public.join(file)));
+    } else {
+        let failed = |e| BuildError::ImageOptFailed { path: icon.to_path_buf(), source: e };
+        let image = image::open(icon).map_err(failed)?;
+        for (name, size) in PNG_ICONS {
+            if pending.contains(&name) {
+                outputs.write(&public.join(name), encode_png(&image, size).map_err(failed)?)?;
+            }
+        }
+        if pending.contains(&"favicon.ico") {
+            outputs.write(&public.join("favicon.ico"), encode_ico(&image).map_err(failed)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Web manifest entries of the large icons.
+pub fn manifest_icons() -> Vec<Value> {
+    PNG_ICONS
+        .iter()
+        .filter(|(_, size)| *size >= 192)
+        .map(|(name, size)| json!({ "src": name, "sizes": format!("{size}x{size}"), "type": "image/png" }))
+        .collect()
+}
+
+fn resize(image: &DynamicImage, size: u32) -> DynamicImage {
+    image.resize_exact(size, size, FilterType::Lanczos3)
+}
+
+fn encode_png(image: &DynamicImage, size: u32) -> Result<Vec<u8>, image::ImageError> {
+    let mut png = Cursor::new(Vec::new());
+    resize(image, size).write_to(&mut png, ImageFormat::Png)?;
+    Ok(png.into_inner())
+}
+
+fn encode_ico(image: &DynamicImage) -> Result<Vec<u8>, image::ImageError> {
+    let frames = ICO_SIZES
+        .iter()
+        .map(|&size| {
+            let rgba = resize(image, size).to_rgba8();
+            IcoFrame::as_png(rgba.as_raw(), size, size, ExtendedColorType::Rgba8)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut ico = Vec::new();
+    IcoEncoder::new(&mut ico).encode_images(&frames)?;
+    Ok(ico)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_icons() {
+        let dir = tempfile::tempdir().unwrap();
+        let icon = dir.path().join("icon.png");
+        image::RgbaImage::new(64, 64).save(&icon).unwrap();
+        let public = dir.path().join("public");
+        fs::create_dir(&public).unwrap();
+        let config = Config::new().public_dir(&public);
+
+        let outputs = OutputSet::new();
+        generate(&icon, &config, &outputs).unwrap();
+        let favicon = image::ImageReader::open(public.join("favicon.ico")).unwrap().with_guessed_format().unwrap();
+        assert_eq!(favicon.format(), Some(ImageFormat::Ico));
+        assert_eq!(image::open(public.join("apple-touch-icon.png")).unwrap().width(), 180);
+
+        assert_eq!(manifest_icons()[1]["src"], "icon-512.png");
+
+        let missing = generate(&dir.path().join("nope.png"), &config, &OutputSet::new()).unwrap_err();
+        assert_eq!(missing.category(), "image_not_found");
+    }
+}
@@ -1,158 +1,738 @@
 //! Image optimization with caching and modification time checking.
+//!
+//! Decoding and encoding are bounded by a `DecodeLimit` so that many large
+//! photos rendered in parallel cannot all sit in memory at once.
 
 use std::fs;
-use std::path::Path;
-use std::time::SystemTime;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
 
-use image::GenericImageView;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace_span};
 
 use crate::error::BuildError;
+use crate::image_cache::ImageCache;
+use crate::output::{content_hash, Change, OutputSet};
+use crate::types::{AbsoluteUrl, Href, RelPath};
 
 /// Result of image optimization.
 #[derive(Debug, Clone)]
 pub struct OptimizedImage {
-    /// Relative path from public root (e.g., "images/photo.webp").
-    pub rel_path: String,
+    /// External URL, or path relative to the public root (e.g.,
+    /// "images/photo.webp").
+    pub src: Href,
     
     /// Image width in pixels (0 if unknown).
     pub width: u32,
     
     /// Image height in pixels (0 if unknown).
     pub height: u32,
+
+    /// Optimized file in the public directory (None for external/missing).
+    pub output_path: Option<PathBuf>,
+
+    /// Size of the source file in bytes (0 if unknown).
+    pub source_bytes: u64,
+
+    /// Size of the optimized file in bytes (0 if unknown or not written).
+    pub output_bytes: u64,
+
+    /// Time spent on this image, including cache checks.
+    pub elapsed: Duration,
 }
 
 impl OptimizedImage {
     /// Create for external URLs (no processing needed).
-    pub fn external(url: &str) -> Self {
+    pub fn external(url: AbsoluteUrl) -> Self {
         Self {
-            rel_path: url.to_string(),
+            src: Href::Absolute(url),
             width: 0,
             height: 0,
+            output_path: None,
+            source_bytes: 0,
+            output_bytes: 0,
+            elapsed: Duration::ZERO,
         }
     }
 
     /// Create for missing/invalid images.
     pub fn missing(original_path: &str) -> Self {
         Self {
-            rel_path: original_path.to_string(),
+            src: Href::parse(original_path).unwrap_or_else(|_| Href::Relative(RelPath::default().join(original_path))),
             width: 0,
             height: 0,
+            output_path: None,
+            source_bytes: 0,
+            output_bytes: 0,
+            elapsed: Duration::ZERO,
         }
     }
 
     /// Check if this is an external URL.
     pub fn is_external(&self) -> bool {
-        self.rel_path.starts_with("http://") || self.rel_path.starts_with("https://")
+        self.src.is_absolute()
+    }
+}
+
+/// Encoding of an optimized image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    WebP,
+    Png,
+    Jpeg,
+}
+
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::WebP => "webp",
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+        }
+    }
+}
+
+/// How images over the height limit are fitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFit {
+    /// Scale down to fit inside the limits, keeping the aspect ratio.
+    #[default]
+    Contain,
+    /// Scale down to the width limit only and cut off what is below the
+    /// height limit (the top of tall screenshots stays).
+    Crop,
+}
+
+impl ImageFit {
+    /// `contain` or `crop`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "contain" => Some(Self::Contain),
+            "crop" => Some(Self::Crop),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Contain => "contain",
+            Self::Crop => "crop",
+        }
+    }
+}
+
+/// Size limits of an optimized image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageBounds {
+    pub max_width: u32,
+    pub max_height: Option<u32>,
+    pub fit: ImageFit,
+}
+
+impl ImageBounds {
+    /// Width limit only.
+    pub fn width(max_width: u32) -> Self {
+        Self { max_width, max_height: None, fit: ImageFit::Contain }
+    }
+
+    /// Output dimensions of a `width` x `height` source (never upscaled).
+    /// Matches the rounding of `DynamicImage::resize`.
+    pub fn fit(&self, width: u32, height: u32) -> (u32, u32) {
+        let max_height = self.max_height.unwrap_or(u32::MAX);
+        if width <= self.max_width && height <= max_height || width == 0 || height == 0 {
+            return (width, height);
+        }
+        let scale = |n: u32, ratio: f64| ((n as f64 * ratio).round() as u32).max(1);
+        let width_ratio = f64::min(self.max_width as f64 / width as f64, 1.0);
+        if self.fit == ImageFit::Crop {
+            return (scale(width, width_ratio), scale(height, width_ratio).min(max_height));
+        }
+        let ratio = f64::min(width_ratio, max_height as f64 / height as f64);
+        (scale(width, ratio), scale(height, ratio))
+    }
+}
+
+/// JPEG quality without a `quality` override.
+const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+/// Per-image overrides from the query of an image URL:
+/// `photo.jpg?width=600&quality=60&format=jpeg`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImageQuery {
+    /// Maximum width instead of `max_image_width`.
+    pub width: Option<u32>,
+    /// Maximum height instead of `max_image_height`.
+    pub height: Option<u32>,
+    pub fit: Option<ImageFit>,
+    /// JPEG quality, 1-100 (WebP and PNG output are lossless).
+    pub quality: Option<u8>,
+    pub format: OutputFormat,
+}
+
+impl ImageQuery {
+    /// Split `src` into the file path and its overrides.
+    pub fn parse(src: &str) -> Result<(&str, Self), String> {
+        let Some((path, query)) = src.split_once('?') else {
+            return Ok((src, Self::default()));
+        };
+        let mut parsed = Self::default();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let invalid = || format!("invalid {key}: {value:?}");
+            match key {
+                "width" => parsed.width = Some(value.parse().ok().filter(|w| *w > 0).ok_or_else(invalid)?),
+                "height" => parsed.height = Some(value.parse().ok().filter(|h| *h > 0).ok_or_else(invalid)?),
+                "fit" => parsed.fit = Some(ImageFit::parse(value).ok_or_else(invalid)?),
+                "quality" => {
+                    parsed.quality = Some(value.parse().ok().filter(|q| (1..=100).contains(q)).ok_or_else(invalid)?)
+                }
+                "format" => {
+                    parsed.format = match value {
+                        "webp" => OutputFormat::WebP,
+                        "png" => OutputFormat::Png,
+                        "jpeg" | "jpg" => OutputFormat::Jpeg,
+                        _ => return Err(invalid()),
+                    }
+                }
+                _ => return Err(format!("unknown image option {key:?}")),
+            }
+        }
+        Ok((path, parsed))
+    }
+
+    /// `src` with the size limits of `self` its own query doesn't set
+    /// (`a.jpg?quality=60` + width 800 → `a.jpg?quality=60&width=800`).
+    /// External and unparsable srcs are returned unchanged.
+    pub fn apply_to(&self, src: &str) -> String {
+        if src.starts_with("http://") || src.starts_with("https://") {
+            return src.to_string();
+        }
+        let Ok((_, own)) = Self::parse(src) else {
+            return src.to_string();
+        };
+        let mut options = Vec::new();
+        if let (Some(width), None) = (self.width, own.width) {
+            options.push(format!("width={width}"));
+        }
+        if let (Some(height), None) = (self.height, own.height) {
+            options.push(format!("height={height}"));
+        }
+        if let (Some(fit), None) = (self.fit, own.fit) {
+            options.push(format!("fit={}", fit.as_str()));
+        }
+        if options.is_empty() {
+            return src.to_string();
+        }
+        let separator = if src.contains('?') { '&' } else { '?' };
+        format!("{src}{separator}{}", options.join("&"))
+    }
+
+    /// File stem suffix for the overrides that change the output.
+    fn suffix(&self) -> String {
+        let mut suffix = String::new();
+        if let Some(width) = self.width {
+            suffix.push_str(&format!("-w{width}"));
+        }
+        if let Some(height) = self.height {
+            suffix.push_str(&format!("-h{height}"));
+        }
+        if let Some(fit) = self.fit {
+            suffix.push_str(&format!("-{}", fit.as_str()));
+        }
+        if let Some(quality) = self.quality.filter(|_| self.format == OutputFormat::Jpeg) {
+            suffix.push_str(&format!("-q{quality}"));
+        }
+        suffix
+    }
+}
+
+/// Output path, relative to the public root, of the image `original_src`
+/// (with its query) in `content_dir` as optimized by `optimize_image`.
+/// None if the source can't be read.
+pub fn output_rel_path(original_src: &str, content_dir: &Path) -> Option<String> {
+    let (path, query) = ImageQuery::parse(original_src).ok()?;
+    let stem = Path::new(path).file_stem()?.to_str()?;
+    let hash = content_hash(&content_dir.join(path))?;
+    let stem = format!("{stem}{}", query.suffix());
+    Some(format!("images/{}", hashed_name(&stem, &hash, query.format.extension())))
+}
+
+/// Content-addressed output file name, `<stem>.<hash8>.<extension>`:
+/// a changed source gets a new name, so cached copies never go stale.
+pub(crate) fn hashed_name(stem: &str, hash: &str, extension: &str) -> String {
+    format!("{stem}.{}.{extension}", &hash[..HASH_LEN.min(hash.len())])
+}
+
+/// Hex digits of the source hash in output file names.
+const HASH_LEN: usize = 8;
+
+/// The dark-mode sibling of a local image (`diagram.png` →
+/// `diagram.dark.png`, same query), if it exists in `content_dir`.
+pub fn dark_variant(original_src: &str, content_dir: &Path) -> Option<String> {
+    if original_src.starts_with("http://") || original_src.starts_with("https://") {
+        return None;
+    }
+    let (path, query) = original_src.split_once('?').map_or((original_src, None), |(p, q)| (p, Some(q)));
+    if is_dark_variant(path) {
+        return None;
+    }
+    let (stem, extension) = path.rsplit_once('.').filter(|(stem, _)| !stem.is_empty() && !stem.ends_with('/'))?;
+    let dark = format!("{stem}.dark.{extension}");
+    if !content_dir.join(&dark).is_file() {
+        return None;
+    }
+    Some(match query {
+        Some(query) => format!("{dark}?{query}"),
+        None => dark,
+    })
+}
+
+/// Whether `path` names a dark-mode variant (`*.dark.png`).
+pub fn is_dark_variant(path: &str) -> bool {
+    Path::new(path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| stem.ends_with(".dark"))
+}
+
+/// Bounds how many images are decoded and encoded at the same time.
+#[derive(Debug, Default)]
+pub struct DecodeLimit {
+    /// Free permits (None: unbounded).
+    permits: Option<Mutex<usize>>,
+    freed: Condvar,
+}
+
+/// Held while an image is being processed; frees its permit on drop.
+struct DecodePermit<'a> {
+    limit: &'a DecodeLimit,
+}
+
+impl DecodeLimit {
+    /// Allow at most `max` concurrent images (None: unbounded).
+    pub fn new(max: Option<usize>) -> Self {
+        Self {
+            permits: max.map(|n| Mutex::new(n.max(1))),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Block until a permit is free.
+    fn acquire(&self) -> DecodePermit<'_> {
+        if let Some(ref permits) = self.permits {
+            let mut free = permits.lock().unwrap();
+            while *free == 0 {
+                free = self.freed.wait(free).unwrap();
+            }
+            *free -= 1;
+        }
+        DecodePermit { limit: self }
+    }
+}
+
+impl Drop for DecodePermit<'_> {
+    fn drop(&mut self) {
+        if let Some(ref permits) = self.limit.permits {
+            *permits.lock().unwrap() += 1;
+            self.limit.freed.notify_one();
+        }
     }
 }
 
 /// Optimize a local image to WebP format with caching.
 ///
+/// A query on `original_src` (see `ImageQuery`) overrides the width limit
+/// and output format of this image; it is not part of the output path.
+///
 /// # Cache behavior
-/// - If destination exists and is newer than source, returns cached version
+/// - If destination exists and the sidecar entry still matches the source,
+///   returns the cached dimensions without opening any image
+/// - Without a sidecar entry, a destination newer than the source is
+///   reused (its header is read once to fill the sidecar)
 /// - Otherwise, regenerates the optimized image
 ///
+/// The output is recorded in `outputs`. In dry-run mode only the source
+/// header is read to plan the output dimensions; nothing is encoded.
+///
 /// # Arguments
 /// * `original_src` - Source path relative to content_dir
 /// * `content_dir` - Root directory for content
 /// * `public_dir` - Root directory for output
-/// * `max_width` - Maximum width (larger images are resized)
+/// * `bounds` - Size limits (larger images are resized or cropped)
+/// * `outputs` - Output set the optimized file is recorded in
+/// * `limit` - Bound on concurrent decodes (cache hits need no permit)
+/// * `cache` - Sidecar cache of previously optimized images
 pub fn optimize_image(
+    original_src: &str,
+    content_dir: &Path,
+    public_dir: &Path,
+    bounds: ImageBounds,
+    outputs: &OutputSet,
+    limit: &DecodeLimit,
+    cache: &ImageCache,
+) -> Result<OptimizedImage, BuildError> {
+    let variant = Variant { suffix: "", bounds };
+    optimize_variant(original_src, variant, content_dir, public_dir, outputs, limit, cache)
+}
+
+/// Like `optimize_image`, but writes a small `images/<stem>-thumb.<hash>.webp`
+/// of at most `max_width` pixels next to the full-size output.
+pub fn optimize_thumbnail(
     original_src: &str,
     content_dir: &Path,
     public_dir: &Path,
     max_width: u32,
+    outputs: &OutputSet,
+    limit: &DecodeLimit,
+    cache: &ImageCache,
+) -> Result<OptimizedImage, BuildError> {
+    let variant = Variant { suffix: "-thumb", bounds: ImageBounds::width(max_width) };
+    optimize_variant(original_src, variant, content_dir, public_dir, outputs, limit, cache)
+}
+
+/// Output flavour of a source image.
+#[derive(Debug, Clone, Copy)]
+struct Variant {
+    /// Appended to the file stem of the output and to the cache key.
+    suffix: &'static str,
+    bounds: ImageBounds,
+}
+
+/// Where and how one output is encoded.
+struct Target {
+    /// Source path relative to the content directory, without query.
+    src: String,
+    /// Image cache key.
+    key: String,
+    /// Output file stem plus the query and variant suffix.
+    stem: String,
+    bounds: ImageBounds,
+    format: OutputFormat,
+    quality: u8,
+}
+
+impl Target {
+    fn new(original_src: &str, variant: Variant, content_dir: &Path) -> Result<Self, BuildError> {
+        let path = original_src.split('?').next().unwrap_or(original_src);
+        let src_path = content_dir.join(path);
+        let (src, query) = ImageQuery::parse(original_src).map_err(|message| BuildError::InvalidImageOptions {
+            path: src_path.clone(),
+            message,
+        })?;
+        let stem = Path::new(src)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| BuildError::Internal(format!("Invalid image filename: {:?}", src_path)))?;
+
+        let suffix = format!("{}{}", query.suffix(), variant.suffix);
+        let mut key = format!("{src}{suffix}");
+        if query.format != OutputFormat::WebP {
+            key = format!("{key}.{}", query.format.extension());
+        }
+        Ok(Self {
+            src: src.to_string(),
+            key,
+            stem: format!("{stem}{suffix}"),
+            bounds: ImageBounds {
+                max_width: query.width.unwrap_or(variant.bounds.max_width),
+                max_height: query.height.or(variant.bounds.max_height),
+                fit: query.fit.unwrap_or(variant.bounds.fit),
+            },
+            format: query.format,
+            quality: query.quality.unwrap_or(DEFAULT_JPEG_QUALITY),
+        })
+    }
+
+    /// Output file name for a source with contents hashing to `hash`.
+    fn file_name(&self, hash: &str) -> String {
+        hashed_name(&self.stem, hash, self.format.extension())
+    }
+}
+
+fn optimize_variant(
+    original_src: &str,
+    variant: Variant,
+    content_dir: &Path,
+    public_dir: &Path,
+    outputs: &OutputSet,
+    limit: &DecodeLimit,
+    cache: &ImageCache,
 ) -> Result<OptimizedImage, BuildError> {
     // External URLs pass through unchanged
     if original_src.starts_with("http://") || original_src.starts_with("https://") {
-        return Ok(OptimizedImage::external(original_src));
+        return AbsoluteUrl::new(original_src).map(OptimizedImage::external);
     }
 
-    let src_path = content_dir.join(original_src);
+    let _span = trace_span!("images", src = original_src).entered();
+    let start = Instant::now();
+    let mut image = optimize_local(original_src, variant, content_dir, public_dir, outputs, limit, cache)?;
+    image.elapsed = start.elapsed();
+    Ok(image)
+}
+
+/// Optimize an image inside the content directory into
+/// `images/<stem><suffix>.<hash>.<format>`.
+fn optimize_local(
+    original_src: &str,
+    variant: Variant,
+    content_dir: &Path,
+    public_dir: &Path,
+    outputs: &OutputSet,
+    limit: &DecodeLimit,
+    cache: &ImageCache,
+) -> Result<OptimizedImage, BuildError> {
+    let target = Target::new(original_src, variant, content_dir)?;
+    let src_path = content_dir.join(&target.src);
+    let (key, bounds) = (target.key.as_str(), target.bounds);
     
-    // Check source exists
+    // Check source exists (caller falls back to the original path)
     if !src_path.exists() {
-        // Not an error, just fallback to original path
-        return Ok(OptimizedImage::missing(original_src));
+        return Err(BuildError::ImageNotFound { path: src_path });
     }
 
-    // Generate destination path
-    let file_stem = src_path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .ok_or_else(|| BuildError::Internal(format!(
-            "Invalid image filename: {:?}", src_path
-        )))?;
-    
-    let dest_filename = format!("{file_stem}.webp");
-    let dest_path = public_dir.join("images").join(&dest_filename);
-    let rel_path = format!("images/{dest_filename}");
-
-    // Cache check: compare modification times
-    if dest_path.exists() {
-        if let (Ok(src_meta), Ok(dest_meta)) = (fs::metadata(&src_path), fs::metadata(&dest_path)) {
-            let src_mtime = src_meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
-            let dest_mtime = dest_meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
-            
-            // Cache hit: destination is newer
-            if dest_mtime >= src_mtime {
-                return read_cached_dimensions(&dest_path, rel_path);
-            }
+    let images_dir = public_dir.join("images");
+    let output = |file_name: &str| {
+        (images_dir.join(file_name), Href::Relative(RelPath::default().join("images").join(file_name)))
+    };
+
+    // Cache check: sidecar entry, no decoding at all
+    if let Some(entry) = cache.lookup(key, &src_path, bounds) {
+        let (dest_path, src) = output(&target.file_name(&entry.hash));
+        if dest_path.exists() {
+            outputs.record(&dest_path);
+            return Ok(OptimizedImage {
+                src,
+                width: entry.width,
+                height: entry.height,
+                output_path: Some(dest_path),
+                source_bytes: entry.source_len,
+                output_bytes: entry.output_bytes,
+                elapsed: Duration::ZERO,
+            });
         }
     }
 
-    // Process image
-    println!("  → Optimizing: {:?}", src_path);
+    let hash = content_hash(&src_path).ok_or_else(|| BuildError::ImageNotFound { path: src_path.clone() })?;
+    let (dest_path, src) = output(&target.file_name(&hash));
+
+    // Outputs without a sidecar entry: the name already proves the contents
+    if dest_path.exists() && !cache.contains(key) {
+        outputs.record(&dest_path);
+        let image = read_cached_dimensions(&src_path, dest_path, src)?;
+        if !outputs.is_dry_run() {
+            cache.insert(key, &src_path, bounds, &image);
+        }
+        return Ok(image);
+    }
+
+    let change = if dest_path.exists() { Change::Update } else { Change::Create };
+    if outputs.is_dry_run() {
+        outputs.record_change(&dest_path, change);
+        return plan_dimensions(&src_path, dest_path, src, bounds);
+    }
+
+    // Process image (the decoded bitmap lives until the permit drops)
+    let _permit = limit.acquire();
+    debug!("Optimizing: {:?}", src_path);
     
     let img = image::open(&src_path).map_err(|e| BuildError::ImageOptFailed {
         path: src_path.clone(),
         source: e,
     })?;
 
-    let (width, _) = img.dimensions();
-    
-    let final_img = if width > max_width {
-        img.resize(max_width, u32::MAX, image::imageops::FilterType::Lanczos3)
-    } else {
+    let (width, height) = img.dimensions();
+    let (fit_width, fit_height) = bounds.fit(width, height);
+    let filter = image::imageops::FilterType::Lanczos3;
+    let final_img = if (fit_width, fit_height) == (width, height) {
         img
+    } else if bounds.fit == ImageFit::Crop {
+        let scaled = if fit_width < width { img.resize(fit_width, u32::MAX, filter) } else { img };
+        scaled.crop_imm(0, 0, fit_width, fit_height)
+    } else {
+        img.resize(fit_width, fit_height, filter)
     };
 
     let (new_width, new_height) = final_img.dimensions();
 
-    // Save as WebP
-    final_img
-        .save_with_format(&dest_path, image::ImageFormat::WebP)
+    encode(&final_img, &dest_path, target.format, target.quality).map_err(|e| BuildError::ImageOptFailed {
+        path: dest_path.clone(),
+        source: e,
+    })?;
+    outputs.record_change(&dest_path, change);
+
+    let image = OptimizedImage {
+        src,
+        width: new_width,
+        height: new_height,
+        source_bytes: file_size(&src_path),
+        output_bytes: file_size(&dest_path),
+        output_path: Some(dest_path),
+        elapsed: Duration::ZERO,
+    };
+    cache.insert(key, &src_path, bounds, &image);
+    Ok(image)
+}
+
+/// Write `img` to `path` in `format`.
+fn encode(img: &DynamicImage, path: &Path, format: OutputFormat, quality: u8) -> Result<(), image::ImageError> {
+    match format {
+        OutputFormat::WebP => img.save_with_format(path, image::ImageFormat::WebP),
+        OutputFormat::Png => img.save_with_format(path, image::ImageFormat::Png),
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel
+            let file = BufWriter::new(fs::File::create(path)?);
+            DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(JpegEncoder::new_with_quality(file, quality))
+        }
+    }
+}
+
+/// Size of a file in bytes (0 if unreadable).
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Read dimensions from a cached WebP file.
+fn read_cached_dimensions(src_path: &Path, path: PathBuf, src: Href) -> Result<OptimizedImage, BuildError> {
+    let (width, height) = image::ImageReader::open(&path)
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok())
+        .unwrap_or((0, 0));
+
+    Ok(OptimizedImage {
+        src,
+        width,
+        height,
+        source_bytes: file_size(src_path),
+        output_bytes: file_size(&path),
+        output_path: Some(path),
+        elapsed: Duration::ZERO,
+    })
+}
+
+/// Compute output dimensions from the source header without decoding.
+fn plan_dimensions(
+    src_path: &Path,
+    dest_path: PathBuf,
+    src: Href,
+    bounds: ImageBounds,
+) -> Result<OptimizedImage, BuildError> {
+    let (width, height) = image::ImageReader::open(src_path)
         .map_err(|e| BuildError::ImageOptFailed {
-            path: dest_path.clone(),
+            path: src_path.to_path_buf(),
+            source: image::ImageError::IoError(e),
+        })?
+        .into_dimensions()
+        .map_err(|e| BuildError::ImageOptFailed {
+            path: src_path.to_path_buf(),
             source: e,
         })?;
 
+    let (width, height) = bounds.fit(width, height);
+
     Ok(OptimizedImage {
-        rel_path,
-        width: new_width,
-        height: new_height,
+        src,
+        width,
+        height,
+        output_path: Some(dest_path),
+        source_bytes: file_size(src_path),
+        output_bytes: 0,
+        elapsed: Duration::ZERO,
     })
 }
 
-/// Read dimensions from a cached WebP file.
-fn read_cached_dimensions(path: &Path, rel_path: String) -> Result<OptimizedImage, BuildError> {
-    match image::ImageReader::open(path) {
-        Ok(reader) => match reader.into_dimensions() {
-            Ok((w, h)) => Ok(OptimizedImage {
-                rel_path,
-                width: w,
-                height: h,
-            }),
-            Err(_) => Ok(OptimizedImage {
-                rel_path,
-                width: 0,
-                height: 0,
-            }),
-        },
-        Err(_) => Ok(OptimizedImage {
-            rel_path,
-            width: 0,
-            height: 0,
-        }),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn dark_variants_next_to_the_image() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("img")).unwrap();
+        fs::write(dir.path().join("img/chart.png"), b"").unwrap();
+        fs::write(dir.path().join("img/chart.dark.png"), b"").unwrap();
+
+        assert_eq!(dark_variant("img/chart.png?width=600", dir.path()).as_deref(), Some("img/chart.dark.png?width=600"));
+        assert_eq!(dark_variant("img/chart.dark.png", dir.path()), None);
+        assert_eq!(dark_variant("img/other.png", dir.path()), None);
+        assert_eq!(dark_variant("https://cdn.dev/chart.png", dir.path()), None);
+        assert!(is_dark_variant("img/chart.dark.png") && !is_dark_variant("img/dark.png"));
+    }
+
+    #[test]
+    fn bounds_contain_or_crop() {
+        let contain = ImageBounds { max_height: Some(1600), ..ImageBounds::width(1200) };
+        assert_eq!(contain.fit(1200, 4000), (480, 1600));
+        assert_eq!(contain.fit(2400, 1000), (1200, 500));
+        assert_eq!(contain.fit(800, 600), (800, 600));
+        assert_eq!(ImageBounds::width(1200).fit(1200, 4000), (1200, 4000));
+
+        let crop = ImageBounds { fit: ImageFit::Crop, ..contain };
+        assert_eq!(crop.fit(1200, 4000), (1200, 1600));
+        assert_eq!(crop.fit(3000, 4000), (1200, 1600));
+        assert_eq!(crop.fit(2400, 1000), (1200, 500));
+        assert_eq!(crop.fit(1000, 900), (1000, 900));
+    }
+
+    #[test]
+    fn query_overrides_width_and_format() {
+        assert_eq!(ImageQuery::parse("a.jpg"), Ok(("a.jpg", ImageQuery::default())));
+        assert!(ImageQuery::parse("a.jpg?quality=0").is_err());
+        assert!(ImageQuery::parse("a.jpg?size=2").is_err());
+
+        let defaults = ImageQuery { width: Some(800), fit: Some(ImageFit::Crop), ..Default::default() };
+        assert_eq!(defaults.apply_to("a.jpg"), "a.jpg?width=800&fit=crop");
+        assert_eq!(defaults.apply_to("a.jpg?width=600&quality=60"), "a.jpg?width=600&quality=60&fit=crop");
+        assert_eq!(defaults.apply_to("https://cdn.dev/a.jpg"), "https://cdn.dev/a.jpg");
+        assert_eq!(ImageQuery::default().apply_to("a.jpg"), "a.jpg");
+
+        let dir = tempfile::tempdir().unwrap();
+        image::RgbImage::new(40, 20).save(dir.path().join("a.png")).unwrap();
+        let public = dir.path().join("public");
+        fs::create_dir_all(public.join("images")).unwrap();
+        let (outputs, limit, cache) = (OutputSet::new(), DecodeLimit::default(), ImageCache::default());
+
+        let src = "a.png?width=10&format=jpeg&quality=50";
+        let image = optimize_image(src, dir.path(), &public, ImageBounds::width(1200), &outputs, &limit, &cache).unwrap();
+        let path = image.src.to_string();
+        assert!(path.starts_with("images/a-w10-q50.") && path.ends_with(".jpg"), "{path}");
+        assert_eq!((image.width, image.height), (10, 5));
+        assert_eq!(output_rel_path(src, dir.path()), Some(path.clone()));
+        assert!(image::ImageReader::open(public.join(&path)).unwrap().format() == Some(image::ImageFormat::Jpeg));
+        assert_eq!(output_rel_path("a.png?height=300&fit=crop", dir.path()), Some(path.replace("-w10-q50", "-h300-crop").replace(".jpg", ".webp")));
+        assert_eq!(output_rel_path("a.png?format=png", dir.path()), Some(path.replace("-w10-q50", "").replace(".jpg", ".png")));
+        assert_eq!(output_rel_path("missing.png", dir.path()), None);
+
+        // New contents, new name
+        image::RgbImage::new(20, 20).save(dir.path().join("a.png")).unwrap();
+        assert_ne!(output_rel_path(src, dir.path()), Some(path));
+    }
+
+    #[test]
+    fn decode_limit_bounds_concurrency() {
+        let limit = DecodeLimit::new(Some(2));
+        let active = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _permit = limit.acquire();
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(5));
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
     }
 }
@@ -0,0 +1,179 @@
+//! Sidecar manifest of optimized images.
+//!
+//! For every optimized image the build stores the source stamp (size and
+//! mtime), a content hash, the size limits it was encoded with, the
+//! output path and dimensions in `images/.ssg-images.json`, keyed by
+//! source path, so tools can rewrite links to the hashed output names. A cache hit then needs
+//! only a `stat` of the source: no image is opened. When the mtime changed
+//! but the size did not (fresh checkout, `touch`), the hash decides.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::BuildError;
+use crate::image::{ImageBounds, ImageFit, OptimizedImage};
+use crate::output::{content_hash, OutputSet};
+
+/// Sidecar file name inside the images output directory.
+pub const IMAGE_MANIFEST_FILE: &str = ".ssg-images.json";
+
+/// Cached facts about one optimized image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub source_len: u64,
+    pub source_modified: SystemTime,
+    /// FNV-1a hash of the source bytes, hex encoded.
+    pub hash: String,
+    pub max_width: u32,
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    #[serde(default)]
+    pub fit: ImageFit,
+    /// Output path relative to the public root (`images/a.1f2e3d4c.webp`).
+    #[serde(default)]
+    pub output: String,
+    pub width: u32,
+    pub height: u32,
+    pub output_bytes: u64,
+}
+
+impl CacheEntry {
+    /// Size limits the image was encoded with.
+    pub fn bounds(&self) -> ImageBounds {
+        ImageBounds { max_width: self.max_width, max_height: self.max_height, fit: self.fit }
+    }
+}
+
+/// Image cache of the previous build plus entries of the current one.
+#[derive(Debug, Default)]
+pub struct ImageCache {
+    previous: BTreeMap<String, CacheEntry>,
+    current: Mutex<BTreeMap<String, CacheEntry>>,
+}
+
+impl ImageCache {
+    /// Load the sidecar of `images_dir` (empty if missing or unreadable).
+    pub fn load(images_dir: &Path) -> Self {
+        let previous = fs::read_to_string(images_dir.join(IMAGE_MANIFEST_FILE))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Self {
+            previous,
+            current: Mutex::default(),
+        }
+    }
+
+    /// Whether the previous build recorded `key` at all.
+    pub fn contains(&self, key: &str) -> bool {
+        self.previous.contains_key(key)
+    }
+
+    /// Look up a still-valid entry for `key` (the source path relative to
+    /// the content directory).
+    pub fn lookup(&self, key: &str, src_path: &Path, bounds: ImageBounds) -> Option<CacheEntry> {
+        let entry = self.previous.get(key)?;
+        let meta = fs::metadata(src_path).ok()?;
+        if entry.bounds() != bounds || entry.source_len != meta.len() {
+            return None;
+        }
+
+        let modified = meta.modified().ok()?;
+        if entry.source_modified != modified {
+            // Same size, new mtime: only the contents can tell
+            if content_hash(src_path)? != entry.hash {
+                return None;
+            }
+            let refreshed = CacheEntry { source_modified: modified, ..entry.clone() };
+            return Some(self.keep(key, refreshed));
+        }
+
+        Some(self.keep(key, entry.clone()))
+    }
+
+    /// Record a freshly optimized image.
+    pub fn insert(&self, key: &str, src_path: &Path, bounds: ImageBounds, image: &OptimizedImage) {
+        let Ok(meta) = fs::metadata(src_path) else {
+            return;
+        };
+        let (Ok(modified), Some(hash)) = (meta.modified(), content_hash(src_path)) else {
+            return;
+        };
+        self.keep(key, CacheEntry {
+            source_len: meta.len(),
+            source_modified: modified,
+            hash,
+            max_width: bounds.max_width,
+            max_height: bounds.max_height,
+            fit: bounds.fit,
+            output: image.src.to_string(),
+            width: image.width,
+            height: image.height,
+            output_bytes: image.output_bytes,
+        });
+    }
+
+    fn keep(&self, key: &str, entry: CacheEntry) -> CacheEntry {
+        self.current.lock().unwrap().insert(key.to_string(), entry.clone());
+        entry
+    }
+
+    /// Write the entries used by this build to the sidecar.
+    pub fn save(&self, images_dir: &Path, outputs: &OutputSet) -> Result<(), BuildError> {
+        let json = serde_json::to_string_pretty(&*self.current.lock().unwrap())
+            .map_err(|e| BuildError::Internal(format!("Failed to serialize image cache: {}", e)))?;
+        outputs.write(&images_dir.join(IMAGE_MANIFEST_FILE), json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+
+    fn touch(path: &Path) {
+        let later = fs::metadata(path).unwrap().modified().unwrap() + Duration::from_secs(10);
+        File::options().write(true).open(path).unwrap().set_modified(later).unwrap();
+    }
+
+    #[test]
+    fn round_trip_and_invalidation() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("a.png");
+        fs::write(&src, b"pixels").unwrap();
+
+        let cache = ImageCache::load(dir.path());
+        let image = OptimizedImage {
+            width: 10,
+            height: 20,
+            output_bytes: 99,
+            ..OptimizedImage::missing("images/a.0123abcd.webp")
+        };
+        cache.insert("a.png", &src, ImageBounds::width(1200), &image);
+        cache.save(dir.path(), &OutputSet::new()).unwrap();
+
+        let cache = ImageCache::load(dir.path());
+        let entry = cache.lookup("a.png", &src, ImageBounds::width(1200)).unwrap();
+        assert_eq!((entry.width, entry.height, entry.output_bytes), (10, 20, 99));
+        assert_eq!(entry.output, "images/a.0123abcd.webp");
+
+        // A new mtime alone is confirmed by the hash
+        touch(&src);
+        assert!(cache.lookup("a.png", &src, ImageBounds::width(1200)).is_some());
+
+        // Different size limits or different contents invalidate the entry
+        assert!(cache.lookup("a.png", &src, ImageBounds::width(800)).is_none());
+        let cropped = ImageBounds { max_height: Some(600), fit: ImageFit::Crop, ..ImageBounds::width(1200) };
+        assert!(cache.lookup("a.png", &src, cropped).is_none());
+        fs::write(&src, b"PIXELS").unwrap();
+        touch(&src);
+        assert!(cache.lookup("a.png", &src, ImageBounds::width(1200)).is_none());
+        assert!(cache.lookup("b.png", &src, ImageBounds::width(1200)).is_none());
+    }
+}
@@ -0,0 +1,95 @@
+//! Image sidecars: `photo.jpg.meta.toml` next to `photo.jpg`.
+//!
+//! ```toml
+//! alt = "A duck on a pond"
+//! caption = "Morning at the pond"
+//! credit = "Jane Doe"
+//! license = "CC BY 4.0"
+//! ```
+//!
+//! Every embed of the image uses the sidecar: its alt text and caption
+//! fill in what the markdown leaves empty, and the credit and license are
+//! shown under the figure.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::BuildError;
+use crate::types::EscapeHtml;
+
+/// Appended to the image file name to get its sidecar.
+pub const META_SUFFIX: &str = ".meta.toml";
+
+/// Contents of an image sidecar.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageMeta {
+    pub alt: Option<String>,
+    /// Plain text, used when the markdown gives no caption.
+    pub caption: Option<String>,
+    pub license: Option<String>,
+    pub credit: Option<String>,
+}
+
+impl ImageMeta {
+    /// Read the sidecar of the image `src` (relative to `content_dir`, a
+    /// query is ignored); None for external images and images without one.
+    pub fn load(src: &str, content_dir: &Path) -> Result<Option<Self>, BuildError> {
+        if src.starts_with("http://") || src.starts_with("https://") {
+            return Ok(None);
+        }
+        let path = src.split('?').next().unwrap_or(src);
+        let path = content_dir.join(format!("{path}{META_SUFFIX}"));
+        let invalid = |message: String| BuildError::DataInvalid { path: path.clone(), message };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(invalid(e.to_string())),
+        };
+        toml::from_str(&text).map(Some).map_err(|e| invalid(e.message().to_string()))
+    }
+
+    /// Alt text from the sidecar, if any.
+    pub fn alt(&self) -> Option<&str> {
+        self.alt.as_deref().filter(|alt| !alt.trim().is_empty())
+    }
+
+    /// `<small class="image-credit">` line with the credit and license,
+    /// if the sidecar has either.
+    pub fn credit_html(&self) -> Option<String> {
+        let text = match (self.credit.as_deref(), self.license.as_deref()) {
+            (Some(credit), Some(license)) => format!("{credit} ({license})"),
+            (Some(text), None) | (None, Some(text)) => text.to_string(),
+            (None, None) => return None,
+        };
+        Some(format!(r#"<small class="image-credit">{}</small>"#, text.escape_html()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_sidecar_next_to_the_image() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("trip")).unwrap();
+        fs::write(
+            dir.path().join("trip/duck.jpg.meta.toml"),
+            "alt = \"A duck\"\ncredit = \"Jane & co\"\nlicense = \"CC BY 4.0\"\n",
+        ).unwrap();
+
+        let meta = ImageMeta::load("trip/duck.jpg?width=600", dir.path()).unwrap().unwrap();
+        assert_eq!(meta.alt(), Some("A duck"));
+        assert_eq!(meta.credit_html().unwrap(), r#"<small class="image-credit">Jane &amp; co (CC BY 4.0)</small>"#);
+        assert_eq!(ImageMeta::load("trip/other.jpg", dir.path()).unwrap(), None);
+        assert_eq!(ImageMeta::load("https://cdn.dev/trip/duck.jpg", dir.path()).unwrap(), None);
+        assert_eq!(ImageMeta::default().credit_html(), None);
+
+        fs::write(dir.path().join("trip/duck.jpg.meta.toml"), "author = \"Jane\"\n").unwrap();
+        assert!(matches!(ImageMeta::load("trip/duck.jpg", dir.path()), Err(BuildError::DataInvalid { .. })));
+    }
+}
@@ -0,0 +1,601 @@
+//! `ssg import`: posts from Jekyll, Hugo and Zola (WordPress exports are
+//! in [`crate::wordpress`]).
+//!
+//! Each post's metadata is rewritten into this crate's conventions:
+//!
+//! - the title becomes the `# ` heading (unless the body has one) and tags
+//!   and categories the `Tags:` line
+//! - `date` and `lastmod`/`updated`/`last_modified_at` become `date:` and
+//!   `updated:` front matter, the filename date (`2019-05-04-name.md`)
+//!   filling in a missing `date`
+//! - `description`/`summary`/`excerpt` becomes `summary:`, and `aliases`,
+//!   `redirect_from` and a Jekyll `permalink` become `aliases:`
+//! - the `slug` (else the file stem, or the directory of a Hugo or Zola
+//!   page bundle) names the new file
+//!
+//! Local images are copied into the content directory and their paths
+//! rewritten: root-absolute paths (`/images/a.png`) come from the static
+//! directory (the site root for Jekyll), relative ones from next to the
+//! post, and a bundle's images move into a directory named like the post.
+//! Drafts are left out. Files that already exist are never overwritten.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use pulldown_cmark::{Event, Options, Parser, Tag as MdTag};
+
+use crate::error::BuildError;
+use crate::front_matter;
+use crate::types::Tag;
+use crate::wordpress;
+
+/// Static site generators posts can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    Jekyll,
+    Hugo,
+    Zola,
+    /// A WordPress export (WXR) file rather than a site directory.
+    WordPress,
+}
+
+impl Generator {
+    /// Generator named on the command line (`jekyll`, `hugo`, `zola`).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "jekyll" => Some(Self::Jekyll),
+            "hugo" => Some(Self::Hugo),
+            "zola" => Some(Self::Zola),
+            "wordpress" => Some(Self::WordPress),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Jekyll => "Jekyll",
+            Self::Hugo => "Hugo",
+            Self::Zola => "Zola",
+            Self::WordPress => "WordPress",
+        }
+    }
+
+    /// Directory the posts of the site at `root` are in.
+    fn posts_dir(self, root: &Path) -> PathBuf {
+        match self {
+            Self::Jekyll => root.join("_posts"),
+            Self::Hugo | Self::Zola => root.join("content"),
+            Self::WordPress => root.to_path_buf(),
+        }
+    }
+
+    /// Directory root-absolute paths of the site at `root` point into.
+    fn static_dir(self, root: &Path) -> PathBuf {
+        match self {
+            Self::Jekyll | Self::WordPress => root.to_path_buf(),
+            Self::Hugo | Self::Zola => root.join("static"),
+        }
+    }
+}
+
+/// What an import did (or would do, in a dry run).
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// Imported posts: the source file and the new post.
+    pub posts: Vec<(PathBuf, PathBuf)>,
+    /// Images copied into the content directory.
+    pub images: usize,
+    /// Drafts left out.
+    pub drafts: usize,
+    /// Skipped posts, dropped values and missing images.
+    pub warnings: Vec<BuildError>,
+}
+
+/// Import the posts of the `generator` site at `source` into
+/// `content_dir`. With `dry_run`, nothing is written.
+pub fn import(generator: Generator, source: &Path, content_dir: &Path, dry_run: bool) -> Result<ImportSummary, BuildError> {
+    if generator == Generator::WordPress {
+        return wordpress::import(source, content_dir, dry_run);
+    }
+    let posts_dir = generator.posts_dir(source);
+    let static_dir = generator.static_dir(source);
+    let mut files = Vec::new();
+    markdown_files(&posts_dir, &mut files)
+        .map_err(|e| BuildError::ContentNotReadable { path: posts_dir.clone(), source: e })?;
+    files.sort();
+
+    let mut summary = ImportSummary::default();
+    let mut targets = HashSet::new();
+    let mut copied = HashSet::new();
+    for path in files {
+        let issue = |message: String| BuildError::ImportFailed { path: path.clone(), message };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                summary.warnings.push(issue(e.to_string()));
+                continue;
+            }
+        };
+        let post = match convert(generator, &path, &text) {
+            Ok(post) => post,
+            Err(message) => {
+                summary.warnings.push(issue(message));
+                continue;
+            }
+        };
+        if post.draft {
+            summary.drafts += 1;
+            continue;
+        }
+
+        let target = content_dir.join(format!("{}.md", post.slug));
+        if target.exists() || !targets.insert(target.clone()) {
+            summary.warnings.push(issue(format!("skipped: {} already exists", target.display())));
+            continue;
+        }
+        let images = post.images.iter().map(|image| {
+            let from = match &image.from {
+                ImageSource::Static(rel) => static_dir.join(rel),
+                ImageSource::Post(rel) => path.parent().unwrap_or(Path::new("")).join(rel),
+            };
+            (from, content_dir.join(&image.to))
+        });
+        for (from, to) in images {
+            if !from.is_file() {
+                summary.warnings.push(BuildError::ImageNotFound { path: from });
+                continue;
+            }
+            if to.exists() || !copied.insert(to.clone()) {
+                continue;
+            }
+            if !dry_run {
+                copy(&from, &to)?;
+            }
+            summary.images += 1;
+        }
+        if !dry_run {
+            write(&target, &post.markdown)?;
+        }
+        summary.warnings.extend(post.warnings);
+        summary.posts.push((path, target));
+    }
+    Ok(summary)
+}
+
+/// Posts under `dir`, recursively. `_index.md` section pages are not
+/// posts.
+fn markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            markdown_files(&path, files)?;
+        } else if path.extension().is_some_and(|e| e == "md" || e == "markdown")
+            && path.file_name().is_some_and(|name| name != "_index.md")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn write(path: &Path, contents: &str) -> Result<(), BuildError> {
+    let not_writable = |e| BuildError::OutputNotWritable { path: path.to_path_buf(), source: e };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(not_writable)?;
+    }
+    fs::write(path, contents).map_err(not_writable)
+}
+
+fn copy(from: &Path, to: &Path) -> Result<(), BuildError> {
+    let not_writable = |e| BuildError::OutputNotWritable { path: to.to_path_buf(), source: e };
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(not_writable)?;
+    }
+    fs::copy(from, to).map(drop).map_err(not_writable)
+}
+
+/// A post in this crate's format.
+#[derive(Debug)]
+struct Converted {
+    /// File stem of the new post.
+    slug: String,
+    markdown: String,
+    draft: bool,
+    images: Vec<ImageCopy>,
+    /// Dropped tags and dates.
+    warnings: Vec<BuildError>,
+}
+
+/// A local image the post uses.
+#[derive(Debug, PartialEq, Eq)]
+struct ImageCopy {
+    from: ImageSource,
+    /// Path in the new post, relative to the content directory.
+    to: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ImageSource {
+    /// Relative to the static directory.
+    Static(String),
+    /// Relative to the post.
+    Post(String),
+}
+
+/// Metadata of a post, in the generator's own terms.
+#[derive(Debug, Default)]
+struct Meta {
+    title: Option<String>,
+    date: Option<String>,
+    updated: Option<String>,
+    summary: Option<String>,
+    slug: Option<String>,
+    tags: Vec<String>,
+    aliases: Vec<String>,
+    draft: bool,
+}
+
+impl Meta {
+    /// From YAML front matter.
+    fn from_yaml(front: &front_matter::FrontMatter, generator: Generator) -> Self {
+        let first = |keys: &[&str]| keys.iter().find_map(|key| front.get(key)).map(str::to_string);
+        let mut tags = Vec::new();
+        for key in ["tags", "categories", "category"] {
+            // Jekyll also takes `tags: one two` for a list
+            match front.get(key) {
+                Some(list) if generator == Generator::Jekyll => tags.extend(list.split_whitespace().map(str::to_string)),
+                _ => tags.extend(front.list(key)),
+            }
+        }
+        let mut aliases = front.list("aliases");
+        aliases.extend(front.list("redirect_from"));
+        if generator == Generator::Jekyll
+            && let Some(permalink) = front.get("permalink")
+        {
+            aliases.push(permalink.to_string());
+        }
+        Self {
+            title: first(&["title"]),
+            date: first(&["date"]),
+            updated: first(&["last_modified_at", "lastmod", "updated"]),
+            summary: first(&["description", "summary", "excerpt"]),
+            slug: first(&["slug"]),
+            tags,
+            aliases,
+            draft: front.flag("draft") || front.disabled("published"),
+        }
+    }
+
+    /// From TOML front matter; Zola keeps tags under `[taxonomies]`.
+    fn from_toml(table: &toml::Table) -> Self {
+        let first = |keys: &[&str]| keys.iter().find_map(|key| table.get(*key).and_then(toml_string));
+        let mut tags = Vec::new();
+        let taxonomies = table.get("taxonomies").and_then(toml::Value::as_table);
+        for table in [Some(table), taxonomies].into_iter().flatten() {
+            for key in ["tags", "categories"] {
+                tags.extend(table.get(key).map(toml_list).unwrap_or_default());
+            }
+        }
+        Self {
+            title: first(&["title"]),
+            date: first(&["date"]),
+            updated: first(&["lastmod", "updated"]),
+            summary: first(&["description", "summary"]),
+            slug: first(&["slug"]),
+            tags,
+            aliases: table.get("aliases").map(toml_list).unwrap_or_default(),
+            draft: table.get("draft").and_then(toml::Value::as_bool).unwrap_or(false),
+        }
+    }
+}
+
+fn toml_string(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Datetime(d) => Some(d.to_string()),
+        _ => None,
+    }
+}
+
+fn toml_list(value: &toml::Value) -> Vec<String> {
+    match value {
+        toml::Value::Array(items) => items.iter().filter_map(toml_string).collect(),
+        other => toml_string(other).into_iter().collect(),
+    }
+}
+
+/// Split a post into its `+++` TOML front matter and body.
+fn split_toml(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix("+++\n").or_else(|| text.strip_prefix("+++\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "+++" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Rewrite the post `text` at `path`; Err explains why it can't be.
+fn convert(generator: Generator, path: &Path, text: &str) -> Result<Converted, String> {
+    let (meta, body) = match split_toml(text) {
+        Some((block, body)) => {
+            let table: toml::Table = block.parse().map_err(|e: toml::de::Error| format!("invalid TOML front matter: {}", e.message()))?;
+            (Meta::from_toml(&table), body)
+        }
+        None => {
+            let (front, body) = front_matter::split(text);
+            (Meta::from_yaml(&front, generator), body)
+        }
+    };
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).ok_or("file name is not UTF-8")?;
+    // Hugo and Zola page bundles: `my-post/index.md` with its images
+    let bundle = generator != Generator::Jekyll && stem == "index";
+    let slug = match (&meta.slug, bundle) {
+        (Some(slug), _) => slug.trim_matches('/').rsplit('/').next().unwrap_or_default().to_string(),
+        (None, true) => path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .and_then(|name| name.to_str())
+            .ok_or("page bundle has no directory name")?
+            .to_string(),
+        (None, false) => stem.to_string(),
+    };
+    let slug = slug.replace(char::is_whitespace, "-");
+    if slug.is_empty() || slug.starts_with('.') {
+        return Err(format!("unusable slug {slug:?}"));
+    }
+
+    let mut warnings = Vec::new();
+    let mut date = |raw: Option<&str>, key: &str| {
+        let raw = raw?;
+        let date = normalize_date(raw);
+        if date.is_none() {
+            warnings.push(BuildError::ImportFailed { path: path.to_path_buf(), message: format!("dropped {key} {raw:?}") });
+        }
+        date
+    };
+    let filename_date = stem.get(..11).filter(|prefix| prefix.ends_with('-')).map(|prefix| &prefix[..10]);
+    let created = date(meta.date.as_deref(), "date").or_else(|| filename_date.and_then(normalize_date));
+    let updated = date(meta.updated.as_deref(), "updated");
+
+    let tags = valid_tags(&meta.tags, path, &mut warnings);
+
+    let body = if generator == Generator::Jekyll {
+        body.replace("{{ site.baseurl }}", "").replace("{{site.baseurl}}", "")
+    } else {
+        body.to_string()
+    };
+    let (body, images) = rewrite_images(&body, bundle.then_some(slug.as_str()));
+
+    let header = Header { title: meta.title, date: created, updated, summary: meta.summary, tags, aliases: meta.aliases };
+    let markdown = header.markdown(&body);
+    Ok(Converted { slug, markdown, draft: meta.draft, images, warnings })
+}
+
+/// Metadata of an imported post, in this crate's terms.
+#[derive(Debug, Default)]
+pub(crate) struct Header {
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub updated: Option<String>,
+    pub summary: Option<String>,
+    pub tags: Vec<String>,
+    pub aliases: Vec<String>,
+}
+
+impl Header {
+    /// The post: front matter, the title and `Tags:` line, then `body`.
+    pub fn markdown(&self, body: &str) -> String {
+        let mut front = Vec::new();
+        front.extend(self.date.as_ref().map(|d| format!("date: {d}")));
+        front.extend(self.updated.as_ref().map(|d| format!("updated: {d}")));
+        if !self.aliases.is_empty() {
+            front.push(format!("aliases: [{}]", self.aliases.join(", ")));
+        }
+        front.extend(self.summary.as_ref().map(|s| format!("summary: {}", one_line(s))));
+
+        let mut markdown = String::new();
+        if !front.is_empty() {
+            markdown.push_str(&format!("---\n{}\n---\n", front.join("\n")));
+        }
+        let tags_line = (!self.tags.is_empty()).then(|| format!("Tags: {}\n\n", self.tags.join(", ")));
+        let body = body.trim_start_matches(['\n', '\r']);
+        match body.lines().position(|line| line.starts_with("# ")) {
+            // The body's own title: the tags go below it
+            Some(index) => {
+                let split = body.split_inclusive('\n').take(index + 1).map(str::len).sum();
+                let (heading, rest) = body.split_at(split);
+                markdown.push_str(heading);
+                if let Some(tags_line) = &tags_line {
+                    markdown.push_str(if heading.ends_with('\n') { "\n" } else { "\n\n" });
+                    markdown.push_str(tags_line);
+                }
+                markdown.push_str(rest.trim_start_matches(['\n', '\r']));
+            }
+            None => {
+                if let Some(title) = &self.title {
+                    markdown.push_str(&format!("# {}\n\n", one_line(title)));
+                }
+                markdown.push_str(tags_line.as_deref().unwrap_or_default());
+                markdown.push_str(body);
+            }
+        }
+        markdown
+    }
+}
+
+/// `raw` tags that are valid, without case-insensitive duplicates; the
+/// others are warnings.
+pub(crate) fn valid_tags(raw: &[String], path: &Path, warnings: &mut Vec<BuildError>) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for raw in raw {
+        match Tag::new(raw) {
+            Ok(tag) if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag.as_str())) => tags.push(tag.as_str().to_string()),
+            Ok(_) => {}
+            Err(BuildError::InvalidTag { tag, reason, position, .. }) => {
+                warnings.push(BuildError::InvalidTag { tag, reason, path: Some(path.to_path_buf()), position });
+            }
+            Err(e) => warnings.push(e),
+        }
+    }
+    tags
+}
+
+pub(crate) fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `raw` in a form `date:` front matter accepts, if it is a date.
+pub(crate) fn normalize_date(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if DateTime::parse_from_rfc3339(raw).is_ok() {
+        return Some(raw.to_string());
+    }
+    // Jekyll: `2019-05-04 10:30:00 +0200`
+    for format in ["%Y-%m-%d %H:%M:%S %z", "%Y-%m-%d %H:%M %z"] {
+        if let Ok(date) = DateTime::parse_from_str(raw, format) {
+            return Some(date.to_rfc3339());
+        }
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(raw, format) {
+            return Some(date.format("%Y-%m-%d %H:%M").to_string());
+        }
+    }
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d").ok().map(|date| date.to_string())
+}
+
+/// Point local images in `body` at their new place, which is in a
+/// directory named `bundle` for a page bundle's images.
+fn rewrite_images(body: &str, bundle: Option<&str>) -> (String, Vec<ImageCopy>) {
+    let mut replacements = Vec::new();
+    let mut images = Vec::new();
+    for (event, range) in Parser::new_ext(body, Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES | Options::ENABLE_STRIKETHROUGH).into_offset_iter() {
+        let Event::Start(MdTag::Image { dest_url, .. }) = event else { continue };
+        let Some(image) = local_image(&dest_url, bundle) else { continue };
+        // The destination as written, at the end of `![alt](dest)`
+        if let Some(offset) = body[range.clone()].rfind(&*dest_url) {
+            let start = range.start + offset;
+            replacements.push((start..start + dest_url.len(), image.to.clone()));
+        }
+        if !images.contains(&image) {
+            images.push(image);
+        }
+    }
+
+    let mut rewritten = body.to_string();
+    for (range, to) in replacements.into_iter().rev() {
+        rewritten.replace_range(range, &to);
+    }
+    (rewritten, images)
+}
+
+fn local_image(src: &str, bundle: Option<&str>) -> Option<ImageCopy> {
+    if src.is_empty() || src.starts_with("//") || src.starts_with("data:") || src.contains("://") {
+        return None;
+    }
+    if let Some(rel) = src.strip_prefix('/') {
+        let rel = normalize(rel)?;
+        return Some(ImageCopy { from: ImageSource::Static(rel.clone()), to: rel });
+    }
+    let rel = src.trim_start_matches("./");
+    let name = normalize(rel)?;
+    let to = match bundle {
+        Some(slug) => format!("{slug}/{name}"),
+        None => name,
+    };
+    Some(ImageCopy { from: ImageSource::Post(rel.to_string()), to })
+}
+
+/// `path` without `.` and `..` segments; `..` segments are dropped, so
+/// an image from outside the post's directory lands next to it.
+pub(crate) fn normalize(path: &str) -> Option<String> {
+    let parts: Vec<_> = Path::new(path)
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_jekyll_posts() {
+        let text = "---\nlayout: post\ntitle: \"Hello, Jekyll\"\ndate: 2019-05-04 10:30:00 +0200\n\
+            tags: rust web\ncategories: [Notes]\nredirect_from: /old/hello\nexcerpt: A first post.\n---\n\
+            Intro ![Duck]({{ site.baseurl }}/assets/duck.png) and ![](https://example.com/x.png).\n";
+        let post = convert(Generator::Jekyll, Path::new("_posts/2019-05-04-hello.md"), text).unwrap();
+        assert_eq!(post.slug, "2019-05-04-hello");
+        assert_eq!(
+            post.markdown,
+            "---\ndate: 2019-05-04T10:30:00+02:00\naliases: [/old/hello]\nsummary: A first post.\n---\n\
+             # Hello, Jekyll\n\nTags: rust, web, Notes\n\n\
+             Intro ![Duck](assets/duck.png) and ![](https://example.com/x.png).\n"
+        );
+        assert_eq!(post.images, [ImageCopy { from: ImageSource::Static("assets/duck.png".to_string()), to: "assets/duck.png".to_string() }]);
+        assert!(post.warnings.is_empty());
+
+        let draft = convert(Generator::Jekyll, Path::new("_posts/x.md"), "---\npublished: false\n---\nText\n").unwrap();
+        assert!(draft.draft);
+    }
+
+    #[test]
+    fn converts_zola_and_hugo_posts() {
+        let text = "+++\ntitle = \"Bundled\"\ndate = 2020-01-02\nupdated = \"yesterday\"\n\n[taxonomies]\ntags = [\"zola\", \"a<b\"]\n+++\n\
+            # Own title\nSee ![map](./map.png).\n";
+        let post = convert(Generator::Zola, Path::new("content/trip/index.md"), text).unwrap();
+        assert_eq!(post.slug, "trip");
+        assert_eq!(post.markdown, "---\ndate: 2020-01-02\n---\n# Own title\n\nTags: zola\n\nSee ![map](trip/map.png).\n");
+        assert_eq!(post.images, [ImageCopy { from: ImageSource::Post("map.png".to_string()), to: "trip/map.png".to_string() }]);
+        let messages: Vec<String> = post.warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].ends_with("dropped updated \"yesterday\""), "{}", messages[0]);
+        assert!(messages[1].contains("'a<b'"), "{}", messages[1]);
+
+        let hugo = "---\ntitle: Moved\nslug: new-name\ndate: 2021-03-04T05:06:07\naliases:\n  - /posts/old/\ndraft: false\n---\nBody\n";
+        let post = convert(Generator::Hugo, Path::new("content/posts/old.md"), hugo).unwrap();
+        assert_eq!(post.slug, "new-name");
+        assert_eq!(post.markdown, "---\ndate: 2021-03-04 05:06\naliases: [/posts/old/]\n---\n# Moved\n\nBody\n");
+    }
+
+    #[test]
+    fn imports_into_the_content_directory() {
+        let site = tempfile::tempdir().unwrap();
+        let posts = site.path().join("content/posts");
+        fs::create_dir_all(posts.join("trip")).unwrap();
+        fs::create_dir_all(site.path().join("static/img")).unwrap();
+        fs::write(posts.join("_index.md"), "+++\ntitle = \"Posts\"\n+++\n").unwrap();
+        fs::write(posts.join("trip/index.md"), "---\ntitle: Trip\n---\n![a](a.png) ![b](/img/b.png) ![c](c.png)\n").unwrap();
+        fs::write(posts.join("trip/a.png"), "png").unwrap();
+        fs::write(site.path().join("static/img/b.png"), "png").unwrap();
+        fs::write(posts.join("wip.md"), "---\ndraft: true\n---\n").unwrap();
+        fs::write(posts.join("taken.md"), "# Taken\n").unwrap();
+
+        let content = tempfile::tempdir().unwrap();
+        fs::write(content.path().join("taken.md"), "# Mine\n").unwrap();
+        let dry = import(Generator::Hugo, site.path(), content.path(), true).unwrap();
+        assert_eq!(dry.posts.len(), 1);
+        assert!(!content.path().join("trip.md").exists());
+
+        let summary = import(Generator::Hugo, site.path(), content.path(), false).unwrap();
+        assert_eq!(summary.posts, [(posts.join("trip/index.md"), content.path().join("trip.md"))]);
+        assert_eq!((summary.images, summary.drafts), (2, 1));
+        assert!(content.path().join("trip/a.png").is_file());
+        assert!(content.path().join("img/b.png").is_file());
+        assert_eq!(fs::read_to_string(content.path().join("taken.md")).unwrap(), "# Mine\n");
+        let categories: Vec<&str> = summary.warnings.iter().map(BuildError::category).collect();
+        assert_eq!(categories, ["import_failed", "image_not_found"]);
+
+        assert!(import(Generator::Jekyll, site.path(), content.path(), true).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! `{{include "..."}}` directives for shared markdown.
+//!
+//! A line consisting of `{{include "fragments/disclaimer.md"}}` is replaced
+//! by that file, relative to the content directory (like images), before
+//! the post is parsed. Included files may include others; cycles and
+//! missing files fail the post. Directives inside fenced code blocks are
+//! left alone, so they can be documented.
+//!
+//! Errors point at the directive in the post's body that led to them,
+//! also when the failing one is in a fragment.
+//!
+//! Fragments live in subdirectories, which are not scanned for posts.
+//! Every file pulled in is returned as a dependency, so that editing a
+//! fragment rebuilds the posts using it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{BuildError, SourcePos};
+use crate::parser::lines_outside_code;
+
+/// Deepest include nesting allowed.
+const MAX_DEPTH: usize = 16;
+
+/// Markdown with includes expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expanded {
+    pub text: String,
+    /// Included files, in first-use order, without duplicates.
+    pub dependencies: Vec<PathBuf>,
+}
+
+/// Expand the includes in `markdown`, the body of `path`.
+pub fn expand(markdown: &str, path: &Path, content_dir: &Path) -> Result<Expanded, BuildError> {
+    let mut expanded = Expanded {
+        text: String::with_capacity(markdown.len()),
+        dependencies: Vec::new(),
+    };
+    let mut stack = vec![path.to_path_buf()];
+    expand_into(markdown, content_dir, &mut stack, &mut expanded, None)?;
+    Ok(expanded)
+}
+
+fn expand_into(
+    markdown: &str,
+    content_dir: &Path,
+    stack: &mut Vec<PathBuf>,
+    out: &mut Expanded,
+    origin: Option<SourcePos>,
+) -> Result<(), BuildError> {
+    for (index, (line, in_code)) in lines_outside_code(markdown).enumerate() {
+        let Some(target) = (!in_code).then(|| directive(line.trim())).flatten() else {
+            out.text.push_str(line);
+            continue;
+        };
+        // The directive in the post this one came from
+        let indent = line.len() - line.trim_start().len();
+        let position = origin.unwrap_or(SourcePos::new(index + 1, line[..indent].chars().count() + 1));
+        let failed = |stack: &[PathBuf], message: String| BuildError::ParseFailed {
+            path: stack[0].clone(),
+            position: Some(position),
+            message,
+        };
+
+        let file = content_dir.join(target);
+        if stack.contains(&file) {
+            let chain: Vec<String> = stack.iter().chain([&file]).map(|p| p.display().to_string()).collect();
+            return Err(failed(stack, format!("include cycle: {}", chain.join(" -> "))));
+        }
+        if stack.len() > MAX_DEPTH {
+            return Err(failed(stack, format!("includes nested deeper than {MAX_DEPTH}")));
+        }
+        let included = fs::read_to_string(&file)
+            .map_err(|e| failed(stack, format!("cannot include {}: {}", file.display(), e)))?;
+        if !out.dependencies.contains(&file) {
+            out.dependencies.push(file.clone());
+        }
+
+        stack.push(file);
+        expand_into(&included, content_dir, stack, out, Some(position))?;
+        stack.pop();
+        if !out.text.ends_with('\n') && line.ends_with('\n') {
+            out.text.push('\n');
+        }
+    }
+    Ok(())
+}
+
+/// Target of an include directive line: `{{include "path"}}`, spaces allowed.
+fn directive(line: &str) -> Option<&str> {
+    let inner = line.strip_prefix("{{")?.strip_suffix("}}")?.trim();
+    let target = inner.strip_prefix("include")?.trim();
+    target.strip_prefix('"')?.strip_suffix('"').filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_nested_includes_outside_code() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("fragments")).unwrap();
+        fs::write(dir.path().join("fragments/a.md"), "A start\n{{ include \"fragments/b.md\" }}\nA end").unwrap();
+        fs::write(dir.path().join("fragments/b.md"), "B\n").unwrap();
+
+        let post = dir.path().join("post.md");
+        let body = "# Post\n{{include \"fragments/a.md\"}}\n```\n{{include \"fragments/b.md\"}}\n```\n";
+        let expanded = expand(body, &post, dir.path()).unwrap();
+        assert_eq!(expanded.text, "# Post\nA start\nB\nA end\n```\n{{include \"fragments/b.md\"}}\n```\n");
+        assert_eq!(expanded.dependencies, [dir.path().join("fragments/a.md"), dir.path().join("fragments/b.md")]);
+    }
+
+    #[test]
+    fn cycles_and_missing_files_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.md"), "{{include \"b.md\"}}\n").unwrap();
+        fs::write(dir.path().join("b.md"), "{{include \"a.md\"}}\n").unwrap();
+
+        let err = expand("Intro\n  {{include \"a.md\"}}\n", &dir.path().join("post.md"), dir.path()).unwrap_err();
+        assert!(err.to_string().contains("include cycle"), "{err}");
+        assert_eq!(err.position(), Some(SourcePos::new(2, 3)));
+        assert!(expand("{{include \"nope.md\"}}\n", &dir.path().join("post.md"), dir.path()).is_err());
+    }
+}
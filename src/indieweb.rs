@@ -0,0 +1,79 @@
+//! IndieWeb discovery: Webmention and pingback endpoints, author h-card.
+//!
+//! Post pages are marked up as `h-entry` (title, permalink, dates, tags,
+//! content and author) so readers and Webmention receivers can parse them;
+//! the `[indieweb]` table names the author and the endpoints advertised on
+//! every page:
+//!
+//! ```toml
+//! [indieweb]
+//! author = "Duck"
+//! author_url = "https://duck.example"
+//! webmention = "https://webmention.io/duck.example/webmention"
+//! pingback = "https://webmention.io/duck.example/xmlrpc"
+//! ```
+
+use serde::Deserialize;
+
+use crate::types::EscapeHtml;
+
+/// IndieWeb settings (the `[indieweb]` table).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndieWebConfig {
+    /// Author name of the posts' h-card (default: the brand name).
+    pub author: Option<String>,
+    /// Author home page (default: `base_url`, else the index).
+    pub author_url: Option<String>,
+    /// Author photo URL.
+    pub author_photo: Option<String>,
+    /// Webmention endpoint, linked with `rel="webmention"`.
+    pub webmention: Option<String>,
+    /// Pingback endpoint, linked with `rel="pingback"`.
+    pub pingback: Option<String>,
+}
+
+impl IndieWebConfig {
+    /// `<link>` tags for the configured endpoints.
+    pub fn head_links(&self) -> String {
+        [("webmention", &self.webmention), ("pingback", &self.pingback)]
+            .into_iter()
+            .filter_map(|(rel, href)| {
+                href.as_ref().map(|href| format!(r#"<link rel="{rel}" href="{}">"#, href.escape_html()))
+            })
+            .collect()
+    }
+
+    /// `p-author h-card` for a post, `hidden` unless it is shown; `brand`
+    /// and `home` stand in for an unset author and URL.
+    pub fn author_card(&self, brand: &str, home: &str, hidden: bool) -> String {
+        let name = self.author.as_deref().unwrap_or(brand).escape_html();
+        let url = self.author_url.as_deref().unwrap_or(home).escape_html();
+        let photo = self
+            .author_photo
+            .as_ref()
+            .map(|src| format!(r#"<img class="u-photo" src="{}" alt="">"#, src.escape_html()))
+            .unwrap_or_default();
+        let hidden = if hidden { " hidden" } else { "" };
+        format!(r#"<a class="p-author h-card" href="{url}"{hidden}>{photo}{name}</a>"#)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_and_author_card() {
+        let config = IndieWebConfig {
+            webmention: Some("https://webmention.io/x/webmention".to_string()),
+            ..IndieWebConfig::default()
+        };
+        assert_eq!(config.head_links(), r#"<link rel="webmention" href="https://webmention.io/x/webmention">"#);
+        assert_eq!(
+            config.author_card("Duck & Co", "../index.html", true),
+            r#"<a class="p-author h-card" href="../index.html" hidden>Duck &amp; Co</a>"#
+        );
+        assert_eq!(IndieWebConfig::default().head_links(), "");
+    }
+}
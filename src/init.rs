@@ -0,0 +1,185 @@
+//! `ssg init`: scaffold a new site.
+//!
+//! Creates the standard layout in a directory:
+//!
+//! ```text
+//! ssg.toml            # configuration (paths relative to this file)
+//! content/            # markdown posts and their images
+//! static/style.css    # copied verbatim into the output root
+//! ```
+//!
+//! plus an example post, so that `ssg build` works right away.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use crate::config::CONFIG_FILE;
+use crate::error::BuildError;
+
+const CONFIG_TEMPLATE: &str = r#"# Site configuration. Paths are relative to this file.
+brand_name = "My Blog"
+# description = "What this blog is about"
+content_dir = "content"
+static_dir = "static"
+data_dir = "data"
+# templates_dir = "templates"        # page layouts for `layout:` front matter
+public_dir = "public"
+# gemini_dir = "public_gemini"       # Gemtext mirror of the posts
+# base_url = "https://example.com"   # enables sitemap.xml
+
+# max_image_width = 1200
+# max_image_height = 1600      # image_fit = "crop" cuts tall screenshots
+# timezone = "Europe/Berlin"   # or timezone_offset_hours = 0
+# date_format = "%Y.%m.%d %H:%M"
+# inline_css = true
+# lightbox = "css"             # or "script"; default: download links
+# icon = "icon.png"            # favicons and app icons from one image
+# pwa = true                   # installable, readable offline
+# post_source = "txt"          # posts/{stem}.txt siblings and llms.txt
+# a11y_lint = true            # warn about missing alt text, skipped headings
+# require_alt_text = true     # every image needs alt text (fails --strict)
+# body_end_html = { file = "analytics.html" }   # also head_html
+# [[suppress]]                 # silence one warning category
+# category = "image_not_found"
+# path = "content/legacy"
+"#;
+
+const STYLE_TEMPLATE: &str = r#"body {
+    max-width: 44rem;
+    margin: 0 auto;
+    padding: 1rem;
+    font-family: system-ui, sans-serif;
+    line-height: 1.6;
+    color: #222;
+}
+
+.skip-link { position: absolute; left: -999px; }
+.skip-link:focus { left: 1rem; top: 1rem; padding: 0.3rem 0.6rem; background: #fff; }
+
+header { display: flex; flex-wrap: wrap; gap: 1rem; align-items: baseline; }
+.brand { font-weight: bold; letter-spacing: 0.1em; }
+.nav-section { display: inline-flex; flex-wrap: wrap; gap: 0.5rem; }
+.nav-header { color: #888; }
+.nav-link { color: inherit; }
+.nav-link.active, .nav-header.active { font-weight: bold; text-decoration: none; }
+.nav-tags { display: block; }
+.nav-tags summary { cursor: pointer; }
+.nav-tags > a { margin-right: 0.5rem; }
+
+.meta { color: #666; font-size: 0.9em; }
+.meta a { color: inherit; text-decoration: none; }
+.tag { margin-right: 0.4em; color: #0a6; }
+
+.post-entry { display: flex; justify-content: space-between; gap: 1rem; padding: 0.3rem 0; }
+.post-entry a { color: inherit; text-decoration: none; }
+.meta a.tag, .post-entry a.tag { color: #0a6; }
+.entry-title .tag { margin: 0 0 0 0.4em; font-size: 0.9em; }
+.entry-date { color: #888; white-space: nowrap; }
+.pinned-posts { border-bottom: 1px solid #ddd; margin-bottom: 1rem; }
+.archive-link { text-align: right; }
+.jump-nav { display: flex; flex-wrap: wrap; gap: 0.5rem; margin-bottom: 1rem; }
+.list-group { font-size: 1.1em; color: #888; scroll-margin-top: 1rem; }
+.tag-index { list-style: none; padding: 0; }
+.tag-count { color: #888; }
+.related-tags ul { display: flex; flex-wrap: wrap; gap: 0.5rem; list-style: none; padding: 0; }
+.search-form { display: flex; gap: 0.5rem; margin-bottom: 1rem; }
+.search-form input { flex: 1; }
+
+.image-container { margin: 1.5rem 0; }
+.image-container img, .image-container video { max-width: 100%; height: auto; }
+.download-link { font-size: 0.8em; color: #888; }
+.file-size { font-size: 0.9em; color: #888; }
+.archived-link { font-size: 0.8em; color: #888; }
+.image-credit { display: block; font-size: 0.8em; color: #888; }
+.audio-player { width: 100%; margin: 1rem 0; }
+.diagram { margin: 1.5rem 0; overflow-x: auto; }
+.diagram svg { max-width: 100%; height: auto; }
+figure.chart { margin: 1.5rem 0; }
+figure.chart svg { display: block; width: 100%; height: auto; }
+.gallery { display: grid; grid-template-columns: repeat(auto-fill, minmax(12rem, 1fr)); gap: 0.5rem; margin: 1.5rem 0; }
+.gallery img { display: block; width: 100%; height: 100%; object-fit: cover; aspect-ratio: 4 / 3; }
+.protected form { margin: 1.5rem 0; }
+.protected-error { color: #c33; }
+.lightbox { display: none; position: fixed; inset: 0; z-index: 1000; align-items: center; justify-content: center; background: rgba(0, 0, 0, 0.85); cursor: zoom-out; }
+.lightbox:target { display: flex; }
+.lightbox img { width: auto; height: auto; max-width: 95vw; max-height: 95vh; aspect-ratio: auto; object-fit: contain; }
+.embed { margin: 1.5rem 0; }
+.embed iframe { width: 100%; aspect-ratio: 16 / 9; border: 0; }
+.embed-mastodon iframe { aspect-ratio: auto; height: 24rem; }
+.comments { margin-top: 3rem; }
+"#;
+
+const POST_TEMPLATE: &str = "# Hello, World
+
+Tags: Meta
+
+This is your first post. Edit or delete it, then add your own markdown
+files to `content/`. Posts are listed newest first by the date of their
+first git commit, `date:` front matter or file modification time.
+
+Put images next to your posts and reference them with `![alt](photo.png)`;
+they are converted to WebP automatically.
+";
+
+/// Create the scaffold in `dir`. Returns the created files.
+///
+/// Refuses to touch a directory that already has an `ssg.toml`; other
+/// existing files are left alone.
+pub fn init(dir: &Path) -> Result<Vec<PathBuf>, BuildError> {
+    let config_path = dir.join(CONFIG_FILE);
+    if config_path.exists() {
+        return Err(BuildError::InvalidArgs(format!(
+            "{} already exists",
+            config_path.display()
+        )));
+    }
+
+    let post = format!("{}-hello-world.md", Local::now().format("%Y-%m-%d"));
+    let files = [
+        (config_path, CONFIG_TEMPLATE),
+        (dir.join("static").join("style.css"), STYLE_TEMPLATE),
+        (dir.join("content").join(post), POST_TEMPLATE),
+    ];
+
+    let mut created = Vec::new();
+    for (path, contents) in files {
+        if path.exists() {
+            continue;
+        }
+        write_new(&path, contents)?;
+        created.push(path);
+    }
+    Ok(created)
+}
+
+fn write_new(path: &Path, contents: &str) -> Result<(), BuildError> {
+    let not_writable = |e| BuildError::OutputNotWritable {
+        path: path.to_path_buf(),
+        source: e,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(not_writable)?;
+    }
+    fs::write(path, contents).map_err(not_writable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn scaffold_is_loadable_and_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let created = init(dir.path()).unwrap();
+        assert_eq!(created.len(), 3);
+
+        let config = Config::load(&dir.path().join(CONFIG_FILE)).unwrap();
+        assert_eq!(config.content_dir, dir.path().join("content"));
+        assert!(config.static_dir.join("style.css").exists());
+
+        assert!(init(dir.path()).is_err());
+    }
+}
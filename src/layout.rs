@@ -0,0 +1,196 @@
+//! Page layouts selected with `layout:` front matter.
+//!
+//! A post with `layout: photo` is rendered into `templates/photo.html`
+//! instead of the built-in page skeleton. Layouts are HTML files with
+//! `{{ slot }}` placeholders for the generated parts:
+//!
+//! - `lang`: language code for `<html lang>`
+//! - `head`: everything in `<head>` (charset, title, stylesheets, ...)
+//! - `header`: the site header with the brand and navigation
+//! - `brand`, `nav`: the brand name and the navigation links alone
+//! - `content`: the post, as an `<article>`; put it in `<main id="content">`,
+//!   the target of the header's skip link
+//! - `body_end`: scripts, loaded at the end of `<body>`
+//! - `body_class`: classes of the page, for `<body class="{{ body_class }}">`:
+//!   its kind (`page-post`) and tags (`tag-rust`)
+//!
+//! `content` is required. Slots are filled in one pass, so `{{ ... }}` in
+//! a post is left alone.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::BuildError;
+
+/// Layout name that always means the built-in skeleton.
+pub const DEFAULT_LAYOUT: &str = "default";
+
+const SLOTS: &[&str] = &["lang", "head", "header", "brand", "nav", "content", "body_end", "body_class"];
+
+/// A page skeleton from the templates directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    html: String,
+}
+
+/// The generated parts of a page, already HTML.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LayoutSlots<'a> {
+    pub lang: &'a str,
+    pub head: &'a str,
+    pub header: &'a str,
+    pub brand: &'a str,
+    pub nav: &'a str,
+    pub content: &'a str,
+    pub body_end: &'a str,
+    pub body_class: &'a str,
+}
+
+impl LayoutSlots<'_> {
+    fn get(&self, slot: &str) -> Option<&str> {
+        Some(match slot {
+            "lang" => self.lang,
+            "head" => self.head,
+            "header" => self.header,
+            "brand" => self.brand,
+            "nav" => self.nav,
+            "content" => self.content,
+            "body_end" => self.body_end,
+            "body_class" => self.body_class,
+            _ => return None,
+        })
+    }
+}
+
+impl Layout {
+    /// A layout from its HTML; Err names the problem.
+    pub fn parse(path: &Path, html: String) -> Result<Self, BuildError> {
+        let invalid = |message: String| BuildError::InvalidLayout { path: path.to_path_buf(), message };
+        let mut has_content = false;
+        for (_, slot) in placeholders(&html) {
+            if !SLOTS.contains(&slot) {
+                return Err(invalid(format!("unknown slot {{{{ {slot} }}}}")));
+            }
+            has_content |= slot == "content";
+        }
+        if !has_content {
+            return Err(invalid("no {{ content }} slot".to_string()));
+        }
+        Ok(Self { html })
+    }
+
+    /// The page: this layout with its slots filled.
+    pub fn render(&self, slots: &LayoutSlots<'_>) -> String {
+        let mut page = String::with_capacity(self.html.len() + slots.content.len() + slots.head.len());
+        let mut last = 0;
+        for (range, slot) in placeholders(&self.html) {
+            page.push_str(&self.html[last..range.start]);
+            page.push_str(slots.get(slot).unwrap_or_default());
+            last = range.end;
+        }
+        page.push_str(&self.html[last..]);
+        page
+    }
+}
+
+/// `{{ slot }}` placeholders in `html`, with their byte ranges.
+fn placeholders(html: &str) -> impl Iterator<Item = (std::ops::Range<usize>, &str)> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        let start = pos + html[pos..].find("{{")?;
+        let end = start + html[start..].find("}}")? + 2;
+        pos = end;
+        Some((start..end, html[start + 2..end - 2].trim()))
+    })
+}
+
+/// The layouts of a site, by file stem.
+#[derive(Debug, Default)]
+pub struct Layouts {
+    by_name: HashMap<String, Arc<Layout>>,
+}
+
+impl Layouts {
+    /// Read the `.html` files in `dir`. A missing directory has no
+    /// layouts; unusable files are left out and reported.
+    pub fn load(dir: &Path) -> (Self, Vec<BuildError>) {
+        let mut layouts = Self::default();
+        let mut errors = Vec::new();
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return (layouts, errors),
+            Err(e) => {
+                errors.push(BuildError::InvalidLayout { path: dir.to_path_buf(), message: e.to_string() });
+                return (layouts, errors);
+            }
+        };
+        let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok().map(|e| e.path())).collect();
+        paths.sort();
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            if path.extension().is_none_or(|e| e != "html") {
+                continue;
+            }
+            let layout = fs::read_to_string(&path)
+                .map_err(|e| BuildError::InvalidLayout { path: path.clone(), message: e.to_string() })
+                .and_then(|html| Layout::parse(&path, html));
+            match layout {
+                Ok(layout) => {
+                    layouts.by_name.insert(name.to_string(), Arc::new(layout));
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        (layouts, errors)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<Layout>> {
+        self.by_name.get(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_slots_once() {
+        let layout = Layout::parse(
+            Path::new("photo.html"),
+            "<html lang=\"{{ lang }}\"><head>{{head}}</head><body class=\"photo\">{{ content }}</body></html>".to_string(),
+        )
+        .unwrap();
+        let slots = LayoutSlots { lang: "en", head: "<title>T</title>", content: "<article>{{ nav }}</article>", ..Default::default() };
+        assert_eq!(
+            layout.render(&slots),
+            "<html lang=\"en\"><head><title>T</title></head><body class=\"photo\"><article>{{ nav }}</article></body></html>"
+        );
+    }
+
+    #[test]
+    fn loads_valid_layouts_and_reports_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("photo.html"), "<body>{{ content }}</body>").unwrap();
+        fs::write(dir.path().join("bare.html"), "<body>{{ header }}</body>").unwrap();
+        fs::write(dir.path().join("typo.html"), "<body>{{ contents }}</body>").unwrap();
+        fs::write(dir.path().join("notes.txt"), "{{ content }}").unwrap();
+
+        let (layouts, errors) = Layouts::load(dir.path());
+        assert!(layouts.get("photo").is_some());
+        assert!(layouts.get("notes").is_none());
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].ends_with("no {{ content }} slot"), "{}", messages[0]);
+        assert!(messages[1].ends_with("unknown slot {{ contents }}"), "{}", messages[1]);
+
+        let (layouts, errors) = Layouts::load(&dir.path().join("missing"));
+        assert!(layouts.is_empty() && errors.is_empty());
+    }
+}
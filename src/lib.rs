@@ -2,9 +2,65 @@
 //!
 //! Provides type-safe abstractions for building static blog sites.
 
+pub mod a11y;
+pub mod attachment;
+pub mod audio;
+pub mod blogroll;
+pub mod chart;
+pub mod cli;
+pub mod comments;
 pub mod config;
+pub mod csp;
+#[cfg(unix)]
+pub mod daemon;
+pub mod data;
+pub mod deploy;
+pub mod diagram;
+pub mod embed;
+pub mod epub;
 pub mod error;
+pub mod feed;
+pub mod fonts;
+pub mod front_matter;
+pub mod gallery;
+pub mod gemini;
+pub mod git;
+pub mod hosting;
+pub mod i18n;
+pub mod icons;
 pub mod image;
+pub mod image_cache;
+pub mod image_meta;
+pub mod import;
+pub mod include;
+pub mod indieweb;
+pub mod init;
+pub mod layout;
+pub mod lightbox;
+pub mod logging;
+pub mod output;
 pub mod parser;
+pub mod plugin;
+pub mod post_config;
+pub mod print;
+pub mod progress;
+pub mod protect;
+pub mod purge;
+pub mod pwa;
+pub mod registry;
 pub mod renderer;
+pub mod report;
+pub mod search;
+pub mod shortcode;
+pub mod site;
+pub mod sitemap;
+pub mod social;
+pub mod source;
+pub mod sri;
+pub mod timezone;
+pub mod timings;
+pub mod transform;
 pub mod types;
+pub mod validate;
+pub mod video;
+pub mod wordpress;
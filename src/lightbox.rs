@@ -0,0 +1,108 @@
+//! Lightbox markup for optimized images.
+//!
+//! `lightbox = "css"` wraps each image in a link to a `:target` overlay
+//! holding the full-size image, which needs no JavaScript (the rules are
+//! in the scaffold's `style.css`). `lightbox = "script"` links images to
+//! their full-size file with a `data-lightbox` attribute and includes the
+//! bundled `lightbox.js`, which opens them in an overlay.
+
+use serde::Deserialize;
+
+use crate::types::{EscapeHtml, HtmlSafe};
+
+/// File name of the bundled script in the output root.
+pub const LIGHTBOX_SCRIPT: &str = "lightbox.js";
+
+/// The bundled lightbox script.
+pub const LIGHTBOX_JS: &str = r#"document.addEventListener("click", (event) => {
+  const link = event.target.closest("a[data-lightbox]");
+  if (!link || event.ctrlKey || event.metaKey) return;
+  event.preventDefault();
+  const overlay = document.createElement("div");
+  overlay.className = "lightbox-overlay";
+  overlay.style.cssText = "position:fixed;inset:0;z-index:1000;display:flex;align-items:center;justify-content:center;background:rgba(0,0,0,.85);cursor:zoom-out";
+  const image = document.createElement("img");
+  image.src = link.href;
+  image.alt = link.querySelector("img")?.alt ?? "";
+  image.style.cssText = "max-width:95vw;max-height:95vh";
+  overlay.append(image);
+  const close = () => {
+    overlay.remove();
+    document.removeEventListener("keydown", onKey);
+  };
+  const onKey = (e) => { if (e.key === "Escape") close(); };
+  overlay.addEventListener("click", close);
+  document.addEventListener("keydown", onKey);
+  document.body.append(overlay);
+});
+"#;
+
+/// How images open in full size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lightbox {
+    /// A "Download full size" link below each image.
+    #[default]
+    None,
+    /// A CSS-only `:target` overlay.
+    Css,
+    /// `data-lightbox` links for the bundled script.
+    Script,
+}
+
+/// Lightbox markup for the images of one page.
+#[derive(Debug)]
+pub struct LightboxPage {
+    mode: Lightbox,
+    /// Overlays written so far, for unique ids.
+    count: usize,
+}
+
+impl LightboxPage {
+    pub fn new(mode: Lightbox) -> Self {
+        Self { mode, count: 0 }
+    }
+
+    /// Whether any image so far needs the bundled script.
+    pub fn needs_script(&self) -> bool {
+        self.mode == Lightbox::Script && self.count > 0
+    }
+
+    /// Wrap `img` (an `<img>` tag) so that it opens `full_src`; None in
+    /// `Lightbox::None` mode.
+    pub fn wrap(&mut self, img: &str, full_src: &HtmlSafe, alt: &str) -> Option<String> {
+        let html = match self.mode {
+            Lightbox::None => return None,
+            Lightbox::Css => format!(
+                r##"<a href="#lightbox-{id}" class="lightbox-open">{img}</a><a href="#_" class="lightbox" id="lightbox-{id}"><img src="{full_src}" alt="{alt}" loading="lazy" decoding="async"></a>"##,
+                id = self.count + 1,
+                alt = alt.escape_html(),
+            ),
+            Lightbox::Script => format!(r#"<a href="{full_src}" data-lightbox>{img}</a>"#),
+        };
+        self.count += 1;
+        Some(html)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn css_overlays_get_unique_ids() {
+        let mut page = LightboxPage::new(Lightbox::Css);
+        let src = HtmlSafe::escape("a.webp");
+        let first = page.wrap("<img>", &src, "A & B").unwrap();
+        assert!(first.starts_with(r##"<a href="#lightbox-1" class="lightbox-open"><img></a>"##));
+        assert!(first.contains(r#"id="lightbox-1"><img src="a.webp" alt="A &amp; B""#));
+        assert!(page.wrap("<img>", &src, "").unwrap().contains(r#"id="lightbox-2""#));
+        assert!(!page.needs_script());
+
+        let mut script = LightboxPage::new(Lightbox::Script);
+        assert!(!script.needs_script());
+        assert_eq!(script.wrap("<img>", &src, "").unwrap(), r#"<a href="a.webp" data-lightbox><img></a>"#);
+        assert!(script.needs_script());
+        assert_eq!(LightboxPage::new(Lightbox::None).wrap("<img>", &src, ""), None);
+    }
+}
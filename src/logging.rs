@@ -0,0 +1,58 @@
+//! Structured logging setup.
+//!
+//! All diagnostics go through `tracing` to stderr, so stdout stays free for
+//! command output (JSON reports, dry-run listings). Build phases are spans
+//! (`scan`, `parse`, `render`, `images`, `write`); from `-v` upwards their
+//! close events report how long each phase took.
+//!
+//! Log lines are written through the build's `Progress`, so they appear
+//! above any progress bars instead of breaking them.
+
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+use crate::progress::Progress;
+
+/// Console verbosity selected with `-q` / `-v` / `-vv`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Warnings and errors only.
+    Quiet,
+    /// Progress and summary.
+    #[default]
+    Normal,
+    /// Per-post details and phase timings.
+    Verbose,
+    /// Everything, including per-image spans.
+    Trace,
+}
+
+impl Verbosity {
+    pub fn level(self) -> Level {
+        match self {
+            Self::Quiet => Level::WARN,
+            Self::Normal => Level::INFO,
+            Self::Verbose => Level::DEBUG,
+            Self::Trace => Level::TRACE,
+        }
+    }
+}
+
+/// Install the global subscriber. Safe to call more than once.
+pub fn init(verbosity: Verbosity, progress: &Progress) {
+    let progress = progress.clone();
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(verbosity.level())
+        .with_writer(move || progress.writer())
+        .with_target(false);
+
+    // Span timings need a timer, so timestamps only appear when verbose
+    let _ = if verbosity >= Verbosity::Verbose {
+        builder
+            .with_timer(tracing_subscriber::fmt::time::Uptime::default())
+            .with_span_events(FmtSpan::CLOSE)
+            .try_init()
+    } else {
+        builder.without_time().try_init()
+    };
+}
@@ -1,317 +1,280 @@
 //! Blog generator main entry point.
 //!
-//! Orchestrates the build process using the library modules.
-
-use std::collections::HashSet;
-use std::fs;
-use std::path::PathBuf;
-
-use chrono::{DateTime, FixedOffset, Utc};
-use rayon::prelude::*;
-
-use generator::config::Config;
-use generator::error::{BuildError, BuildResult};
-use generator::parser::{extract_metadata, render_markdown, PostMetadata};
-use generator::renderer::{template, render_post_meta, render_post_list, PostListItem, RenderContext};
-use generator::types::{HtmlSafe, Tag};
-
-fn main() -> Result<(), BuildError> {
-    let start_time = std::time::Instant::now();
-    println!("Building blog (Multi-threaded)...");
-    
-    let config = Config::new();
-    
-    // Create output directories
-    fs::create_dir_all(config.posts_dir()).map_err(|e| BuildError::OutputNotWritable {
-        path: config.posts_dir(),
-        source: e,
-    })?;
-    fs::create_dir_all(config.tags_dir()).map_err(|e| BuildError::OutputNotWritable {
-        path: config.tags_dir(),
-        source: e,
-    })?;
-    fs::create_dir_all(config.images_dir()).map_err(|e| BuildError::OutputNotWritable {
-        path: config.images_dir(),
-        source: e,
-    })?;
-
-    // Load CSS for inlining (eliminates render-blocking)
-    let css_content = if config.inline_css {
-        let css_path = config.content_dir.join("style.css");
-        match fs::read_to_string(&css_path) {
-            Ok(css) => {
-                println!("  → CSS will be inlined ({} bytes)", css.len());
-                Some(css)
-            }
-            Err(_) => {
-                eprintln!("  ⚠ CSS file not found for inlining, using external link");
-                None
-            }
+//! Parses the command line and dispatches to the library modules.
+
+use std::io::IsTerminal;
+use std::path::Path;
+use std::process::ExitCode;
+
+use chrono::DateTime;
+use generator::cli::{self, BuildArgs, Command, ExportArgs, OutputFormat};
+use generator::config::{Config, CONFIG_FILE};
+use generator::deploy;
+use generator::error::{BuildError, EXIT_SUCCESS};
+use generator::logging::{self, Verbosity};
+use generator::import::{self, Generator};
+use generator::init;
+use generator::output;
+use generator::progress::Progress;
+use generator::report::BuildReport;
+use generator::site::Site;
+use generator::timings;
+use generator::types::Tag;
+use tracing::{error, info, warn};
+
+fn main() -> ExitCode {
+    let parsed = match cli::parse(std::env::args().skip(1)) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            return ExitCode::from(e.exit_code());
         }
-    } else {
-        None
     };
-
-    // Copy static assets (only favicon now if CSS is inlined, or both if not)
-    let static_files: Vec<&str> = if css_content.is_some() {
-        vec!["favicon.ico"]
-    } else {
-        vec!["favicon.ico", "style.css"]
+    let progress = progress_for(&parsed.command, parsed.verbosity);
+    logging::init(parsed.verbosity, &progress);
+
+    let config = parsed.config.as_deref();
+    let failure = if matches!(parsed.command, Command::Build(_)) { "Build failed" } else { "Failed" };
+    let result = match parsed.command {
+        Command::Build(args) => load_config(config).and_then(|c| build(c, &args, progress)),
+        Command::Check => load_config(config).and_then(check),
+        Command::Clean { dry_run } => load_config(config).and_then(|c| clean(&c, dry_run)),
+        Command::Daemon { socket } => load_config(config).and_then(|c| daemon(c, &socket)),
+        Command::Deploy { dry_run } => load_config(config).and_then(|c| upload(&c, dry_run)),
+        Command::Export(args) => load_config(config).and_then(|c| export(c, &args)),
+        Command::Import { generator, source, dry_run } => {
+            load_config(config).and_then(|c| import_posts(&c, generator, &source, dry_run))
+        }
+        Command::Init { dir } => scaffold(&dir),
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(EXIT_SUCCESS)
+        }
     };
-    
-    for file in static_files {
-        let src = config.content_dir.join(file);
-        if src.exists() {
-            if let Err(e) = fs::copy(&src, config.public_dir.join(file)) {
-                eprintln!("  ⚠ Failed to copy {}: {}", file, e);
-            }
+
+    match result {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            error!("{}: {}", failure, e);
+            ExitCode::from(e.exit_code())
         }
     }
+}
 
-    // Phase 1: Discover markdown files (IO-bound, sequential)
-    let entries = fs::read_dir(&config.content_dir).map_err(|e| BuildError::ContentNotReadable {
-        path: config.content_dir.clone(),
-        source: e,
-    })?;
-    
-    let paths: Vec<PathBuf> = entries
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("md"))
-        .collect();
-
-    println!("Found {} markdown files.", paths.len());
-
-    // Phase 2: Parse metadata (CPU-bound, parallel)
-    let parsed_results: Vec<_> = paths.par_iter()
-        .map(|path| parse_post(path, &config))
-        .collect();
-
-    // Collect results and tags
-    let mut build_result = BuildResult::new();
-    let mut valid_posts: Vec<ParsedPost> = Vec::new();
-    let mut all_tags: HashSet<Tag> = HashSet::new();
-
-    for res in parsed_results {
-        match res {
-            Ok(post) => {
-                for tag in &post.metadata.tags {
-                    all_tags.insert(tag.clone());
-                }
-                valid_posts.push(post);
-                build_result.record_success();
-            }
-            Err(e) => build_result.record_failure(e),
+/// Load the given config file, else `ssg.toml` if present, else defaults,
+/// and apply `SOURCE_DATE_EPOCH`.
+fn load_config(path: Option<&Path>) -> Result<Config, BuildError> {
+    let config = match path {
+        Some(path) => Config::load(path)?,
+        None if Path::new(CONFIG_FILE).exists() => Config::load(Path::new(CONFIG_FILE))?,
+        None => Config::new(),
+    };
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(value) => {
+            let time = value.trim().parse().ok().and_then(|secs| DateTime::from_timestamp(secs, 0)).ok_or_else(|| {
+                BuildError::InvalidArgs(format!("SOURCE_DATE_EPOCH must be a Unix timestamp, got {value:?}"))
+            })?;
+            Ok(config.source_date_epoch(time))
         }
+        Err(_) => Ok(config),
     }
+}
 
-    println!("Parsed {} valid posts. Generating HTML...", valid_posts.len());
-
-    // Phase 3: Render HTML (CPU-bound, parallel)
-    let css_ref = css_content.as_deref();
-    let render_results: Vec<_> = valid_posts.par_iter()
-        .map(|post| render_post(post, &all_tags, &config, css_ref))
-        .collect();
-
-    for res in render_results {
-        if let Err(e) = res {
-            build_result.record_failure(e);
+/// Progress bars only for interactive human-readable builds.
+fn progress_for(command: &Command, verbosity: Verbosity) -> Progress {
+    match command {
+        Command::Build(args)
+            if args.format == OutputFormat::Human
+                && verbosity != Verbosity::Quiet
+                && std::io::stderr().is_terminal() =>
+        {
+            Progress::stderr()
         }
+        _ => Progress::hidden(),
     }
+}
 
-    // Phase 4: Generate index pages (sequential)
-    let post_items: Vec<PostListItem> = valid_posts.iter()
-        .map(|p| PostListItem {
-            title: p.metadata.title.clone(),
-            filename: format!("posts/{}.html", p.file_stem),
-            date: p.date.clone(),
-            tags: p.metadata.tags.clone(),
-        })
-        .collect();
-
-    // Sort by filename (newest first based on naming convention)
-    let mut sorted_items = post_items;
-    sorted_items.sort_by(|a, b| b.filename.cmp(&a.filename));
-
-    // Generate main index
-    generate_list_page(&sorted_items, &all_tags, "Index", config.public_dir.join("index.html"), "", &config, css_ref)?;
+/// Run the full build pipeline once. Returns the process exit code.
+fn build(mut config: Config, args: &BuildArgs, progress: Progress) -> Result<u8, BuildError> {
+    let start_time = std::time::Instant::now();
+    let human = args.format == OutputFormat::Human;
+    if human {
+        info!("Building blog (Multi-threaded)...");
+    }
 
-    // Generate tag pages
-    for tag in &all_tags {
-        let tag_posts: Vec<_> = sorted_items.iter()
-            .filter(|p| p.tags.contains(tag))
-            .cloned()
-            .collect();
-        
-        let filename = format!("tag_{}.html", tag.to_lowercase());
-        let title = format!("Tag: {}", tag);
-        generate_list_page(&tag_posts, &all_tags, &title, config.tags_dir().join(&filename), "../", &config, css_ref)?;
+    if args.no_prune {
+        config = config.prune(false);
+    }
+    if args.strict {
+        config = config.strict(true);
+    }
+    if let Some(jobs) = args.jobs {
+        config = config.threads(jobs);
     }
-    
+    let strict = config.strict;
+    let mut site = Site::new(config).with_progress(progress);
+    let result = if args.dry_run {
+        site.dry_run().map(|(summary, report)| {
+            if human {
+                report.print_report();
+            }
+            summary
+        })
+    } else {
+        site.build()
+    };
     let duration = start_time.elapsed();
-    
-    // Finalize and report
-    match build_result.finalize() {
-        Ok(summary) => {
-            summary.print_report();
-            println!("Done! Built in {duration:.2?}");
-            Ok(())
-        }
+
+    // Finalize and report; an aborted build still gets a report
+    let summary = match result {
+        Ok(summary) => summary,
         Err(e) => {
-            eprintln!("Build failed: {}", e);
-            Err(e)
+            let report = BuildReport::failed(&e, duration);
+            if let Some(ref path) = args.report
+                && let Err(write_error) = report.write(path)
+            {
+                warn!("{}", write_error);
+            }
+            if !human {
+                println!("{}", report.to_json());
+            }
+            return Err(e);
         }
+    };
+    let code = summary.exit_code(strict);
+    let report = BuildReport::new(&summary, strict, duration);
+    if let Some(ref path) = args.report {
+        report.write(path)?;
     }
+    if !human {
+        println!("{}", report.to_json());
+        return Ok(code);
+    }
+
+    summary.print_report();
+    if args.timings {
+        timings::print_report(&summary, timings::DEFAULT_LIMIT);
+    }
+    if code == EXIT_SUCCESS {
+        info!("Done! Built in {duration:.2?}");
+    } else if summary.posts_skipped > 0 {
+        error!("Build failed: {} posts skipped, after {duration:.2?}", summary.posts_skipped);
+    } else {
+        error!("Build failed (strict mode) after {duration:.2?}");
+    }
+    Ok(code)
 }
 
-/// Intermediate parsed post data.
-struct ParsedPost {
-    file_stem: String,
-    metadata: PostMetadata,
-    date: String,
-    content: String,
-    first_image_url: Option<String>,
+/// Build and validate the pages. Any problem fails the check.
+fn check(config: Config) -> Result<u8, BuildError> {
+    let summary = Site::new(config).check()?;
+    summary.print_report();
+    let code = summary.exit_code(true);
+    if code == EXIT_SUCCESS {
+        info!("Check passed.");
+    } else {
+        error!("Check failed.");
+    }
+    Ok(code)
 }
 
-/// Parse a single markdown file.
-fn parse_post(path: &PathBuf, config: &Config) -> Result<ParsedPost, BuildError> {
-    let file_stem = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .ok_or_else(|| BuildError::ParseFailed {
-            path: path.clone(),
-            message: "Invalid filename".to_string(),
-        })?
-        .to_string();
+/// Remove all generated outputs (or list them with `dry_run`).
+fn clean(config: &Config, dry_run: bool) -> Result<u8, BuildError> {
+    let (removed, failures) = output::clean(config, dry_run);
+    let verb = if dry_run { "Would remove" } else { "Removed" };
 
-    // Get modification time
-    let metadata = fs::metadata(path).map_err(|e| BuildError::ParseFailed {
-        path: path.clone(),
-        message: format!("Failed to read metadata: {}", e),
-    })?;
-    
-    let modified: DateTime<Utc> = metadata
-        .modified()
-        .map_err(|e| BuildError::ParseFailed {
-            path: path.clone(),
-            message: format!("Failed to get mtime: {}", e),
-        })?
-        .into();
-    
-    let offset = FixedOffset::east_opt(config.timezone_offset_hours * 3600)
-        .ok_or_else(|| BuildError::Internal("Invalid timezone offset".to_string()))?;
-    let modified_local = modified.with_timezone(&offset);
-    let date_str = modified_local.format("%Y.%m.%d %H:%M").to_string();
+    for path in &removed {
+        println!("  - {}", path.display());
+    }
+    println!("{} {} files.", verb, removed.len());
 
-    let content = fs::read_to_string(path).map_err(|e| BuildError::ParseFailed {
-        path: path.clone(),
-        message: format!("Failed to read file: {}", e),
-    })?;
+    for err in &failures {
+        warn!("{}", err);
+    }
+    Ok(EXIT_SUCCESS)
+}
 
-    let post_metadata = extract_metadata(&content, &file_stem);
-    
-    // Extract first image URL for LCP preload
-    let first_image_url = extract_first_image(&content);
+/// Upload changed outputs to the configured target.
+fn upload(config: &Config, dry_run: bool) -> Result<u8, BuildError> {
+    let plan = deploy::deploy(config, dry_run)?;
+    for path in &plan.upload {
+        println!("  + {}", path.display());
+    }
+    for path in &plan.delete {
+        println!("  - {}", path.display());
+    }
 
-    println!("  ✓ {} [{}] Tags: {:?}", 
-        post_metadata.raw_title,
-        date_str,
-        post_metadata.tags.iter().map(|t| t.as_str()).collect::<Vec<_>>()
+    let verb = if dry_run { "Would upload" } else { "Uploaded" };
+    println!(
+        "{} {} files, {} deletions, {} unchanged.",
+        verb,
+        plan.upload.len(),
+        plan.delete.len(),
+        plan.unchanged,
     );
-
-    Ok(ParsedPost {
-        file_stem,
-        metadata: post_metadata,
-        date: date_str,
-        content,
-        first_image_url,
-    })
+    Ok(EXIT_SUCCESS)
 }
 
-/// Extract first image URL from markdown for LCP preload.
-fn extract_first_image(content: &str) -> Option<String> {
-    // Simple regex-free extraction: find ![...](...) pattern
-    let start = content.find("![")?;
-    let after_alt = content[start..].find("](")?;
-    let url_start = start + after_alt + 2;
-    let url_end = content[url_start..].find(')')?;
-    Some(content[url_start..url_start + url_end].to_string())
-}
-
-/// Render a single post to HTML file.
-fn render_post(post: &ParsedPost, all_tags: &HashSet<Tag>, config: &Config, css: Option<&str>) -> Result<(), BuildError> {
-    let html_content = render_markdown(
-        &post.content,
-        config,
-        &config.content_dir,
-        &config.public_dir,
-        "../",
-    )?;
+/// Write the posts (or those with a tag) to an EPUB book.
+fn export(config: Config, args: &ExportArgs) -> Result<u8, BuildError> {
+    let tag = args.tag.as_deref().map(Tag::new).transpose()?;
+    let mut site = Site::new(config);
+    let (mut book, warnings) = site.epub_book(tag.as_ref())?;
+    for warning in &warnings {
+        warn!("{}", warning);
+    }
+    if book.chapters.is_empty() {
+        return Err(BuildError::NoValidPosts { path: site.config().content_dir.clone() });
+    }
 
-    let meta_html = render_post_meta(&post.date, &post.metadata.tags);
-    let full_content = format!("{}{}", meta_html, html_content);
+    if let Some(cover) = &args.cover {
+        book = book.cover(cover);
+    }
+    book.write(&args.output)?;
+    info!("Exported {} posts to {}", book.chapters.len(), args.output.display());
+    Ok(EXIT_SUCCESS)
+}
 
-    // Build render context with CSS and LCP preload
-    let mut ctx = RenderContext::new(config);
-    if let Some(css_str) = css {
-        ctx = ctx.with_css(css_str);
+/// Import another generator's posts into the content directory (or list
+/// them with `dry_run`).
+fn import_posts(config: &Config, generator: Generator, source: &Path, dry_run: bool) -> Result<u8, BuildError> {
+    let summary = import::import(generator, source, &config.content_dir, dry_run)?;
+    for (from, to) in &summary.posts {
+        println!("  + {} <- {}", to.display(), from.display());
     }
-    if let Some(ref img_url) = post.first_image_url {
-        // Convert to proper relative URL for the post page
-        let lcp_url = if img_url.starts_with("http") {
-            img_url.clone()
-        } else {
-            format!("../images/{}.webp", 
-                std::path::Path::new(img_url)
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or(img_url))
-        };
-        ctx = ctx.with_lcp_image(lcp_url);
+    for warning in &summary.warnings {
+        warn!("{}", warning);
     }
 
-    let html_page = template(
-        &post.metadata.title,
-        &full_content,
-        all_tags,
-        "../",
-        &ctx,
+    let verb = if dry_run { "Would import" } else { "Imported" };
+    println!(
+        "{} {} {} posts and {} images, skipped {} drafts.",
+        verb,
+        summary.posts.len(),
+        generator.name(),
+        summary.images,
+        summary.drafts,
     );
-
-    let output_path = config.posts_dir().join(format!("{}.html", post.file_stem));
-    fs::write(&output_path, html_page).map_err(|e| BuildError::OutputNotWritable {
-        path: output_path,
-        source: e,
-    })?;
-
-    Ok(())
+    Ok(EXIT_SUCCESS)
 }
 
-/// Generate a list page (index or tag page).
-fn generate_list_page(
-    posts: &[PostListItem],
-    all_tags: &HashSet<Tag>,
-    title: &str,
-    path: PathBuf,
-    relative_root: &str,
-    config: &Config,
-    css: Option<&str>,
-) -> Result<(), BuildError> {
-    let posts_html = render_post_list(posts, relative_root);
-    let safe_title = HtmlSafe::escape(title);
-    let content = format!("<h1>{}</h1>{}", safe_title, posts_html);
-
-    let mut ctx = RenderContext::new(config);
-    if let Some(css_str) = css {
-        ctx = ctx.with_css(css_str);
+/// Create a new site and list what was written.
+fn scaffold(dir: &Path) -> Result<u8, BuildError> {
+    let created = init::init(dir)?;
+    for path in &created {
+        println!("  + {}", path.display());
     }
+    println!("Created site in {}. Run `ssg build` there to build it.", dir.display());
+    Ok(EXIT_SUCCESS)
+}
 
-    let html = template(&safe_title, &content, all_tags, relative_root, &ctx);
-    
-    fs::write(&path, html).map_err(|e| BuildError::OutputNotWritable {
-        path,
-        source: e,
-    })?;
+/// Serve build commands until shut down.
+#[cfg(unix)]
+fn daemon(config: Config, socket: &std::path::Path) -> Result<u8, BuildError> {
+    generator::daemon::serve(Site::new(config), socket).map(|()| EXIT_SUCCESS)
+}
 
-    Ok(())
-}
\ No newline at end of file
+#[cfg(not(unix))]
+fn daemon(_config: Config, _socket: &std::path::Path) -> Result<u8, BuildError> {
+    Err(BuildError::InvalidArgs("daemon mode requires Unix sockets".to_string()))
+}
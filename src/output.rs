@@ -0,0 +1,433 @@
+//! Output tracking, orphan pruning and cleaning.
+//!
+//! Every file a build produces is recorded in an `OutputSet`. After the
+//! build, the set is persisted as a manifest in the public directory so
+//! that later builds (and `ssg clean`) know which files the generator owns.
+//!
+//! In dry-run mode the set records what each write *would* do (create,
+//! update, unchanged) without touching the filesystem.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::error::BuildError;
+
+/// Manifest file listing every output of the last build (relative paths).
+pub const MANIFEST_FILE: &str = ".ssg-manifest";
+
+/// What a build does (or would do) to an output file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// File does not exist yet.
+    Create,
+    /// File exists with different contents.
+    Update,
+    /// File exists and is kept as-is (identical or cached).
+    Unchanged,
+}
+
+impl Change {
+    /// Classify a planned write by comparing against the existing file.
+    fn planned(path: &Path, contents: &[u8]) -> Self {
+        match fs::read(path) {
+            Err(_) => Self::Create,
+            Ok(existing) if existing == contents => Self::Unchanged,
+            Ok(_) => Self::Update,
+        }
+    }
+}
+
+/// Thread-safe set of files produced by the current build.
+#[derive(Debug, Default)]
+pub struct OutputSet {
+    dry_run: bool,
+    changes: Mutex<BTreeMap<PathBuf, Change>>,
+}
+
+impl OutputSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a set that plans writes without performing them.
+    pub fn dry_run() -> Self {
+        Self {
+            dry_run: true,
+            ..Self::default()
+        }
+    }
+
+    /// Whether writes are only being planned.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Record a file kept as-is (e.g. reused from cache).
+    pub fn record(&self, path: impl Into<PathBuf>) {
+        self.record_change(path, Change::Unchanged);
+    }
+
+    /// Record a produced file with the change it represents.
+    pub fn record_change(&self, path: impl Into<PathBuf>, change: Change) {
+        self.changes.lock().unwrap().insert(path.into(), change);
+    }
+
+    /// Write a file and record it as an output.
+    pub fn write(&self, path: &Path, contents: impl AsRef<[u8]>) -> Result<(), BuildError> {
+        let contents = contents.as_ref();
+        if self.dry_run {
+            self.record_change(path, Change::planned(path, contents));
+            return Ok(());
+        }
+
+        let change = if path.exists() { Change::Update } else { Change::Create };
+        fs::write(path, contents).map_err(|e| BuildError::OutputNotWritable {
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+        self.record_change(path, change);
+        Ok(())
+    }
+
+    /// Copy a file and record the destination as an output.
+    pub fn copy(&self, src: &Path, dst: &Path) -> Result<(), BuildError> {
+        if self.dry_run {
+            let contents = fs::read(src).unwrap_or_default();
+            self.record_change(dst, Change::planned(dst, &contents));
+            return Ok(());
+        }
+
+        let change = if dst.exists() { Change::Update } else { Change::Create };
+        fs::copy(src, dst).map_err(|e| BuildError::OutputNotWritable {
+            path: dst.to_path_buf(),
+            source: e,
+        })?;
+        self.record_change(dst, change);
+        Ok(())
+    }
+
+    /// Create a directory (skipped in dry-run mode).
+    pub fn create_dir(&self, dir: &Path) -> Result<(), BuildError> {
+        if self.dry_run {
+            return Ok(());
+        }
+        fs::create_dir_all(dir).map_err(|e| BuildError::OutputNotWritable {
+            path: dir.to_path_buf(),
+            source: e,
+        })
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.changes.lock().unwrap().contains_key(path)
+    }
+
+    /// Snapshot of all recorded paths, sorted.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.changes.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Snapshot of all recorded paths with their changes, sorted by path.
+    pub fn changes(&self) -> Vec<(PathBuf, Change)> {
+        self.changes.lock().unwrap().iter().map(|(p, c)| (p.clone(), *c)).collect()
+    }
+}
+
+/// Planned changes of a dry-run build.
+#[derive(Debug, Default)]
+pub struct DryRunReport {
+    /// Every output the build would produce.
+    pub changes: Vec<(PathBuf, Change)>,
+    /// Stale outputs the build would prune.
+    pub deletions: Vec<PathBuf>,
+}
+
+impl DryRunReport {
+    /// Print a diff-style listing (`+` create, `~` update, `-` delete).
+    pub fn print_report(&self) {
+        let mut unchanged = 0;
+        for (path, change) in &self.changes {
+            match change {
+                Change::Create => println!("+ {}", path.display()),
+                Change::Update => println!("~ {}", path.display()),
+                Change::Unchanged => unchanged += 1,
+            }
+        }
+        for path in &self.deletions {
+            println!("- {}", path.display());
+        }
+
+        let count = |kind| self.changes.iter().filter(|(_, c)| *c == kind).count();
+        println!(
+            "Dry run: {} to create, {} to update, {} to delete, {} unchanged.",
+            count(Change::Create),
+            count(Change::Update),
+            self.deletions.len(),
+            unchanged,
+        );
+    }
+}
+
+/// Persist the output set as the manifest for the next build.
+pub fn write_manifest(config: &Config, outputs: &OutputSet) -> Result<(), BuildError> {
+    let mut listing = String::new();
+    for path in outputs.paths() {
+        if let Ok(rel) = path.strip_prefix(&config.public_dir) {
+            listing.push_str(&rel.to_string_lossy());
+            listing.push('\n');
+        }
+    }
+
+    let manifest_path = config.public_dir.join(MANIFEST_FILE);
+    fs::write(&manifest_path, listing).map_err(|e| BuildError::OutputNotWritable {
+        path: manifest_path,
+        source: e,
+    })
+}
+
+/// Read the manifest of the previous build (empty if none exists).
+///
+/// Entries that are absolute or contain `..`, which the generator never
+/// writes, are dropped: they would name files outside the public directory.
+pub fn read_manifest(config: &Config) -> Vec<PathBuf> {
+    fs::read_to_string(config.public_dir.join(MANIFEST_FILE))
+        .map(|listing| {
+            listing
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .map(Path::new)
+                .filter(|rel| rel.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)))
+                .map(|rel| config.public_dir.join(rel))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Files the generator owns: everything in the generated subdirectories
+/// plus everything listed in the previous manifest, as long as it is in
+/// the public directory once symlinks are resolved.
+fn owned_files(config: &Config) -> BTreeSet<PathBuf> {
+    let mut files: BTreeSet<PathBuf> = read_manifest(config)
+        .into_iter()
+        .filter(|p| p.is_file())
+        .collect();
+
+    for dir in config.owned_dirs() {
+        collect_files(&dir, &mut files);
+    }
+
+    let Ok(public_dir) = fs::canonicalize(&config.public_dir) else {
+        return BTreeSet::new();
+    };
+    files.retain(|path| fs::canonicalize(path).is_ok_and(|path| path.starts_with(&public_dir)));
+    files
+}
+
+fn collect_files(dir: &Path, files: &mut BTreeSet<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        // Not into symlinked directories, which may lead anywhere
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            collect_files(&path, files);
+        } else {
+            files.insert(path);
+        }
+    }
+}
+
+/// Find owned files that the current build did not produce.
+pub fn find_orphans(config: &Config, outputs: &OutputSet) -> Vec<PathBuf> {
+    owned_files(config)
+        .into_iter()
+        .filter(|p| !outputs.contains(p))
+        .collect()
+}
+
+/// Remove the given files. With `dry_run`, nothing is deleted.
+///
+/// Returns the files that were (or would be) removed, plus any failures.
+pub fn remove_files(paths: Vec<PathBuf>, dry_run: bool) -> (Vec<PathBuf>, Vec<BuildError>) {
+    if dry_run {
+        return (paths, Vec::new());
+    }
+
+    let mut removed = Vec::new();
+    let mut failures = Vec::new();
+    for path in paths {
+        match fs::remove_file(&path) {
+            Ok(()) => removed.push(path),
+            Err(e) => failures.push(BuildError::PruneFailed { path, source: e }),
+        }
+    }
+    (removed, failures)
+}
+
+/// Remove every output the generator owns, including the manifest.
+pub fn clean(config: &Config, dry_run: bool) -> (Vec<PathBuf>, Vec<BuildError>) {
+    let mut files: Vec<PathBuf> = owned_files(config).into_iter().collect();
+    let manifest = config.public_dir.join(MANIFEST_FILE);
+    if manifest.exists() {
+        files.push(manifest);
+    }
+    remove_files(files, dry_run)
+}
+
+/// FNV-1a hash of a file's contents (None if unreadable).
+pub fn content_hash(path: &Path) -> Option<String> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let mut hasher = StableHasher::default();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).ok()?;
+        if n == 0 {
+            break;
+        }
+        hasher.write(&buf[..n]);
+    }
+    Some(format!("{:016x}", hasher.finish()))
+}
+
+/// FNV-1a [`Hasher`]: unlike `DefaultHasher`, the same on every machine
+/// and Rust release, for hashes that end up in the output.
+#[derive(Debug, Clone, Copy)]
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    // Fixed width, so 32- and 64-bit builds agree
+    fn write_usize(&mut self, n: usize) {
+        self.write(&(n as u64).to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::Hash;
+
+    #[test]
+    fn orphans_exclude_current_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new().public_dir(dir.path());
+        fs::create_dir_all(config.posts_dir()).unwrap();
+
+        let kept = config.posts_dir().join("kept.html");
+        let stale = config.posts_dir().join("stale.html");
+        fs::write(&kept, "kept").unwrap();
+        fs::write(&stale, "stale").unwrap();
+
+        let outputs = OutputSet::new();
+        outputs.record(&kept);
+
+        assert_eq!(find_orphans(&config, &outputs), vec![stale]);
+    }
+
+    #[test]
+    fn stable_hasher_is_fnv1a() {
+        let mut hasher = StableHasher::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+
+        let hash = |value: &(&str, Option<String>)| {
+            let mut hasher = StableHasher::default();
+            value.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&("sw.js", None)), hash(&("sw.js", None)));
+        assert_ne!(hash(&("sw.js", None)), hash(&("sw.js", Some(String::new()))));
+    }
+
+    #[test]
+    fn manifest_round_trip_tracks_root_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new().public_dir(dir.path());
+
+        let outputs = OutputSet::new();
+        outputs.write(&dir.path().join("index.html"), "index").unwrap();
+        write_manifest(&config, &outputs).unwrap();
+
+        assert_eq!(read_manifest(&config), vec![dir.path().join("index.html")]);
+
+        // Next build produces nothing: the old index is now an orphan
+        let orphans = find_orphans(&config, &OutputSet::new());
+        assert_eq!(orphans, vec![dir.path().join("index.html")]);
+    }
+
+    #[test]
+    fn manifest_entries_stay_in_the_public_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let public = dir.path().join("public");
+        let config = Config::new().public_dir(&public);
+        fs::create_dir_all(config.posts_dir()).unwrap();
+        fs::write(public.join("index.html"), "index").unwrap();
+        fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        let listing = format!("index.html\n../secret.txt\nposts/../../secret.txt\n{}\n", dir.path().join("secret.txt").display());
+        fs::write(public.join(MANIFEST_FILE), listing).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.path(), config.posts_dir().join("up")).unwrap();
+
+        assert_eq!(read_manifest(&config), vec![public.join("index.html")]);
+        let (removed, failures) = clean(&config, false);
+        assert!(failures.is_empty());
+        assert_eq!(removed, vec![public.join("index.html"), public.join(MANIFEST_FILE)]);
+        assert!(dir.path().join("secret.txt").exists());
+    }
+
+    #[test]
+    fn dry_run_classifies_without_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let same = dir.path().join("same.html");
+        let changed = dir.path().join("changed.html");
+        let new = dir.path().join("new.html");
+        fs::write(&same, "same").unwrap();
+        fs::write(&changed, "old").unwrap();
+
+        let outputs = OutputSet::dry_run();
+        outputs.write(&same, "same").unwrap();
+        outputs.write(&changed, "new").unwrap();
+        outputs.write(&new, "new").unwrap();
+
+        assert_eq!(outputs.changes(), vec![
+            (changed.clone(), Change::Update),
+            (new.clone(), Change::Create),
+            (same, Change::Unchanged),
+        ]);
+        assert!(!new.exists());
+        assert_eq!(fs::read_to_string(&changed).unwrap(), "old");
+    }
+
+    #[test]
+    fn dry_run_removes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.html");
+        fs::write(&file, "a").unwrap();
+
+        let (listed, failures) = remove_files(vec![file.clone()], true);
+        assert_eq!(listed, vec![file.clone()]);
+        assert!(failures.is_empty());
+        assert!(file.exists());
+    }
+}
@@ -2,11 +2,21 @@
 
 use std::path::Path;
 
-use pulldown_cmark::{Event, Parser, Tag, TagEnd, html};
+use chrono::NaiveDate;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use serde::Deserialize;
 
 use crate::config::Config;
-use crate::error::BuildError;
-use crate::image::{OptimizedImage, optimize_image};
+use crate::data::SiteData;
+use crate::error::{BuildError, SourcePos};
+use crate::image::{DecodeLimit, OptimizedImage};
+use crate::image_cache::ImageCache;
+use crate::lightbox::LIGHTBOX_SCRIPT;
+use crate::output::OutputSet;
+use crate::post_config::ResolvedPostConfig;
+use crate::registry::Registry;
+use crate::search;
+use crate::transform::{Pipeline, RenderState};
 use crate::types::{HtmlSafe, EscapeHtml, Tag as BlogTag};
 
 /// Parsed metadata from a markdown post.
@@ -15,6 +25,123 @@ pub struct PostMetadata {
     pub title: HtmlSafe,
     pub tags: Vec<BlogTag>,
     pub raw_title: String,
+    /// Tags that failed validation: (raw tag, reason, position in the
+    /// markdown).
+    pub rejected_tags: Vec<(String, &'static str, SourcePos)>,
+    /// Plain text of the first paragraph, shortened to `EXCERPT_LENGTH`.
+    pub excerpt: String,
+}
+
+/// Markup for links leaving the site (the `[external_links]` table).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExternalLinks {
+    /// Add `rel="noopener noreferrer"`.
+    pub noreferrer: bool,
+    /// Open in a new tab (`target="_blank"`).
+    pub new_tab: bool,
+    /// CSS class for an external-link icon.
+    pub class: Option<String>,
+    /// Point to the Wayback Machine's copy of the page.
+    pub archive: Option<ArchiveLinks>,
+}
+
+/// How external links point to archived copies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveLinks {
+    /// A visible "archived" link after the link.
+    Link,
+    /// A `data-archive` attribute on the link, for scripts and styles.
+    Attribute,
+}
+
+impl Default for ExternalLinks {
+    fn default() -> Self {
+        Self {
+            noreferrer: true,
+            new_tab: false,
+            class: None,
+            archive: None,
+        }
+    }
+}
+
+/// Wayback Machine URL of `url` as of `date` (the copy closest to it), or
+/// of its latest copy.
+pub fn archive_url(url: &str, date: Option<NaiveDate>) -> String {
+    match date {
+        Some(date) => format!("https://web.archive.org/web/{}/{url}", date.format("%Y%m%d")),
+        None => format!("https://web.archive.org/web/{url}"),
+    }
+}
+
+impl ExternalLinks {
+    /// Extra `<a>` attributes, with a leading space (empty if none).
+    pub(crate) fn attributes(&self) -> String {
+        let mut attrs = String::new();
+        if self.noreferrer {
+            attrs.push_str(r#" rel="noopener noreferrer""#);
+        }
+        if self.new_tab {
+            attrs.push_str(r#" target="_blank""#);
+        }
+        if let Some(class) = &self.class {
+            attrs.push_str(&format!(r#" class="{}""#, class.escape_html()));
+        }
+        attrs
+    }
+}
+
+/// Whether `url` points off the site: an absolute `http(s)` URL whose host
+/// differs from `base_url`'s (every such URL without a base URL).
+pub fn is_external(url: &str, base_url: Option<&str>) -> bool {
+    let Some(host) = url_host(url) else {
+        return false;
+    };
+    base_url.and_then(url_host).is_none_or(|site| !site.eq_ignore_ascii_case(host))
+}
+
+/// Host (and port) of an absolute or protocol-relative `http(s)` URL.
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .or_else(|| url.strip_prefix("//"))?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    (!host.is_empty()).then_some(host)
+}
+
+/// Maximum length of an extracted excerpt, in characters.
+pub const EXCERPT_LENGTH: usize = 160;
+
+/// Reading speed for reading times, in words per minute; two CJK
+/// characters count as a word.
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// Rendered post body plus recoverable problems found while rendering.
+#[derive(Debug)]
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub warnings: Vec<BuildError>,
+    /// Every image embedded in the body, after optimization.
+    pub images: Vec<OptimizedImage>,
+    /// Scripts the body needs (client-side diagrams).
+    pub scripts: Vec<String>,
+}
+
+/// Build-wide state markdown rendering draws on.
+#[derive(Debug, Clone, Copy)]
+pub struct MarkdownEnv<'a> {
+    pub outputs: &'a OutputSet,
+    pub decode_limit: &'a DecodeLimit,
+    pub image_cache: &'a ImageCache,
+    /// Files of the data directory, for `data` shortcodes.
+    pub data: &'a SiteData,
+    /// Posts of the content tree, for links between posts.
+    pub registry: &'a Registry,
+    pub pipeline: &'a Pipeline,
 }
 
 /// Extract metadata (title, tags) from markdown content.
@@ -29,15 +156,19 @@ pub fn extract_metadata(markdown: &str, fallback_title: &str) -> PostMetadata {
 
     // Extract tags from "Tags:" line
     let mut tags = Vec::new();
-    if let Some(tag_line) = markdown.lines().find(|l| l.trim().starts_with("Tags:")) {
-        let tag_str = tag_line.trim_start_matches("Tags:").trim();
+    let mut rejected_tags = Vec::new();
+    if let Some((index, tag_line)) = markdown.lines().enumerate().find(|(_, l)| l.trim().starts_with("Tags:")) {
+        let tag_str = tag_line.trim_start().trim_start_matches("Tags:");
+        let mut offset = tag_line.len() - tag_str.len();
         for tag in tag_str.split(',') {
+            let indent = tag.len() - tag.trim_start().len();
+            let position = SourcePos::new(index + 1, tag_line[..offset + indent].chars().count() + 1);
+            offset += tag.len() + 1;
             match BlogTag::new(tag) {
                 Ok(t) => tags.push(t),
-                Err(e) => {
-                    // Log but don't fail - skip invalid tags
-                    eprintln!("  ⚠ Skipping invalid tag: {}", e);
-                }
+                // Don't fail - skip invalid tags, reported as warnings
+                Err(BuildError::InvalidTag { tag, reason, .. }) => rejected_tags.push((tag, reason, position)),
+                Err(_) => {}
             }
         }
     }
@@ -46,155 +177,291 @@ pub fn extract_metadata(markdown: &str, fallback_title: &str) -> PostMetadata {
         title: raw_title.escape_html(),
         tags,
         raw_title,
+        rejected_tags,
+        excerpt: extract_excerpt(markdown, EXCERPT_LENGTH),
     }
 }
 
-/// Convert markdown to HTML with custom image handling.
-pub fn render_markdown(
-    markdown: &str,
-    config: &Config,
-    content_dir: &Path,
-    public_dir: &Path,
-    relative_root: &str,
-) -> Result<String, BuildError> {
-    let parser = Parser::new(markdown);
-    
-    let mut events: Vec<Event<'_>> = Vec::new();
-    let mut in_image = false;
-    let mut image_url = String::new();
-    let mut image_title = String::new();
-    let mut image_alt = String::new();
-    let mut first_image = true;
-
-    for event in parser {
+/// Plain text of the first paragraph (not the `Tags:` line), cut at a
+/// word boundary to at most `max_chars` characters.
+pub fn extract_excerpt(markdown: &str, max_chars: usize) -> String {
+    let mut text = String::new();
+    let mut in_paragraph = false;
+    let mut image_depth = 0;
+    for event in Parser::new(markdown) {
         match event {
-            Event::Start(Tag::Image { dest_url, title, .. }) => {
-                in_image = true;
-                image_url = dest_url.to_string();
-                image_title = title.to_string();
-                image_alt.clear();
-            }
-            Event::End(TagEnd::Image) => {
-                in_image = false;
-                
-                // Optimize image
-                let opt = optimize_image(
-                    &image_url,
-                    content_dir,
-                    public_dir,
-                    config.max_image_width,
-                ).unwrap_or_else(|_| OptimizedImage::missing(&image_url));
-
-                // Build final src URL
-                let final_src = if opt.is_external() {
-                    opt.rel_path.clone()
-                } else {
-                    format!("{}{}", relative_root, opt.rel_path)
-                };
-                let final_src_escaped = final_src.escape_html();
-
-                // Build dimension attributes
-                let (width_attr, height_attr) = parse_dimensions_or_image(
-                    &image_title,
-                    opt.width,
-                    opt.height,
-                );
-
-                // Escape alt text for XSS prevention
-                let safe_alt = image_alt.escape_html();
-                
-                // Title attribute (only if not a dimension spec)
-                let title_attr = if !is_dimension_spec(&image_title) && !image_title.is_empty() {
-                    let safe_title = image_title.escape_html();
-                    format!(r#"title="{}""#, safe_title)
-                } else {
-                    String::new()
-                };
-
-                // Loading strategy
-                let loading_attrs = if first_image {
-                    first_image = false;
-                    r#"loading="eager" fetchpriority="high" decoding="sync""#
-                } else {
-                    r#"loading="lazy" decoding="async""#
-                };
-
-                let html = format!(
-                    r#"<figure class="image-container">
-                        <img src="{}" alt="{}" {} {} {} {} />
-                        <figcaption>
-                            <a href="{}" target="_blank" class="download-link">[ Download Full Size ]</a>
-                        </figcaption>
-                    </figure>"#,
-                    final_src_escaped,
-                    safe_alt,
-                    width_attr,
-                    height_attr,
-                    title_attr,
-                    loading_attrs,
-                    final_src_escaped,
-                );
-                events.push(Event::Html(html.into()));
-            }
-            Event::Text(text) if in_image => {
-                image_alt.push_str(&text);
-            }
-            Event::Code(text) if in_image => {
-                image_alt.push_str(&text);
-            }
-            e if !in_image => {
-                events.push(e);
+            Event::Start(Tag::Paragraph) => in_paragraph = true,
+            Event::End(TagEnd::Paragraph) => {
+                in_paragraph = false;
+                // Tag lines, includes and shortcodes aren't prose
+                let text_start = text.trim_start();
+                if ["Tags:", "Caption:", "{{"].iter().any(|p| text_start.starts_with(p)) {
+                    text.clear();
+                } else if !text.trim().is_empty() {
+                    break;
+                }
             }
+            Event::Start(Tag::Image { .. }) => image_depth += 1,
+            Event::End(TagEnd::Image) => image_depth -= 1,
+            Event::Text(t) | Event::Code(t) if in_paragraph && image_depth == 0 => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak if in_paragraph => text.push(' '),
             _ => {}
         }
     }
 
-    let mut html_output = String::new();
-    html::push_html(&mut html_output, events.into_iter());
-    
-    Ok(html_output)
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut excerpt = String::new();
+    for word in words {
+        let len = excerpt.chars().count() + usize::from(!excerpt.is_empty()) + word.chars().count();
+        if len > max_chars {
+            excerpt.push('…');
+            break;
+        }
+        if !excerpt.is_empty() {
+            excerpt.push(' ');
+        }
+        excerpt.push_str(word);
+    }
+    excerpt
 }
 
-/// Parse dimension specification from title or use from image.
-fn parse_dimensions_or_image(title: &str, img_w: u32, img_h: u32) -> (String, String) {
-    let clean = title.trim();
-    
-    // Try "WxH" format
-    if let Some(x_pos) = clean.find('x') {
-        let (w_str, h_str) = clean.split_at(x_pos);
-        let h_str = &h_str[1..];
-        if let (Ok(w), Ok(h)) = (w_str.parse::<u32>(), h_str.parse::<u32>()) {
-            return (format!(r#"width="{}""#, w), format!(r#"height="{}""#, h));
+/// Estimated minutes to read `markdown`, at least one.
+pub fn reading_minutes(markdown: &str) -> usize {
+    let text = search::plain_text(markdown);
+    let cjk = text.chars().filter(|&c| search::is_cjk(c)).count();
+    let words = text.split(|c: char| c.is_whitespace() || search::is_cjk(c)).filter(|w| !w.is_empty()).count();
+    (words + cjk.div_ceil(2)).div_ceil(WORDS_PER_MINUTE).max(1)
+}
+
+/// Lines of `markdown` (with their line endings), each flagged with
+/// whether it belongs to a fenced code block, fences included.
+pub(crate) fn lines_outside_code(markdown: &str) -> impl Iterator<Item = (&str, bool)> {
+    let mut fence: Option<&str> = None;
+    markdown.split_inclusive('\n').map(move |line| {
+        let trimmed = line.trim();
+        match fence {
+            Some(marker) => {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                (line, true)
+            }
+            None => {
+                fence = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+                (line, fence.is_some())
+            }
         }
+    })
+}
+
+/// pulldown-cmark extensions enabled by `config`.
+pub fn markdown_options(config: &Config) -> Options {
+    let mut options = Options::empty();
+    // Typographic quotes and dashes; code spans and blocks are left alone
+    options.set(Options::ENABLE_SMART_PUNCTUATION, config.smart_punctuation);
+    options
+}
+
+/// Convert markdown to HTML through `env.pipeline`.
+///
+/// `source` is the markdown file, used to attribute warnings. Images are
+/// read from `post.config.content_dir` and written below its `public_dir`.
+pub fn render_markdown(
+    markdown: &str,
+    source: &Path,
+    post: &ResolvedPostConfig<'_>,
+    relative_root: &str,
+    env: &MarkdownEnv<'_>,
+) -> Result<RenderedMarkdown, BuildError> {
+    let mut state = RenderState::new(&post.config, source, relative_root, *env);
+    state.image_defaults = post.images;
+    state.published = post.published;
+    let html = env.pipeline.run(markdown, &mut state);
+    let RenderState { lightbox, warnings, images, mut scripts, .. } = state;
+    if lightbox.needs_script() {
+        scripts.push(format!("{relative_root}{LIGHTBOX_SCRIPT}"));
     }
-    
-    // Try single width value
-    if let Ok(w) = clean.parse::<u32>() {
-        return (format!(r#"width="{}""#, w), String::new());
+    Ok(RenderedMarkdown { html, warnings, images, scripts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_links_by_host() {
+        let base = Some("https://duck.dev/blog");
+        assert!(is_external("https://rust-lang.org/learn", base));
+        assert!(is_external("//cdn.dev/x", base));
+        assert!(!is_external("https://DUCK.dev/blog/posts/a.html", base));
+        assert!(!is_external("posts/a.html", base));
+        assert!(!is_external("mailto:me@duck.dev", base));
+        assert!(is_external("https://duck.dev", None));
+
+        let links = ExternalLinks { new_tab: true, class: Some("ext".to_string()), ..Default::default() };
+        assert_eq!(links.attributes(), r#" rel="noopener noreferrer" target="_blank" class="ext""#);
     }
-    
-    // Use image dimensions if available
-    if img_w > 0 && img_h > 0 {
-        return (format!(r#"width="{}""#, img_w), format!(r#"height="{}""#, img_h));
+
+    #[test]
+    fn smart_punctuation_skips_code() {
+        let markdown = "\"Quack\" -- it's... `\"raw\" --`\n\n```\n\"block\" ---\n```\n";
+        let render = |config: &Config| {
+            let mut out = String::new();
+            pulldown_cmark::html::push_html(&mut out, Parser::new_ext(markdown, markdown_options(config)));
+            out
+        };
+        let html = render(&Config::new().smart_punctuation(true));
+        assert!(html.starts_with("<p>“Quack” – it’s… <code>\"raw\" --</code></p>"));
+        assert!(html.contains("\"block\" ---"));
+        assert!(render(&Config::new()).starts_with("<p>\"Quack\" -- it's..."));
     }
-    
-    (String::new(), String::new())
-}
 
-/// Check if title is a dimension specification.
-fn is_dimension_spec(title: &str) -> bool {
-    let clean = title.trim();
-    if clean.is_empty() {
-        return false;
+    #[test]
+    fn rejected_tags_have_positions() {
+        let metadata = extract_metadata("# Title\n\nTags: Rust, a/b,  , Web\n", "fallback");
+        assert_eq!(metadata.tags.iter().map(|t| t.as_str()).collect::<Vec<_>>(), ["Rust", "Web"]);
+        let rejected: Vec<_> = metadata.rejected_tags.iter().map(|(tag, _, position)| (tag.as_str(), *position)).collect();
+        assert_eq!(rejected, [(" a/b", SourcePos::new(3, 13)), ("  ", SourcePos::new(3, 19))]);
+    }
+
+    #[test]
+    fn reading_time_counts_words_and_cjk_characters() {
+        assert_eq!(reading_minutes("# Title\n\nQuack."), 1);
+        assert_eq!(reading_minutes(&"quack ".repeat(401)), 3);
+        // 400 characters read like 200 words
+        assert_eq!(reading_minutes(&"静态网站".repeat(100)), 1);
+        assert_eq!(reading_minutes(&"静态网站".repeat(101)), 2);
+    }
+
+    #[test]
+    fn excerpt_is_first_paragraph_text() {
+        let post = "# Title\n\nTags: Rust\n\n![photo](a.png)\n\nSome *emphasis* and `code`\nacross lines.\n\nSecond paragraph.\n";
+        assert_eq!(extract_excerpt(post, 160), "Some emphasis and code across lines.");
+        assert_eq!(extract_excerpt(post, 20), "Some emphasis and…");
+        assert_eq!(extract_excerpt("# Only a title\n", 160), "");
+        assert_eq!(extract_excerpt("{{< youtube \"dQw4w9WgXcQ\" >}}\n\nWatch this.\n", 160), "Watch this.");
+    }
+
+    #[test]
+    fn captions_from_title_or_caption_paragraph() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new().content_dir(dir.path()).public_dir(dir.path().join("public"));
+        let markdown = "![a](a.png \"Tom & Jerry\")\n\n![b](b.png \"300\")\n\nCaption: The *best* view\n\nCaption: text\n";
+        let rendered = render_markdown(
+            markdown,
+            Path::new("post.md"),
+            &ResolvedPostConfig::site(&config),
+            "../",
+            &MarkdownEnv {
+                outputs: &OutputSet::new(),
+                decode_limit: &DecodeLimit::default(),
+                image_cache: &ImageCache::default(),
+                data: &SiteData::default(),
+                registry: &Registry::default(),
+                pipeline: &Pipeline::standard(),
+            },
+        )
+        .unwrap();
+
+        let figures: Vec<&str> = rendered.html.split("<figure").skip(1).collect();
+        assert!(figures[0].contains("<figcaption>Tom &amp; Jerry <a href=\"../a.png\""), "{}", figures[0]);
+        assert!(figures[1].contains(r#"width="300""#));
+        assert!(figures[1].contains("<figcaption>The <em>best</em> view <a href=\"../b.png\""), "{}", figures[1]);
+        assert!(figures[1].contains("</figcaption></figure>\n<p>Caption: text</p>"), "{}", figures[1]);
+        assert!(!rendered.html.contains("<p><figure"), "{}", rendered.html);
     }
-    
-    // "WxH" format
-    if let Some(x_pos) = clean.find('x') {
-        let (w_str, h_str) = clean.split_at(x_pos);
-        let h_str = &h_str[1..];
-        return w_str.parse::<u32>().is_ok() && h_str.parse::<u32>().is_ok();
+
+    #[test]
+    fn linked_files_become_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("slides.pdf"), vec![0; 2048]).unwrap();
+        let config = Config::new().content_dir(dir.path()).public_dir(dir.path().join("public"));
+        let outputs = OutputSet::new();
+        let rendered = render_markdown(
+            "[Slides](slides.pdf#page=2 \"Talk\") and [notes](missing.txt)\n",
+            Path::new("post.md"),
+            &ResolvedPostConfig::site(&config),
+            "../",
+            &MarkdownEnv {
+                outputs: &outputs,
+                decode_limit: &DecodeLimit::default(),
+                image_cache: &ImageCache::default(),
+                data: &SiteData::default(),
+                registry: &Registry::default(),
+                pipeline: &Pipeline::standard(),
+            },
+        )
+        .unwrap();
+
+        assert!(rendered.html.contains(
+            r#"<a href="../files/slides.pdf#page=2" title="Talk" class="download" download>Slides</a> <span class="file-size">(2.0 KB)</span>"#
+        ), "{}", rendered.html);
+        assert!(dir.path().join("public/files/slides.pdf").exists());
+        assert!(rendered.html.contains(r#"<a href="missing.txt">notes</a>"#), "{}", rendered.html);
+        assert!(matches!(&rendered.warnings[..], [BuildError::BrokenLink { .. }]), "{:?}", rendered.warnings);
+    }
+
+    #[test]
+    fn external_links_point_to_archived_copies() {
+        let render = |archive| {
+            let config = Config::new().external_links(ExternalLinks { archive: Some(archive), ..ExternalLinks::default() });
+            let post = ResolvedPostConfig { published: NaiveDate::from_ymd_opt(2024, 5, 4), ..ResolvedPostConfig::site(&config) };
+            render_markdown(
+                "[Docs](https://duck.example/docs?a=1&b=2) and [home](index.html)\n",
+                Path::new("post.md"),
+                &post,
+                "../",
+                &MarkdownEnv {
+                    outputs: &OutputSet::new(),
+                    decode_limit: &DecodeLimit::default(),
+                    image_cache: &ImageCache::default(),
+                    data: &SiteData::default(),
+                    registry: &Registry::default(),
+                    pipeline: &Pipeline::standard(),
+                },
+            )
+            .unwrap()
+            .html
+        };
+
+        let html = render(ArchiveLinks::Link);
+        assert!(html.contains(concat!(
+            r#"<a href="https://duck.example/docs?a=1&amp;b=2" rel="noopener noreferrer">Docs</a> "#,
+            r#"<a href="https://web.archive.org/web/20240504/https://duck.example/docs?a=1&amp;b=2" class="archived-link" rel="nofollow">(archived)</a> and"#,
+        )), "{html}");
+        assert_eq!(html.matches("archived-link").count(), 1);
+
+        let html = render(ArchiveLinks::Attribute);
+        assert!(html.contains(r#"rel="noopener noreferrer" data-archive="https://web.archive.org/web/20240504/https://duck.example/docs?a=1&amp;b=2">Docs</a> and"#), "{html}");
+        assert_eq!(archive_url("https://duck.example", None), "https://web.archive.org/web/https://duck.example");
+    }
+
+    #[test]
+    fn image_sidecar_fills_alt_caption_and_credit() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.png.meta.toml"), "alt = \"A duck\"\ncaption = \"Pond\"\ncredit = \"Jane\"\n").unwrap();
+        let config = Config::new().content_dir(dir.path()).public_dir(dir.path().join("public"));
+        let markdown = "![](a.png)\n\n![Mine](a.png \"Own caption\")\n";
+        let rendered = render_markdown(
+            markdown,
+            Path::new("post.md"),
+            &ResolvedPostConfig::site(&config),
+            "../",
+            &MarkdownEnv {
+                outputs: &OutputSet::new(),
+                decode_limit: &DecodeLimit::default(),
+                image_cache: &ImageCache::default(),
+                data: &SiteData::default(),
+                registry: &Registry::default(),
+                pipeline: &Pipeline::standard(),
+            },
+        )
+        .unwrap();
+
+        let figures: Vec<&str> = rendered.html.split("<figure").skip(1).collect();
+        assert!(figures[0].contains(r#"alt="A duck""#), "{}", figures[0]);
+        assert!(figures[0].contains(r#"<figcaption>Pond <a href="../a.png""#), "{}", figures[0]);
+        assert!(figures[0].contains(r#"<small class="image-credit">Jane</small></figcaption>"#), "{}", figures[0]);
+        assert!(figures[1].contains(r#"alt="Mine""#) && figures[1].contains("<figcaption>Own caption "), "{}", figures[1]);
     }
-    
-    // Single number
-    clean.parse::<u32>().is_ok()
 }
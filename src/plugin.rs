@@ -0,0 +1,88 @@
+//! Build pipeline hooks for library users.
+//!
+//! A [`Plugin`] registered with `Site::with_plugin` sees every post after
+//! it is parsed, every HTML page before it is written and the summary of
+//! every finished build, so features can be added without forking:
+//!
+//! ```no_run
+//! use generator::config::Config;
+//! use generator::plugin::Plugin;
+//! use generator::site::Site;
+//!
+//! struct Analytics;
+//!
+//! impl Plugin for Analytics {
+//!     fn after_render(&self, html: &mut String) {
+//!         *html = html.replace("</body>", "<script src=\"/stats.js\"></script></body>");
+//!     }
+//! }
+//!
+//! let mut site = Site::new(Config::new()).with_plugin(Analytics);
+//! site.build().unwrap();
+//! ```
+//!
+//! Hooks run in registration order. Posts and pages are processed in
+//! parallel, hence `Send + Sync` and `&self`.
+
+use crate::error::BuildSummary;
+use crate::parser::PostMetadata;
+
+/// Hooks into the build pipeline; every hook defaults to doing nothing.
+pub trait Plugin: Send + Sync {
+    /// A post was parsed: its metadata and markdown body (front matter
+    /// removed, includes expanded) can still change. Parse results are
+    /// cached, so this runs again only when the post changes.
+    fn after_parse(&self, _metadata: &mut PostMetadata, _content: &mut String) {}
+
+    /// A full HTML page (post, list, 404 or links page) was rendered.
+    fn after_render(&self, _html: &mut String) {}
+
+    /// A build finished.
+    fn after_build(&self, _summary: &BuildSummary) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::site::tests::test_site;
+    use crate::site::Site;
+
+    struct Stamp {
+        builds: Arc<AtomicUsize>,
+    }
+
+    impl Plugin for Stamp {
+        fn after_parse(&self, _metadata: &mut PostMetadata, content: &mut String) {
+            content.push_str("\nStamped.\n");
+        }
+
+        fn after_render(&self, html: &mut String) {
+            *html = html.replace("</body>", "<!-- stamped --></body>");
+        }
+
+        fn after_build(&self, _summary: &BuildSummary) {
+            self.builds.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn hooks_run_during_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("hello.md"), "# Hello\n\nHi.\n").unwrap();
+
+        let builds = Arc::new(AtomicUsize::new(0));
+        let mut site = Site::new(config).with_plugin(Stamp { builds: builds.clone() });
+        site.build().unwrap();
+
+        let post = fs::read_to_string(public.join("posts/hello.html")).unwrap();
+        assert!(post.contains("<p>Stamped.</p>"));
+        assert!(post.ends_with("<!-- stamped --></body>\n</html>"));
+        assert!(fs::read_to_string(public.join("index.html")).unwrap().contains("<!-- stamped -->"));
+        assert_eq!(builds.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,156 @@
+//! Per-post overrides of rendering options.
+//!
+//! Front matter keys named like one of the `OVERRIDES` config options
+//! apply to that post only:
+//!
+//! ```text
+//! ---
+//! max_image_width: 800
+//! lightbox: script
+//! smart_punctuation: true
+//! ---
+//! ```
+//!
+//! Image limits become defaults for each image's own options (as if it
+//! were `a.jpg?width=800`), so a post's smaller copies get their own
+//! output files instead of replacing the ones other posts use. A bad value
+//! is an `InvalidOverride` warning and the site setting stays.
+
+use std::borrow::Cow;
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::front_matter::FrontMatter;
+use crate::image::{ImageFit, ImageQuery};
+use crate::lightbox::Lightbox;
+
+/// Config options a post can override.
+pub const OVERRIDES: &[&str] = &[
+    "max_image_width",
+    "max_image_height",
+    "image_fit",
+    "lightbox",
+    "smart_punctuation",
+    "demote_headings",
+    "strip_title",
+    "a11y_lint",
+    "require_alt_text",
+];
+
+/// The config one post is rendered with.
+#[derive(Debug, Clone)]
+pub struct ResolvedPostConfig<'a> {
+    /// The site config, with the post's overrides applied.
+    pub config: Cow<'a, Config>,
+    /// Image limits the post overrides (see `ImageQuery::apply_to`).
+    pub images: ImageQuery,
+    /// Publication date of the post (None for other pages), which links
+    /// to archived copies of external pages ask for.
+    pub published: Option<NaiveDate>,
+}
+
+impl<'a> ResolvedPostConfig<'a> {
+    /// The site config without overrides.
+    pub fn site(config: &'a Config) -> Self {
+        Self { config: Cow::Borrowed(config), images: ImageQuery::default(), published: None }
+    }
+
+    /// Merge the overrides in `front` onto `config`, with warnings for
+    /// bad values in `source`.
+    pub fn resolve(config: &'a Config, front: &FrontMatter, source: &Path) -> (Self, Vec<BuildError>) {
+        let mut resolved = Self::site(config);
+        let mut warnings = Vec::new();
+        for &key in OVERRIDES {
+            if let Some(value) = front.get(key)
+                && let Err(message) = resolved.apply(key, value)
+            {
+                warnings.push(BuildError::InvalidOverride { path: source.to_path_buf(), key, message });
+            }
+        }
+        (resolved, warnings)
+    }
+
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = |expected: &str| format!("expected {expected}, got {value:?}");
+        match key {
+            "max_image_width" => self.images.width = Some(pixels(value).ok_or_else(|| invalid("pixels"))?),
+            "max_image_height" => self.images.height = Some(pixels(value).ok_or_else(|| invalid("pixels"))?),
+            "image_fit" => self.images.fit = Some(ImageFit::parse(value).ok_or_else(|| invalid("contain or crop"))?),
+            "lightbox" => {
+                self.config.to_mut().lightbox = match value {
+                    "none" => Lightbox::None,
+                    "css" => Lightbox::Css,
+                    "script" => Lightbox::Script,
+                    _ => return Err(invalid("none, css or script")),
+                }
+            }
+            "smart_punctuation" => self.config.to_mut().smart_punctuation = boolean(value).ok_or_else(|| invalid("true or false"))?,
+            "demote_headings" => self.config.to_mut().demote_headings = boolean(value).ok_or_else(|| invalid("true or false"))?,
+            "strip_title" => self.config.to_mut().strip_title = boolean(value).ok_or_else(|| invalid("true or false"))?,
+            "a11y_lint" => self.config.to_mut().a11y_lint = boolean(value).ok_or_else(|| invalid("true or false"))?,
+            "require_alt_text" => self.config.to_mut().require_alt_text = boolean(value).ok_or_else(|| invalid("true or false"))?,
+            _ => unreachable!("{key} is not in OVERRIDES"),
+        }
+        Ok(())
+    }
+
+    /// `src` of an image in the post, with the post's image limits.
+    pub fn image_src(&self, src: &str) -> String {
+        self.images.apply_to(src)
+    }
+}
+
+fn pixels(value: &str) -> Option<u32> {
+    value.parse().ok().filter(|n| *n > 0)
+}
+
+/// `true`/`yes` or `false`/`no`, in any case.
+fn boolean(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" => Some(true),
+        "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::front_matter;
+
+    use super::*;
+
+    #[test]
+    fn overrides_merge_onto_the_site_config() {
+        let config = Config::new().lightbox(Lightbox::Css);
+        let (front, _) = front_matter::split("---\nmax_image_width: 800\nlightbox: script\nsmart_punctuation: Yes\n---\n");
+        let (resolved, warnings) = ResolvedPostConfig::resolve(&config, &front, Path::new("post.md"));
+        assert!(warnings.is_empty());
+        assert_eq!(resolved.config.lightbox, Lightbox::Script);
+        assert!(resolved.config.smart_punctuation);
+        // Image limits stay out of the config, so outputs don't collide
+        assert_eq!(resolved.config.max_image_width, config.max_image_width);
+        assert_eq!(resolved.image_src("a.jpg"), "a.jpg?width=800");
+
+        let (front, _) = front_matter::split("---\ntitle: Plain\n---\n");
+        let (resolved, _) = ResolvedPostConfig::resolve(&config, &front, Path::new("post.md"));
+        assert!(matches!(resolved.config, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn bad_values_keep_the_site_setting() {
+        let config = Config::new();
+        let (front, _) = front_matter::split("---\nmax_image_width: wide\nlightbox: modal\na11y_lint: true\n---\n");
+        let (resolved, warnings) = ResolvedPostConfig::resolve(&config, &front, Path::new("post.md"));
+        assert_eq!(resolved.images, ImageQuery::default());
+        assert_eq!(resolved.config.lightbox, Lightbox::None);
+        assert!(resolved.config.a11y_lint);
+        let messages: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(messages, [
+            "Invalid max_image_width override in \"post.md\": expected pixels, got \"wide\"",
+            "Invalid lightbox override in \"post.md\": expected none, css or script, got \"modal\"",
+        ]);
+    }
+}
@@ -0,0 +1,65 @@
+//! Print stylesheet.
+//!
+//! Pages carry a print-only stylesheet (inlined with `inline_css`, else
+//! linked as `print.css`) so posts print and save to PDF cleanly: the
+//! header, navigation, comments and players are hidden, external link
+//! URLs are spelled out after the link text, and figures, code blocks and
+//! tables are kept on one page. With a linked stylesheet, a
+//! `static/print.css` replaces the built-in one.
+
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::output::OutputSet;
+
+/// Print stylesheet file name at the output root.
+pub const PRINT_CSS_FILE: &str = "print.css";
+
+/// Built-in print stylesheet.
+pub const PRINT_CSS: &str = r#"@page { margin: 2cm; }
+header, nav, .comments, .audio-player, .embed, .lightbox, .download-link, .lang-switch { display: none !important; }
+body { max-width: none; margin: 0; color: #000; background: #fff; font: 11pt/1.5 Georgia, serif; }
+a { color: inherit; }
+article a[href^="http"]:not(.u-url):not(.p-author)::after { content: " (" attr(href) ")"; font-size: 0.8em; word-break: break-all; }
+figure, .image-container, .gallery, pre, blockquote, table { break-inside: avoid; page-break-inside: avoid; }
+h1, h2, h3, h4 { break-after: avoid; page-break-after: avoid; }
+img { max-width: 100% !important; height: auto; }
+pre { white-space: pre-wrap; }
+"#;
+
+/// `<head>` tag for the print stylesheet at `asset_root`.
+pub fn head_tag(config: &Config, asset_root: &str) -> String {
+    match (config.print_css, config.inline_css) {
+        (false, _) => String::new(),
+        (true, true) => format!(r#"<style media="print">{PRINT_CSS}</style>"#),
+        (true, false) => format!(r#"<link rel="stylesheet" href="{asset_root}{PRINT_CSS_FILE}" media="print">"#),
+    }
+}
+
+/// Write `print.css` when pages link it and `static/` has none.
+pub fn write(config: &Config, outputs: &OutputSet) -> Result<(), BuildError> {
+    let path = config.public_dir.join(PRINT_CSS_FILE);
+    if config.print_css && !config.inline_css && !outputs.contains(&path) {
+        outputs.write(&path, PRINT_CSS)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inlined_or_linked() {
+        let config = Config::new();
+        assert!(head_tag(&config, "../").starts_with(r#"<style media="print">@page"#));
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::new().public_dir(dir.path());
+        config.inline_css = false;
+        assert_eq!(head_tag(&config, "../"), r#"<link rel="stylesheet" href="../print.css" media="print">"#);
+        write(&config, &OutputSet::new()).unwrap();
+        assert!(dir.path().join(PRINT_CSS_FILE).exists());
+
+        assert_eq!(head_tag(&config.print_css(false), "../"), "");
+    }
+}
@@ -0,0 +1,82 @@
+//! Per-phase progress bars.
+//!
+//! A `Progress` is either visible (drawing to stderr) or hidden; hidden
+//! bars are no-ops, so the pipeline can tick them unconditionally from any
+//! rayon worker. Log output is routed through `SuspendingStderr` so that
+//! tracing lines print above the bars instead of tearing them.
+
+use std::io::{self, Write};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+/// Handle for creating phase bars.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    multi: Option<MultiProgress>,
+}
+
+impl Progress {
+    /// Progress that draws nothing (library default, non-TTY runs).
+    pub fn hidden() -> Self {
+        Self::default()
+    }
+
+    /// Progress drawn to stderr.
+    pub fn stderr() -> Self {
+        Self {
+            multi: Some(MultiProgress::new()),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.multi.is_some()
+    }
+
+    /// Bar for a phase with a known number of items.
+    pub fn phase(&self, label: &'static str, len: usize) -> ProgressBar {
+        let style = ProgressStyle::with_template("{prefix:>8} [{bar:30}] {pos}/{len} {wide_msg}")
+            .expect("valid progress template")
+            .progress_chars("=> ");
+        self.add(ProgressBar::new(len as u64).with_style(style).with_prefix(label))
+    }
+
+    /// Counter for a phase whose total is not known upfront.
+    pub fn counter(&self, label: &'static str) -> ProgressBar {
+        let style = ProgressStyle::with_template("{prefix:>8} {spinner} {pos} {wide_msg}")
+            .expect("valid progress template");
+        self.add(ProgressBar::no_length().with_style(style).with_prefix(label))
+    }
+
+    fn add(&self, bar: ProgressBar) -> ProgressBar {
+        match self.multi {
+            Some(ref multi) => multi.add(bar),
+            None => ProgressBar::hidden(),
+        }
+    }
+
+    /// Writer for log output that does not corrupt visible bars.
+    pub fn writer(&self) -> SuspendingStderr {
+        SuspendingStderr {
+            multi: self.multi.clone(),
+        }
+    }
+}
+
+/// Stderr writer that hides the bars while a log line is written.
+#[derive(Debug, Clone)]
+pub struct SuspendingStderr {
+    multi: Option<MultiProgress>,
+}
+
+impl Write for SuspendingStderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.multi {
+            Some(ref multi) => multi.suspend(|| io::stderr().write_all(buf)).map(|()| buf.len()),
+            None => io::stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
@@ -0,0 +1,154 @@
+//! Password-protected posts.
+//!
+//! A post with `protected: <password>` front matter is published with its
+//! body encrypted: AES-256-GCM under a key derived from the password with
+//! PBKDF2-HMAC-SHA384 (the `aes-gcm`, `pbkdf2` and `hkdf` crates). The page
+//! holds the ciphertext and a password form; the bundled `protect.js`
+//! decrypts it in the browser with WebCrypto.
+//!
+//! Two HKDF-SHA384 subkeys are derived from the PBKDF2 output: the
+//! AES-GCM key, and a key for the IV alone. Encryption is deterministic, so
+//! builds stay reproducible: the salt comes from the post's URL, and the
+//! IV is an HMAC of the body under the IV key (a synthetic IV: it changes
+//! whenever the body does, and only an unchanged body encrypts the same).
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac;
+use sha2::{Digest, Sha384};
+
+use crate::i18n::Strings;
+use crate::types::EscapeHtml;
+
+/// File name of the bundled script in the output root.
+pub const PROTECT_SCRIPT: &str = "protect.js";
+
+/// PBKDF2 rounds; each password guess costs as much.
+pub const ITERATIONS: u32 = 100_000;
+
+/// HKDF `info` of the AES-GCM key, and of the key the IV is derived with.
+const KEY_INFO: &str = "ssg-protect aes-gcm";
+const IV_INFO: &str = "ssg-protect iv";
+
+/// The bundled decryption script.
+pub const PROTECT_JS: &str = r#"const bytes = (text) => Uint8Array.from(atob(text), (c) => c.charCodeAt(0));
+document.querySelectorAll(".protected").forEach((box) => {
+  const form = box.querySelector("form");
+  form.addEventListener("submit", async (event) => {
+    event.preventDefault();
+    const password = new TextEncoder().encode(form.querySelector("input").value);
+    try {
+      const material = await crypto.subtle.importKey("raw", password, "PBKDF2", false, ["deriveBits"]);
+      const master = await crypto.subtle.deriveBits(
+        { name: "PBKDF2", hash: "SHA-384", salt: bytes(box.dataset.salt), iterations: Number(box.dataset.iterations) },
+        material,
+        256,
+      );
+      const key = await crypto.subtle.deriveKey(
+        { name: "HKDF", hash: "SHA-384", salt: new Uint8Array(), info: new TextEncoder().encode("ssg-protect aes-gcm") },
+        await crypto.subtle.importKey("raw", master, "HKDF", false, ["deriveKey"]),
+        { name: "AES-GCM", length: 256 },
+        false,
+        ["decrypt"],
+      );
+      const html = await crypto.subtle.decrypt({ name: "AES-GCM", iv: bytes(box.dataset.iv) }, key, bytes(box.dataset.ciphertext));
+      box.outerHTML = new TextDecoder().decode(html);
+    } catch {
+      box.querySelector(".protected-error").hidden = false;
+    }
+  });
+});
+"#;
+
+/// Password form holding `html` encrypted with `password`; `salt_seed`
+/// (the post's URL) tells posts apart.
+pub fn render(html: &str, password: &str, salt_seed: &str, strings: &Strings) -> String {
+    let salt: [u8; 16] = Sha384::digest(format!("ssg-protect\0{salt_seed}"))[..16]
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("SHA-384 digests are 48 bytes"));
+    let (key, iv_key) = derive_keys(password, &salt, ITERATIONS);
+    let iv = synthetic_iv(&iv_key, html.as_bytes());
+    // The tag follows the ciphertext, as WebCrypto expects
+    let ciphertext = Aes256Gcm::new(&key.into())
+        .encrypt(Nonce::from_slice(&iv), html.as_bytes())
+        .unwrap_or_else(|_| unreachable!("AES-GCM seals any page-sized plaintext"));
+    format!(
+        concat!(
+            r#"<div class="protected" data-salt="{}" data-iv="{}" data-iterations="{}" data-ciphertext="{}">"#,
+            r#"<form><label>{} <input type="password" autocomplete="current-password" required></label> "#,
+            r#"<button>{}</button><p class="protected-error" hidden>{}</p></form></div>"#,
+        ),
+        BASE64.encode(salt),
+        BASE64.encode(iv),
+        ITERATIONS,
+        BASE64.encode(ciphertext),
+        strings.protected.escape_html(),
+        strings.unlock.escape_html(),
+        strings.wrong_password.escape_html(),
+    )
+}
+
+/// AES-256 key and IV key of `password`: PBKDF2-HMAC-SHA384, then an
+/// HKDF-SHA384 subkey for each use.
+fn derive_keys(password: &str, salt: &[u8], iterations: u32) -> ([u8; 32], [u8; 32]) {
+    let mut master = [0u8; 32];
+    pbkdf2_hmac::<Sha384>(password.as_bytes(), salt, iterations, &mut master);
+    let hkdf = Hkdf::<Sha384>::new(Some(&[]), &master);
+    let (mut key, mut iv_key) = ([0u8; 32], [0u8; 32]);
+    // 32 bytes are well below HKDF-SHA384's limit
+    hkdf.expand(KEY_INFO.as_bytes(), &mut key).unwrap_or_else(|_| unreachable!("HKDF expands to 32 bytes"));
+    hkdf.expand(IV_INFO.as_bytes(), &mut iv_key).unwrap_or_else(|_| unreachable!("HKDF expands to 32 bytes"));
+    (key, iv_key)
+}
+
+/// Synthetic IV of `plaintext`: its HMAC-SHA384 under `iv_key`, truncated.
+fn synthetic_iv(iv_key: &[u8; 32], plaintext: &[u8]) -> [u8; 12] {
+    let mut mac = <Hmac<Sha384> as Mac>::new_from_slice(iv_key).unwrap_or_else(|_| unreachable!("HMAC takes any key length"));
+    mac.update(plaintext);
+    let tag = mac.finalize().into_bytes();
+    tag[..12].try_into().unwrap_or_else(|_| unreachable!("HMAC-SHA384 tags are 48 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `data-name` attribute of the protected box.
+    fn attribute<'a>(html: &'a str, name: &str) -> &'a str {
+        let start = html.find(&format!(r#"data-{name}=""#)).unwrap() + name.len() + 7;
+        &html[start..start + html[start..].find('"').unwrap()]
+    }
+
+    #[test]
+    fn the_page_decrypts_with_the_password() {
+        let body = "<p>Members only, not for ducklings.</p>";
+        let html = render(body, "quack", "posts/secret.html", &Strings::for_locale(chrono::Locale::en_US));
+        let bytes = |name| BASE64.decode(attribute(&html, name)).unwrap();
+        assert_eq!(attribute(&html, "iterations"), "100000");
+
+        // What protect.js does: PBKDF2, then the HKDF AES key
+        let (key, _) = derive_keys("quack", &bytes("salt"), ITERATIONS);
+        let cipher = Aes256Gcm::new(&key.into());
+        let plaintext = cipher.decrypt(Nonce::from_slice(&bytes("iv")), bytes("ciphertext").as_slice()).unwrap();
+        assert_eq!(plaintext, body.as_bytes());
+
+        let (wrong, _) = derive_keys("honk", &bytes("salt"), ITERATIONS);
+        assert!(Aes256Gcm::new(&wrong.into()).decrypt(Nonce::from_slice(&bytes("iv")), bytes("ciphertext").as_slice()).is_err());
+    }
+
+    #[test]
+    fn the_iv_has_its_own_key_and_follows_the_body() {
+        let salt: [u8; 16] = std::array::from_fn(|i| i as u8);
+        let (key, iv_key) = derive_keys("quack", &salt, 1000);
+        assert_ne!(key, iv_key);
+        assert_eq!(synthetic_iv(&iv_key, b"<p>a</p>"), synthetic_iv(&iv_key, b"<p>a</p>"));
+        assert_ne!(synthetic_iv(&iv_key, b"<p>a</p>"), synthetic_iv(&iv_key, b"<p>b</p>"));
+
+        let strings = Strings::for_locale(chrono::Locale::en_US);
+        assert_eq!(render("<p>a</p>", "quack", "a.html", &strings), render("<p>a</p>", "quack", "a.html", &strings));
+    }
+}
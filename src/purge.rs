@@ -0,0 +1,362 @@
+//! Removal of unused stylesheet rules (the `[purge]` table).
+//!
+//! With `enabled`, rules whose selectors name classes or ids the pages
+//! don't use are left out: per page when the stylesheet is inlined
+//! (`inline_css`), else from `style.css` against every page of the site.
+//! A selector is used when each class and id in it is in a `class` or
+//! `id` attribute; elements, attributes and pseudo-classes are not
+//! checked, nor is anything in brackets or parentheses (`:not(.a)`). A
+//! rule stays when one of its selectors is used. `@media`, `@supports`,
+//! `@layer` and `@container` blocks are purged inside (and dropped when
+//! empty), other at-rules (`@font-face`, `@keyframes`) are kept whole.
+//!
+//! Classes that scripts add never show up in the markup: `safelist`
+//! keeps them (`"is-open"`, `"#modal"` for an id, `"hljs-*"` for a
+//! prefix). Those of the built-in scripts are always kept, and pages of
+//! protected posts, decrypted in the browser, keep the whole stylesheet.
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::csp::parse_attributes;
+
+/// Unused rule removal (the `[purge]` table).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PurgeConfig {
+    pub enabled: bool,
+    /// Classes (`is-open`), ids (`#modal`) and prefixes (`hljs-*`) kept
+    /// although no page has them.
+    pub safelist: Vec<String>,
+}
+
+impl PurgeConfig {
+    fn safelisted(&self, kind: char, name: &str) -> bool {
+        self.safelist.iter().any(|entry| {
+            let (entry_kind, pattern) = match entry.strip_prefix('#') {
+                Some(id) => ('#', id),
+                None => ('.', entry.strip_prefix('.').unwrap_or(entry)),
+            };
+            entry_kind == kind && match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            }
+        })
+    }
+}
+
+/// Classes the built-in scripts add: the search results and the
+/// lightbox.
+const SCRIPT_CLASSES: &[&str] = &["post-list", "post-entry", "entry-title", "entry-date", "lightbox-overlay"];
+
+/// Classes and ids of the markup of one or more pages.
+#[derive(Debug, Clone, Default)]
+pub struct UsedNames {
+    classes: HashSet<String>,
+    ids: HashSet<String>,
+}
+
+impl UsedNames {
+    pub fn new() -> Self {
+        Self {
+            classes: SCRIPT_CLASSES.iter().map(|class| class.to_string()).collect(),
+            ids: HashSet::new(),
+        }
+    }
+
+    /// Add the `class` and `id` attributes of the tags of `html`.
+    pub fn scan(&mut self, html: &str) {
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start + 1..];
+            if let Some(comment) = rest.strip_prefix("!--") {
+                rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+                continue;
+            }
+            let name_len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+            if name_len == 0 {
+                continue;
+            }
+            let name = rest[..name_len].to_ascii_lowercase();
+            let (attrs, after) = parse_attributes(&rest[name_len..]);
+            rest = after;
+            for (key, value) in attrs {
+                match key.as_str() {
+                    "class" => self.classes.extend(value.split_whitespace().map(str::to_string)),
+                    "id" => {
+                        self.ids.insert(value);
+                    }
+                    _ => {}
+                }
+            }
+            // Markup in scripts and styles isn't the page's
+            if name == "script" || name == "style" {
+                rest = rest.find(&format!("</{name}")).map_or("", |end| &rest[end..]);
+            }
+        }
+    }
+
+    fn contains(&self, kind: char, name: &str) -> bool {
+        match kind {
+            '#' => self.ids.contains(name),
+            _ => self.classes.contains(name),
+        }
+    }
+}
+
+/// A stylesheet split into rules, to purge for each page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stylesheet {
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    /// A style rule, with the classes and ids of each of its selectors.
+    Rule { selectors: Vec<Vec<(char, String)>>, text: String },
+    /// A conditional at-rule, purged inside.
+    Block { prelude: String, items: Vec<Item> },
+    /// Anything else, kept as it is.
+    Keep(String),
+}
+
+impl Stylesheet {
+    pub fn parse(css: &str) -> Self {
+        Self { items: parse_items(css) }
+    }
+
+    /// The stylesheet without the rules `used` has no use for.
+    pub fn purge(&self, used: &UsedNames, config: &PurgeConfig) -> String {
+        let mut css = String::new();
+        write_items(&self.items, used, config, &mut css);
+        css
+    }
+}
+
+fn write_items(items: &[Item], used: &UsedNames, config: &PurgeConfig, css: &mut String) {
+    let is_used = |(kind, name): &(char, String)| used.contains(*kind, name) || config.safelisted(*kind, name);
+    for item in items {
+        match item {
+            Item::Rule { selectors, text } => {
+                if selectors.iter().any(|names| names.iter().all(is_used)) {
+                    css.push_str(text);
+                    css.push('\n');
+                }
+            }
+            Item::Block { prelude, items } => {
+                let mut inner = String::new();
+                write_items(items, used, config, &mut inner);
+                if !inner.is_empty() {
+                    css.push_str(&format!("{prelude} {{\n{inner}}}\n"));
+                }
+            }
+            Item::Keep(text) => {
+                css.push_str(text);
+                css.push('\n');
+            }
+        }
+    }
+}
+
+/// At-rules whose blocks hold style rules.
+const CONDITIONAL_RULES: &[&str] = &["media", "supports", "layer", "container"];
+
+fn parse_items(css: &str) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut rest = skip_space(css);
+    while !rest.is_empty() {
+        let Some((at, stop)) = find_top_level(rest, b"{;") else {
+            items.push(Item::Keep(rest.trim().to_string()));
+            break;
+        };
+        let prelude = rest[..at].trim();
+        if stop == b';' {
+            items.push(Item::Keep(rest[..=at].trim().to_string()));
+            rest = skip_space(&rest[at + 1..]);
+            continue;
+        }
+        let end = closing_brace(rest, at);
+        let text = rest[..end].trim();
+        let body = rest[at + 1..end].strip_suffix('}').unwrap_or(&rest[at + 1..end]);
+        let item = match prelude.strip_prefix('@') {
+            Some(rule) => {
+                let name = rule.split(|c: char| c.is_whitespace() || c == '(').next().unwrap_or_default();
+                if CONDITIONAL_RULES.contains(&name.to_ascii_lowercase().as_str()) {
+                    Item::Block { prelude: prelude.to_string(), items: parse_items(body) }
+                } else {
+                    Item::Keep(text.to_string())
+                }
+            }
+            None => Item::Rule { selectors: split_selectors(prelude).iter().map(|s| required_names(s)).collect(), text: text.to_string() },
+        };
+        items.push(item);
+        rest = skip_space(&rest[end..]);
+    }
+    items
+}
+
+/// `css` after leading whitespace and comments.
+fn skip_space(mut css: &str) -> &str {
+    loop {
+        css = css.trim_start();
+        match css.strip_prefix("/*") {
+            Some(comment) => css = comment.find("*/").map_or("", |end| &comment[end + 2..]),
+            None => return css,
+        }
+    }
+}
+
+/// Index after the string or comment starting at `i`, if one does.
+fn skip_quoted(bytes: &[u8], i: usize) -> Option<usize> {
+    match bytes[i] {
+        quote @ (b'"' | b'\'') => {
+            let mut j = i + 1;
+            while j < bytes.len() && bytes[j] != quote {
+                j += if bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            Some((j + 1).min(bytes.len()))
+        }
+        b'/' if bytes.get(i + 1) == Some(&b'*') => {
+            let end = bytes[i + 2..].windows(2).position(|w| w == b"*/").map_or(bytes.len(), |p| i + 2 + p + 2);
+            Some(end)
+        }
+        _ => None,
+    }
+}
+
+/// Index and byte of the first of `stops` outside strings, comments and
+/// parentheses.
+fn find_top_level(css: &str, stops: &[u8]) -> Option<(usize, u8)> {
+    let bytes = css.as_bytes();
+    let (mut i, mut depth) = (0, 0usize);
+    while i < bytes.len() {
+        if let Some(next) = skip_quoted(bytes, i) {
+            i = next;
+            continue;
+        }
+        match bytes[i] {
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            b if depth == 0 && stops.contains(&b) => return Some((i, b)),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Index after the `}` closing the `{` at `open` (the end if unclosed).
+fn closing_brace(css: &str, open: usize) -> usize {
+    let bytes = css.as_bytes();
+    let (mut i, mut depth) = (open + 1, 1);
+    while i < bytes.len() {
+        if let Some(next) = skip_quoted(bytes, i) {
+            i = next;
+            continue;
+        }
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// The selectors of a comma-separated list.
+fn split_selectors(list: &str) -> Vec<&str> {
+    let mut selectors = Vec::new();
+    let mut rest = list;
+    while let Some((at, _)) = find_top_level(rest, b",") {
+        selectors.push(rest[..at].trim());
+        rest = &rest[at + 1..];
+    }
+    selectors.push(rest.trim());
+    selectors
+}
+
+/// Classes (`.`) and ids (`#`) a selector requires, outside brackets and
+/// parentheses; escapes (`.md\:flex`) are undone.
+fn required_names(selector: &str) -> Vec<(char, String)> {
+    let mut names = Vec::new();
+    let mut depth = 0usize;
+    let mut chars = selector.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            '.' | '#' if depth == 0 => {
+                let mut name = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next == '\\' {
+                        chars.next();
+                        name.extend(chars.next());
+                    } else if next.is_alphanumeric() || next == '-' || next == '_' || !next.is_ascii() {
+                        name.push(next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if !name.is_empty() {
+                    names.push((c, name));
+                }
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSS: &str = r#"/* Site */
+body { margin: 0; }
+.nav-link, .unused-a { color: red; }
+.unused-b > #main { color: blue; }
+a:not(.unused-c)[title="a.b, #c"] { color: green; }
+.md\:flex { display: flex; }
+@media (max-width: 600px) {
+    .unused-d { display: none; }
+    #main { padding: 0; }
+}
+@media print { .unused-e { display: none; } }
+@font-face { font-family: "Duck"; src: url("duck.woff2"); }
+.hljs-keyword { color: purple; }
+.is-open { display: block; }
+"#;
+
+    #[test]
+    fn keeps_rules_the_page_uses() {
+        let mut used = UsedNames::new();
+        used.scan(r#"<body class="nav-link md:flex"><main id="main"><!-- <b class="unused-a"> --></main><script>"<b class='unused-b'>"</script></body>"#);
+        let config = PurgeConfig { enabled: true, safelist: vec!["hljs-*".to_string(), "#toc".to_string()] };
+        let css = Stylesheet::parse(CSS).purge(&used, &config);
+        assert_eq!(css, r#"body { margin: 0; }
+.nav-link, .unused-a { color: red; }
+a:not(.unused-c)[title="a.b, #c"] { color: green; }
+.md\:flex { display: flex; }
+@media (max-width: 600px) {
+#main { padding: 0; }
+}
+@font-face { font-family: "Duck"; src: url("duck.woff2"); }
+.hljs-keyword { color: purple; }
+"#);
+    }
+
+    #[test]
+    fn keeps_the_classes_of_scripts() {
+        let config = PurgeConfig { enabled: true, safelist: vec!["is-open".to_string()] };
+        let css = Stylesheet::parse(".post-entry { margin: 0; }\n.is-open { display: block; }\n.closed { display: none; }").purge(&UsedNames::new(), &config);
+        assert_eq!(css, ".post-entry { margin: 0; }\n.is-open { display: block; }\n");
+    }
+}
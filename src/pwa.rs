@@ -0,0 +1,164 @@
+//! Web app manifest and offline service worker.
+//!
+//! `site.webmanifest` is written when the site has an `icon` or
+//! `pwa = true`. With `pwa`, pages also register `sw.js`, which precaches
+//! the index, the most recent posts and the stylesheet on install and
+//! then serves pages network-first, falling back to the cache (and to the
+//! index for pages never visited) when offline. The cache name changes
+//! whenever a precached file does, so a new build replaces the old cache.
+
+use std::hash::{Hash, Hasher};
+
+use serde_json::json;
+
+use crate::config::Config;
+use crate::error::BuildError;
+use crate::icons;
+use crate::output::{OutputSet, StableHasher, content_hash};
+
+/// Web app manifest file name.
+pub const MANIFEST_FILE: &str = "site.webmanifest";
+
+/// Service worker file name.
+pub const SERVICE_WORKER_FILE: &str = "sw.js";
+
+/// Posts precached per language, newest first.
+pub const PRECACHE_POSTS: usize = 10;
+
+/// Head and body tags for the manifest and service worker at
+/// `asset_root`: (head links, end-of-body script).
+pub fn page_tags(config: &Config, asset_root: &str) -> (String, String) {
+    let manifest = if config.icon.is_some() || config.pwa {
+        format!(r#"<link rel="manifest" href="{asset_root}{MANIFEST_FILE}">"#)
+    } else {
+        String::new()
+    };
+    let register = if config.pwa {
+        format!(
+            r#"<script>if ("serviceWorker" in navigator) navigator.serviceWorker.register("{asset_root}{SERVICE_WORKER_FILE}");</script>"#
+        )
+    } else {
+        String::new()
+    };
+    (manifest, register)
+}
+
+/// Write the manifest and, with `pwa`, the service worker precaching
+/// `urls` (relative to the output root) to the output root. Files of the
+/// same name in `static/` win.
+pub fn write(config: &Config, urls: &[String], outputs: &OutputSet) -> Result<(), BuildError> {
+    let manifest = config.public_dir.join(MANIFEST_FILE);
+    if (config.icon.is_some() || config.pwa) && !outputs.contains(&manifest) {
+        outputs.write(&manifest, render_manifest(config))?;
+    }
+    let worker = config.public_dir.join(SERVICE_WORKER_FILE);
+    if config.pwa && !outputs.contains(&worker) {
+        let mut hasher = StableHasher::default();
+        for url in urls {
+            (url, content_hash(&config.public_dir.join(url))).hash(&mut hasher);
+        }
+        outputs.write(&worker, render_service_worker(urls, &format!("{:016x}", hasher.finish())))?;
+    }
+    Ok(())
+}
+
+/// `site.webmanifest` naming the site and its large icons.
+pub fn render_manifest(config: &Config) -> String {
+    let mut manifest = json!({
+        "name": config.brand_name,
+        "short_name": config.brand_name,
+    });
+    if let Some(description) = &config.description {
+        manifest["description"] = json!(description);
+    }
+    if config.icon.is_some() {
+        manifest["icons"] = json!(icons::manifest_icons());
+    }
+    if config.pwa {
+        manifest["start_url"] = json!("./");
+        manifest["scope"] = json!("./");
+        manifest["display"] = json!("standalone");
+    }
+    serde_json::to_string_pretty(&manifest).unwrap_or_default()
+}
+
+/// `sw.js` precaching `urls` into a cache named after `version`.
+pub fn render_service_worker(urls: &[String], version: &str) -> String {
+    let precache = serde_json::to_string_pretty(urls).unwrap_or_default();
+    format!(
+        r#"const CACHE = "ssg-{version}";
+const PRECACHE = {precache};
+
+self.addEventListener("install", (event) => {{
+  event.waitUntil(
+    caches.open(CACHE).then((cache) => cache.addAll(PRECACHE)).then(() => self.skipWaiting())
+  );
+}});
+
+self.addEventListener("activate", (event) => {{
+  event.waitUntil(
+    caches.keys()
+      .then((keys) => Promise.all(keys.filter((key) => key !== CACHE).map((key) => caches.delete(key))))
+      .then(() => self.clients.claim())
+  );
+}});
+
+self.addEventListener("fetch", (event) => {{
+  const request = event.request;
+  if (request.method !== "GET" || new URL(request.url).origin !== location.origin) return;
+  event.respondWith(
+    fetch(request)
+      .then((response) => {{
+        if (response.ok) {{
+          const copy = response.clone();
+          caches.open(CACHE).then((cache) => cache.put(request, copy));
+        }}
+        return response;
+      }})
+      .catch(() => caches.match(request).then((cached) =>
+        cached ?? (request.mode === "navigate" ? caches.match(PRECACHE[0]) : Response.error())
+      ))
+  );
+}});
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_manifest_and_service_worker() {
+        let dir = tempfile::tempdir().unwrap();
+        let public = dir.path().join("public");
+        std::fs::create_dir(&public).unwrap();
+        std::fs::write(public.join("index.html"), "v1").unwrap();
+        let urls = vec!["index.html".to_string(), "style.css".to_string()];
+
+        let config = Config::new().public_dir(&public).brand_name("Duck");
+        let outputs = OutputSet::new();
+        write(&config, &urls, &outputs).unwrap();
+        assert!(!public.join(MANIFEST_FILE).exists());
+        assert_eq!(page_tags(&config, "../"), (String::new(), String::new()));
+
+        let config = config.pwa(true);
+        write(&config, &urls, &OutputSet::new()).unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(public.join(MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(manifest["name"], "Duck");
+        assert_eq!(manifest["display"], "standalone");
+        assert!(manifest.get("icons").is_none());
+
+        let worker = std::fs::read_to_string(public.join(SERVICE_WORKER_FILE)).unwrap();
+        assert!(worker.contains(r#""style.css""#));
+        let (head, body) = page_tags(&config, "../");
+        assert_eq!(head, r#"<link rel="manifest" href="../site.webmanifest">"#);
+        assert!(body.contains(r#"register("../sw.js")"#));
+
+        // A changed page renames the cache
+        std::fs::write(public.join("index.html"), "v2").unwrap();
+        write(&config, &urls, &OutputSet::new()).unwrap();
+        assert_ne!(std::fs::read_to_string(public.join(SERVICE_WORKER_FILE)).unwrap().lines().next(), worker.lines().next());
+    }
+}
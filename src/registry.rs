@@ -0,0 +1,109 @@
+//! Site-wide registry of the posts of a content tree.
+//!
+//! Built once the posts are parsed and handed to rendering, so anything
+//! that refers to another post (slug or title lookups, `.md` links) goes
+//! through one mapping to output pages instead of formatting
+//! `posts/{stem}.html` itself.
+
+use std::collections::HashMap;
+
+use crate::types::RelPath;
+
+/// A post as other pages refer to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostRef {
+    /// File stem, which is also the output name.
+    pub slug: String,
+    pub title: String,
+    /// Page below the output root (`posts/{slug}.html`).
+    pub path: RelPath,
+}
+
+impl PostRef {
+    pub fn new(slug: &str, title: &str) -> Self {
+        Self { slug: slug.to_string(), title: title.to_string(), path: post_path(slug) }
+    }
+}
+
+/// Page of the post `slug` below the output root.
+pub fn post_path(slug: &str) -> RelPath {
+    RelPath::default().join("posts").join(&format!("{slug}.html"))
+}
+
+/// Posts by slug and by title.
+#[derive(Debug, Default)]
+pub struct Registry {
+    posts: Vec<PostRef>,
+    by_slug: HashMap<String, usize>,
+    /// Lowercased titles; the first post with a title wins.
+    by_title: HashMap<String, usize>,
+}
+
+impl Registry {
+    pub fn new(posts: impl IntoIterator<Item = PostRef>) -> Self {
+        let mut registry = Self::default();
+        for post in posts {
+            let index = registry.posts.len();
+            registry.by_slug.insert(post.slug.clone(), index);
+            registry.by_title.entry(post.title.to_lowercase()).or_insert(index);
+            registry.posts.push(post);
+        }
+        registry
+    }
+
+    pub fn by_slug(&self, slug: &str) -> Option<&PostRef> {
+        self.by_slug.get(slug).map(|&i| &self.posts[i])
+    }
+
+    /// Case-insensitive title lookup.
+    pub fn by_title(&self, title: &str) -> Option<&PostRef> {
+        self.by_title.get(&title.trim().to_lowercase()).map(|&i| &self.posts[i])
+    }
+
+    /// A post by slug, else by title.
+    pub fn resolve(&self, key: &str) -> Option<&PostRef> {
+        self.by_slug(key).or_else(|| self.by_title(key))
+    }
+
+    /// Page of a link to a sibling markdown file (`other.md#setup` →
+    /// `posts/other.html#setup`); None for other links and unknown posts.
+    pub fn resolve_link(&self, target: &str) -> Option<RelPath> {
+        let split = target.find(['?', '#']).unwrap_or(target.len());
+        let (path, suffix) = target.split_at(split);
+        let slug = path.strip_prefix("./").unwrap_or(path).strip_suffix(".md")?;
+        if slug.contains('/') {
+            return None;
+        }
+        let post = self.by_slug(slug)?;
+        RelPath::new(&format!("{}{suffix}", post.path)).ok()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PostRef> {
+        self.posts.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.posts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.posts.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_slugs_titles_and_md_links() {
+        let registry = Registry::new([PostRef::new("2024-01-01-hello", "Hello"), PostRef::new("setup", "Setting Up")]);
+        assert_eq!(registry.by_slug("setup").unwrap().path.as_str(), "posts/setup.html");
+        assert_eq!(registry.by_title(" setting up ").unwrap().slug, "setup");
+        assert_eq!(registry.resolve("Hello").unwrap().slug, "2024-01-01-hello");
+        assert_eq!(registry.resolve_link("./setup.md#cargo").unwrap().href("../"), "../posts/setup.html#cargo");
+        assert_eq!(registry.resolve_link("missing.md"), None);
+        assert_eq!(registry.resolve_link("setup.html"), None);
+        assert_eq!(registry.resolve_link("drafts/setup.md"), None);
+    }
+}
@@ -1,106 +1,806 @@
 //! HTML template rendering with type-safe content.
 
-use std::collections::HashSet;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use serde_json::json;
 
 use crate::config::Config;
-use crate::types::{HtmlSafe, EscapeHtml, Tag};
+use crate::csp::{self, ScriptTargets};
+use crate::i18n::Strings;
+use crate::icons;
+use crate::layout::{Layout, LayoutSlots};
+use crate::print;
+use crate::purge::{Stylesheet, UsedNames};
+use crate::pwa;
+use crate::social::{self, SocialImage};
+use crate::sri::Integrity;
+use crate::types::{HtmlSafe, EscapeHtml, Href, RelPath, Tag};
+
+/// How pages are rendered: the site-wide options, and those a page adds
+/// to a clone of them. Owns everything it holds, so library code can keep
+/// one around and clone it per page.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub config: Arc<Config>,
+    /// UI strings for `config`'s locale.
+    pub strings: Strings,
+    /// Stylesheet inlined instead of linking `style.css`.
+    pub inline_css: Option<Arc<str>>,
+    /// `inline_css` parsed, to inline only the rules the page uses
+    /// (`Config::purge`).
+    pub purge: Option<Arc<Stylesheet>>,
+    /// Resources fetched early, the LCP image first.
+    pub preloads: Vec<Preload>,
+    /// Further markup at the end of `<head>`, before `Config::head_html`.
+    pub head_tags: Vec<String>,
+    /// Classes of `<body>`, after the `page-{kind}` one.
+    pub body_classes: Vec<String>,
+    /// Hrefs of the site's Atom and RSS feeds, linked for feed discovery.
+    pub feed_url: Option<String>,
+    pub rss_url: Option<String>,
+    /// Href of the OpenSearch description, for browsers to offer the
+    /// site's search.
+    pub search_url: Option<String>,
+    /// Href of the post's markdown source (`Config::post_source`).
+    pub source_url: Option<String>,
+    /// Structured data for post pages.
+    pub article: Option<ArticleMeta>,
+    /// Where `style.css` and `favicon.ico` live, if not at `relative_root`
+    /// (language subtrees of a multilingual site).
+    pub asset_root: Option<String>,
+    /// Translations of the page, including itself.
+    pub alternates: Vec<Alternate>,
+    /// Script URLs loaded (deferred) at the end of the body.
+    pub scripts: Vec<String>,
+    /// Integrity hashes of external scripts and stylesheets.
+    pub integrity: Option<Arc<Integrity>>,
+    /// Skeleton instead of the built-in one.
+    pub layout: Option<Arc<Layout>>,
+    /// `<html lang>` if not the site's (`lang:` front matter).
+    pub lang: Option<String>,
+    /// Nav entry of the page, marked `aria-current`.
+    pub current: Option<NavPage>,
+    /// Tags shown in the nav, if not all of them (`Config::nav_tags`).
+    pub nav_tags: Option<Arc<BTreeSet<Tag>>>,
+    /// Links added to the nav after the index.
+    pub nav_links: Vec<NavLink>,
+}
+
+/// A resource the page fetches early (`<link rel="preload">`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preload {
+    pub href: Href,
+    /// Its destination (`as`): `image`, `font`, `script`, ...
+    pub kind: String,
+    /// MIME type, for browsers to skip formats they can't use.
+    pub media_type: Option<String>,
+    /// Fetch it ahead of other resources (`fetchpriority="high"`).
+    pub high_priority: bool,
+}
+
+impl Preload {
+    pub fn new(href: Href, kind: impl Into<String>) -> Self {
+        Self { href, kind: kind.into(), media_type: None, high_priority: false }
+    }
+
+    /// `<link>` tag, for a page at `relative_root`. Fonts are always
+    /// fetched in CORS mode, so their preloads need `crossorigin`.
+    fn to_link(&self, relative_root: &str) -> String {
+        let media_type = self.media_type.as_ref()
+            .map(|t| format!(r#" type="{}""#, t.escape_html()))
+            .unwrap_or_default();
+        let crossorigin = if self.kind == "font" { " crossorigin" } else { "" };
+        let priority = if self.high_priority { r#" fetchpriority="high""# } else { "" };
+        format!(
+            r#"<link rel="preload" as="{}" href="{}"{media_type}{crossorigin}{priority}>"#,
+            self.kind.escape_html(), self.href.href(relative_root).escape_html()
+        )
+    }
+}
+
+/// Kind of a rendered page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageKind {
+    Post,
+    /// The index and the archive.
+    Index,
+    /// Tag pages and the tags index.
+    Tag,
+    /// Any other page.
+    Page,
+}
+
+impl PageKind {
+    /// Class of the page's `<body>` (`page-post`).
+    pub fn class(self) -> &'static str {
+        match self {
+            Self::Post => "page-post",
+            Self::Index => "page-index",
+            Self::Tag => "page-tag",
+            Self::Page => "page-page",
+        }
+    }
+}
+
+/// Class of `<body>` on the pages of `tag` (`tag-rust`), its lowercased
+/// name with runs of other characters than letters and digits as `-`.
+pub fn tag_class(tag: &Tag) -> String {
+    let mut class = String::from("tag-");
+    for c in tag.to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            class.push(c);
+        } else if !class.ends_with('-') {
+            class.push('-');
+        }
+    }
+    class.trim_end_matches('-').to_string()
+}
 
-/// Render context with optional CSS content and LCP preload.
-pub struct RenderContext<'a> {
-    pub config: &'a Config,
-    pub inline_css: Option<&'a str>,
-    pub lcp_image_url: Option<String>, // Owned to avoid lifetime issues
+/// A step on the way from the index to a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breadcrumb {
+    pub label: String,
+    /// Absolute with a `base_url`.
+    pub url: String,
 }
 
-impl<'a> RenderContext<'a> {
-    pub fn new(config: &'a Config) -> Self {
+/// The page `template` renders: its title and head metadata, next to the
+/// site-wide `RenderOptions`. New head tags get a field here rather than
+/// an argument of `template`.
+#[derive(Debug, Clone)]
+pub struct PageContext {
+    pub title: HtmlSafe,
+    pub kind: PageKind,
+    /// Prefix from the page to the output root.
+    pub relative_root: String,
+    /// Plain-text `<meta name="description">`.
+    pub description: Option<String>,
+    /// Absolute URL of the page, linked as canonical.
+    pub canonical: Option<String>,
+    /// Trail from the index, emitted as a schema.org `BreadcrumbList`.
+    pub breadcrumbs: Vec<Breadcrumb>,
+    /// Further `<meta name content>` pairs.
+    pub meta: Vec<(String, String)>,
+}
+
+impl PageContext {
+    pub fn new(title: HtmlSafe, kind: PageKind, relative_root: impl Into<String>) -> Self {
         Self {
+            title,
+            kind,
+            relative_root: relative_root.into(),
+            description: None,
+            canonical: None,
+            breadcrumbs: Vec::new(),
+            meta: Vec::new(),
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_canonical(mut self, url: impl Into<String>) -> Self {
+        self.canonical = Some(url.into());
+        self
+    }
+
+    pub fn with_breadcrumb(mut self, label: impl Into<String>, url: impl Into<String>) -> Self {
+        self.breadcrumbs.push(Breadcrumb { label: label.into(), url: url.into() });
+        self
+    }
+
+    pub fn with_meta(mut self, name: impl Into<String>, content: impl Into<String>) -> Self {
+        self.meta.push((name.into(), content.into()));
+        self
+    }
+
+    /// Ask search engines not to index the page.
+    pub fn with_noindex(self, noindex: bool) -> Self {
+        if noindex { self.with_meta("robots", "noindex") } else { self }
+    }
+
+    /// `<script type="application/ld+json">` block of the breadcrumbs.
+    fn breadcrumbs_script(&self) -> String {
+        if self.breadcrumbs.is_empty() {
+            return String::new();
+        }
+        let items: Vec<_> = self.breadcrumbs.iter()
+            .enumerate()
+            .map(|(i, crumb)| json!({
+                "@type": "ListItem",
+                "position": i + 1,
+                "name": crumb.label,
+                "item": crumb.url,
+            }))
+            .collect();
+        let data = json!({
+            "@context": "https://schema.org",
+            "@type": "BreadcrumbList",
+            "itemListElement": items,
+        });
+        // `<` can't end the script early when escaped
+        let data = data.to_string().replace('<', "\\u003c");
+        format!(r#"<script type="application/ld+json">{}</script>"#, data)
+    }
+}
+
+/// A page linked from the nav.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NavPage {
+    Index,
+    /// The tags index.
+    Tags,
+    Tag(Tag),
+    /// Another page, by its path from the output root.
+    Page(RelPath),
+}
+
+/// A link in the nav (see `RenderOptions::with_nav_link`), marked when
+/// the page is `NavPage::Page` of its path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NavLink {
+    pub path: RelPath,
+    pub label: String,
+}
+
+/// A translation of a page, linked with `hreflang`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alternate {
+    /// Language code.
+    pub lang: String,
+    pub href: String,
+    /// Whether this is the page being rendered.
+    pub current: bool,
+}
+
+/// schema.org `BlogPosting` data, emitted as JSON-LD.
+#[derive(Debug, Clone)]
+pub struct ArticleMeta {
+    /// Plain-text title.
+    pub headline: String,
+    /// Plain-text summary, if any.
+    pub description: Option<String>,
+    /// Formatted with `Config::format_iso_date`.
+    pub published: String,
+    pub modified: String,
+    /// Language tag of the post.
+    pub lang: String,
+    /// Permalink, absolute with a `base_url`.
+    pub url: String,
+    /// Preview image for link cards.
+    pub image: Option<SocialImage>,
+}
+
+impl ArticleMeta {
+    /// `<script type="application/ld+json">` block.
+    fn to_script(&self) -> String {
+        let mut data = json!({
+            "@context": "https://schema.org",
+            "@type": "BlogPosting",
+            "headline": self.headline,
+            "datePublished": self.published,
+            "dateModified": self.modified,
+            "inLanguage": self.lang,
+        });
+        if let Some(description) = &self.description {
+            data["description"] = json!(description);
+        }
+        // `<` can't end the script early when escaped
+        let data = data.to_string().replace('<', "\\u003c");
+        format!(r#"<script type="application/ld+json">{}</script>"#, data)
+    }
+
+    /// Open Graph tags (`social::head_tags`).
+    fn social_tags(&self) -> String {
+        social::head_tags(&self.headline, self.description.as_deref(), &self.url, self.image.as_ref())
+    }
+}
+
+impl RenderOptions {
+    pub fn new(config: impl Into<Arc<Config>>) -> Self {
+        let config = config.into();
+        Self {
+            strings: config.ui_strings(),
             config,
             inline_css: None,
-            lcp_image_url: None,
+            purge: None,
+            preloads: Vec::new(),
+            head_tags: Vec::new(),
+            body_classes: Vec::new(),
+            feed_url: None,
+            rss_url: None,
+            search_url: None,
+            source_url: None,
+            article: None,
+            asset_root: None,
+            alternates: Vec::new(),
+            scripts: Vec::new(),
+            integrity: None,
+            layout: None,
+            lang: None,
+            current: None,
+            nav_tags: None,
+            nav_links: Vec::new(),
+        }
+    }
+
+    pub fn with_css(mut self, css: impl Into<Arc<str>>) -> Self {
+        self.inline_css = Some(css.into());
+        self
+    }
+
+    /// Purge the inlined stylesheet for each page, or stop (None).
+    pub fn with_purge(mut self, stylesheet: Option<Arc<Stylesheet>>) -> Self {
+        self.purge = stylesheet;
+        self
+    }
+
+    /// Preload the page's largest image, ahead of the others.
+    pub fn with_lcp_image(mut self, url: Href) -> Self {
+        self.preloads.insert(0, Preload { high_priority: true, ..Preload::new(url, "image") });
+        self
+    }
+
+    pub fn with_preload(mut self, preload: Preload) -> Self {
+        self.preloads.push(preload);
+        self
+    }
+
+    pub fn with_head_tag(mut self, html: impl Into<String>) -> Self {
+        self.head_tags.push(html.into());
+        self
+    }
+
+    pub fn with_body_class(mut self, class: impl Into<String>) -> Self {
+        self.body_classes.push(class.into());
+        self
+    }
+
+    pub fn with_feed(mut self, href: impl Into<String>) -> Self {
+        self.feed_url = Some(href.into());
+        self
+    }
+
+    pub fn with_rss_feed(mut self, href: impl Into<String>) -> Self {
+        self.rss_url = Some(href.into());
+        self
+    }
+
+    pub fn with_search(mut self, href: impl Into<String>) -> Self {
+        self.search_url = Some(href.into());
+        self
+    }
+
+    pub fn with_source(mut self, href: impl Into<String>) -> Self {
+        self.source_url = Some(href.into());
+        self
+    }
+
+    pub fn with_article(mut self, article: ArticleMeta) -> Self {
+        self.article = Some(article);
+        self
+    }
+
+    pub fn with_asset_root(mut self, root: impl Into<String>) -> Self {
+        self.asset_root = Some(root.into());
+        self
+    }
+
+    pub fn with_alternates(mut self, alternates: Vec<Alternate>) -> Self {
+        self.alternates = alternates;
+        self
+    }
+
+    pub fn with_scripts(mut self, scripts: Vec<String>) -> Self {
+        self.scripts = scripts;
+        self
+    }
+
+    pub fn with_integrity(mut self, integrity: Arc<Integrity>) -> Self {
+        self.integrity = Some(integrity);
+        self
+    }
+
+    pub fn with_layout(mut self, layout: Arc<Layout>) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    pub fn with_lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    pub fn with_current(mut self, page: NavPage) -> Self {
+        self.current = Some(page);
+        self
+    }
+
+    pub fn with_nav_tags(mut self, tags: Arc<BTreeSet<Tag>>) -> Self {
+        self.nav_tags = Some(tags);
+        self
+    }
+
+    pub fn with_nav_link(mut self, link: NavLink) -> Self {
+        self.nav_links.push(link);
+        self
+    }
+}
+
+/// The nav of a page, built from sections in the order they are added.
+/// Each section is a `nav-section`; the link to the page itself is marked
+/// `active` and `aria-current`.
+pub struct NavBuilder<'a> {
+    ctx: &'a RenderOptions,
+    relative_root: &'a str,
+    html: String,
+}
+
+impl<'a> NavBuilder<'a> {
+    pub fn new(ctx: &'a RenderOptions, relative_root: &'a str) -> Self {
+        Self { ctx, relative_root, html: String::new() }
+    }
+
+    /// Class suffix and attribute of a link to `page`.
+    fn current(&self, page: &NavPage) -> (&'static str, &'static str) {
+        if self.ctx.current.as_ref() == Some(page) {
+            (" active", r#" aria-current="page""#)
+        } else {
+            ("", "")
+        }
+    }
+
+    /// A section linking `path` as `label`, with the link class `class`.
+    pub fn link(mut self, path: &RelPath, label: &str, class: &str, page: NavPage) -> Self {
+        let (active, aria) = self.current(&page);
+        self.html.push_str(&format!(
+            r#"<div class="nav-section"><a href="{}" class="nav-link {class}{active}"{aria}>{}</a></div>"#,
+            path.href(self.relative_root), label.escape_html()
+        ));
+        self
+    }
+
+    /// The link to the index.
+    pub fn index(self) -> Self {
+        let label = self.ctx.strings.index.clone();
+        self.link(&RelPath::default().join("index.html"), &label, "main-link", NavPage::Index)
+    }
+
+    /// The links of `RenderOptions::nav_links`.
+    pub fn registered(mut self) -> Self {
+        for link in &self.ctx.nav_links {
+            self = self.link(&link.path, &link.label, "page-link", NavPage::Page(link.path.clone()));
+        }
+        self
+    }
+
+    /// The tag filter: a header linking the tags index and the tags
+    /// (`Config::nav_tags` of them), folded with `collapse_nav_tags`.
+    pub fn tags(mut self, all_tags: &BTreeSet<Tag>) -> Self {
+        if all_tags.is_empty() {
+            return self;
         }
+        let (ctx, relative_root) = (self.ctx, self.relative_root);
+        let tag_index = RelPath::default().join("tags").join("index.html").href(relative_root);
+        let shown = ctx.nav_tags.as_deref().unwrap_or(all_tags);
+        let filter = ctx.strings.filter.escape_html();
+        let (active, aria) = self.current(&NavPage::Tags);
+        let mut tag_links = String::new();
+        for tag in shown {
+            let (active, aria) = self.current(&NavPage::Tag(tag.clone()));
+            tag_links.push_str(&format!(
+                r#"<a href="{}" class="nav-link tag-link{}"{}>{}</a>"#,
+                tag_href(tag, relative_root), active, aria, tag
+            ));
+        }
+        let all_link = |active: &str, aria: &str| format!(
+            r#"<a href="{tag_index}" class="nav-link all-tags{active}"{aria}>{} →</a>"#,
+            ctx.strings.all_tags.escape_html()
+        );
+        if ctx.config.collapse_nav_tags {
+            // Open on tag pages, where the current tag is marked
+            let open = if matches!(ctx.current, Some(NavPage::Tag(_))) { " open" } else { "" };
+            self.html.push_str(&format!(
+                r#"<details class="nav-section nav-tags"{open}><summary class="nav-header">{filter}</summary>{tag_links}{}</details>"#,
+                all_link(active, aria)
+            ));
+        } else {
+            let more = if shown.len() < all_tags.len() { all_link("", "") } else { String::new() };
+            self.html.push_str(&format!(
+                r#"<div class="nav-section" role="group" aria-label="{filter}"><a href="{tag_index}" class="nav-header{active}"{aria}>{filter}</a>{tag_links}{more}</div>"#
+            ));
+        }
+        self
     }
 
-    pub fn with_css(mut self, css: &'a str) -> Self {
-        self.inline_css = Some(css);
+    /// Links to the other translations of the page.
+    pub fn languages(mut self) -> Self {
+        let alternates = &self.ctx.alternates;
+        if alternates.len() < 2 {
+            return self;
+        }
+        self.html.push_str(r#"<div class="nav-section lang-switch">"#);
+        for alternate in alternates {
+            let (class, aria) = if alternate.current {
+                ("nav-link lang-link active", r#" aria-current="page""#)
+            } else {
+                ("nav-link lang-link", "")
+            };
+            self.html.push_str(&format!(
+                r#"<a href="{}" hreflang="{}" class="{}"{}>{}</a>"#,
+                alternate.href.escape_html(), alternate.lang.escape_html(), class, aria, alternate.lang.escape_html()
+            ));
+        }
+        self.html.push_str("</div>");
         self
     }
 
-    pub fn with_lcp_image(mut self, url: impl Into<String>) -> Self {
-        self.lcp_image_url = Some(url.into());
+    /// The link to the site feed, if there is one and `feed.nav_link` is
+    /// set.
+    pub fn feed(self) -> Self {
+        match &self.ctx.feed_url {
+            Some(href) if self.ctx.config.feed.nav_link => {
+                let html = format!(
+                    r#"<div class="nav-section"><a href="{}" class="nav-link feed-link" type="application/atom+xml">{}</a></div>"#,
+                    href.escape_html(), self.ctx.strings.feed.escape_html()
+                );
+                self.section(&html)
+            }
+            _ => self,
+        }
+    }
+
+    /// A section of other markup.
+    pub fn section(mut self, html: &str) -> Self {
+        self.html.push_str(html);
         self
     }
+
+    pub fn build(self) -> String {
+        self.html
+    }
 }
 
+/// Stands for the purged stylesheet until the page is rendered.
+const PURGED_CSS: &str = "<!-- purged stylesheet -->";
+
 /// Render the HTML page template.
 pub fn template(
-    title: &HtmlSafe,
+    page: &PageContext,
     content: &str,
-    all_tags: &HashSet<Tag>,
-    relative_root: &str,
-    ctx: &RenderContext<'_>,
+    all_tags: &BTreeSet<Tag>,
+    ctx: &RenderOptions,
 ) -> String {
-    let mut sorted_tags: Vec<_> = all_tags.iter().collect();
-    sorted_tags.sort_by_key(|t| t.as_str());
-    
-    let index_link = format!("{}index.html", relative_root);
+    let (title, relative_root) = (&page.title, page.relative_root.as_str());
     let brand = ctx.config.brand_name.escape_html();
-    
-    let mut nav_html = format!(
-        r#"<div class="nav-section"><a href="{}" class="nav-link main-link">Index</a></div>"#,
-        index_link
-    );
-    
-    if !sorted_tags.is_empty() {
-        nav_html.push_str(r#"<div class="nav-section"><span class="nav-header">Filter</span>"#);
-        for tag in sorted_tags {
-            let tag_lower = tag.to_lowercase();
-            let link = format!("{}tags/tag_{}.html", relative_root, tag_lower);
-            nav_html.push_str(&format!(
-                r#"<a href="{}" class="nav-link tag-link">{}</a>"#,
-                link, tag
-            ));
-        }
-        nav_html.push_str("</div>");
-    }
+    let nav_html = NavBuilder::new(ctx, relative_root)
+        .index()
+        .registered()
+        .tags(all_tags)
+        .languages()
+        .feed()
+        .build();
+    let alternates_block: String = ctx.alternates
+        .iter()
+        .map(|a| format!(
+            r#"<link rel="alternate" hreflang="{}" href="{}">"#,
+            a.lang.escape_html(), a.href.escape_html()
+        ))
+        .collect();
+    let asset_root = ctx.asset_root.as_deref().unwrap_or(relative_root);
 
-    // CSS: either inline or external link
-    let css_block = if let Some(css) = ctx.inline_css {
+    // CSS: either inline or external link; a purged stylesheet is filled
+    // in once the page's markup is known
+    let purge = ctx.purge.as_ref().filter(|_| ctx.inline_css.is_some());
+    let css_block = if purge.is_some() {
+        PURGED_CSS.to_string()
+    } else if let Some(css) = &ctx.inline_css {
         format!("<style>{}</style>", css)
     } else {
-        format!(r#"<link rel="stylesheet" href="{}style.css">"#, relative_root)
+        format!(r#"<link rel="stylesheet" href="{}style.css">"#, asset_root)
+    };
+
+    // Preload hints, the LCP image first; fonts are static assets
+    let preload_block: String = ctx.preloads.iter()
+        .map(|p| p.to_link(if p.kind == "font" { asset_root } else { relative_root }))
+        .collect();
+
+    let description_block = match page.description.as_deref() {
+        Some(description) if !description.is_empty() => format!(
+            r#"<meta name="description" content="{}">"#,
+            description.escape_html()
+        ),
+        _ => String::new(),
     };
+    let meta_block: String = page.meta
+        .iter()
+        .map(|(name, content)| format!(r#"<meta name="{}" content="{}">"#, name.escape_html(), content.escape_html()))
+        .collect();
+    let canonical_block = page.canonical
+        .as_ref()
+        .map(|href| format!(r#"<link rel="canonical" href="{}">"#, href.escape_html()))
+        .unwrap_or_default();
+
+    let atom_block = ctx.feed_url
+        .as_ref()
+        .map(|href| format!(
+            r#"<link rel="alternate" type="application/atom+xml" title="{}" href="{}">"#,
+            brand, href.escape_html()
+        ))
+        .unwrap_or_default();
+    let rss_block = ctx.rss_url
+        .as_ref()
+        .map(|href| format!(
+            r#"<link rel="alternate" type="application/rss+xml" title="{} (RSS)" href="{}">"#,
+            brand, href.escape_html()
+        ))
+        .unwrap_or_default();
+    let feed_block = atom_block + &rss_block;
+
+    let search_block = ctx.search_url
+        .as_ref()
+        .map(|href| format!(
+            r#"<link rel="search" type="application/opensearchdescription+xml" title="{}" href="{}">"#,
+            brand, href.escape_html()
+        ))
+        .unwrap_or_default();
 
-    // LCP preload hint for first image
-    let preload_block = if let Some(ref lcp_url) = ctx.lcp_image_url {
-        format!(r#"<link rel="preload" as="image" href="{}" fetchpriority="high">"#, lcp_url)
+    let source_block = ctx.source_url
+        .as_ref()
+        .zip(ctx.config.post_source)
+        .map(|(href, format)| format!(
+            r#"<link rel="alternate" type="{}" href="{}">"#,
+            format.media_type(), href.escape_html()
+        ))
+        .unwrap_or_default();
+
+    let integrity = |url: &str| ctx.integrity.as_ref().map(|i| i.attributes(url)).unwrap_or_default();
+    let resources = &ctx.config.resources;
+    let styles_block: String = resources.styles
+        .iter()
+        .map(|href| format!(r#"<link rel="stylesheet" href="{}"{}>"#, href.escape_html(), integrity(href)))
+        .collect();
+    let scripts_block: String = resources.scripts
+        .iter()
+        .chain(&ctx.scripts)
+        .map(|src| format!(r#"<script src="{}" defer{}></script>"#, src.escape_html(), integrity(src)))
+        .collect();
+
+    let icon_block = if ctx.config.icon.is_some() {
+        icons::head_links(asset_root)
     } else {
-        String::new()
+        format!(r#"<link rel="icon" href="{asset_root}favicon.ico" type="image/x-icon">"#)
     };
 
-    format!(
-r##"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{brand} | {title}</title>
-    <link rel="icon" href="{relative_root}favicon.ico" type="image/x-icon">
-    {css_block}
-    {preload_block}
-</head>
-<body>
+    let (manifest_block, worker_block) = pwa::page_tags(&ctx.config, asset_root);
+    let print_block = print::head_tag(&ctx.config, asset_root);
+
+    let article_block = ctx.article.as_ref().map(ArticleMeta::to_script).unwrap_or_default();
+    let breadcrumbs_block = page.breadcrumbs_script();
+    let social_block = ctx.article.as_ref().map(ArticleMeta::social_tags).unwrap_or_default();
+    // Posts are h-entries
+    let article_class = if ctx.article.is_some() { r#" class="h-entry""# } else { "" };
+    let indieweb_block = ctx.config.indieweb.head_links();
+    let head_tags = ctx.head_tags.concat();
+    let head_html = ctx.config.head_html.as_deref().unwrap_or_default();
+    let body_end_html = ctx.config.body_end_html.as_deref().unwrap_or_default();
+
+    let head = [
+        r#"<meta charset="UTF-8">"#,
+        r#"<meta name="viewport" content="width=device-width, initial-scale=1.0">"#,
+        &format!("<title>{brand} | {title}</title>"),
+        &description_block,
+        &meta_block,
+        &canonical_block,
+        &alternates_block,
+        &feed_block,
+        &search_block,
+        &source_block,
+        &indieweb_block,
+        &icon_block,
+        &manifest_block,
+        &css_block,
+        &print_block,
+        &styles_block,
+        &preload_block,
+        &article_block,
+        &breadcrumbs_block,
+        &social_block,
+        &head_tags,
+        head_html,
+    ]
+    .join("\n    ");
+    let skip = ctx.strings.skip_to_content.escape_html();
+    let nav_label = ctx.strings.navigation.escape_html();
+    let header = format!(
+r##"<a class="skip-link" href="#content">{skip}</a>
     <header>
         <span class="brand">[ {brand} ]</span>
-        <nav>
+        <nav aria-label="{nav_label}">
             {nav_html}
         </nav>
-    </header>
-    <article>
+    </header>"##
+    );
+    let body_end = [scripts_block.as_str(), &worker_block, body_end_html].join("\n    ");
+
+    let body_class = std::iter::once(page.kind.class())
+        .chain(ctx.body_classes.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+        .escape_html();
+    let lang = ctx.lang.clone().unwrap_or_else(|| ctx.config.html_lang()).escape_html();
+    let html = match &ctx.layout {
+        Some(layout) => layout.render(&LayoutSlots {
+            lang: lang.as_str(),
+            head: &head,
+            header: &header,
+            brand: brand.as_str(),
+            nav: &nav_html,
+            content: &format!("<article{article_class}>\n{content}\n</article>"),
+            body_end: &body_end,
+            body_class: body_class.as_str(),
+        }),
+        None => format!(
+r##"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    {head}
+</head>
+<body class="{body_class}">
+    {header}
+    <main id="content">
+    <article{article_class}>
         {content}
     </article>
+    </main>
+    {body_end}
 </body>
 </html>"##
+        ),
+    };
+    let html = match purge {
+        Some(stylesheet) => {
+            let mut used = UsedNames::new();
+            used.scan(&html);
+            let css = stylesheet.purge(&used, &ctx.config.purge);
+            html.replacen(PURGED_CSS, &format!("<style>{css}</style>"), 1)
+        }
+        None => html,
+    };
+    if resources.csp {
+        let mut targets = ScriptTargets { connect: resources.connect.clone(), frames: resources.frames.clone() };
+        // Only post pages carry the comments widget
+        if let Some(comments) = &ctx.config.comments
+            && html.contains(r#"<section class="comments">"#)
+        {
+            let widget = comments.script_targets();
+            targets.connect.extend(widget.connect);
+            targets.frames.extend(widget.frames);
+        }
+        csp::with_meta(html, ctx.integrity.as_deref(), &targets)
+    } else {
+        html
+    }
+}
+
+/// Page that sends visitors on to `target` (the root of a multilingual
+/// site points at the default language).
+pub fn render_redirect_page(target: &str) -> String {
+    let target = target.escape_html();
+    format!(
+r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <meta http-equiv="refresh" content="0; url={target}">
+    <link rel="canonical" href="{target}">
+    <title>{target}</title>
+</head>
+<body><a href="{target}">{target}</a></body>
+</html>"#
     )
 }
 
@@ -108,45 +808,109 @@ r##"<!DOCTYPE html>
 pub fn template_simple(
     title: &HtmlSafe,
     content: &str,
-    all_tags: &HashSet<Tag>,
+    all_tags: &BTreeSet<Tag>,
     relative_root: &str,
     config: &Config,
 ) -> String {
-    let ctx = RenderContext::new(config);
-    template(title, content, all_tags, relative_root, &ctx)
+    let ctx = RenderOptions::new(config.clone());
+    let page = PageContext::new(title.clone(), PageKind::Page, relative_root);
+    template(&page, content, all_tags, &ctx)
+}
+
+/// Header of a post page, marked up as `h-entry` properties.
+pub struct PostMeta<'a> {
+    /// Plain-text title.
+    pub title: &'a str,
+    /// Show the title as an `<h1>`, for bodies rendered without it.
+    pub heading: bool,
+    /// Permalink of the post.
+    pub url: &'a str,
+    /// Prefix from the page to the output root, for tag links.
+    pub relative_root: &'a str,
+    /// Shown publication date and its `Config::format_iso_date` form.
+    pub date: &'a str,
+    pub date_iso: &'a str,
+    /// Shown last update, if it counts as one, and its ISO form.
+    pub updated: Option<&'a str>,
+    pub updated_iso: &'a str,
+    pub tags: &'a [Tag],
+    /// `p-author h-card` markup, shown as a byline with `show_author`.
+    pub author: &'a str,
+    pub show_author: bool,
+    /// Estimated reading time, if shown.
+    pub reading_minutes: Option<usize>,
 }
 
-/// Generate metadata header for a post.
-pub fn render_post_meta(date: &str, tags: &[Tag]) -> String {
-    let tags_html: String = tags
+/// Generate metadata header for a post: the dates as `<time>`, tags
+/// linking to their pages, and the byline and reading time when set. The
+/// title is an `<h1>` with `heading`.
+pub fn render_post_meta(meta: &PostMeta<'_>, strings: &Strings) -> String {
+    let tags_html: String = meta.tags
         .iter()
-        .map(|t| format!(r#"<span class="tag">#{}</span>"#, t))
+        .map(|t| format!(
+            r#"<a class="tag" href="{}" rel="tag">#<span class="p-category">{}</span></a>"#,
+            tag_href(t, meta.relative_root), t
+        ))
         .collect();
     
-    let safe_date = date.escape_html();
-    
+    let date_html = format!(
+        r#"<a class="u-url" href="{}"><time class="dt-published" datetime="{}">{}</time></a>"#,
+        meta.url.escape_html(), meta.date_iso.escape_html(), meta.date.escape_html()
+    );
+    let updated_html = meta.updated
+        .map(|u| format!(
+            r#" <span class="meta-item">{}: <time class="dt-updated" datetime="{}">{}</time></span>"#,
+            strings.updated.escape_html(), meta.updated_iso.escape_html(), u.escape_html()
+        ))
+        .unwrap_or_default();
+    let author_html = if meta.show_author {
+        format!(r#" <span class="meta-item meta-author">{} {}</span>"#, strings.by.escape_html(), meta.author)
+    } else {
+        meta.author.to_string()
+    };
+    let reading_html = meta.reading_minutes
+        .map(|m| format!(r#" <span class="meta-item reading-time">{}</span>"#, strings.reading_time(m).escape_html()))
+        .unwrap_or_default();
+
+    let title_html = if meta.heading {
+        format!(r#"<h1 class="p-name">{}</h1>"#, meta.title.escape_html())
+    } else {
+        format!(r#"<data class="p-name" value="{}"></data>"#, meta.title.escape_html())
+    };
     format!(
-        r#"<div class="meta"><span class="meta-item">UPLOAD: {}</span> <span class="meta-item">{}</span></div>"#,
-        safe_date, tags_html
+        r#"{}<div class="meta"><span class="meta-item">{}: {}</span>{}{}{} <span class="meta-item">{}</span></div>"#,
+        title_html, strings.upload.escape_html(), date_html, updated_html, author_html, reading_html, tags_html
     )
 }
 
-/// Generate the post list HTML for index/tag pages.
-pub fn render_post_list(posts: &[PostListItem], relative_root: &str) -> String {
+/// Link to the page of `tag`.
+fn tag_href(tag: &Tag, relative_root: &str) -> String {
+    RelPath::default().join("tags").join(&format!("tag_{}.html", tag.to_lowercase())).href(relative_root)
+}
+
+/// Generate the post list HTML for index/tag pages; entry titles and
+/// tags link to their pages.
+pub fn render_post_list(posts: &[PostListItem], relative_root: &str, strings: &Strings) -> String {
     let mut html = String::from(r#"<div class="post-list">"#);
+    let pin_marker = format!(r#"<span class="pin-marker" title="{}">📌</span> "#, strings.pinned.escape_html());
     
     for post in posts {
         let tags_html: String = post.tags
             .iter()
-            .map(|t| format!(r#"<span class="tag">#{}</span>"#, t))
+            .map(|t| format!(r#" <a class="tag" href="{}" rel="tag">#{}</a>"#, tag_href(t, relative_root), t))
             .collect();
 
-        let link = format!("{}{}", relative_root, post.filename);
+        let link = post.path.href(relative_root).escape_html();
         let safe_date = post.date.escape_html();
+        let (class, marker) = if post.pinned {
+            ("post-entry pinned", pin_marker.as_str())
+        } else {
+            ("post-entry", "")
+        };
 
         html.push_str(&format!(
-            r#"<div class="post-entry"><a href="{}"><span class="entry-title">{} {}</span><span class="entry-date">{}</span></a></div>"#,
-            link, post.title, tags_html, safe_date
+            r#"<div class="{}"><span class="entry-title">{}<a href="{}">{}</a>{}</span><span class="entry-date">{}</span></div>"#,
+            class, marker, link, post.title, tags_html, safe_date
         ));
     }
     
@@ -154,11 +918,295 @@ pub fn render_post_list(posts: &[PostListItem], relative_root: &str) -> String {
     html
 }
 
+/// Generate the related tags of a tag page, each with the number of posts
+/// it shares with the page's tag.
+pub fn render_related_tags(related: &[(Tag, usize)], relative_root: &str, strings: &Strings) -> String {
+    let links: String = related.iter()
+        .map(|(tag, count)| format!(
+            r#"<li><a class="tag" href="{}" rel="tag">#{}</a> <span class="tag-count">({})</span></li>"#,
+            tag_href(tag, relative_root), tag, count
+        ))
+        .collect();
+    format!(
+        r#"<nav class="related-tags" aria-label="{0}"><h2>{0}</h2><ul>{1}</ul></nav>"#,
+        strings.related_tags.escape_html(), links
+    )
+}
+
+/// Generate the post list of an index/tag page, split into years with a
+/// jump navigation (`#y2024`) when the posts span more than one.
+pub fn render_post_archive(posts: &[PostListItem], relative_root: &str, strings: &Strings) -> String {
+    let years: Vec<&[PostListItem]> = posts.chunk_by(|a, b| a.year == b.year).collect();
+    if years.len() < 2 {
+        return render_post_list(posts, relative_root, strings);
+    }
+
+    let groups: Vec<(String, String)> = years.iter()
+        .map(|year| (format!("y{}", year[0].year), year[0].year.to_string()))
+        .collect();
+    let mut html = render_jump_nav(&groups);
+    for ((id, label), year) in groups.iter().zip(years) {
+        html.push_str(&format!(r#"<h2 class="list-group" id="{id}">{label}</h2>"#));
+        html.push_str(&render_post_list(year, relative_root, strings));
+    }
+    html
+}
+
+/// Generate the tags index: every tag with its number of posts, under
+/// A–Z headings (`#letter-r`) with a jump navigation. Links are relative
+/// to `tags/`.
+pub fn render_tag_index(tags: &[(Tag, usize)]) -> String {
+    let mut tags: Vec<(char, &Tag, usize)> = tags.iter()
+        .map(|(tag, count)| (index_letter(tag.as_str()), tag, *count))
+        .collect();
+    tags.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.to_lowercase().cmp(&b.1.to_lowercase())));
+    let letters: Vec<&[(char, &Tag, usize)]> = tags.chunk_by(|a, b| a.0 == b.0).collect();
+
+    let groups: Vec<(String, String)> = letters.iter()
+        .map(|letter| match letter[0].0 {
+            '#' => ("letter-other".to_string(), "#".to_string()),
+            c => (format!("letter-{}", c.to_lowercase()), c.to_string()),
+        })
+        .collect();
+    let mut html = render_jump_nav(&groups);
+    for ((id, label), letter) in groups.iter().zip(letters) {
+        html.push_str(&format!(r#"<h2 class="list-group" id="{}">{}</h2><ul class="tag-index">"#, id.escape_html(), label.escape_html()));
+        for (_, tag, count) in letter {
+            html.push_str(&format!(
+                r#"<li><a href="tag_{}.html" class="tag-link">{}</a> <span class="tag-count">({})</span></li>"#,
+                tag.to_lowercase(), tag, count
+            ));
+        }
+        html.push_str("</ul>");
+    }
+    html
+}
+
+/// Heading letter of `name` in an A–Z index: its first letter in upper
+/// case, `#` for digits and symbols.
+fn index_letter(name: &str) -> char {
+    name.chars()
+        .next()
+        .filter(|c| c.is_alphabetic())
+        .and_then(|c| c.to_uppercase().next())
+        .unwrap_or('#')
+}
+
+/// `<nav class="jump-nav">` linking to the `(id, label)` anchors.
+fn render_jump_nav(groups: &[(String, String)]) -> String {
+    let links: String = groups.iter()
+        .map(|(id, label)| format!(r##"<a href="#{}">{}</a>"##, id.escape_html(), label.escape_html()))
+        .collect();
+    format!(r#"<nav class="jump-nav">{links}</nav>"#)
+}
+
 /// Item in the post list (for index/tag pages).
 #[derive(Debug, Clone)]
 pub struct PostListItem {
     pub title: HtmlSafe,
-    pub filename: String,
+    /// Page path below the root (`posts/a.html`).
+    pub path: RelPath,
     pub date: String,
+    /// Publish year, grouping the list.
+    pub year: i32,
     pub tags: Vec<Tag>,
+    /// `pinned: true` front matter; marked in the list.
+    pub pinned: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Locale;
+
+    use super::*;
+
+    fn page(title: &str, relative_root: &str) -> PageContext {
+        PageContext::new(HtmlSafe::escape(title), PageKind::Page, relative_root)
+    }
+
+    fn item(title: &str, year: i32) -> PostListItem {
+        PostListItem {
+            title: HtmlSafe::escape(title),
+            path: RelPath::default().join("posts").join(&format!("{title}.html")),
+            date: format!("{year}-01-01"),
+            year,
+            tags: Vec::new(),
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn archive_jumps_to_years() {
+        let strings = Strings::for_locale(Locale::en_US);
+        let single = render_post_archive(&[item("a", 2024), item("b", 2024)], "", &strings);
+        assert!(!single.contains("jump-nav"));
+
+        let html = render_post_archive(&[item("a", 2024), item("b", 2023), item("c", 2023)], "", &strings);
+        assert!(html.starts_with(r##"<nav class="jump-nav"><a href="#y2024">2024</a><a href="#y2023">2023</a></nav>"##));
+        assert!(html.contains(r#"<h2 class="list-group" id="y2023">2023</h2><div class="post-list">"#));
+        assert_eq!(html.matches("post-entry").count(), 3);
+    }
+
+    #[test]
+    fn tag_classes_are_css_identifiers() {
+        let class = |tag: &str| tag_class(&Tag::new(tag).unwrap());
+        assert_eq!(class("Rust"), "tag-rust");
+        assert_eq!(class("Machine  Learning"), "tag-machine-learning");
+        assert_eq!(class("C++"), "tag-c");
+        assert_eq!(class("静态网站"), "tag-静态网站");
+    }
+
+    #[test]
+    fn post_list_links_tags() {
+        let strings = Strings::for_locale(Locale::en_US);
+        let post = PostListItem { tags: vec![Tag::new("Rust").unwrap()], ..item("a", 2024) };
+        let html = render_post_list(&[post], "../", &strings);
+        assert!(html.contains(
+            r#"<span class="entry-title"><a href="../posts/a.html">a</a> <a class="tag" href="../tags/tag_rust.html" rel="tag">#Rust</a></span>"#
+        ));
+    }
+
+    #[test]
+    fn tag_index_groups_by_letter() {
+        let tags: Vec<(Tag, usize)> = [("rust", 3), ("Ruby", 1), ("3d", 2), ("art", 1)]
+            .into_iter()
+            .map(|(tag, count)| (Tag::new(tag).unwrap(), count))
+            .collect();
+        let html = render_tag_index(&tags);
+        assert!(html.starts_with(r##"<nav class="jump-nav"><a href="#letter-other">#</a><a href="#letter-a">A</a><a href="#letter-r">R</a></nav>"##));
+        assert!(html.contains(r#"<h2 class="list-group" id="letter-r">R</h2><ul class="tag-index"><li><a href="tag_ruby.html" class="tag-link">Ruby</a> <span class="tag-count">(1)</span></li><li><a href="tag_rust.html""#));
+    }
+
+    #[test]
+    fn post_meta_links_tags_and_shows_optional_items() {
+        let strings = Strings::for_locale(Locale::en_US);
+        let tags = [Tag::new("Rust").unwrap()];
+        let mut meta = PostMeta {
+            title: "A & B",
+            heading: false,
+            url: "a.html",
+            relative_root: "../",
+            date: "May 4, 2024",
+            date_iso: "2024-05-04T00:00:00+08:00",
+            updated: None,
+            updated_iso: "2024-05-04T00:00:00+08:00",
+            tags: &tags,
+            author: r#"<a class="p-author h-card" href="../index.html" hidden>Duck</a>"#,
+            show_author: false,
+            reading_minutes: None,
+        };
+        let html = render_post_meta(&meta, &strings);
+        assert!(html.contains(r#"<time class="dt-published" datetime="2024-05-04T00:00:00+08:00">May 4, 2024</time>"#));
+        assert!(html.contains(r#"<a class="tag" href="../tags/tag_rust.html" rel="tag">#<span class="p-category">Rust</span></a>"#));
+        assert!(html.contains(" hidden>Duck</a>") && !html.contains("meta-author") && !html.contains("reading-time"));
+
+        meta.show_author = true;
+        meta.author = r#"<a class="p-author h-card" href="../index.html">Duck</a>"#;
+        meta.reading_minutes = Some(3);
+        let html = render_post_meta(&meta, &strings);
+        assert!(html.contains(r#"<span class="meta-item meta-author">by <a class="p-author h-card" href="../index.html">Duck</a></span>"#));
+        assert!(html.contains(r#"<span class="meta-item reading-time">3 min read</span>"#));
+    }
+
+    #[test]
+    fn template_has_landmarks_and_marks_the_current_page() {
+        let config = Config::default();
+        let tags: BTreeSet<Tag> = ["rust", "web"].into_iter().map(|tag| Tag::new(tag).unwrap()).collect();
+        let rust = Tag::new("rust").unwrap();
+        let ctx = RenderOptions::new(config.clone()).with_current(NavPage::Tag(rust));
+        let html = template(&page("Rust", "../"), "<h1>Rust</h1>", &tags, &ctx);
+
+        assert!(html.contains(r##"<body class="page-page">
+    <a class="skip-link" href="#content">Skip to content</a>"##));
+        assert!(html.contains(r#"<nav aria-label="Navigation">"#));
+        assert!(html.contains(r#"<main id="content">"#));
+        assert!(html.contains(r#"<div class="nav-section" role="group" aria-label="Filter">"#));
+        assert!(html.contains(r#"<a href="../tags/tag_rust.html" class="nav-link tag-link active" aria-current="page">rust</a>"#));
+        assert!(html.contains(r#"<a href="../tags/tag_web.html" class="nav-link tag-link">web</a>"#));
+        assert_eq!(html.matches("aria-current").count(), 1);
+
+        let index = template(&page("Index", ""), "", &tags, &RenderOptions::new(config.clone()).with_current(NavPage::Index));
+        assert!(index.contains(r#"class="nav-link main-link active" aria-current="page">"#));
+    }
+
+    #[test]
+    fn nav_shows_the_top_tags_or_folds_them() {
+        let tags: BTreeSet<Tag> = ["go", "rust", "web"].into_iter().map(|tag| Tag::new(tag).unwrap()).collect();
+        let shown: BTreeSet<Tag> = [Tag::new("rust").unwrap()].into();
+        let config = Config::default();
+        let html = template(&page("A", "../"), "", &tags, &RenderOptions::new(config.clone()).with_nav_tags(Arc::new(shown)));
+        assert!(html.contains(r#"<a href="../tags/tag_rust.html" class="nav-link tag-link">rust</a><a href="../tags/index.html" class="nav-link all-tags">All tags →</a></div>"#));
+        assert!(!html.contains("tag_go.html"));
+
+        let config = Config::default().collapse_nav_tags(true);
+        let ctx = RenderOptions::new(config.clone()).with_current(NavPage::Tag(Tag::new("web").unwrap()));
+        let html = template(&page("A", "../"), "", &tags, &ctx);
+        assert!(html.contains(r#"<details class="nav-section nav-tags" open><summary class="nav-header">Filter</summary><a href="../tags/tag_go.html""#));
+        assert!(html.contains(r#"<a href="../tags/index.html" class="nav-link all-tags">All tags →</a></details>"#));
+    }
+
+    #[test]
+    fn nav_builder_adds_registered_links_and_sections() {
+        let config = Config::default();
+        let about = RelPath::default().join("about.html");
+        let ctx = RenderOptions::new(config.clone())
+            .with_nav_link(NavLink { path: about.clone(), label: "About & more".to_string() })
+            .with_current(NavPage::Page(about));
+        let nav = NavBuilder::new(&ctx, "../").index().registered().section(r#"<div class="nav-section">RSS</div>"#).build();
+        assert_eq!(
+            nav,
+            r#"<div class="nav-section"><a href="../index.html" class="nav-link main-link">Index</a></div>"#.to_string()
+                + r#"<div class="nav-section"><a href="../about.html" class="nav-link page-link active" aria-current="page">About &amp; more</a></div>"#
+                + r#"<div class="nav-section">RSS</div>"#
+        );
+    }
+
+    #[test]
+    fn head_links_feeds_and_search() {
+        let mut config = Config::default().brand_name("Blog");
+        let ctx = RenderOptions::new(config.clone()).with_feed("../atom.xml").with_rss_feed("../rss.xml").with_search("../opensearch.xml");
+        let html = template(&page("A", "../"), "", &BTreeSet::new(), &ctx);
+        assert!(html.contains(r#"<link rel="alternate" type="application/rss+xml" title="Blog (RSS)" href="../rss.xml">"#));
+        assert!(html.contains(r#"<link rel="search" type="application/opensearchdescription+xml" title="Blog" href="../opensearch.xml">"#));
+        assert!(html.contains(r#"<a href="../atom.xml" class="nav-link feed-link" type="application/atom+xml">Feed</a>"#));
+
+        config.feed.nav_link = false;
+        let html = template(&page("A", "../"), "", &BTreeSet::new(), &RenderOptions::new(config.clone()).with_feed("../atom.xml"));
+        assert!(html.contains("application/atom+xml") && !html.contains("feed-link"));
+    }
+
+    #[test]
+    fn page_context_fills_the_head() {
+        let config = Config::default();
+        let page = page("A", "../")
+            .with_description("About <ducks>")
+            .with_canonical("https://duck.dev/posts/a.html")
+            .with_noindex(true)
+            .with_breadcrumb("Index", "https://duck.dev/index.html");
+        let html = template(&page, "", &BTreeSet::new(), &RenderOptions::new(config.clone()));
+        assert!(html.contains(r#"<meta name="description" content="About &lt;ducks&gt;">"#));
+        assert!(html.contains(r#"<meta name="robots" content="noindex">"#));
+        assert!(html.contains(r#"<link rel="canonical" href="https://duck.dev/posts/a.html">"#));
+        assert!(html.contains(r#""@type":"BreadcrumbList","itemListElement":[{"@type":"ListItem","item":"https://duck.dev/index.html","name":"Index","position":1}]"#));
+    }
+
+    #[test]
+    fn options_add_preloads_head_tags_and_body_classes() {
+        let site = RenderOptions::new(Config::default()).with_head_tag(r#"<meta name="generator" content="ssg">"#);
+        let font = Preload { media_type: Some("font/woff2".to_string()), ..Preload::new(Href::parse("fonts/a.woff2").unwrap(), "font") };
+        let post = site.clone()
+            .with_preload(font)
+            .with_lcp_image(Href::parse("images/a.webp").unwrap())
+            .with_body_class("photo");
+        let html = template(&page("A", "../"), "", &BTreeSet::new(), &post);
+        assert!(html.contains(concat!(
+            r#"<link rel="preload" as="image" href="../images/a.webp" fetchpriority="high">"#,
+            r#"<link rel="preload" as="font" href="../fonts/a.woff2" type="font/woff2" crossorigin>"#,
+        )));
+        assert!(html.contains(r#"<meta name="generator" content="ssg">"#));
+        assert!(html.contains(r#"<body class="page-page photo">"#));
+
+        let other = template(&page("B", "../"), "", &BTreeSet::new(), &site);
+        assert!(other.contains(r#"<body class="page-page">"#) && !other.contains("preload"));
+    }
 }
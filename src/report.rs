@@ -0,0 +1,237 @@
+//! Machine-readable build report.
+//!
+//! Built from a `BuildSummary` and serialized as JSON, either to a file
+//! (`--report build-report.json`) or to stdout (`--format json`).
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::error::{BuildError, BuildSummary, Severity, SeverityCounts};
+use crate::image::OptimizedImage;
+use crate::timings::PhaseTiming;
+
+/// Outcome of a single post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostStatus {
+    Built,
+    Failed,
+}
+
+/// Per-post status and timings.
+#[derive(Debug, Clone, Serialize)]
+pub struct PostReport {
+    pub source: PathBuf,
+    pub output: Option<PathBuf>,
+    pub status: PostStatus,
+    /// Parse result was reused from the in-memory cache.
+    pub cached: bool,
+    pub parse_ms: f64,
+    pub render_ms: f64,
+    pub warnings: usize,
+}
+
+/// A failure or warning with its category.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorReport {
+    pub category: &'static str,
+    pub severity: Severity,
+    pub recoverable: bool,
+    pub path: Option<PathBuf>,
+    /// 1-based position in `path`, if known.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl From<&BuildError> for ErrorReport {
+    fn from(err: &BuildError) -> Self {
+        Self {
+            category: err.category(),
+            severity: err.severity(),
+            recoverable: err.is_recoverable(),
+            path: err.path().map(Path::to_path_buf),
+            line: err.position().map(|p| p.line),
+            column: err.position().map(|p| p.column),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Aggregate image optimization savings (each output counted once).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImageStats {
+    pub count: usize,
+    pub source_bytes: u64,
+    pub output_bytes: u64,
+    pub bytes_saved: i64,
+    /// Output path and optimization time of each counted image.
+    #[serde(skip)]
+    pub timings: Vec<(PathBuf, Duration)>,
+    #[serde(skip)]
+    seen: BTreeSet<PathBuf>,
+}
+
+impl ImageStats {
+    /// Add an optimized image; external and missing images are ignored.
+    pub fn add(&mut self, image: &OptimizedImage) {
+        let Some(ref path) = image.output_path else {
+            return;
+        };
+        if !self.seen.insert(path.clone()) {
+            return;
+        }
+
+        self.count += 1;
+        self.source_bytes += image.source_bytes;
+        self.output_bytes += image.output_bytes;
+        self.bytes_saved = self.source_bytes as i64 - self.output_bytes as i64;
+        self.timings.push((path.clone(), image.elapsed));
+    }
+}
+
+/// Full build report.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildReport {
+    pub success: bool,
+    pub exit_code: u8,
+    pub duration_ms: f64,
+    pub phases: Vec<PhaseTiming>,
+    pub posts_built: usize,
+    pub posts_skipped: usize,
+    pub posts: Vec<PostReport>,
+    pub failures: Vec<ErrorReport>,
+    pub warnings: Vec<ErrorReport>,
+    /// Failures and warnings by severity.
+    pub counts: SeverityCounts,
+    /// Warnings dropped by `[[suppress]]` rules.
+    pub suppressed: usize,
+    pub images: ImageStats,
+}
+
+impl BuildReport {
+    pub fn new(summary: &BuildSummary, strict: bool, duration: Duration) -> Self {
+        let exit_code = summary.exit_code(strict);
+        Self {
+            success: exit_code == crate::error::EXIT_SUCCESS,
+            exit_code,
+            duration_ms: millis(duration),
+            phases: summary.phases.clone(),
+            posts_built: summary.posts_built,
+            posts_skipped: summary.posts_skipped,
+            posts: summary.posts.clone(),
+            failures: summary.failures.iter().map(ErrorReport::from).collect(),
+            warnings: summary.warnings.iter().map(ErrorReport::from).collect(),
+            counts: summary.counts(),
+            suppressed: summary.suppressed,
+            images: summary.images.clone(),
+        }
+    }
+
+    /// Report of a build that aborted with `error`: nothing built, the
+    /// error as the only failure.
+    pub fn failed(error: &BuildError, duration: Duration) -> Self {
+        Self {
+            success: false,
+            exit_code: error.exit_code(),
+            duration_ms: millis(duration),
+            phases: Vec::new(),
+            posts_built: 0,
+            posts_skipped: 0,
+            posts: Vec::new(),
+            failures: vec![ErrorReport::from(error)],
+            warnings: Vec::new(),
+            // Errors that abort a build are never recoverable
+            counts: SeverityCounts { error: 1, ..SeverityCounts::default() },
+            suppressed: 0,
+            images: ImageStats::default(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("build report is always serializable")
+    }
+
+    /// Write the report as JSON to `path`.
+    pub fn write(&self, path: &Path) -> Result<(), BuildError> {
+        fs::write(path, self.to_json()).map_err(|e| BuildError::OutputNotWritable {
+            path: path.to_path_buf(),
+            source: e,
+        })
+    }
+}
+
+/// Duration in fractional milliseconds.
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::SourcePos;
+    use crate::types::{AbsoluteUrl, Href};
+
+    fn image(path: &str, source: u64, output: u64) -> OptimizedImage {
+        OptimizedImage {
+            src: Href::parse(path).unwrap(),
+            width: 1,
+            height: 1,
+            output_path: Some(PathBuf::from(path)),
+            source_bytes: source,
+            output_bytes: output,
+            elapsed: Duration::from_millis(output),
+        }
+    }
+
+    #[test]
+    fn image_stats_count_each_output_once() {
+        let mut stats = ImageStats::default();
+        stats.add(&image("a.webp", 1000, 400));
+        stats.add(&image("a.webp", 1000, 400));
+        stats.add(&image("b.webp", 500, 100));
+        stats.add(&OptimizedImage::external(AbsoluteUrl::new("https://x/y.png").unwrap()));
+
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.bytes_saved, 1000);
+        assert_eq!(stats.timings.len(), 2);
+    }
+
+    #[test]
+    fn error_report_has_category() {
+        let err = BuildError::ImageNotFound { path: PathBuf::from("a.png") };
+        let report = ErrorReport::from(&err);
+        assert_eq!(report.category, "image_not_found");
+        assert!(report.recoverable);
+        assert_eq!(report.path, Some(PathBuf::from("a.png")));
+        assert_eq!(report.line, None);
+
+        let err = BuildError::InvalidTag {
+            tag: "a/b".to_string(),
+            reason: "tag contains HTML special characters",
+            path: Some(PathBuf::from("content/post.md")),
+            position: Some(SourcePos::new(3, 7)),
+        }
+        .below_lines(2);
+        let report = ErrorReport::from(&err);
+        assert_eq!((report.line, report.column), (Some(5), Some(7)));
+        assert_eq!(report.message, "Invalid tag 'a/b' in content/post.md:5:7: tag contains HTML special characters");
+    }
+
+    #[test]
+    fn aborted_build_reports_its_error() {
+        let err = BuildError::NoValidPosts { path: PathBuf::from("content") };
+        let report = BuildReport::failed(&err, Duration::from_millis(5));
+        assert!(!report.success);
+        assert_eq!(report.exit_code, crate::error::EXIT_TOTAL_FAILURE);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].category, "no_valid_posts");
+        assert_eq!(report.counts.error, 1);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["success"], false);
+    }
+}
@@ -0,0 +1,486 @@
+//! Search index (`search.json`) for a client-side search box.
+//!
+//! ```toml
+//! [search]
+//! enabled = true
+//! stemming = true   # "ducks", "ducking" → "duck"
+//! cjk = "bigram"    # or "char"
+//! ```
+//!
+//! Text is split into words of letters and digits, and into runs of CJK
+//! characters, which have no spaces between words. Runs become overlapping
+//! bigrams ("静态网站" → "静态", "态网", "网站"), which find any two-character
+//! query without a dictionary. The index records its settings, since a
+//! search box has to split queries the same way.
+//!
+//! `exports` adds the input files of existing search widgets, which
+//! tokenize on their own: `stork.toml` for `stork build --input`, and
+//! `tinysearch.json` for `tinysearch`.
+//!
+//! With `page` (the default), `search.html` searches the index in the
+//! browser: `search.js` splits the `?q=` query like the index and lists
+//! the posts having every term. Sites with a `base_url` also get
+//! `opensearch.xml`, which lets browsers add the page as a search engine.
+
+use std::collections::BTreeMap;
+
+use pulldown_cmark::{Event, Parser, TagEnd};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::i18n::Strings;
+use crate::types::EscapeHtml;
+
+/// Index file name, relative to the index page.
+pub const SEARCH_FILE: &str = "search.json";
+
+/// Stork input file name, relative to the index page.
+pub const STORK_FILE: &str = "stork.toml";
+
+/// tinysearch input file name, relative to the index page.
+pub const TINYSEARCH_FILE: &str = "tinysearch.json";
+
+/// Search page, relative to the index page.
+pub const SEARCH_PAGE: &str = "search.html";
+
+/// Script of the search page, next to it.
+pub const SEARCH_SCRIPT: &str = "search.js";
+
+/// OpenSearch description, relative to the index page.
+pub const OPENSEARCH_FILE: &str = "opensearch.xml";
+
+/// Longest `ShortName` the OpenSearch format allows, in characters.
+const OPENSEARCH_NAME_LENGTH: usize = 16;
+
+/// Searches `search.json` for the `?q=` query of the page; terms are made
+/// as in `SearchConfig::tokenize`.
+pub const SEARCH_JS: &str = r#"const form = document.querySelector(".search-form");
+const results = document.querySelector(".search-results");
+const query = new URLSearchParams(location.search).get("q") || "";
+form.q.value = query;
+
+const isCjk = (c) => /[\u3040-\u30ff\u3400-\u4dbf\u4e00-\u9fff\uac00-\ud7af\uf900-\ufaff\u{20000}-\u{2fa1f}]/u.test(c);
+const isWordChar = (c) => /[\p{Alphabetic}\p{N}]/u.test(c);
+
+function stem(word) {
+  if (!/^[a-z]+$/.test(word)) return word;
+  const longEnough = (s) => s.length >= 3 && /[aeiouy]/.test(s);
+  const undouble = (s) => s.length >= 2 && s.at(-1) === s.at(-2) && !"aeioulsz".includes(s.at(-1)) ? s.slice(0, -1) : s;
+  if (word.endsWith("ies") && word.length >= 5) return word.slice(0, -3) + "y";
+  if (word.endsWith("sses")) return word.slice(0, -2);
+  for (const suffix of ["ing", "ed"]) {
+    if (word.endsWith(suffix) && longEnough(word.slice(0, -suffix.length))) return undouble(word.slice(0, -suffix.length));
+  }
+  if (word.endsWith("ly") && longEnough(word.slice(0, -2))) return word.slice(0, -2);
+  if (word.endsWith("s") && !/(ss|us|is)$/.test(word) && longEnough(word.slice(0, -1))) return word.slice(0, -1);
+  return word;
+}
+
+function tokenize(index, text) {
+  const terms = [];
+  let word = "";
+  let run = [];
+  const pushWord = () => {
+    if (word) terms.push(index.stemming ? stem(word) : word);
+    word = "";
+  };
+  const pushRun = () => {
+    if (index.cjk === "char" || run.length === 1) terms.push(...run);
+    else for (let i = 0; i + 1 < run.length; i++) terms.push(run[i] + run[i + 1]);
+    run = [];
+  };
+  for (const c of text) {
+    if (isCjk(c)) {
+      pushWord();
+      run.push(c);
+    } else if (isWordChar(c)) {
+      pushRun();
+      word += c.toLowerCase();
+    } else {
+      pushWord();
+      pushRun();
+    }
+  }
+  pushWord();
+  pushRun();
+  return terms;
+}
+
+function show(index) {
+  let hits = null;
+  for (const term of tokenize(index, query)) {
+    const postings = new Set(index.terms[term] || []);
+    hits = hits === null ? postings : new Set([...hits].filter((i) => postings.has(i)));
+  }
+  const posts = [...(hits || [])].map((i) => index.posts[i]);
+  if (!posts.length) {
+    results.textContent = results.dataset.none;
+    return;
+  }
+  const list = document.createElement("div");
+  list.className = "post-list";
+  for (const post of posts) {
+    const entry = document.createElement("div");
+    entry.className = "post-entry";
+    const title = document.createElement("span");
+    title.className = "entry-title";
+    const link = document.createElement("a");
+    link.href = post.url;
+    link.textContent = post.title;
+    title.append(link);
+    const date = document.createElement("span");
+    date.className = "entry-date";
+    date.textContent = post.date;
+    entry.append(title, date);
+    list.append(entry);
+  }
+  results.replaceChildren(list);
+}
+
+if (query.trim()) {
+  fetch(results.dataset.index).then((response) => response.json()).then(show);
+}
+"#;
+
+/// How runs of CJK characters are split.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CjkTokens {
+    /// Overlapping pairs of characters.
+    #[default]
+    Bigram,
+    /// Single characters.
+    Char,
+}
+
+/// Input file for another search tool, written next to `search.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchExport {
+    /// `stork.toml`.
+    Stork,
+    /// `tinysearch.json`.
+    Tinysearch,
+}
+
+impl SearchExport {
+    /// Output file name.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Stork => STORK_FILE,
+            Self::Tinysearch => TINYSEARCH_FILE,
+        }
+    }
+
+    /// Contents for `docs`, whose URLs are made absolute with
+    /// `url_prefix` (`/de/`).
+    pub fn render(self, docs: &[SearchDoc<'_>], url_prefix: &str) -> String {
+        match self {
+            Self::Stork => render_stork(docs, url_prefix),
+            Self::Tinysearch => render_tinysearch(docs, url_prefix),
+        }
+    }
+}
+
+/// Search settings (the `[search]` table).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    /// Write `search.json` next to the index.
+    pub enabled: bool,
+    /// Reduce English words to their stem.
+    pub stemming: bool,
+    pub cjk: CjkTokens,
+    /// Further formats to write.
+    pub exports: Vec<SearchExport>,
+    /// Write `search.html`, a search page over the index, linked from the
+    /// nav (and `opensearch.xml` with a `base_url`).
+    pub page: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            stemming: true,
+            cjk: CjkTokens::Bigram,
+            exports: Vec::new(),
+            page: true,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Index terms of `text` in order, repeats included.
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        let mut terms = Vec::new();
+        let mut word = String::new();
+        let mut run: Vec<char> = Vec::new();
+        for c in text.chars() {
+            if is_cjk(c) {
+                self.push_word(&mut word, &mut terms);
+                run.push(c);
+            } else if c.is_alphanumeric() {
+                self.push_run(&mut run, &mut terms);
+                word.extend(c.to_lowercase());
+            } else {
+                self.push_word(&mut word, &mut terms);
+                self.push_run(&mut run, &mut terms);
+            }
+        }
+        self.push_word(&mut word, &mut terms);
+        self.push_run(&mut run, &mut terms);
+        terms
+    }
+
+    fn push_word(&self, word: &mut String, terms: &mut Vec<String>) {
+        if word.is_empty() {
+            return;
+        }
+        let term = if self.stemming { stem(word) } else { word.clone() };
+        terms.push(term);
+        word.clear();
+    }
+
+    fn push_run(&self, run: &mut Vec<char>, terms: &mut Vec<String>) {
+        match (self.cjk, run.len()) {
+            (_, 0) => return,
+            (CjkTokens::Char, _) | (CjkTokens::Bigram, 1) => terms.extend(run.iter().map(char::to_string)),
+            (CjkTokens::Bigram, _) => terms.extend(run.windows(2).map(|pair| pair.iter().collect::<String>())),
+        }
+        run.clear();
+    }
+}
+
+/// Han, kana and Hangul.
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FA1F}'
+    )
+}
+
+/// Strip common English suffixes from a lowercase word: plurals, `-ing`,
+/// `-ed` and `-ly`. Other words are returned as they are; stems are kept
+/// at three letters or more.
+fn stem(word: &str) -> String {
+    if !word.chars().all(|c| c.is_ascii_lowercase()) {
+        return word.to_string();
+    }
+    let long_enough = |stem: &str| stem.len() >= 3 && stem.contains(['a', 'e', 'i', 'o', 'u', 'y']);
+    if let Some(stem) = word.strip_suffix("ies").filter(|s| s.len() >= 2) {
+        return format!("{stem}y");
+    }
+    if let Some(stem) = word.strip_suffix("sses") {
+        return format!("{stem}ss");
+    }
+    for suffix in ["ing", "ed"] {
+        if let Some(stem) = word.strip_suffix(suffix).filter(|s| long_enough(s)) {
+            return undouble(stem).to_string();
+        }
+    }
+    if let Some(stem) = word.strip_suffix("ly").filter(|s| long_enough(s)) {
+        return stem.to_string();
+    }
+    if word.ends_with('s') && !["ss", "us", "is"].iter().any(|end| word.ends_with(end)) && long_enough(&word[..word.len() - 1]) {
+        return word[..word.len() - 1].to_string();
+    }
+    word.to_string()
+}
+
+/// `stopp` → `stop`, but `fall` and `kiss` stay.
+fn undouble(stem: &str) -> &str {
+    let bytes = stem.as_bytes();
+    match bytes {
+        [.., a, b] if a == b && !b"aeioulsz".contains(b) => &stem[..stem.len() - 1],
+        _ => stem,
+    }
+}
+
+/// Plain text of `markdown`: text and inline code, blocks separated by
+/// spaces.
+pub fn plain_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak | Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::CodeBlock) => text.push(' '),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// A post in the index.
+#[derive(Debug, Clone)]
+pub struct SearchDoc<'a> {
+    pub title: &'a str,
+    /// Path relative to the index (`posts/a.html`).
+    pub url: String,
+    pub date: &'a str,
+    pub summary: Option<&'a str>,
+    /// Plain text of the post body.
+    pub text: String,
+}
+
+/// `search.json`: the posts, and for every term the positions of the
+/// posts containing it (in title or text), ascending.
+pub fn render_index(config: &SearchConfig, docs: &[SearchDoc<'_>]) -> String {
+    let mut terms: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, doc) in docs.iter().enumerate() {
+        for term in config.tokenize(doc.title).into_iter().chain(config.tokenize(&doc.text)) {
+            let postings = terms.entry(term).or_default();
+            if postings.last() != Some(&i) {
+                postings.push(i);
+            }
+        }
+    }
+    let posts: Vec<_> = docs.iter()
+        .map(|doc| json!({ "title": doc.title, "url": doc.url, "date": doc.date, "summary": doc.summary }))
+        .collect();
+    json!({
+        "stemming": config.stemming,
+        "cjk": config.cjk,
+        "posts": posts,
+        "terms": terms,
+    }).to_string()
+}
+
+/// Body of the search page: the form, and where the results go.
+pub fn render_page(strings: &Strings) -> String {
+    let search = strings.search.escape_html();
+    format!(
+        concat!(
+            r#"<h1>{search}</h1><form class="search-form" role="search" action="{page}">"#,
+            r#"<input type="search" name="q" aria-label="{search}" required> <button>{search}</button></form>"#,
+            r#"<div class="search-results" aria-live="polite" data-index="{index}" data-none="{none}"></div>"#,
+        ),
+        search = search,
+        page = SEARCH_PAGE,
+        index = SEARCH_FILE,
+        none = strings.no_results.escape_html(),
+    )
+}
+
+/// `opensearch.xml` for the site `name`, whose search page is at the
+/// absolute URL `page`.
+pub fn render_opensearch(name: &str, description: &str, page: &str) -> String {
+    let short_name: String = name.chars().take(OPENSEARCH_NAME_LENGTH).collect();
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<OpenSearchDescription xmlns=\"http://a9.com/-/spec/opensearch/1.1/\">\n",
+            "  <ShortName>{}</ShortName>\n",
+            "  <Description>{}</Description>\n",
+            "  <InputEncoding>UTF-8</InputEncoding>\n",
+            "  <Url type=\"text/html\" method=\"get\" template=\"{}?q={{searchTerms}}\"/>\n",
+            "</OpenSearchDescription>\n",
+        ),
+        short_name.escape_html(),
+        description.escape_html(),
+        page.escape_html(),
+    )
+}
+
+/// Stork input configuration with the posts' text inline.
+fn render_stork(docs: &[SearchDoc<'_>], url_prefix: &str) -> String {
+    #[derive(Serialize)]
+    struct Stork<'a> {
+        input: StorkInput<'a>,
+    }
+    #[derive(Serialize)]
+    struct StorkInput<'a> {
+        url_prefix: &'a str,
+        files: Vec<StorkFile<'a>>,
+    }
+    #[derive(Serialize)]
+    struct StorkFile<'a> {
+        title: &'a str,
+        url: &'a str,
+        contents: &'a str,
+        filetype: &'static str,
+    }
+
+    let files = docs.iter()
+        .map(|doc| StorkFile { title: doc.title, url: &doc.url, contents: &doc.text, filetype: "PlainText" })
+        .collect();
+    let stork = Stork { input: StorkInput { url_prefix, files } };
+    toml::to_string(&stork).expect("stork input is plain strings")
+}
+
+/// tinysearch input: `title`, `url` and `body` of every post.
+fn render_tinysearch(docs: &[SearchDoc<'_>], url_prefix: &str) -> String {
+    let posts: Vec<_> = docs.iter()
+        .map(|doc| json!({ "title": doc.title, "url": format!("{url_prefix}{}", doc.url), "body": doc.text }))
+        .collect();
+    serde_json::Value::from(posts).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stems_english_words() {
+        let words = ["ducks", "ducking", "stopped", "stories", "quickly", "kisses", "bus", "analysis", "need", "falling"];
+        let stems: Vec<String> = words.iter().map(|w| stem(w)).collect();
+        assert_eq!(stems, ["duck", "duck", "stop", "story", "quick", "kiss", "bus", "analysis", "need", "fall"]);
+    }
+
+    #[test]
+    fn splits_cjk_runs_into_bigrams() {
+        let config = SearchConfig::default();
+        assert_eq!(config.tokenize("Rust 静态网站, 生成器!"), ["rust", "静态", "态网", "网站", "生成", "成器"]);
+        assert_eq!(config.tokenize("用Rust写"), ["用", "rust", "写"]);
+
+        let chars = SearchConfig { cjk: CjkTokens::Char, stemming: false, ..SearchConfig::default() };
+        assert_eq!(chars.tokenize("Ducks 网站"), ["ducks", "网", "站"]);
+    }
+
+    #[test]
+    fn index_lists_posts_per_term() {
+        let config = SearchConfig::default();
+        let doc = |title, text: &str| SearchDoc {
+            title,
+            url: format!("posts/{title}.html"),
+            date: "2024-05-04",
+            summary: None,
+            text: plain_text(text),
+        };
+        let json = render_index(&config, &[doc("a", "Ducks **quack**.\n\n静态网站"), doc("b", "A `duck`.")]);
+        let index: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(index["terms"]["duck"], json!([0, 1]));
+        assert_eq!(index["terms"]["网站"], json!([0]));
+        assert_eq!(index["posts"][1]["url"], "posts/b.html");
+        assert_eq!(index["cjk"], "bigram");
+    }
+
+    #[test]
+    fn exports_for_stork_and_tinysearch() {
+        let docs = [SearchDoc {
+            title: "A \"duck\"",
+            url: "posts/a.html".to_string(),
+            date: "2024-05-04",
+            summary: None,
+            text: "Quack.".to_string(),
+        }];
+        let stork: toml::Table = toml::from_str(&SearchExport::Stork.render(&docs, "/de/")).unwrap();
+        assert_eq!(stork["input"]["url_prefix"].as_str(), Some("/de/"));
+        assert_eq!(stork["input"]["files"][0]["title"].as_str(), Some("A \"duck\""));
+        assert_eq!(stork["input"]["files"][0]["contents"].as_str(), Some("Quack."));
+
+        let tinysearch: serde_json::Value = serde_json::from_str(&SearchExport::Tinysearch.render(&docs, "/de/")).unwrap();
+        assert_eq!(tinysearch, json!([{ "title": "A \"duck\"", "url": "/de/posts/a.html", "body": "Quack." }]));
+    }
+
+    #[test]
+    fn opensearch_names_are_short() {
+        let xml = render_opensearch("Duck & Co Engineering", "Notes", "https://duck.dev/search.html");
+        assert!(xml.contains("<ShortName>Duck &amp; Co Engine</ShortName>"));
+        assert!(xml.contains(r#"<Url type="text/html" method="get" template="https://duck.dev/search.html?q={searchTerms}"/>"#));
+    }
+}
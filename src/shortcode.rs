@@ -0,0 +1,146 @@
+//! `{{< name "arg" key="value" >}}` shortcodes.
+//!
+//! A shortcode stands on a line of its own and is replaced by HTML before
+//! the markdown is parsed (lines inside fenced code blocks are left
+//! alone). Built in:
+//!
+//! ```text
+//! {{< youtube "https://youtu.be/dQw4w9WgXcQ" >}}
+//! {{< vimeo "76979871" title="Talk" >}}
+//! {{< mastodon "https://mastodon.social/@user/109876543210" >}}
+//! {{< gallery "trip/*.jpg" >}}
+//! {{< chart "bar" values="Jan: 3, Feb: 5" >}}
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::error::SourcePos;
+use crate::parser::lines_outside_code;
+
+/// A parsed shortcode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcode {
+    pub name: String,
+    /// Positional (quoted or bare) arguments.
+    pub args: Vec<String>,
+    /// `key="value"` arguments.
+    pub named: BTreeMap<String, String>,
+}
+
+impl Shortcode {
+    /// Parse a line holding only a shortcode.
+    pub fn parse(line: &str) -> Option<Self> {
+        let inner = line.trim().strip_prefix("{{<")?.strip_suffix(">}}")?;
+        let mut tokens = tokenize(inner)?.into_iter();
+        let name = tokens.next().filter(|(key, _)| key.is_none())?.1;
+
+        let mut args = Vec::new();
+        let mut named = BTreeMap::new();
+        for (key, value) in tokens {
+            match key {
+                Some(key) => {
+                    named.insert(key, value);
+                }
+                None => args.push(value),
+            }
+        }
+        Some(Self { name, args, named })
+    }
+
+    /// First positional argument.
+    pub fn arg(&self) -> Option<&str> {
+        self.args.first().map(String::as_str)
+    }
+}
+
+/// Split shortcode arguments into `(key, value)` pairs; None on an
+/// unterminated quote.
+fn tokenize(input: &str) -> Option<Vec<(Option<String>, String)>> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+    while !rest.is_empty() {
+        let mut key = None;
+        let word_end = rest.find(|c: char| c.is_whitespace() || c == '"').unwrap_or(rest.len());
+        let word = &rest[..word_end];
+        if let Some(k) = word.strip_suffix('=') {
+            key = Some(k.to_string());
+            rest = &rest[word_end..];
+        } else if let Some((k, v)) = word.split_once('=') {
+            tokens.push((Some(k.to_string()), v.to_string()));
+            rest = rest[word_end..].trim_start();
+            continue;
+        }
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"')?;
+            rest = &quoted[end + 1..];
+            quoted[..end].to_string()
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let value = rest[..end].to_string();
+            rest = &rest[end..];
+            value
+        };
+        tokens.push((key, value));
+        rest = rest.trim_start();
+    }
+    Some(tokens)
+}
+
+/// Replace every shortcode line outside code blocks with `render`'s HTML.
+/// Shortcodes that fail to render are left as text; their errors are
+/// returned with their position.
+pub fn expand(
+    markdown: &str,
+    mut render: impl FnMut(&Shortcode) -> Result<String, String>,
+) -> (String, Vec<(SourcePos, String)>) {
+    let mut out = String::with_capacity(markdown.len());
+    let mut errors = Vec::new();
+    for (index, (line, in_code)) in lines_outside_code(markdown).enumerate() {
+        let Some(shortcode) = (!in_code).then(|| Shortcode::parse(line)).flatten() else {
+            out.push_str(line);
+            continue;
+        };
+        match render(&shortcode) {
+            // Blank lines around make it a raw HTML block
+            Ok(html) => {
+                out.push('\n');
+                out.push_str(&html);
+                out.push_str("\n\n");
+            }
+            Err(e) => {
+                let indent = line.len() - line.trim_start().len();
+                let position = SourcePos::new(index + 1, line[..indent].chars().count() + 1);
+                errors.push((position, format!("{{{{< {} >}}}}: {}", shortcode.name, e)));
+                out.push_str(line);
+            }
+        }
+    }
+    (out, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_positional_and_named_arguments() {
+        let code = Shortcode::parse(r#"{{< vimeo "76979871" title="A talk" width=640 wide >}}"#).unwrap();
+        assert_eq!(code.name, "vimeo");
+        assert_eq!(code.args, ["76979871", "wide"]);
+        assert_eq!(code.named["title"], "A talk");
+        assert_eq!(code.named["width"], "640");
+        assert_eq!(Shortcode::parse(r#"{{< x "open >}}"#), None);
+        assert_eq!(Shortcode::parse("text {{< x >}}"), None);
+    }
+
+    #[test]
+    fn expands_outside_code_blocks() {
+        let markdown = "a\n{{< hi >}}\n```\n{{< hi >}}\n```\n{{< bad >}}\n";
+        let (out, errors) = expand(markdown, |code| match code.name.as_str() {
+            "hi" => Ok("<div>hi</div>".to_string()),
+            _ => Err("unknown shortcode".to_string()),
+        });
+        assert_eq!(out, "a\n\n<div>hi</div>\n\n```\n{{< hi >}}\n```\n{{< bad >}}\n");
+        assert_eq!(errors, [(SourcePos::new(6, 1), "{{< bad >}}: unknown shortcode".to_string())]);
+    }
+}
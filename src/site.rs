@@ -0,0 +1,2729 @@
+//! Site build pipeline with an in-memory parse cache.
+//!
+//! A `Site` owns the configuration and the parsed posts of previous builds.
+//! Posts whose source file is unchanged are reused instead of re-parsed,
+//! which makes repeated builds from a long-lived process (the daemon) cheap.
+//!
+//! Parallel work runs on a dedicated rayon pool when `Config::threads` is
+//! set, otherwise on the global pool. Image encoding happens inside render
+//! workers, so the limit covers it too; `Config::image_jobs` further
+//! bounds how many images are processed at once.
+//!
+//! Parse, render and image work is reported to the site's `Progress`,
+//! which is hidden unless the CLI enables it.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::{DateTime, Datelike, FixedOffset};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use tracing::{debug, debug_span, info, info_span, warn};
+
+use crate::a11y;
+use crate::audio::{self, Audio};
+use crate::blogroll::{Blogroll, LINKS_PAGE, OPML_FILE};
+use crate::comments::CommentsConfig;
+use crate::config::{Config, SortOrder};
+use crate::data::SiteData;
+use crate::epub::{self, Book, Chapter};
+use crate::error::{BuildError, BuildResult, BuildSummary};
+use crate::feed::{self, Enclosure, FeedContent, FeedEntry, FeedMeta, FEED_FILE, RSS_FILE};
+use crate::fonts::{self, UsedChars};
+use crate::front_matter::{self, FrontMatter};
+use crate::gemini::{self, GeminiPost};
+use crate::git::{FileDates, GitHistory};
+use crate::hosting::{self, HeaderRules, Redirect};
+use crate::icons;
+use crate::image::{self, DecodeLimit, OptimizedImage};
+use crate::include;
+use crate::image_cache::ImageCache;
+use crate::layout::{Layouts, DEFAULT_LAYOUT};
+use crate::lightbox::{Lightbox, LIGHTBOX_JS, LIGHTBOX_SCRIPT};
+use crate::output::{self, DryRunReport, OutputSet};
+use crate::parser::{extract_metadata, reading_minutes, render_markdown, MarkdownEnv, PostMetadata, RenderedMarkdown};
+use crate::plugin::Plugin;
+use crate::post_config::ResolvedPostConfig;
+use crate::print;
+use crate::progress::Progress;
+use crate::protect::{self, PROTECT_JS, PROTECT_SCRIPT};
+use crate::purge::{Stylesheet, UsedNames};
+use crate::pwa;
+use crate::registry::{self, PostRef, Registry};
+use crate::renderer::{
+    template, render_post_archive, render_post_meta, render_post_list, render_related_tags, render_redirect_page, render_tag_index, tag_class, Alternate, ArticleMeta, Breadcrumb, NavLink, NavPage, PageContext, PageKind, PostListItem, PostMeta, RenderOptions,
+};
+use crate::report::{millis, PostReport, PostStatus};
+use crate::search::{self, SearchDoc, OPENSEARCH_FILE, SEARCH_FILE, SEARCH_JS, SEARCH_PAGE, SEARCH_SCRIPT};
+use crate::sitemap::{self, SitemapEntry};
+use crate::social::{self, SocialImage};
+use crate::source::{render_llms_txt, LlmsEntry, PostSource, LLMS_FILE};
+use crate::sri::Integrity;
+use crate::timings::PhaseTimer;
+use crate::transform::Pipeline;
+use crate::types::{AbsoluteUrl, EscapeHtml, Href, HtmlSafe, RelPath, Tag};
+use crate::validate;
+use crate::video;
+
+/// Optional source of the not-found page; never built as a post.
+const NOT_FOUND_SOURCE: &str = "404.md";
+
+/// Source of the intro on the index page, not a post.
+const INDEX_SOURCE: &str = "_index.md";
+
+/// Directory of tag descriptions (`tags/rust.md`).
+const TAG_SOURCES: &str = "tags";
+
+/// Page with every post when the index shows only the latest
+/// (`index_posts`).
+const ARCHIVE_PAGE: &str = "archive.html";
+
+/// A site with a warm cache of parsed posts.
+pub struct Site {
+    config: Config,
+    cache: HashMap<PathBuf, CachedPost>,
+    progress: Progress,
+    pool: Option<Arc<ThreadPool>>,
+    image_limit: DecodeLimit,
+    /// Hashes of the external resources, kept across rebuilds.
+    integrity: Integrity,
+    plugins: Vec<Box<dyn Plugin>>,
+    pipeline: Pipeline,
+}
+
+/// Parsed post plus the source stamps it was parsed from.
+struct CachedPost {
+    stamp: SourceStamp,
+    /// Stamps of the files the post includes.
+    dependencies: Vec<(PathBuf, SourceStamp)>,
+    post: ParsedPost,
+    /// What the last build rendered, for the list pages and feeds of a
+    /// partial rebuild; None if the post wasn't built.
+    rendered: Option<Attachments>,
+}
+
+impl CachedPost {
+    fn new(stamp: SourceStamp, post: ParsedPost) -> Self {
+        let dependencies = post.includes.iter()
+            .filter_map(|dep| source_stamp(dep, None).ok().map(|s| (dep.clone(), s)))
+            .collect();
+        Self { stamp, dependencies, post, rendered: None }
+    }
+
+    /// Whether neither the post nor anything it includes changed.
+    fn is_fresh(&self, stamp: &SourceStamp) -> bool {
+        self.stamp == *stamp
+            && self.dependencies.iter().all(|(dep, s)| source_stamp(dep, None).is_ok_and(|now| now == *s))
+    }
+}
+
+/// Cheap change detection for source files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceStamp {
+    modified: SystemTime,
+    len: u64,
+    /// Commit dates, so that new commits refresh cached post dates.
+    git: Option<FileDates>,
+}
+
+/// Intermediate parsed post data.
+#[derive(Debug, Clone)]
+struct ParsedPost {
+    source: PathBuf,
+    file_stem: String,
+    front: FrontMatter,
+    metadata: PostMetadata,
+    /// Git, front matter `date:` or mtime, in that order.
+    published: DateTime<FixedOffset>,
+    /// Last updated: front matter `updated:`, git, or `published`.
+    modified: DateTime<FixedOffset>,
+    /// Last announced update, front matter `updated:` or `published`;
+    /// feeds use it so that silent edits don't resurface the post.
+    revised: DateTime<FixedOffset>,
+    /// `published` for display.
+    date: String,
+    /// Plain text: front matter `summary:`, else the first paragraph.
+    summary: Option<String>,
+    /// Markdown body without front matter, includes expanded.
+    content: String,
+    /// Line of the source file the body starts on.
+    body_line: usize,
+    /// Files pulled in by `{{include}}` directives.
+    includes: Vec<PathBuf>,
+    first_image_url: Option<String>,
+}
+
+impl ParsedPost {
+    fn post_ref(&self) -> PostRef {
+        PostRef::new(&self.file_stem, &self.metadata.raw_title)
+    }
+
+    /// `lang:` front matter, for a post not in the site's language.
+    fn lang(&self) -> Option<&str> {
+        self.front.get("lang").map(str::trim).filter(|lang| !lang.is_empty())
+    }
+
+    /// `protected:` front matter: the password the body is encrypted with.
+    fn password(&self) -> Option<&str> {
+        self.front.get("protected").filter(|password| !password.is_empty())
+    }
+}
+
+/// Result of parsing one source file.
+struct ParseOutcome {
+    source: PathBuf,
+    elapsed: Duration,
+    cached: bool,
+    result: Result<ParsedPost, BuildError>,
+}
+
+/// Rendered extras of a built post.
+#[derive(Debug, Clone, Default)]
+struct Attachments {
+    /// Post body HTML, kept for full-content feeds.
+    body: Option<String>,
+    audio: Option<Audio>,
+}
+
+/// Rendered post ready to be recorded in the build result.
+struct RenderOutcome {
+    output: PathBuf,
+    elapsed: Duration,
+    warnings: Vec<BuildError>,
+    images: Vec<OptimizedImage>,
+    /// Post body HTML, kept for full-content feeds.
+    body: Option<String>,
+    audio: Option<Audio>,
+}
+
+/// Shared state for writing pages during a build.
+struct BuildContext<'a> {
+    config: &'a Config,
+    /// Site-wide render options, cloned for each page.
+    render: RenderOptions,
+    outputs: &'a OutputSet,
+    decode_limit: &'a DecodeLimit,
+    image_cache: &'a ImageCache,
+    data: &'a SiteData,
+    layouts: &'a Layouts,
+    registry: &'a Registry,
+    translations: &'a Translations,
+    /// Language being built on a multilingual site.
+    language: Option<&'a str>,
+    plugins: &'a [Box<dyn Plugin>],
+    pipeline: &'a Pipeline,
+}
+
+impl BuildContext<'_> {
+    /// Run the plugins' `after_render` hooks on a finished page.
+    fn after_render(&self, mut html: String) -> String {
+        for plugin in self.plugins {
+            plugin.after_render(&mut html);
+        }
+        html
+    }
+
+    /// State for rendering markdown.
+    fn markdown_env(&self) -> MarkdownEnv<'_> {
+        MarkdownEnv {
+            outputs: self.outputs,
+            decode_limit: self.decode_limit,
+            image_cache: self.image_cache,
+            data: self.data,
+            registry: self.registry,
+            pipeline: self.pipeline,
+        }
+    }
+
+    /// Render options for a page whose links are relative to `relative_root`.
+    fn page_context(&self, relative_root: &str) -> RenderOptions {
+        let mut ctx = self.render.clone();
+        if self.config.feed_enabled() {
+            ctx = ctx
+                .with_feed(format!("{relative_root}{FEED_FILE}"))
+                .with_rss_feed(format!("{relative_root}{RSS_FILE}"));
+        }
+        if self.config.search.enabled && self.config.search.page {
+            let label = ctx.strings.search.clone();
+            ctx = ctx.with_nav_link(NavLink { path: RelPath::default().join(SEARCH_PAGE), label });
+            if self.config.base_url.is_some() {
+                ctx = ctx.with_search(format!("{relative_root}{OPENSEARCH_FILE}"));
+            }
+        }
+        if let Some(code) = self.language {
+            ctx = ctx.with_body_class(format!("lang-{}", code.to_lowercase()));
+        }
+        // Static assets stay at the site root, above the language directory
+        if self.language.is_some() {
+            let asset_root = if relative_root.starts_with('/') {
+                "/".to_string()
+            } else {
+                format!("{relative_root}../")
+            };
+            ctx = ctx.with_asset_root(asset_root);
+        }
+        ctx
+    }
+
+    /// Absolute URL of the page at `path` (relative to the language
+    /// directory), with a `base_url`.
+    fn canonical_url(&self, path: &RelPath) -> Option<String> {
+        let prefix = self.language.map(|code| format!("{code}/")).unwrap_or_default();
+        self.config.base_url.as_ref().map(|base| base.join(format!("{prefix}{path}")).to_string())
+    }
+
+    /// Breadcrumbs from the index through the `(label, path)` pages of
+    /// `trail`, linked from a page at `relative_root` (absolutely with a
+    /// `base_url`).
+    fn breadcrumbs(&self, trail: &[(&str, &RelPath)], relative_root: &str) -> Vec<Breadcrumb> {
+        let index = RelPath::default().join("index.html");
+        std::iter::once((self.config.ui_strings().index.as_str(), &index))
+            .chain(trail.iter().copied())
+            .map(|(label, path)| Breadcrumb {
+                label: label.to_string(),
+                url: self.canonical_url(path).unwrap_or_else(|| path.href(relative_root)),
+            })
+            .collect()
+    }
+
+    /// Translations of the page at `path` (relative to the language
+    /// directory); `slug` is None for pages every language has.
+    fn alternates(&self, slug: Option<&str>, path: &str) -> Vec<Alternate> {
+        match self.language {
+            Some(current) => self.translations.alternates(slug, path, current, self.config),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Site-wide render options: the stylesheet `css` inlined, if set, the
+/// webfont preloads, and the `nav_tags` shown.
+fn render_options(config: &Config, css: Option<&str>, integrity: &Integrity, nav_tags: Option<BTreeSet<Tag>>) -> RenderOptions {
+    let mut render = RenderOptions::new(config.clone()).with_integrity(Arc::new(integrity.clone()));
+    if let Some(css) = css {
+        let purge = config.purge.enabled.then(|| Arc::new(Stylesheet::parse(css)));
+        render = render.with_css(css).with_purge(purge);
+    }
+    if config.fonts.preload {
+        for preload in fonts::preloads(&config.static_dir) {
+            render = render.with_preload(preload);
+        }
+    }
+    if let Some(tags) = nav_tags {
+        render = render.with_nav_tags(Arc::new(tags));
+    }
+    render
+}
+
+/// How to build one content tree.
+struct TreeSpec<'a> {
+    /// Language code on a multilingual site.
+    language: Option<&'a str>,
+    css: Option<&'a str>,
+    translations: &'a Translations,
+    /// Also write the 404 page here (the site root).
+    root_not_found: Option<PathBuf>,
+    /// `data/blogroll.toml`, for the links page.
+    blogroll: Option<&'a Blogroll>,
+    data: &'a SiteData,
+    layouts: &'a Layouts,
+}
+
+impl TreeSpec<'_> {
+    /// URL prefix of the tree relative to the site root (`de/`).
+    fn prefix(&self) -> String {
+        self.language.map(|code| format!("{code}/")).unwrap_or_default()
+    }
+}
+
+/// Output of building one content tree.
+struct BuiltTree {
+    prefix: String,
+    sources: Vec<PathBuf>,
+    posts: Vec<ParsedPost>,
+    sitemap: Vec<SitemapEntry>,
+}
+
+/// Which languages of a multilingual site have a post, by slug.
+#[derive(Debug, Default)]
+struct Translations {
+    /// Language codes, default first.
+    languages: Vec<String>,
+    slugs: HashMap<String, Vec<String>>,
+}
+
+impl Translations {
+    /// Scan the content directory of every language.
+    fn scan(config: &Config) -> Self {
+        let mut translations = Self::default();
+        for language in &config.languages {
+            translations.languages.push(language.code.clone());
+            let Ok(entries) = fs::read_dir(config.content_dir.join(&language.code)) else {
+                continue;
+            };
+            for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+                if is_post(&path)
+                    && let Some(slug) = path.file_stem().and_then(|s| s.to_str())
+                {
+                    translations.slugs.entry(slug.to_string()).or_default().push(language.code.clone());
+                }
+            }
+        }
+        translations
+    }
+
+    fn alternates(&self, slug: Option<&str>, path: &str, current: &str, config: &Config) -> Vec<Alternate> {
+        let languages = match slug {
+            Some(slug) => self.slugs.get(slug).map(Vec::as_slice).unwrap_or_default(),
+            None => self.languages.as_slice(),
+        };
+        // A page without translations has nothing to link to
+        if languages.len() < 2 {
+            return Vec::new();
+        }
+        languages
+            .iter()
+            .map(|lang| {
+                let path = format!("{lang}/{path}");
+                let href = match &config.base_url {
+                    Some(base) => base.join(&path).to_string(),
+                    None => format!("/{path}"),
+                };
+                Alternate { lang: lang.clone(), href, current: lang == current }
+            })
+            .collect()
+    }
+}
+
+impl Site {
+    pub fn new(config: Config) -> Self {
+        Self {
+            image_limit: DecodeLimit::new(config.image_jobs),
+            config,
+            cache: HashMap::new(),
+            progress: Progress::hidden(),
+            pool: None,
+            integrity: Integrity::default(),
+            plugins: Vec::new(),
+            pipeline: Pipeline::standard(),
+        }
+    }
+
+    /// Builder: report build phases to `progress`.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Builder: register a plugin; hooks run in registration order.
+    pub fn with_plugin(mut self, plugin: impl Plugin + 'static) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Builder: render markdown through `pipeline` instead of the
+    /// standard stages.
+    pub fn with_pipeline(mut self, pipeline: Pipeline) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Number of posts currently held in the parse cache.
+    pub fn cached_posts(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Run the full build pipeline into the configured public directory.
+    pub fn build(&mut self) -> Result<BuildSummary, BuildError> {
+        let outputs = OutputSet::new();
+        let build_result = self.run(&outputs)?;
+        let summary = self.finalize(build_result)?;
+        for plugin in &self.plugins {
+            plugin.after_build(&summary);
+        }
+        Ok(summary)
+    }
+
+    /// Build, then check every page the build wrote for well-formed HTML
+    /// (`ssg check`); problems are added to the summary's warnings.
+    pub fn check(&mut self) -> Result<BuildSummary, BuildError> {
+        let outputs = OutputSet::new();
+        let mut build_result = self.run(&outputs)?;
+        let timer = PhaseTimer::start("validate");
+        let pages: Vec<PathBuf> = outputs.paths().into_iter()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+            .collect();
+        for issue in validate::check_pages(&pages) {
+            build_result.record_warning(issue);
+        }
+        for page in &pages {
+            if let Ok(html) = fs::read_to_string(page) {
+                social::check_html(&html, page).into_iter().for_each(|issue| build_result.record_warning(issue));
+            }
+        }
+        build_result.record_phase(timer.stop());
+        let summary = self.finalize(build_result)?;
+        for plugin in &self.plugins {
+            plugin.after_build(&summary);
+        }
+        Ok(summary)
+    }
+
+    /// Run the full pipeline without writing anything.
+    ///
+    /// Returns the summary plus the outputs that would be created, updated
+    /// or pruned.
+    pub fn dry_run(&mut self) -> Result<(BuildSummary, DryRunReport), BuildError> {
+        let outputs = OutputSet::dry_run();
+        let build_result = self.run(&outputs)?;
+
+        let deletions = if self.config.prune {
+            output::find_orphans(&self.config, &outputs)
+        } else {
+            Vec::new()
+        };
+        let report = DryRunReport {
+            changes: outputs.changes(),
+            deletions,
+        };
+
+        Ok((self.finalize(build_result)?, report))
+    }
+
+    /// Apply the `[[suppress]]` rules and finalize.
+    fn finalize(&self, mut build_result: BuildResult) -> Result<BuildSummary, BuildError> {
+        build_result.suppress(&self.config.suppress);
+        build_result.finalize()
+    }
+
+    /// Run `f` on the configured thread pool (the global pool if unset).
+    fn in_pool<R: Send>(&mut self, f: impl FnOnce(&mut Self) -> R + Send) -> Result<R, BuildError> {
+        let Some(threads) = self.config.threads else {
+            return Ok(f(self));
+        };
+
+        let pool = match self.pool {
+            Some(ref pool) => Arc::clone(pool),
+            None => {
+                let pool = ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(|e| BuildError::Internal(format!("Failed to start thread pool: {}", e)))?;
+                debug!("Using {} worker threads", threads);
+                Arc::clone(self.pool.insert(Arc::new(pool)))
+            }
+        };
+        Ok(pool.install(|| f(self)))
+    }
+
+    /// Shared pipeline: every write goes through `outputs`.
+    fn run(&mut self, outputs: &OutputSet) -> Result<BuildResult, BuildError> {
+        self.in_pool(|site| site.run_phases(outputs))?
+    }
+
+    fn run_phases(&mut self, outputs: &OutputSet) -> Result<BuildResult, BuildError> {
+        let scan_span = info_span!("scan").entered();
+        let scan_timer = PhaseTimer::start("scan");
+        let mut build_result = BuildResult::new();
+        let config = &self.config;
+
+        outputs.create_dir(&config.public_dir)?;
+        let css_content = load_css(config);
+
+        if config.resources.integrity {
+            let resources = &config.resources;
+            let external = resources.scripts.iter().chain(&resources.styles);
+            for e in self.integrity.resolve(external.map(String::as_str)) {
+                build_result.record_warning(e);
+            }
+        }
+
+        // Copy static assets: the static directory as-is, plus the legacy
+        // favicon (and stylesheet unless inlined) from the content directory
+        copy_tree(&config.static_dir, &config.public_dir, outputs);
+
+        let static_files: Vec<&str> = if css_content.is_some() {
+            vec!["favicon.ico"]
+        } else {
+            vec!["favicon.ico", "style.css"]
+        };
+
+        for file in static_files {
+            let src = config.content_dir.join(file);
+            let dest = config.public_dir.join(file);
+            if src.exists()
+                && !outputs.contains(&dest)
+                && let Err(e) = outputs.copy(&src, &dest)
+            {
+                warn!("Failed to copy {}: {}", file, e);
+            }
+        }
+
+        if let Some(icon) = &config.icon
+            && let Err(e) = icons::generate(icon, config, outputs)
+        {
+            build_result.record_warning(e);
+        }
+        print::write(config, outputs)?;
+
+        // A multilingual site builds each language as its own tree
+        let translations = Translations::scan(config);
+        let trees: Vec<(Option<String>, Config)> = if config.languages.is_empty() {
+            vec![(None, config.clone())]
+        } else {
+            config.languages.iter().map(|l| (Some(l.code.clone()), config.for_language(l))).collect()
+        };
+        let root_not_found = config.public_dir.join("404.html");
+        let blogroll = Blogroll::load(&config.data_dir).unwrap_or_else(|e| {
+            build_result.record_warning(e);
+            None
+        });
+        let (data, data_errors) = SiteData::load(&config.data_dir);
+        data_errors.into_iter().for_each(|e| build_result.record_warning(e));
+        let (layouts, layout_errors) = Layouts::load(&config.templates_dir);
+        layout_errors.into_iter().for_each(|e| build_result.record_warning(e));
+        build_result.record_phase(scan_timer.stop());
+        drop(scan_span);
+
+        let mut built = Vec::new();
+        for (i, (language, tree_config)) in trees.into_iter().enumerate() {
+            let tree = TreeSpec {
+                language: language.as_deref(),
+                css: css_content.as_deref(),
+                translations: &translations,
+                // Hosts only serve the root 404 page: use the default language's
+                root_not_found: (i == 0 && language.is_some()).then(|| root_not_found.clone()),
+                blogroll: blogroll.as_ref(),
+                data: &data,
+                layouts: &layouts,
+            };
+            built.push(self.build_tree(&tree_config, outputs, &tree, &mut build_result)?);
+        }
+
+        let _write_span = info_span!("write").entered();
+        let write_timer = PhaseTimer::start("write");
+        let config = &self.config;
+        self.cache.retain(|path, _| built.iter().any(|tree| tree.sources.contains(path)));
+
+        // The root of a multilingual site points at the default language
+        if let Some(default) = config.languages.first() {
+            let target = format!("{}/index.html", default.code);
+            outputs.write(&config.public_dir.join("index.html"), render_redirect_page(&target))?;
+        }
+
+        // Protected posts use rules no page shows before decryption
+        let protected = built.iter().any(|tree| tree.posts.iter().any(|post| post.password().is_some()));
+        if config.purge.enabled && css_content.is_none() && !protected && !outputs.is_dry_run() {
+            purge_stylesheet(config, outputs)?;
+        }
+        if config.fonts.subset && !outputs.is_dry_run() {
+            for e in subset_fonts(config, protected, outputs) {
+                build_result.record_warning(e);
+            }
+        }
+        write_site_files(&built, css_content.is_none(), config, outputs, &mut build_result)?;
+
+        if outputs.is_dry_run() {
+            build_result.record_phase(write_timer.stop());
+            return Ok(build_result);
+        }
+
+        // Remove outputs left behind by deleted or renamed posts
+        if config.prune {
+            let (removed, failures) = output::remove_files(output::find_orphans(config, outputs), false);
+            for path in &removed {
+                info!("Pruned {}", path.display());
+            }
+            for err in failures {
+                build_result.record_warning(err);
+            }
+        }
+        output::write_manifest(config, outputs)?;
+        build_result.record_phase(write_timer.stop());
+
+        Ok(build_result)
+    }
+
+    /// Build one content tree (the configured content directory) into
+    /// the configured public directory: posts, list pages and 404 page.
+    fn build_tree(
+        &mut self,
+        config: &Config,
+        outputs: &OutputSet,
+        tree: &TreeSpec<'_>,
+        build_result: &mut BuildResult,
+    ) -> Result<BuiltTree, BuildError> {
+        for dir in [config.posts_dir(), config.tags_dir(), config.images_dir()] {
+            outputs.create_dir(&dir)?;
+        }
+
+        // Phase 1: Discover markdown files (IO-bound, sequential)
+        let scan_timer = PhaseTimer::start("scan");
+        let paths = discover(config)?;
+        info!("Found {} markdown files.", paths.len());
+        build_result.record_phase(scan_timer.stop());
+
+        // Phase 2: Parse metadata (CPU-bound, parallel)
+        let parse_span = info_span!("parse").entered();
+        let parse_timer = PhaseTimer::start("parse");
+        let parsed_results = self.parse_all(config, &paths);
+
+        // Collect results and tags
+        let mut valid_posts: Vec<(ParsedPost, Duration, bool)> = Vec::new();
+        let mut all_tags: BTreeSet<Tag> = BTreeSet::new();
+
+        for outcome in parsed_results {
+            match outcome.result {
+                Ok(post) => {
+                    for tag in &post.metadata.tags {
+                        all_tags.insert(tag.clone());
+                    }
+                    valid_posts.push((post, outcome.elapsed, outcome.cached));
+                }
+                Err(e) => {
+                    build_result.record_post(PostReport {
+                        source: outcome.source,
+                        output: None,
+                        status: PostStatus::Failed,
+                        cached: outcome.cached,
+                        parse_ms: millis(outcome.elapsed),
+                        render_ms: 0.0,
+                        warnings: 0,
+                    });
+                    build_result.record_failure(e);
+                }
+            }
+        }
+
+        info!("Parsed {} valid posts. Generating HTML...", valid_posts.len());
+        let registry = Registry::new(valid_posts.iter().map(|(post, _, _)| post.post_ref()));
+        build_result.record_phase(parse_timer.stop());
+        drop(parse_span);
+
+        // Phase 3: Render HTML (CPU-bound, parallel)
+        let render_span = info_span!("render").entered();
+        let render_timer = PhaseTimer::start("render");
+        let image_cache = ImageCache::load(&config.images_dir());
+        let shown_tags = nav_tags(config, valid_posts.iter().flat_map(|(post, _, _)| &post.metadata.tags));
+        let build_ctx = BuildContext {
+            config,
+            render: render_options(config, tree.css, &self.integrity, shown_tags),
+            outputs,
+            decode_limit: &self.image_limit,
+            image_cache: &image_cache,
+            data: tree.data,
+            layouts: tree.layouts,
+            registry: &registry,
+            translations: tree.translations,
+            language: tree.language,
+            plugins: &self.plugins,
+            pipeline: &self.pipeline,
+        };
+        let render_bar = self.progress.phase("render", valid_posts.len());
+        let images_bar = self.progress.counter("images");
+        let lightbox_script = config.lightbox == Lightbox::Script
+            || valid_posts.iter().any(|(post, _, _)| post.front.get("lightbox") == Some("script"));
+        let render_results: Vec<_> = valid_posts.par_iter()
+            .map(|(post, _, _)| {
+                // Rayon workers don't inherit the current span; link explicitly
+                let _post_span = debug_span!(parent: &render_span, "post", file = %post.file_stem).entered();
+                let result = write_post(post, &all_tags, &build_ctx);
+                render_bar.inc(1);
+                if let Ok(rendered) = &result {
+                    images_bar.inc(rendered.images.iter().filter(|img| img.output_path.is_some()).count() as u64);
+                }
+                result
+            })
+            .collect();
+        render_bar.finish();
+        images_bar.finish();
+
+        let mut built_posts = Vec::new();
+        let mut bodies = HashMap::new();
+        let mut audio = HashMap::new();
+        for ((post, parse_elapsed, cached), res) in valid_posts.into_iter().zip(render_results) {
+            let Some(rendered) = record_render(&post, parse_elapsed, cached, res, build_result) else {
+                continue;
+            };
+            if let Some(body) = &rendered.body {
+                bodies.insert(post.file_stem.clone(), body.clone());
+            }
+            if let Some(file) = &rendered.audio {
+                audio.insert(post.file_stem.clone(), file.clone());
+            }
+            if let Some(cached) = self.cache.get_mut(&post.source) {
+                cached.rendered = Some(rendered);
+            }
+            built_posts.push(post);
+        }
+
+        build_result.record_phase(render_timer.stop());
+        drop(render_span);
+
+        // Phase 4: Generate index pages (sequential)
+        let _write_span = info_span!("write").entered();
+        let write_timer = PhaseTimer::start("write");
+        let attachments = PostAttachments { bodies: &bodies, audio: &audio };
+        let indexed = write_lists(&mut built_posts, &all_tags, None, attachments, tree, &build_ctx, build_result)?;
+        if lightbox_script {
+            outputs.write(&config.public_dir.join(LIGHTBOX_SCRIPT), LIGHTBOX_JS)?;
+        }
+        if built_posts.iter().any(|post| post.password().is_some()) {
+            outputs.write(&config.public_dir.join(PROTECT_SCRIPT), PROTECT_JS)?;
+        }
+
+        image_cache.save(&config.images_dir(), outputs)?;
+        build_result.record_phase(write_timer.stop());
+
+        Ok(BuiltTree {
+            prefix: tree.prefix(),
+            sources: paths,
+            posts: built_posts,
+            sitemap: indexed,
+        })
+    }
+
+    /// Rebuild after `path` changed: re-render that one post plus the
+    /// index, the pages of its old and new tags, and the site-wide files.
+    ///
+    /// Falls back to a full [`build`](Self::build) whenever the change can
+    /// reach other pages (a new, deleted or retitled post, a tag appearing
+    /// or disappearing) or the site is multilingual. Outputs a partial
+    /// rebuild leaves behind are pruned by the next full build.
+    pub fn rebuild_post(&mut self, path: &Path) -> Result<BuildSummary, BuildError> {
+        let outputs = OutputSet::new();
+        let Some(build_result) = self.in_pool(|site| site.rebuild_phases(path, &outputs))?? else {
+            debug!("Full rebuild for {}", path.display());
+            return self.build();
+        };
+        let summary = self.finalize(build_result)?;
+        for plugin in &self.plugins {
+            plugin.after_build(&summary);
+        }
+        Ok(summary)
+    }
+
+    /// Partial build behind [`rebuild_post`](Self::rebuild_post); None
+    /// when a full build is needed.
+    fn rebuild_phases(&mut self, path: &Path, outputs: &OutputSet) -> Result<Option<BuildResult>, BuildError> {
+        // Every tag, and those the nav shows
+        let site_tags = |cache: &HashMap<PathBuf, CachedPost>, config: &Config| -> (BTreeSet<Tag>, Option<BTreeSet<Tag>>) {
+            let posts = || cache.values().filter(|c| c.post.source.parent() == Some(config.content_dir.as_path()));
+            let tags = posts().flat_map(|c| c.post.metadata.tags.iter().cloned()).collect();
+            (tags, nav_tags(config, posts().flat_map(|c| &c.post.metadata.tags)))
+        };
+        let previous = match self.cache.get(path) {
+            // A purged `style.css` and subset fonts depend on every page
+            Some(cached) if cached.rendered.is_some() && self.config.languages.is_empty() && path.is_file()
+                && (self.config.inline_css || !self.config.purge.enabled) && !self.config.fonts.follows_pages() => {
+                cached.post.clone()
+            }
+            _ => return Ok(None),
+        };
+        let old_tags = site_tags(&self.cache, &self.config);
+
+        let parse_timer = PhaseTimer::start("parse");
+        let mut build_result = BuildResult::new();
+        let config = &self.config.clone();
+        let Some(outcome) = self.parse_all(config, &[path.to_path_buf()]).pop() else {
+            return Ok(None);
+        };
+        let post = match outcome.result {
+            Ok(post) => post,
+            Err(e) => {
+                build_result.record_post(PostReport {
+                    source: outcome.source,
+                    output: None,
+                    status: PostStatus::Failed,
+                    cached: false,
+                    parse_ms: millis(outcome.elapsed),
+                    render_ms: 0.0,
+                    warnings: 0,
+                });
+                build_result.record_failure(e);
+                return Ok(Some(build_result));
+            }
+        };
+        let tags = site_tags(&self.cache, &self.config);
+        if tags != old_tags || post.metadata.raw_title != previous.metadata.raw_title {
+            return Ok(None);
+        }
+        let (all_tags, shown_tags) = tags;
+        build_result.record_phase(parse_timer.stop());
+
+        let render_timer = PhaseTimer::start("render");
+        let css_content = load_css(config);
+        let image_cache = ImageCache::load(&config.images_dir());
+        let (data, data_errors) = SiteData::load(&config.data_dir);
+        data_errors.into_iter().for_each(|e| build_result.record_warning(e));
+        let (layouts, layout_errors) = Layouts::load(&config.templates_dir);
+        layout_errors.into_iter().for_each(|e| build_result.record_warning(e));
+        let blogroll = Blogroll::load(&config.data_dir).unwrap_or_else(|e| {
+            build_result.record_warning(e);
+            None
+        });
+        let translations = Translations::default();
+        let tree = TreeSpec {
+            language: None,
+            css: css_content.as_deref(),
+            translations: &translations,
+            root_not_found: None,
+            blogroll: blogroll.as_ref(),
+            data: &data,
+            layouts: &layouts,
+        };
+        let built: Vec<&CachedPost> = self.cache.values()
+            .filter(|c| c.rendered.is_some() || c.post.source == post.source)
+            .filter(|c| c.post.source.parent() == Some(config.content_dir.as_path()))
+            .collect();
+        let registry = Registry::new(built.iter().map(|c| c.post.post_ref()));
+        let build_ctx = BuildContext {
+            config,
+            render: render_options(config, tree.css, &self.integrity, shown_tags),
+            outputs,
+            decode_limit: &self.image_limit,
+            image_cache: &image_cache,
+            data: tree.data,
+            layouts: tree.layouts,
+            registry: &registry,
+            translations: tree.translations,
+            language: None,
+            plugins: &self.plugins,
+            pipeline: &self.pipeline,
+        };
+
+        let result = write_post(&post, &all_tags, &build_ctx);
+        let Some(attachments) = record_render(&post, outcome.elapsed, false, result, &mut build_result) else {
+            return Ok(Some(build_result));
+        };
+        build_result.record_phase(render_timer.stop());
+
+        // Every other post keeps what the last build rendered
+        let write_timer = PhaseTimer::start("write");
+        let mut posts = vec![post.clone()];
+        let mut bodies = HashMap::new();
+        let mut audio = HashMap::new();
+        for (other, rendered) in built.iter()
+            .filter(|c| c.post.source != post.source)
+            .filter_map(|c| Some((&c.post, c.rendered.as_ref()?)))
+            .chain([(&post, &attachments)])
+        {
+            if let Some(body) = &rendered.body {
+                bodies.insert(other.file_stem.clone(), body.clone());
+            }
+            if let Some(file) = &rendered.audio {
+                audio.insert(other.file_stem.clone(), file.clone());
+            }
+            if other.source != post.source {
+                posts.push(other.clone());
+            }
+        }
+        let tags: BTreeSet<Tag> = previous.metadata.tags.iter().chain(&post.metadata.tags).cloned().collect();
+        let lists = PostAttachments { bodies: &bodies, audio: &audio };
+        let sitemap = write_lists(&mut posts, &all_tags, Some(&tags), lists, &tree, &build_ctx, &mut build_result)?;
+        let lightbox_script = config.lightbox == Lightbox::Script
+            || posts.iter().any(|post| post.front.get("lightbox") == Some("script"));
+        if lightbox_script {
+            outputs.write(&config.public_dir.join(LIGHTBOX_SCRIPT), LIGHTBOX_JS)?;
+        }
+        if posts.iter().any(|post| post.password().is_some()) {
+            outputs.write(&config.public_dir.join(PROTECT_SCRIPT), PROTECT_JS)?;
+        }
+        image_cache.save(&config.images_dir(), outputs)?;
+
+        let sources = built.iter().map(|c| c.post.source.clone()).collect();
+        let built = [BuiltTree { prefix: String::new(), sources, posts, sitemap }];
+        write_site_files(&built, css_content.is_none(), config, outputs, &mut build_result)?;
+        build_result.record_phase(write_timer.stop());
+
+        if let Some(cached) = self.cache.get_mut(path) {
+            cached.rendered = Some(attachments);
+        }
+        Ok(Some(build_result))
+    }
+
+    /// Build into a different public directory, reusing the parse cache.
+    pub fn build_into(&mut self, public_dir: &Path) -> Result<BuildSummary, BuildError> {
+        let original = std::mem::replace(&mut self.config.public_dir, public_dir.to_path_buf());
+        let result = self.build();
+        self.config.public_dir = original;
+        result
+    }
+
+    /// Render a single post page to HTML without writing it.
+    ///
+    /// Nav tags come from the cache; an empty cache triggers a parse pass.
+    pub fn preview(&mut self, path: &Path) -> Result<String, BuildError> {
+        // Posts of a multilingual site render as part of their language
+        let language = self.config.languages.iter()
+            .find(|l| path.starts_with(self.config.content_dir.join(&l.code)))
+            .cloned();
+        let Some(language) = language else {
+            let config = self.config.clone();
+            return self.in_pool(|site| site.render_preview(&config, path, None))?;
+        };
+
+        let translations = Translations::scan(&self.config);
+        let config = self.config.for_language(&language);
+        self.in_pool(|site| site.render_preview(&config, path, Some((&language.code, &translations))))?
+    }
+
+    fn render_preview(&mut self, config: &Config, path: &Path, language: Option<(&str, &Translations)>) -> Result<String, BuildError> {
+        if self.cache.is_empty() {
+            let paths = discover(config)?;
+            self.parse_all(config, &paths);
+        }
+
+        let post = match self.parse_all(config, &[path.to_path_buf()]).pop() {
+            Some(outcome) => outcome.result?,
+            None => return Err(BuildError::Internal("preview produced no post".to_string())),
+        };
+
+        let all_tags: BTreeSet<Tag> = self.cache.values()
+            .flat_map(|c| c.post.metadata.tags.iter().cloned())
+            .collect();
+        let shown_tags = nav_tags(config, self.cache.values().flat_map(|c| &c.post.metadata.tags));
+        let registry = Registry::new(self.cache.values()
+            .filter(|c| c.post.source.parent() == Some(config.content_dir.as_path()))
+            .map(|c| c.post.post_ref()));
+
+        let css_content = load_css(config);
+        let outputs = OutputSet::new();
+        let image_cache = ImageCache::load(&config.images_dir());
+        let (data, _) = SiteData::load(&config.data_dir);
+        let (layouts, _) = Layouts::load(&config.templates_dir);
+        let no_translations = Translations::default();
+        let build_ctx = BuildContext {
+            config,
+            render: render_options(config, css_content.as_deref(), &self.integrity, shown_tags),
+            outputs: &outputs,
+            decode_limit: &self.image_limit,
+            image_cache: &image_cache,
+            data: &data,
+            layouts: &layouts,
+            registry: &registry,
+            translations: language.map_or(&no_translations, |(_, t)| t),
+            language: language.map(|(code, _)| code),
+            plugins: &self.plugins,
+            pipeline: &self.pipeline,
+        };
+        let (post_config, _) = ResolvedPostConfig::resolve(config, &post.front, &post.source);
+        render_post(&post, &post_config, &all_tags, &build_ctx).map(|(html, _, _)| html)
+    }
+
+    /// Render the posts tagged `tag` (all without one), oldest first, as
+    /// an EPUB book. A multilingual site exports its first language; the
+    /// images the posts embed are optimized into the public directory.
+    pub fn epub_book(&mut self, tag: Option<&Tag>) -> Result<(Book, Vec<BuildError>), BuildError> {
+        let config = match self.config.languages.first() {
+            Some(language) => self.config.for_language(language),
+            None => self.config.clone(),
+        };
+        self.in_pool(|site| site.render_book(&config, tag))?
+    }
+
+    fn render_book(&mut self, config: &Config, tag: Option<&Tag>) -> Result<(Book, Vec<BuildError>), BuildError> {
+        let paths = discover(config)?;
+        let mut warnings = Vec::new();
+        let mut posts = Vec::new();
+        for outcome in self.parse_all(config, &paths) {
+            match outcome.result {
+                Ok(post) if post.password().is_none() && tag.is_none_or(|t| post.metadata.tags.contains(t)) => posts.push(post),
+                Ok(_) => {}
+                Err(e) => warnings.push(e),
+            }
+        }
+        posts.sort_by(|a, b| (a.published, &a.file_stem).cmp(&(b.published, &b.file_stem)));
+
+        let config = epub::profile(config);
+        let outputs = OutputSet::new();
+        outputs.create_dir(&config.images_dir())?;
+        let image_cache = ImageCache::load(&config.images_dir());
+        let (data, _) = SiteData::load(&config.data_dir);
+        let registry = Registry::new(posts.iter().map(ParsedPost::post_ref));
+        let env = MarkdownEnv {
+            outputs: &outputs,
+            decode_limit: &self.image_limit,
+            image_cache: &image_cache,
+            data: &data,
+            registry: &registry,
+            pipeline: &self.pipeline,
+        };
+        let chapters: Vec<Chapter> = posts.par_iter()
+            .map(|post| {
+                let (mut post_config, overrides) = ResolvedPostConfig::resolve(&config, &post.front, &post.source);
+                post_config.published = Some(post.published.date_naive());
+                if post_config.config.lightbox != Lightbox::None {
+                    // Books have no lightbox
+                    post_config.config.to_mut().lightbox = Lightbox::None;
+                }
+                if post_config.config.strip_title {
+                    // Chapters have no header to move the title to
+                    post_config.config.to_mut().strip_title = false;
+                }
+                render_markdown(&post.content, &post.source, &post_config, "../", &env)
+                    .map(|rendered| (post, overrides, rendered))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|(post, overrides, rendered)| {
+                warnings.extend(overrides);
+                warnings.extend(rendered.warnings.into_iter().map(|w| w.below_lines(post.body_line - 1)));
+                Chapter { stem: post.file_stem.clone(), title: post.metadata.raw_title.clone(), html: rendered.html }
+            })
+            .collect();
+        image_cache.save(&config.images_dir(), &outputs)?;
+        Ok((Book::new(&config, tag.map(Tag::as_str), chapters), warnings))
+    }
+
+    /// Parse posts in parallel, reusing cached results for unchanged files.
+    fn parse_all(&mut self, config: &Config, paths: &[PathBuf]) -> Vec<ParseOutcome> {
+        let cache = &self.cache;
+        let plugins = &self.plugins;
+        let history = config.git_dates.then(|| GitHistory::load(&config.content_dir)).flatten();
+        let bar = self.progress.phase("parse", paths.len());
+
+        let results: Vec<_> = paths.par_iter()
+            .map(|path| {
+                let start = Instant::now();
+                let parsed = source_stamp(path, history.as_ref()).and_then(|stamp| {
+                    if let Some(cached) = cache.get(path)
+                        && cached.is_fresh(&stamp)
+                    {
+                        return Ok((stamp, cached.post.clone(), true));
+                    }
+                    parse_post(path, stamp, config).map(|mut post| {
+                        for plugin in plugins {
+                            plugin.after_parse(&mut post.metadata, &mut post.content);
+                        }
+                        (stamp, post, false)
+                    })
+                });
+                bar.inc(1);
+                (path.clone(), start.elapsed(), parsed)
+            })
+            .collect();
+        bar.finish();
+
+        results
+            .into_iter()
+            .map(|(source, elapsed, parsed)| match parsed {
+                Ok((stamp, post, cached)) => {
+                    if !cached {
+                        self.cache.insert(source.clone(), CachedPost::new(stamp, post.clone()));
+                    }
+                    ParseOutcome { source, elapsed, cached, result: Ok(post) }
+                }
+                Err(e) => ParseOutcome { source, elapsed, cached: false, result: Err(e) },
+            })
+            .collect()
+    }
+}
+
+/// List markdown files in the content directory.
+fn discover(config: &Config) -> Result<Vec<PathBuf>, BuildError> {
+    let entries = fs::read_dir(&config.content_dir).map_err(|e| BuildError::ContentNotReadable {
+        path: config.content_dir.clone(),
+        source: e,
+    })?;
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| is_post(p))
+        .collect();
+    // Stable order, so that e.g. alias conflicts resolve the same way
+    paths.sort();
+    Ok(paths)
+}
+
+/// Whether `path` is a post source.
+fn is_post(path: &Path) -> bool {
+    let name = path.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+    path.extension().and_then(|s| s.to_str()) == Some("md") && ![NOT_FOUND_SOURCE, INDEX_SOURCE].contains(&name)
+}
+
+/// Load CSS for inlining (eliminates render-blocking).
+fn load_css(config: &Config) -> Option<String> {
+    if !config.inline_css {
+        return None;
+    }
+
+    let css_path = [config.static_dir.join("style.css"), config.content_dir.join("style.css")]
+        .into_iter()
+        .find(|p| p.exists())
+        .unwrap_or_default();
+    match fs::read_to_string(&css_path) {
+        Ok(css) => {
+            debug!("CSS will be inlined ({} bytes)", css.len());
+            Some(css)
+        }
+        Err(_) => {
+            warn!("CSS file not found for inlining, using external link");
+            None
+        }
+    }
+}
+
+/// Leave the rules no page uses out of the linked `style.css`.
+fn purge_stylesheet(config: &Config, outputs: &OutputSet) -> Result<(), BuildError> {
+    let path = config.public_dir.join("style.css");
+    if !outputs.contains(&path) {
+        return Ok(());
+    }
+    let Ok(css) = fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    let mut used = UsedNames::new();
+    for page in outputs.paths().iter().filter(|path| path.extension().is_some_and(|e| e == "html")) {
+        if let Ok(html) = fs::read_to_string(page) {
+            used.scan(&html);
+        }
+    }
+    let purged = Stylesheet::parse(&css).purge(&used, &config.purge);
+    debug!("Purged style.css from {} to {} bytes", css.len(), purged.len());
+    outputs.write(&path, purged)
+}
+
+/// Subset the webfonts to `[fonts] unicodes`, else to the characters of
+/// every page (not on sites with `protected` posts).
+fn subset_fonts(config: &Config, protected: bool, outputs: &OutputSet) -> Vec<BuildError> {
+    let unicodes = match &config.fonts.unicodes {
+        Some(unicodes) => unicodes.clone(),
+        None if protected => return Vec::new(),
+        None => {
+            let mut used = UsedChars::new();
+            for page in outputs.paths().iter().filter(|path| path.extension().is_some_and(|e| e == "html")) {
+                if let Ok(html) = fs::read_to_string(page) {
+                    used.scan(&html);
+                }
+            }
+            used.ranges()
+        }
+    };
+    fonts::subset(&config.static_dir, &config.public_dir, &unicodes, outputs)
+}
+
+/// Site-wide files built from every tree: the web app manifest and
+/// service worker, sitemap and robots.txt, llms.txt, and the redirect
+/// and header files for static hosts.
+fn write_site_files(
+    built: &[BuiltTree],
+    css_linked: bool,
+    config: &Config,
+    outputs: &OutputSet,
+    build_result: &mut BuildResult,
+) -> Result<(), BuildError> {
+    pwa::write(config, &precache_urls(built, css_linked), outputs)?;
+
+    let indexed: Vec<SitemapEntry> = built.iter().flat_map(|tree| tree.sitemap.iter().cloned()).collect();
+    write_crawler_files(&indexed, config, outputs)?;
+    if let Some(format) = config.post_source {
+        write_llms_txt(built, format, config, outputs)?;
+    }
+
+    // Redirect and header files for static hosts
+    let redirects = collect_redirects(built, build_result);
+    let mut headers = config.headers.clone();
+    if config.cache_headers {
+        let prefixes: Vec<&str> = built.iter().map(|tree| tree.prefix.as_str()).collect();
+        hosting::add_cache_rules(&mut headers, &prefixes);
+    }
+    write_hosting_files(&redirects, &headers, config, outputs)
+}
+
+/// Redirects from every post alias to the post's permalink. An alias
+/// claimed by two posts keeps the first and warns.
+fn collect_redirects(trees: &[BuiltTree], build_result: &mut BuildResult) -> Vec<Redirect> {
+    let mut redirects: HashMap<String, Redirect> = HashMap::new();
+    let posts = trees.iter().flat_map(|tree| tree.posts.iter().map(move |post| (&tree.prefix, post)));
+    for (prefix, post) in posts {
+        let permalink = format!("/{prefix}{}", registry::post_path(&post.file_stem));
+        for alias in post.front.list("aliases") {
+            let redirect = Redirect::new(&alias, permalink.clone());
+            if redirects.contains_key(&redirect.from) {
+                build_result.record_warning(BuildError::AliasConflict {
+                    path: post.source.clone(),
+                    alias: redirect.from,
+                });
+                continue;
+            }
+            redirects.insert(redirect.from.clone(), redirect);
+        }
+    }
+
+    let mut redirects: Vec<Redirect> = redirects.into_values().collect();
+    redirects.sort();
+    redirects
+}
+
+/// Pages the service worker keeps offline: the index and newest posts of
+/// every tree, plus the stylesheet when it is linked.
+fn precache_urls(built: &[BuiltTree], css_linked: bool) -> Vec<String> {
+    let mut urls = Vec::new();
+    for tree in built {
+        urls.push(format!("{}index.html", tree.prefix));
+        let mut newest: Vec<&ParsedPost> = tree.posts.iter().collect();
+        newest.sort_by(|a, b| SortOrder::Desc.compare(
+            (&a.published, a.metadata.title.as_str()),
+            (&b.published, b.metadata.title.as_str()),
+        ));
+        urls.extend(newest.iter().take(pwa::PRECACHE_POSTS).map(|p| format!("{}{}", tree.prefix, registry::post_path(&p.file_stem))));
+    }
+    if css_linked {
+        urls.push("style.css".to_string());
+    }
+    urls
+}
+
+/// Write `sitemap.xml` (needs `base_url`) and `robots.txt` unless the
+/// static directory already provided one.
+fn write_crawler_files(indexed: &[SitemapEntry], config: &Config, outputs: &OutputSet) -> Result<(), BuildError> {
+    let base_url = config.base_url.as_ref();
+    if let Some(base) = base_url {
+        outputs.write(&config.public_dir.join("sitemap.xml"), sitemap::render_sitemap(base, indexed))?;
+    }
+
+    let robots = config.public_dir.join("robots.txt");
+    if config.robots.enabled && !outputs.contains(&robots) {
+        outputs.write(&robots, sitemap::render_robots(&config.robots, base_url))?;
+    }
+    Ok(())
+}
+
+/// Write `llms.txt` listing the indexed posts' sources, newest first,
+/// unless the static directory already provided one.
+fn write_llms_txt(built: &[BuiltTree], format: PostSource, config: &Config, outputs: &OutputSet) -> Result<(), BuildError> {
+    let path = config.public_dir.join(LLMS_FILE);
+    if outputs.contains(&path) {
+        return Ok(());
+    }
+    let sections: Vec<(String, Vec<LlmsEntry>)> = built.iter()
+        .map(|tree| {
+            let mut posts: Vec<&ParsedPost> = tree.posts.iter()
+                .filter(|p| !p.front.flag("noindex") && p.password().is_none())
+                .collect();
+            posts.sort_by(|a, b| SortOrder::Desc.compare(
+                (&a.published, a.metadata.title.as_str()),
+                (&b.published, b.metadata.title.as_str()),
+            ));
+            let entries = posts.iter()
+                .map(|p| {
+                    let path = format!("{}posts/{}.{}", tree.prefix, p.file_stem, format.extension());
+                    LlmsEntry {
+                        title: p.metadata.raw_title.clone(),
+                        href: config.base_url.as_ref().map_or(path.clone(), |base| base.join(&path).to_string()),
+                        summary: p.summary.clone(),
+                    }
+                })
+                .collect();
+            let heading = match tree.prefix.trim_end_matches('/') {
+                "" => "Posts".to_string(),
+                code => format!("Posts ({code})"),
+            };
+            (heading, entries)
+        })
+        .collect();
+    outputs.write(&path, render_llms_txt(&config.brand_name, config.description.as_deref(), &sections))
+}
+
+/// Rendered extras of built posts, by file stem.
+#[derive(Clone, Copy)]
+struct PostAttachments<'a> {
+    /// Body HTML (full-content feeds only).
+    bodies: &'a HashMap<String, String>,
+    audio: &'a HashMap<String, Audio>,
+}
+
+/// Pages listing the built `posts` of a tree, which are sorted for them:
+/// the index and the pages of `tags` (every tag when None, plus the 404
+/// and links pages), then the Gemtext mirror and feeds. Returns the
+/// tree's sitemap entries.
+fn write_lists(
+    posts: &mut [ParsedPost],
+    all_tags: &BTreeSet<Tag>,
+    tags: Option<&BTreeSet<Tag>>,
+    attachments: PostAttachments<'_>,
+    tree: &TreeSpec<'_>,
+    build_ctx: &BuildContext<'_>,
+    build_result: &mut BuildResult,
+) -> Result<Vec<SitemapEntry>, BuildError> {
+    let config = build_ctx.config;
+    let outputs = build_ctx.outputs;
+    posts.sort_by(|a, b| config.sort_order.compare(
+        (&a.published, a.metadata.title.as_str()),
+        (&b.published, b.metadata.title.as_str()),
+    ).then_with(|| a.file_stem.cmp(&b.file_stem)));
+    let post_items: Vec<PostListItem> = posts.iter()
+        .map(|p| PostListItem {
+            title: p.metadata.title.clone(),
+            path: registry::post_path(&p.file_stem),
+            date: p.date.clone(),
+            year: p.published.year(),
+            tags: p.metadata.tags.clone(),
+            pinned: p.front.flag("pinned"),
+        })
+        .collect();
+
+    // Generate main index, pinned posts first
+    let (pinned, unpinned): (Vec<_>, Vec<_>) = post_items.iter().cloned().partition(|p| p.pinned);
+    let strings = config.ui_strings();
+    let source = config.content_dir.join(INDEX_SOURCE);
+    let intro = render_standalone(&source, &strings.index, "", build_ctx, build_result)?;
+    let latest = config.index_posts.filter(|&count| count < unpinned.len());
+    let index = ListPage {
+        title: intro.as_ref().map_or(&strings.index, |intro| &intro.title),
+        kind: PageKind::Index,
+        path: RelPath::default().join("index.html"),
+        relative_root: "",
+        intro: intro.as_ref(),
+        pinned: &pinned,
+        posts: &unpinned[..latest.unwrap_or(unpinned.len())],
+        archive_link: latest.is_some(),
+        related: &[],
+        alternates: build_ctx.alternates(None, "index.html"),
+        current: Some(NavPage::Index),
+    };
+    generate_list_page(index, all_tags, build_ctx)?;
+
+    // Every post, when the index has only the latest
+    if latest.is_some() {
+        let archive = ListPage {
+            title: &strings.archive,
+            kind: PageKind::Index,
+            path: RelPath::default().join(ARCHIVE_PAGE),
+            relative_root: "",
+            intro: None,
+            pinned: &[],
+            posts: &post_items,
+            archive_link: false,
+            related: &[],
+            alternates: Vec::new(),
+            current: None,
+        };
+        generate_list_page(archive, all_tags, build_ctx)?;
+    }
+
+    // Generate tag pages
+    let matrix = config.related_tags.map(|limit| (TagMatrix::new(&post_items), limit));
+    for tag in tags.unwrap_or(all_tags) {
+        let tag_posts: Vec<_> = post_items.iter()
+            .filter(|p| p.tags.contains(tag))
+            .cloned()
+            .collect();
+
+        let filename = format!("tag_{}.html", tag.to_lowercase());
+        let title = strings.tag_title(tag.as_str());
+        let source = config.content_dir.join(TAG_SOURCES).join(format!("{}.md", tag.to_lowercase()));
+        let intro = render_standalone(&source, &title, "../", build_ctx, build_result)?;
+        let related = matrix.as_ref().map(|(matrix, limit)| matrix.related(tag, *limit)).unwrap_or_default();
+        let page = ListPage {
+            title: intro.as_ref().map_or(&title, |intro| &intro.title),
+            kind: PageKind::Tag,
+            path: RelPath::default().join("tags").join(&filename),
+            relative_root: "../",
+            intro: intro.as_ref(),
+            pinned: &[],
+            posts: &tag_posts,
+            archive_link: false,
+            related: &related,
+            alternates: Vec::new(),
+            current: Some(NavPage::Tag(tag.clone())),
+        };
+        generate_list_page(page, all_tags, build_ctx)?;
+    }
+
+    // Tags index, whose counts change with any post's tags
+    if !all_tags.is_empty() {
+        let tag_counts: Vec<(Tag, usize)> = all_tags.iter()
+            .map(|tag| (tag.clone(), post_items.iter().filter(|p| p.tags.contains(tag)).count()))
+            .collect();
+        generate_tag_index(&tag_counts, all_tags, build_ctx)?;
+    }
+
+    if tags.is_none() {
+        // Not-found page
+        let mut not_found = vec![config.public_dir.join("404.html")];
+        not_found.extend(tree.root_not_found.clone());
+        generate_not_found(all_tags, build_ctx, &not_found, build_result)?;
+
+        // Blogroll page and its OPML export
+        if let Some(blogroll) = tree.blogroll {
+            generate_links_page(blogroll, all_tags, build_ctx)?;
+        }
+    }
+
+    // Gemtext mirror
+    if config.gemini_dir.is_some() {
+        let posts: Vec<&ParsedPost> = posts.iter().filter(|p| p.password().is_none()).collect();
+        let dates: Vec<String> = posts.iter().map(|p| p.published.format("%Y-%m-%d").to_string()).collect();
+        let posts: Vec<GeminiPost> = posts.iter().zip(&dates)
+            .map(|(p, date)| GeminiPost {
+                stem: &p.file_stem,
+                title: &p.metadata.raw_title,
+                date,
+                content: &p.content,
+            })
+            .collect();
+        gemini::write(config, &posts, outputs)?.into_iter().for_each(|w| build_result.record_warning(w));
+    }
+
+    // Search index
+    if config.search.enabled {
+        let docs: Vec<SearchDoc> = posts.iter()
+            .filter(|p| !p.front.flag("noindex") && p.password().is_none())
+            .map(|p| SearchDoc {
+                title: &p.metadata.raw_title,
+                url: registry::post_path(&p.file_stem).to_string(),
+                date: &p.date,
+                summary: p.summary.as_deref(),
+                text: search::plain_text(&p.content),
+            })
+            .collect();
+        outputs.write(&config.public_dir.join(SEARCH_FILE), search::render_index(&config.search, &docs))?;
+        for export in &config.search.exports {
+            outputs.write(&config.public_dir.join(export.file_name()), export.render(&docs, &format!("/{}", tree.prefix())))?;
+        }
+        if config.search.page {
+            generate_search_page(all_tags, build_ctx)?;
+            if let Some(base_url) = &config.base_url {
+                let page = base_url.join(format!("{}{SEARCH_PAGE}", tree.prefix())).to_string();
+                let description = config.description.as_deref().unwrap_or(&config.brand_name);
+                outputs.write(&config.public_dir.join(OPENSEARCH_FILE), search::render_opensearch(&config.brand_name, description, &page))?;
+            }
+        }
+    }
+
+    // Sitemap entries; list pages change whenever one of their posts does
+    let last_modified = |tag: Option<&Tag>| posts.iter()
+        .filter(|p| tag.is_none_or(|t| p.metadata.tags.contains(t)))
+        .map(|p| p.modified)
+        .max()
+        .map(|d| config.format_iso_date(&d));
+    let prefix = tree.prefix();
+    let mut indexed = vec![SitemapEntry::new(format!("{prefix}index.html"), last_modified(None))];
+    if latest.is_some() {
+        indexed.push(SitemapEntry::new(format!("{prefix}{ARCHIVE_PAGE}"), last_modified(None)));
+    }
+    indexed.extend(posts.iter()
+        .filter(|p| !p.front.flag("noindex"))
+        .map(|p| SitemapEntry::new(format!("{prefix}{}", registry::post_path(&p.file_stem)), Some(config.format_iso_date(&p.modified)))));
+    let mut tag_pages: Vec<SitemapEntry> = all_tags.iter()
+        .map(|t| SitemapEntry::new(format!("{prefix}tags/tag_{}.html", t.to_lowercase()), last_modified(Some(t))))
+        .collect();
+    tag_pages.sort_by(|a, b| a.path.cmp(&b.path));
+    if !all_tags.is_empty() {
+        indexed.push(SitemapEntry::new(format!("{prefix}tags/index.html"), last_modified(None)));
+    }
+    indexed.extend(tag_pages);
+    if tree.blogroll.is_some() {
+        indexed.push(SitemapEntry::new(format!("{prefix}{LINKS_PAGE}"), None));
+    }
+
+    if let Some(base_url) = config.base_url.as_ref().filter(|_| config.feed_enabled()) {
+        write_feeds(base_url, &prefix, posts, attachments, config, outputs)?;
+    }
+    Ok(indexed)
+}
+
+/// Write the Atom and RSS feeds of one content tree, newest posts first.
+fn write_feeds(
+    base_url: &AbsoluteUrl,
+    prefix: &str,
+    posts: &[ParsedPost],
+    attachments: PostAttachments<'_>,
+    config: &Config,
+    outputs: &OutputSet,
+) -> Result<(), BuildError> {
+    let mut newest: Vec<&ParsedPost> = posts.iter().collect();
+    newest.sort_by(|a, b| SortOrder::Desc.compare(
+        (&a.published, a.metadata.title.as_str()),
+        (&b.published, b.metadata.title.as_str()),
+    ));
+    if config.feed.max_items > 0 {
+        newest.truncate(config.feed.max_items);
+    }
+
+    let entries: Vec<FeedEntry> = newest.into_iter()
+        .map(|post| {
+            let path = format!("{prefix}{}", registry::post_path(&post.file_stem));
+            let content = attachments.bodies.get(&post.file_stem)
+                .map(|body| feed::absolutize_urls(body, &base_url.join(&path)));
+            let enclosure = attachments.audio.get(&post.file_stem).map(|audio| Enclosure {
+                url: if audio.is_external() {
+                    audio.src.clone()
+                } else {
+                    base_url.join(format!("{prefix}{}", audio.src)).to_string()
+                },
+                length: audio.length,
+                mime: audio.mime.to_string(),
+            });
+            let aliases = post.front.list("aliases");
+            FeedEntry {
+                title: post.metadata.raw_title.clone(),
+                id: feed::stable_id(post.front.get("id"), &aliases, prefix, base_url),
+                lang: post.lang().map(str::to_string),
+                path,
+                published: post.published,
+                updated: post.revised,
+                summary: post.summary.clone(),
+                content,
+                enclosure,
+            }
+        })
+        .collect();
+
+    let lang = config.html_lang();
+    let meta = FeedMeta {
+        title: &config.brand_name,
+        subtitle: config.description.as_deref(),
+        base_url,
+        prefix,
+        lang: &lang,
+    };
+    outputs.write(&config.public_dir.join(FEED_FILE), feed::render_atom(&meta, &entries))?;
+    outputs.write(&config.public_dir.join(RSS_FILE), feed::render_rss(&meta, &entries))
+}
+
+/// Write `_redirects`, `_headers` and `vercel.json` when there are rules.
+fn write_hosting_files(redirects: &[Redirect], headers: &HeaderRules, config: &Config, outputs: &OutputSet) -> Result<(), BuildError> {
+    if !redirects.is_empty() {
+        outputs.write(&config.public_dir.join(hosting::REDIRECTS_FILE), hosting::render_redirects(redirects))?;
+    }
+    if !headers.is_empty() {
+        outputs.write(&config.public_dir.join(hosting::HEADERS_FILE), hosting::render_headers(headers))?;
+    }
+    if !redirects.is_empty() || !headers.is_empty() {
+        outputs.write(
+            &config.public_dir.join(hosting::VERCEL_FILE),
+            hosting::render_vercel(redirects, headers),
+        )?;
+    }
+    Ok(())
+}
+
+/// Copy a directory tree into `dest`, recording every file. A missing
+/// source directory is not an error.
+fn copy_tree(src: &Path, dest: &Path, outputs: &OutputSet) {
+    let Ok(entries) = fs::read_dir(src) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let target = dest.join(entry.file_name());
+        if path.is_dir() {
+            match outputs.create_dir(&target) {
+                Ok(()) => copy_tree(&path, &target, outputs),
+                Err(e) => warn!("Failed to copy {}: {}", path.display(), e),
+            }
+        } else if let Err(e) = outputs.copy(&path, &target) {
+            warn!("Failed to copy {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Read the change-detection stamp of a source file.
+fn source_stamp(path: &Path, history: Option<&GitHistory>) -> Result<SourceStamp, BuildError> {
+    let metadata = fs::metadata(path).map_err(|e| BuildError::ParseFailed {
+        path: path.to_path_buf(),
+        position: None,
+        message: format!("Failed to read metadata: {}", e),
+    })?;
+
+    let modified = metadata.modified().map_err(|e| BuildError::ParseFailed {
+        path: path.to_path_buf(),
+        position: None,
+        message: format!("Failed to get mtime: {}", e),
+    })?;
+
+    Ok(SourceStamp {
+        modified,
+        len: metadata.len(),
+        git: history.and_then(|h| h.dates(path)),
+    })
+}
+
+/// Parse a single markdown file.
+fn parse_post(path: &Path, stamp: SourceStamp, config: &Config) -> Result<ParsedPost, BuildError> {
+    let file_stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| BuildError::ParseFailed {
+            path: path.to_path_buf(),
+            position: None,
+            message: "Invalid filename".to_string(),
+        })?
+        .to_string();
+
+    let timezone = config.site_timezone()?;
+
+    let content = fs::read_to_string(path).map_err(|e| BuildError::ParseFailed {
+        path: path.to_path_buf(),
+        position: None,
+        message: format!("Failed to read file: {}", e),
+    })?;
+
+    let (front, body) = front_matter::split(&content);
+    let body_line = 1 + content[..content.len() - body.len()].matches('\n').count();
+
+    // Dates: front matter wins (imported posts keep their dates), then git
+    // history, which survives clones; mtime is the last resort
+    // (SOURCE_DATE_EPOCH instead in reproducible builds)
+    let mtime = timezone.localize(config.source_date_epoch.unwrap_or_else(|| stamp.modified.into()));
+    let front_date = |key| {
+        let date = front.date(key, &timezone);
+        if date.is_none() && let Some(value) = front.get(key) {
+            warn!("{}: unrecognized {} {:?}, expected YYYY-MM-DD", path.display(), key, value);
+        }
+        date
+    };
+    let published = front_date("date")
+        .or(stamp.git.map(|git| timezone.localize(git.published)))
+        .unwrap_or(mtime);
+    let updated = front_date("updated");
+    let modified = updated
+        .or(stamp.git.map(|git| timezone.localize(git.modified)))
+        .unwrap_or(published)
+        .max(published);
+    let revised = updated.unwrap_or(published).max(published);
+    let date_str = config.format_date(&published);
+
+    let expanded = include::expand(body, path, &config.content_dir).map_err(|e| e.below_lines(body_line - 1))?;
+    let content = expanded.text;
+    let post_metadata = extract_metadata(&content, &file_stem);
+    // A protected body shows nothing, not even its first paragraph
+    let protected = front.get("protected").is_some_and(|password| !password.is_empty());
+    let summary = match front.get("summary") {
+        Some(summary) => Some(summary.to_string()),
+        None if protected => None,
+        None => Some(post_metadata.excerpt.clone()),
+    }
+    .filter(|s| !s.is_empty());
+
+    // Extract first image URL for LCP preload
+    let first_image_url = extract_first_image(&content).filter(|_| !protected);
+
+    debug!("✓ {} [{}] Tags: {:?}",
+        post_metadata.raw_title,
+        date_str,
+        post_metadata.tags.iter().map(|t| t.as_str()).collect::<Vec<_>>()
+    );
+
+    Ok(ParsedPost {
+        source: path.to_path_buf(),
+        file_stem,
+        front,
+        metadata: post_metadata,
+        published,
+        modified,
+        revised,
+        date: date_str,
+        summary,
+        content,
+        body_line,
+        includes: expanded.dependencies,
+        first_image_url,
+    })
+}
+
+/// Extract first image URL from markdown for LCP preload.
+fn extract_first_image(content: &str) -> Option<String> {
+    // Simple regex-free extraction: find ![...](...) pattern
+    let start = content.find("![")?;
+    let after_alt = content[start..].find("](")?;
+    let url_start = start + after_alt + 2;
+    let url_end = content[url_start..].find(')')?;
+    Some(content[url_start..url_start + url_end].to_string())
+}
+
+/// Render the page of `post`, lint it and write it, with its source when
+/// `post_source` is set.
+fn write_post(post: &ParsedPost, all_tags: &BTreeSet<Tag>, ctx: &BuildContext) -> Result<RenderOutcome, BuildError> {
+    let start = Instant::now();
+    let config = ctx.config;
+    let (post_config, overrides) = ResolvedPostConfig::resolve(config, &post.front, &post.source);
+    let (html, mut rendered, audio) = render_post(post, &post_config, all_tags, ctx)?;
+    rendered.warnings.extend(overrides);
+    let output = config.posts_dir().join(format!("{}.html", post.file_stem));
+    if post_config.config.a11y_lint {
+        rendered.warnings.extend(a11y::lint_markdown(&post.content, &post.source, &post_config.config)
+            .into_iter()
+            .map(|issue| issue.below_lines(post.body_line - 1)));
+        let lang = a11y::expected_lang(config, ctx.language);
+        let lang = post.lang().or(lang.as_deref());
+        rendered.warnings.extend(lang.and_then(|lang| a11y::lint_lang(&html, &output, lang)));
+    }
+    ctx.outputs.write(&output, html)?;
+    if let Some(format) = config.post_source.filter(|_| post.password().is_none()) {
+        let source = config.posts_dir().join(format!("{}.{}", post.file_stem, format.extension()));
+        ctx.outputs.write(&source, &post.content)?;
+    }
+    let keep_body = config.feed_enabled() && config.feed.content == FeedContent::Full && post.password().is_none();
+    Ok(RenderOutcome {
+        output,
+        elapsed: start.elapsed(),
+        warnings: rendered.warnings,
+        images: rendered.images,
+        body: keep_body.then_some(rendered.html),
+        audio,
+    })
+}
+
+/// Record the rendering of `post` in `build_result`, with the tags its
+/// front matter rejected. The post's attachments if it was built.
+fn record_render(
+    post: &ParsedPost,
+    parse_elapsed: Duration,
+    cached: bool,
+    result: Result<RenderOutcome, BuildError>,
+    build_result: &mut BuildResult,
+) -> Option<Attachments> {
+    let mut report = PostReport {
+        source: post.source.clone(),
+        output: None,
+        status: PostStatus::Failed,
+        cached,
+        parse_ms: millis(parse_elapsed),
+        render_ms: 0.0,
+        warnings: post.metadata.rejected_tags.len(),
+    };
+    for (tag, reason, position) in &post.metadata.rejected_tags {
+        let error = BuildError::InvalidTag {
+            tag: tag.clone(),
+            reason,
+            path: Some(post.source.clone()),
+            position: Some(*position),
+        };
+        build_result.record_warning(error.below_lines(post.body_line - 1));
+    }
+
+    let attachments = match result {
+        Ok(rendered) => {
+            report.status = PostStatus::Built;
+            report.output = Some(rendered.output);
+            report.render_ms = millis(rendered.elapsed);
+            report.warnings += rendered.warnings.len();
+            rendered.images.iter().for_each(|img| build_result.record_image(img));
+            rendered.warnings.into_iter().for_each(|w| build_result.record_warning(w));
+            build_result.record_success();
+            Some(Attachments { body: rendered.body, audio: rendered.audio })
+        }
+        Err(e) => {
+            build_result.record_failure(e);
+            None
+        }
+    };
+    build_result.record_post(report);
+    attachments
+}
+
+/// Render a single post with its resolved config to a full HTML page,
+/// plus the rendered body.
+fn render_post(
+    post: &ParsedPost,
+    post_config: &ResolvedPostConfig<'_>,
+    all_tags: &BTreeSet<Tag>,
+    build_ctx: &BuildContext<'_>,
+) -> Result<(String, RenderedMarkdown, Option<Audio>), BuildError> {
+    let config = &*post_config.config;
+    let dated = ResolvedPostConfig { config: Cow::Borrowed(config), published: Some(post.published.date_naive()), ..*post_config };
+    let mut rendered = render_markdown(
+        &post.content,
+        &post.source,
+        &dated,
+        "../",
+        &build_ctx.markdown_env(),
+    )?;
+    rendered.warnings = rendered.warnings.into_iter().map(|w| w.below_lines(post.body_line - 1)).collect();
+
+    // Build render context with CSS and LCP preload
+    let mut ctx = build_ctx.page_context("../");
+    if let Some(ref img_url) = post.first_image_url {
+        // The optimized output, unless the image is external
+        let lcp_url = if video::mime_type(img_url).is_some() {
+            // Videos load nothing until played
+            None
+        } else if img_url.starts_with("http") {
+            Href::parse(img_url).ok()
+        } else {
+            image::output_rel_path(&post_config.image_src(img_url), &config.content_dir).and_then(|path| Href::parse(&path).ok())
+        };
+        if let Some(lcp_url) = lcp_url {
+            ctx = ctx.with_lcp_image(lcp_url);
+        }
+    }
+    for tag in &post.metadata.tags {
+        ctx = ctx.with_body_class(tag_class(tag));
+    }
+    let mut page = PageContext::new(post.metadata.title.clone(), PageKind::Post, "../")
+        .with_noindex(post.front.flag("noindex"));
+    if let Some(lang) = post.lang() {
+        ctx = ctx.with_lang(lang);
+    }
+    if let Some(name) = post.front.get("layout").filter(|name| !name.is_empty() && *name != DEFAULT_LAYOUT) {
+        match build_ctx.layouts.get(name) {
+            Some(layout) => ctx = ctx.with_layout(layout.clone()),
+            None => rendered.warnings.push(BuildError::InvalidLayout {
+                path: post.source.clone(),
+                message: format!("no layout {name:?} in {:?}", config.templates_dir),
+            }),
+        }
+    }
+    if let Some(summary) = &post.summary {
+        page = page.with_description(summary);
+    }
+    let post_path = registry::post_path(&post.file_stem);
+    let page_path = post_path.to_string();
+    if let Some(canonical) = build_ctx.canonical_url(&post_path) {
+        page = page.with_canonical(canonical);
+    }
+    page.breadcrumbs = build_ctx.breadcrumbs(&[(&post.metadata.raw_title, &post_path)], "../");
+    ctx = ctx.with_alternates(build_ctx.alternates(Some(&post.file_stem), &page_path));
+    if let Some(format) = config.post_source.filter(|_| post.password().is_none()) {
+        ctx = ctx.with_source(format!("{}.{}", post.file_stem, format.extension()));
+    }
+
+    // Same-day edits don't count as updates
+    let updated = (post.modified.date_naive() != post.published.date_naive())
+        .then(|| config.format_date(&post.modified));
+    // Permalink and author home, absolute when the site URL is known
+    let prefix = build_ctx.language.map(|code| format!("{code}/")).unwrap_or_default();
+    let (url, home) = match &config.base_url {
+        Some(base) => (base.join(format!("{prefix}{page_path}")).to_string(), base.to_string()),
+        None => (format!("{}.html", post.file_stem), "../index.html".to_string()),
+    };
+    let author = config.indieweb.author_card(&config.brand_name, &home, !config.show_author);
+    if let Some(password) = post.password() {
+        rendered.html = protect::render(&rendered.html, password, &url, &ctx.strings);
+        rendered.scripts.push(format!("../{PROTECT_SCRIPT}"));
+        // The decrypted post may use any rule
+        ctx = ctx.with_purge(None);
+    }
+    ctx = ctx.with_scripts(rendered.scripts.clone());
+    let (date_iso, updated_iso) = (config.format_iso_date(&post.published), config.format_iso_date(&post.modified));
+    let meta = PostMeta {
+        title: &post.metadata.raw_title,
+        heading: config.strip_title,
+        url: &url,
+        relative_root: "../",
+        date: &post.date,
+        date_iso: &date_iso,
+        updated: updated.as_deref(),
+        updated_iso: &updated_iso,
+        tags: &post.metadata.tags,
+        author: &author,
+        show_author: config.show_author,
+        reading_minutes: (config.reading_time && post.password().is_none()).then(|| reading_minutes(&post.content)),
+    };
+    let meta_html = render_post_meta(&meta, &ctx.strings);
+    let audio = match post.front.get("audio").filter(|src| !src.is_empty()) {
+        Some(src) => match audio::attach(src, &config.content_dir, &config.public_dir, build_ctx.outputs) {
+            Ok(audio) => Some(audio),
+            Err(e) if e.is_recoverable() => {
+                rendered.warnings.push(e);
+                None
+            }
+            Err(e) => return Err(e),
+        },
+        None => None,
+    };
+    let player = audio.as_ref().map(|a| a.render_player("../")).unwrap_or_default();
+    let comments = config.comments.as_ref()
+        .filter(|_| !post.front.disabled("comments"))
+        .map(CommentsConfig::render)
+        .unwrap_or_default();
+    let full_content = format!(r#"{}{}<div class="e-content">{}</div>{}"#, meta_html, player, rendered.html, comments);
+    let images = if post.password().is_some() { &[] } else { rendered.images.as_slice() };
+    let image = match social_image(post, post_config, images, &prefix, build_ctx) {
+        Ok(image) => image,
+        Err(e) if e.is_recoverable() => {
+            rendered.warnings.push(e);
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    ctx = ctx.with_article(ArticleMeta {
+        headline: post.metadata.raw_title.clone(),
+        description: post.summary.clone(),
+        published: date_iso.clone(),
+        modified: updated_iso.clone(),
+        lang: post.lang().map_or_else(|| config.html_lang(), str::to_string),
+        url: url.clone(),
+        image,
+    });
+
+    let html = template(&page, &full_content, all_tags, &ctx);
+    Ok((build_ctx.after_render(html), rendered, audio))
+}
+
+/// Preview image of a post: front matter `image:` (optimized like
+/// embedded images), else the first image of the body.
+fn social_image(
+    post: &ParsedPost,
+    post_config: &ResolvedPostConfig<'_>,
+    images: &[OptimizedImage],
+    prefix: &str,
+    build_ctx: &BuildContext<'_>,
+) -> Result<Option<SocialImage>, BuildError> {
+    let config = &*post_config.config;
+    let cover = match post.front.get("image").filter(|src| !src.is_empty()) {
+        Some(src) if src.starts_with("http://") || src.starts_with("https://") => {
+            return Ok(Some(SocialImage { url: src.to_string(), width: 0, height: 0 }));
+        }
+        Some(src) => image::optimize_image(
+            &post_config.image_src(src),
+            &config.content_dir,
+            &config.public_dir,
+            config.image_bounds(),
+            build_ctx.outputs,
+            build_ctx.decode_limit,
+            build_ctx.image_cache,
+        )?,
+        None => match images.first() {
+            Some(image) => image.clone(),
+            None => return Ok(None),
+        },
+    };
+    let url = match (&cover.src, &config.base_url) {
+        (Href::Relative(path), Some(base)) => base.join(format!("{prefix}{path}")).to_string(),
+        (src, _) => src.href("../"),
+    };
+    Ok(Some(SocialImage { url, width: cover.width, height: cover.height }))
+}
+
+/// Generate `404.html` from `content/404.md`, or a built-in page, at
+/// each of `targets`.
+///
+/// Hosts serve it at arbitrary URLs, so links are root-absolute (`/`, or
+/// `/de/` for a language) instead of relative paths.
+fn generate_not_found(
+    all_tags: &BTreeSet<Tag>,
+    build_ctx: &BuildContext<'_>,
+    targets: &[PathBuf],
+    build_result: &mut BuildResult,
+) -> Result<(), BuildError> {
+    let config = build_ctx.config;
+    let source = config.content_dir.join(NOT_FOUND_SOURCE);
+    let site_root = match build_ctx.language {
+        Some(code) => format!("/{code}/"),
+        None => "/".to_string(),
+    };
+
+    let strings = config.ui_strings();
+    let (title, content) = match render_standalone(&source, &strings.not_found, &site_root, build_ctx, build_result)? {
+        Some(page) => (HtmlSafe::escape(&page.title), page.html),
+        None => {
+            let body = format!(
+                r#"<h1>404</h1><p>{} <a href="{}">{}</a>.</p>"#,
+                strings.not_found_text.escape_html(),
+                site_root,
+                strings.back_to_index.escape_html()
+            );
+            (HtmlSafe::escape(&strings.not_found), body)
+        }
+    };
+
+    let ctx = build_ctx.page_context(&site_root);
+    let page = PageContext::new(title, PageKind::Page, site_root).with_noindex(true);
+    let html = build_ctx.after_render(template(&page, &content, all_tags, &ctx));
+    for target in targets {
+        build_ctx.outputs.write(target, html.clone())?;
+    }
+    Ok(())
+}
+
+/// Generate the search page (`search.html`) and its script.
+fn generate_search_page(all_tags: &BTreeSet<Tag>, build_ctx: &BuildContext<'_>) -> Result<(), BuildError> {
+    let config = build_ctx.config;
+    let ctx = build_ctx.page_context("")
+        .with_current(NavPage::Page(RelPath::default().join(SEARCH_PAGE)))
+        .with_scripts(vec![SEARCH_SCRIPT.to_string()]);
+    let page = PageContext::new(HtmlSafe::escape(&ctx.strings.search), PageKind::Page, "").with_noindex(true);
+    let content = search::render_page(&ctx.strings);
+    let html = build_ctx.after_render(template(&page, &content, all_tags, &ctx));
+    build_ctx.outputs.write(&config.public_dir.join(SEARCH_PAGE), html)?;
+    build_ctx.outputs.write(&config.public_dir.join(SEARCH_SCRIPT), SEARCH_JS)
+}
+
+/// A markdown file of the content directory rendered as part of a page
+/// other than a post.
+struct Standalone {
+    /// Plain text: the first H1, else the fallback title.
+    title: String,
+    /// Whether the markdown has its own H1.
+    has_heading: bool,
+    /// Plain text: front matter `description:`, else the first paragraph.
+    description: Option<String>,
+    html: String,
+}
+
+/// Render `source`, if it exists, for a page whose links are relative
+/// to `relative_root`. Include errors and render warnings are recorded.
+fn render_standalone(
+    source: &Path,
+    fallback_title: &str,
+    relative_root: &str,
+    build_ctx: &BuildContext<'_>,
+    build_result: &mut BuildResult,
+) -> Result<Option<Standalone>, BuildError> {
+    let Ok(markdown) = fs::read_to_string(source) else {
+        return Ok(None);
+    };
+    let config = build_ctx.config;
+    let (front, body) = front_matter::split(&markdown);
+    let body = match include::expand(body, source, &config.content_dir) {
+        Ok(expanded) => expanded.text,
+        Err(e) => {
+            build_result.record_warning(e);
+            body.to_string()
+        }
+    };
+    let metadata = extract_metadata(&body, fallback_title);
+    let rendered = render_markdown(
+        &body,
+        source,
+        &ResolvedPostConfig::site(config),
+        relative_root,
+        &build_ctx.markdown_env(),
+    )?;
+    rendered.warnings.into_iter().for_each(|w| build_result.record_warning(w));
+    Ok(Some(Standalone {
+        title: metadata.raw_title,
+        has_heading: body.lines().any(|line| line.starts_with("# ")),
+        description: Some(front.get("description").map_or(metadata.excerpt, str::to_string))
+            .filter(|description| !description.is_empty()),
+        html: rendered.html,
+    }))
+}
+
+/// Generate the blogroll page and its OPML export at the tree root.
+fn generate_links_page(
+    blogroll: &Blogroll,
+    all_tags: &BTreeSet<Tag>,
+    build_ctx: &BuildContext<'_>,
+) -> Result<(), BuildError> {
+    let config = build_ctx.config;
+    let ctx = build_ctx.page_context("").with_alternates(build_ctx.alternates(None, LINKS_PAGE));
+    let title = HtmlSafe::escape(&ctx.strings.links);
+    let content = format!("<h1>{}</h1>{}", title, blogroll.render_list());
+    let path = RelPath::default().join(LINKS_PAGE);
+    let mut page = PageContext::new(title, PageKind::Page, "");
+    if let Some(canonical) = build_ctx.canonical_url(&path) {
+        page = page.with_canonical(canonical);
+    }
+    page.breadcrumbs = build_ctx.breadcrumbs(&[(&ctx.strings.links, &path)], "");
+    let html = build_ctx.after_render(template(&page, &content, all_tags, &ctx));
+    build_ctx.outputs.write(&config.public_dir.join(LINKS_PAGE), html)?;
+
+    let opml_title = format!("{} | {}", config.brand_name, ctx.strings.links);
+    build_ctx.outputs.write(&config.public_dir.join(OPML_FILE), blogroll.render_opml(&opml_title))
+}
+
+/// A list page (index or tag page).
+struct ListPage<'a> {
+    title: &'a str,
+    kind: PageKind,
+    /// Page path below the tree root.
+    path: RelPath,
+    relative_root: &'a str,
+    /// Rendered `_index.md` or tag description, shown above the lists
+    /// (and instead of the heading if it has its own).
+    intro: Option<&'a Standalone>,
+    /// Shown in their own block above `posts`.
+    pinned: &'a [PostListItem],
+    posts: &'a [PostListItem],
+    /// Link to the archive page below `posts`.
+    archive_link: bool,
+    /// Tags sharing posts with the page's tag, and how many.
+    related: &'a [(Tag, usize)],
+    alternates: Vec<Alternate>,
+    current: Option<NavPage>,
+}
+
+/// Tags the nav shows with `nav_tags`: as many as that of `tags` (every
+/// post's), those on the most posts first; None when that is all.
+fn nav_tags<'t>(config: &Config, tags: impl Iterator<Item = &'t Tag>) -> Option<BTreeSet<Tag>> {
+    let limit = config.nav_tags?;
+    let mut counts: BTreeMap<&Tag, usize> = BTreeMap::new();
+    for tag in tags {
+        *counts.entry(tag).or_insert(0) += 1;
+    }
+    if counts.len() <= limit {
+        return None;
+    }
+    let mut counts: Vec<(&Tag, usize)> = counts.into_iter().collect();
+    // Stable: ties keep tag order
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    Some(counts.into_iter().take(limit).map(|(tag, _)| tag.clone()).collect())
+}
+
+/// How many posts each pair of tags shares.
+struct TagMatrix(HashMap<Tag, BTreeMap<Tag, usize>>);
+
+impl TagMatrix {
+    fn new(posts: &[PostListItem]) -> Self {
+        let mut matrix: HashMap<Tag, BTreeMap<Tag, usize>> = HashMap::new();
+        for post in posts {
+            for tag in &post.tags {
+                let row = matrix.entry(tag.clone()).or_default();
+                for other in post.tags.iter().filter(|other| *other != tag) {
+                    *row.entry(other.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+        Self(matrix)
+    }
+
+    /// Up to `limit` tags sharing posts with `tag`, most shared first.
+    fn related(&self, tag: &Tag, limit: usize) -> Vec<(Tag, usize)> {
+        let mut related: Vec<(Tag, usize)> = self.0.get(tag)
+            .map(|row| row.iter().map(|(other, &count)| (other.clone(), count)).collect())
+            .unwrap_or_default();
+        // Stable: ties keep tag order
+        related.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        related.truncate(limit);
+        related
+    }
+}
+
+/// Generate a list page.
+fn generate_list_page(
+    page: ListPage<'_>,
+    all_tags: &BTreeSet<Tag>,
+    build_ctx: &BuildContext<'_>,
+) -> Result<(), BuildError> {
+    let relative_root = page.relative_root;
+    let mut ctx = build_ctx.page_context(relative_root).with_alternates(page.alternates);
+    if let Some(current) = &page.current {
+        if let NavPage::Tag(tag) = current {
+            ctx = ctx.with_body_class(tag_class(tag));
+        }
+        ctx = ctx.with_current(current.clone());
+    }
+    let safe_title = HtmlSafe::escape(page.title);
+    let mut page_ctx = PageContext::new(safe_title.clone(), page.kind, relative_root);
+    let description = page.intro.and_then(|intro| intro.description.as_ref());
+    if let Some(description) = description.or(build_ctx.config.description.as_ref()) {
+        page_ctx = page_ctx.with_description(description);
+    }
+    if let Some(canonical) = build_ctx.canonical_url(&page.path) {
+        page_ctx = page_ctx.with_canonical(canonical);
+    }
+    // The index is the start of every trail
+    if page.current != Some(NavPage::Index) {
+        let tags = RelPath::default().join("tags").join("index.html");
+        let mut trail = vec![(page.title, &page.path)];
+        if page.kind == PageKind::Tag {
+            trail.insert(0, (ctx.strings.tags.as_str(), &tags));
+        }
+        page_ctx.breadcrumbs = build_ctx.breadcrumbs(&trail, relative_root);
+    }
+
+    let mut content = match page.intro {
+        Some(intro) if intro.has_heading => String::new(),
+        _ => format!("<h1>{}</h1>", safe_title),
+    };
+    if let Some(intro) = page.intro {
+        content.push_str(&format!(r#"<section class="list-intro">{}</section>"#, intro.html));
+    }
+    if !page.pinned.is_empty() {
+        content.push_str(&format!(
+            r#"<section class="pinned-posts"><h2>{}</h2>{}</section>"#,
+            ctx.strings.pinned.escape_html(),
+            render_post_list(page.pinned, relative_root, &ctx.strings)
+        ));
+    }
+    content.push_str(&render_post_archive(page.posts, relative_root, &ctx.strings));
+    if page.archive_link {
+        content.push_str(&format!(
+            r#"<p class="archive-link"><a href="{relative_root}{ARCHIVE_PAGE}">{} →</a></p>"#,
+            ctx.strings.archive.escape_html()
+        ));
+    }
+    if !page.related.is_empty() {
+        content.push_str(&render_related_tags(page.related, relative_root, &ctx.strings));
+    }
+
+    let html = build_ctx.after_render(template(&page_ctx, &content, all_tags, &ctx));
+    build_ctx.outputs.write(&build_ctx.config.public_dir.join(page.path.as_str()), html)
+}
+
+/// Generate the tags index (`tags/index.html`).
+fn generate_tag_index(
+    tag_counts: &[(Tag, usize)],
+    all_tags: &BTreeSet<Tag>,
+    build_ctx: &BuildContext<'_>,
+) -> Result<(), BuildError> {
+    let ctx = build_ctx.page_context("../").with_current(NavPage::Tags);
+    let title = HtmlSafe::escape(&ctx.strings.tags);
+    let content = format!("<h1>{}</h1>{}", title, render_tag_index(tag_counts));
+    let path = RelPath::default().join("tags").join("index.html");
+    let mut page = PageContext::new(title, PageKind::Tag, "../");
+    if let Some(canonical) = build_ctx.canonical_url(&path) {
+        page = page.with_canonical(canonical);
+    }
+    page.breadcrumbs = build_ctx.breadcrumbs(&[(&ctx.strings.tags, &path)], "../");
+    let html = build_ctx.after_render(template(&page, &content, all_tags, &ctx));
+    build_ctx.outputs.write(&build_ctx.config.tags_dir().join("index.html"), html)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A site in `dir`: its content directory, created, its public
+    /// directory and the config that builds one into the other.
+    pub(crate) fn test_site(dir: &Path) -> (PathBuf, PathBuf, Config) {
+        let (content, public) = (dir.join("content"), dir.join("public"));
+        fs::create_dir(&content).unwrap();
+        let config = Config::new()
+            .content_dir(&content)
+            .public_dir(&public)
+            .static_dir(dir.join("static"))
+            .data_dir(dir.join("data"));
+        (content, public, config)
+    }
+
+    #[test]
+    fn rebuild_post_renders_only_the_changed_post() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("a.md"), "# A\n\nTags: rust\n\nFirst.\n").unwrap();
+        fs::write(content.join("b.md"), "# B\n\nTags: web\n\nSecond.\n").unwrap();
+        let mut site = Site::new(config);
+        assert_eq!(site.build().unwrap().posts.len(), 2);
+
+        fs::write(content.join("a.md"), "# A\n\nTags: rust\n\nEdited.\n").unwrap();
+        let summary = site.rebuild_post(&content.join("a.md")).unwrap();
+        assert_eq!(summary.posts.len(), 1);
+        assert!(fs::read_to_string(public.join("posts/a.html")).unwrap().contains("Edited."));
+        assert!(fs::read_to_string(public.join("index.html")).unwrap().contains("posts/b.html"));
+
+        // A tag new to the site shows up in every page's nav
+        fs::write(content.join("a.md"), "# A\n\nTags: rust, cli\n\nEdited.\n").unwrap();
+        let summary = site.rebuild_post(&content.join("a.md")).unwrap();
+        assert_eq!(summary.posts.len(), 2);
+        let cli = fs::read_to_string(public.join("tags/tag_cli.html")).unwrap();
+        assert!(cli.contains(r#"class="nav-link tag-link active" aria-current="page">cli</a>"#));
+    }
+
+    #[test]
+    fn rebuild_post_reports_invalid_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, _, config) = test_site(dir.path());
+        fs::write(content.join("a.md"), "# A\n\nTags: rust\n\nFirst.\n").unwrap();
+        let mut site = Site::new(config);
+        assert!(site.build().unwrap().warnings.is_empty());
+
+        // The site's tags stay the same, so only this post is rendered
+        fs::write(content.join("a.md"), "# A\n\nTags: rust, a/b\n\nEdited.\n").unwrap();
+        let summary = site.rebuild_post(&content.join("a.md")).unwrap();
+        assert_eq!(summary.posts.len(), 1);
+        assert_eq!(summary.posts[0].warnings, 1);
+        let categories: Vec<_> = summary.warnings.iter().map(BuildError::category).collect();
+        assert_eq!(categories, ["invalid_tag"]);
+        assert_eq!(summary.exit_code(true), crate::error::EXIT_PARTIAL_FAILURE);
+    }
+
+    #[test]
+    fn languages_build_into_their_own_trees() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::create_dir_all(content.join("en")).unwrap();
+        fs::create_dir_all(content.join("de")).unwrap();
+        fs::write(content.join("en/hello.md"), "---\ndate: 2024-05-01\n---\n# Hello\n\nQuack.\n").unwrap();
+        fs::write(content.join("en/only.md"), "---\ndate: 2024-05-02\n---\n# Only\n\nEnglish.\n").unwrap();
+        fs::write(content.join("de/hello.md"), "---\ndate: 2024-05-01\n---\n# Hallo\n\nQuak.\n").unwrap();
+        let config = config
+            .language(crate::i18n::Language::new("en", chrono::Locale::en_US))
+            .language(crate::i18n::Language::new("de", chrono::Locale::de_DE));
+        Site::new(config).build().unwrap();
+
+        assert!(public.join("en/index.html").exists());
+        assert!(public.join("de/index.html").exists());
+        assert!(!public.join("de/posts/only.html").exists());
+        let root = fs::read_to_string(public.join("index.html")).unwrap();
+        assert!(root.contains(r#"content="0; url=en/index.html""#));
+
+        // Translations share a slug; the page of each links the other
+        let hello = fs::read_to_string(public.join("en/posts/hello.html")).unwrap();
+        assert!(hello.contains(r#"<link rel="alternate" hreflang="en" href="/en/posts/hello.html">"#));
+        assert!(hello.contains(r#"<link rel="alternate" hreflang="de" href="/de/posts/hello.html">"#));
+        assert!(hello.contains(r#"href="../../style.css""#));
+        let hallo = fs::read_to_string(public.join("de/posts/hello.html")).unwrap();
+        assert!(hallo.contains("Quak.") && hallo.contains(r#"hreflang="en" href="/en/posts/hello.html""#));
+        let only = fs::read_to_string(public.join("en/posts/only.html")).unwrap();
+        assert!(!only.contains("hreflang"));
+
+        // Hosts serve the root 404 page, in the default language
+        let not_found = fs::read_to_string(public.join("404.html")).unwrap();
+        assert!(not_found.contains(r#"href="/style.css""#));
+    }
+
+    #[test]
+    fn not_found_page_links_from_the_site_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("a.md"), "---\ndate: 2024-05-01\n---\n# A\n\nTags: rust\n\nQuack.\n").unwrap();
+        let mut site = Site::new(config);
+        site.build().unwrap();
+
+        // Hosts serve it at any URL, so nothing links relative to it
+        let built_in = fs::read_to_string(public.join("404.html")).unwrap();
+        assert!(built_in.contains("<title>CODE A DUCK | Not Found</title>"));
+        assert!(built_in.contains(r#"<meta name="robots" content="noindex">"#));
+        assert!(built_in.contains(r#"<a href="/">Back to the index</a>"#));
+        assert!(built_in.contains(r#"<link rel="stylesheet" href="/style.css">"#));
+        assert!(built_in.contains(r#"<a href="/index.html" class="nav-link main-link">"#));
+        assert!(built_in.contains(r#"<a href="/tags/tag_rust.html" class="nav-link tag-link">"#));
+
+        fs::write(content.join("404.md"), "# Lost\n\nHere is ![a duck](duck.png).\n").unwrap();
+        let summary = site.build().unwrap();
+        let not_found = fs::read_to_string(public.join("404.html")).unwrap();
+        assert!(not_found.contains("<title>CODE A DUCK | Lost</title>"));
+        assert!(not_found.contains(r#"<img src="/duck.png" alt="a duck""#));
+        assert!(!not_found.contains("Back to the index"));
+
+        // It is a page of its own, not a post
+        assert_eq!(summary.posts.len(), 1);
+        assert!(!public.join("posts/404.html").exists());
+        assert!(!fs::read_to_string(public.join("index.html")).unwrap().contains("Lost"));
+
+        // Without a heading, the title is the locale's
+        fs::write(content.join("404.md"), "Hier ist nichts.\n").unwrap();
+        Site::new(site.config.clone().locale(chrono::Locale::de_DE)).build().unwrap();
+        let not_found = fs::read_to_string(public.join("404.html")).unwrap();
+        assert!(not_found.contains("<title>CODE A DUCK | Nicht gefunden</title>"));
+    }
+
+    #[test]
+    fn index_intro_comes_from_index_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("a.md"), "---\ndate: 2024-05-01\n---\n# A\n\nQuack.\n").unwrap();
+        fs::write(content.join("_index.md"), "# Welcome\n\nHello, ducks.\n").unwrap();
+        let mut site = Site::new(config);
+        let summary = site.build().unwrap();
+        assert_eq!(summary.posts.len(), 1);
+        assert!(!public.join("posts/_index.html").exists());
+
+        // Its heading replaces the page's own
+        let index = fs::read_to_string(public.join("index.html")).unwrap();
+        assert!(index.contains("<title>CODE A DUCK | Welcome</title>"));
+        assert!(index.contains(r#"<section class="list-intro"><h1 id="welcome">Welcome</h1>"#));
+        assert!(index.contains("<p>Hello, ducks.</p>"));
+        assert_eq!(index.matches("<h1").count(), 1);
+        assert!(index.contains("posts/a.html"));
+
+        fs::write(content.join("_index.md"), "Hello, ducks.\n").unwrap();
+        site.build().unwrap();
+        let index = fs::read_to_string(public.join("index.html")).unwrap();
+        assert!(index.contains(r#"<h1>Index</h1><section class="list-intro"><p>Hello, ducks.</p>"#));
+        assert_eq!(index.matches("<h1").count(), 1);
+    }
+
+    #[test]
+    fn tag_pages_are_described_by_tag_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::create_dir(content.join("tags")).unwrap();
+        fs::write(content.join("a.md"), "---\ndate: 2024-05-01\n---\n# A\n\nTags: Rust, web, cli\n\nQuack.\n").unwrap();
+        fs::write(content.join("tags/rust.md"), "All about **Rust**.\n").unwrap();
+        fs::write(content.join("tags/web.md"), "---\ndescription: Pages and feeds\n---\n# The web\n\nBrowsers, mostly.\n").unwrap();
+        let config = config.description("A duck's blog");
+        let summary = Site::new(config).build().unwrap();
+        assert_eq!(summary.posts.len(), 1);
+        assert!(!public.join("posts/rust.html").exists());
+
+        // The intro's first paragraph describes the page
+        let rust = fs::read_to_string(public.join("tags/tag_rust.html")).unwrap();
+        assert!(rust.contains(r#"<section class="list-intro"><p>All about <strong>Rust</strong>.</p>"#));
+        assert!(rust.contains(r#"<meta name="description" content="All about Rust.">"#));
+        assert!(rust.contains("posts/a.html"));
+
+        // Front matter wins over the intro
+        let web = fs::read_to_string(public.join("tags/tag_web.html")).unwrap();
+        assert!(web.contains(r#"<meta name="description" content="Pages and feeds">"#));
+        assert!(web.contains("<title>CODE A DUCK | The web</title>"));
+        assert_eq!(web.matches("<h1").count(), 1);
+
+        // Without a source, the site's description
+        let cli = fs::read_to_string(public.join("tags/tag_cli.html")).unwrap();
+        assert!(cli.contains(r#"<meta name="description" content="A duck&#x27;s blog">"#));
+        assert!(!cli.contains("list-intro"));
+    }
+
+    #[test]
+    fn pinned_posts_lead_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("old.md"), "---\ndate: 2020-05-01\npinned: true\n---\n# Old\n\nTags: rust\n\nQuack.\n").unwrap();
+        fs::write(content.join("new.md"), "---\ndate: 2024-05-01\n---\n# New\n\nTags: rust\n\nQuack.\n").unwrap();
+        Site::new(config).build().unwrap();
+
+        let index = fs::read_to_string(public.join("index.html")).unwrap();
+        let (_, pinned) = index.split_once(r#"<section class="pinned-posts"><h2>Pinned</h2>"#).unwrap();
+        let (pinned, rest) = pinned.split_once("</section>").unwrap();
+        assert!(pinned.contains(r#"<div class="post-entry pinned"><span class="entry-title"><span class="pin-marker" title="Pinned">📌</span> <a href="posts/old.html">"#));
+        assert!(!pinned.contains("posts/new.html"));
+        assert!(rest.contains("posts/new.html"));
+        assert!(!rest.contains("posts/old.html"));
+
+        // Other lists keep it in date order, marked
+        let tag = fs::read_to_string(public.join("tags/tag_rust.html")).unwrap();
+        assert!(!tag.contains("pinned-posts"));
+        assert!(tag.find("posts/new.html").unwrap() < tag.find("posts/old.html").unwrap());
+        assert!(tag.contains(r#"<div class="post-entry pinned">"#));
+    }
+
+    #[test]
+    fn links_out_of_the_content_directory_are_broken() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("site")).unwrap();
+        let (content, public, config) = test_site(&dir.path().join("site"));
+        fs::create_dir(content.join("a")).unwrap();
+        fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        fs::write(content.join("a.md"), "# A\n\n[Secret](a/../../../secret.txt)\n").unwrap();
+        let summary = Site::new(config).build().unwrap();
+
+        assert!(!dir.path().join("site/secret.txt").exists());
+        assert!(!public.join("files").exists());
+        let broken: Vec<_> = summary.warnings.iter()
+            .filter_map(|w| match w {
+                BuildError::BrokenLink { target, .. } => Some(target.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(broken, ["a/../../../secret.txt"]);
+        let post = fs::read_to_string(public.join("posts/a.html")).unwrap();
+        assert!(post.contains(r#"<a href="a/../../../secret.txt">Secret</a>"#));
+    }
+
+    #[test]
+    fn index_lists_latest_posts_and_links_the_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        for (stem, date) in [("a", "2024-05-01"), ("b", "2024-05-02"), ("c", "2024-05-03")] {
+            fs::write(content.join(format!("{stem}.md")), format!("---\ndate: {date}\n---\n# {stem}\n")).unwrap();
+        }
+        let config = config.index_posts(2);
+        Site::new(config.clone()).build().unwrap();
+        let index = fs::read_to_string(public.join("index.html")).unwrap();
+        assert!(index.contains("posts/c.html") && index.contains("posts/b.html") && !index.contains("posts/a.html"));
+        assert!(index.contains(r#"<p class="archive-link"><a href="archive.html">Archive →</a></p>"#));
+        let archive = fs::read_to_string(public.join(ARCHIVE_PAGE)).unwrap();
+        assert_eq!(archive.matches("post-entry").count(), 3);
+
+        // Fewer posts than the limit: no archive
+        fs::remove_file(content.join("a.md")).unwrap();
+        Site::new(config).build().unwrap();
+        assert!(!fs::read_to_string(public.join("index.html")).unwrap().contains("archive-link"));
+        assert!(!public.join(ARCHIVE_PAGE).exists());
+    }
+
+    #[test]
+    fn search_page_is_linked_and_described_for_browsers() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("a.md"), "---\ndate: 2024-05-01\n---\n# Ducks\n\nQuack.\n").unwrap();
+        let config = config
+            .base_url(AbsoluteUrl::new("https://duck.dev/blog/").unwrap())
+            .search(search::SearchConfig { enabled: true, ..Default::default() });
+        Site::new(config).build().unwrap();
+
+        let page = fs::read_to_string(public.join(SEARCH_PAGE)).unwrap();
+        assert!(page.contains(r#"<form class="search-form" role="search" action="search.html">"#));
+        assert!(page.contains(r#"<a href="search.html" class="nav-link page-link active" aria-current="page">Search</a>"#));
+        assert!(page.contains(r#"<script src="search.js" defer></script>"#));
+        assert!(public.join(SEARCH_SCRIPT).exists());
+
+        let post = fs::read_to_string(public.join("posts/a.html")).unwrap();
+        assert!(post.contains(r#"<a href="../search.html" class="nav-link page-link">Search</a>"#));
+        assert!(post.contains(r#"<link rel="search" type="application/opensearchdescription+xml" title="CODE A DUCK" href="../opensearch.xml">"#));
+        let opensearch = fs::read_to_string(public.join(OPENSEARCH_FILE)).unwrap();
+        assert!(opensearch.contains(r#"template="https://duck.dev/blog/search.html?q={searchTerms}"/>"#));
+    }
+
+    #[test]
+    fn nav_tags_are_the_most_used() {
+        let tags: Vec<Tag> = ["web", "rust", "go", "rust", "cli", "web"].into_iter().map(|t| Tag::new(t).unwrap()).collect();
+        let shown = nav_tags(&Config::new().nav_tags(2), tags.iter()).unwrap();
+        assert_eq!(shown.iter().map(Tag::as_str).collect::<Vec<_>>(), ["rust", "web"]);
+        assert_eq!(nav_tags(&Config::new().nav_tags(4), tags.iter()), None);
+        assert_eq!(nav_tags(&Config::new(), tags.iter()), None);
+    }
+
+    #[test]
+    fn tag_pages_list_related_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        for (stem, tags) in [("a", "rust, web"), ("b", "rust, cli"), ("c", "rust, cli"), ("d", "go"), ("e", "web")] {
+            fs::write(content.join(format!("{stem}.md")), format!("---\ndate: 2024-05-01\n---\n# {stem}\n\nTags: {tags}\n")).unwrap();
+        }
+        let config = config.related_tags(1);
+        Site::new(config).build().unwrap();
+        let rust = fs::read_to_string(public.join("tags/tag_rust.html")).unwrap();
+        assert!(rust.contains(
+            r#"<nav class="related-tags" aria-label="Related tags"><h2>Related tags</h2><ul><li><a class="tag" href="../tags/tag_cli.html" rel="tag">#cli</a> <span class="tag-count">(2)</span></li></ul></nav>"#
+        ));
+        // Ties keep tag order
+        let web = fs::read_to_string(public.join("tags/tag_web.html")).unwrap();
+        assert!(web.contains(r#"<ul><li><a class="tag" href="../tags/tag_rust.html" rel="tag">#rust</a> <span class="tag-count">(1)</span></li></ul>"#));
+        let go = fs::read_to_string(public.join("tags/tag_go.html")).unwrap();
+        assert!(!go.contains("related-tags"));
+    }
+
+    #[test]
+    fn pages_link_their_canonical_url_and_breadcrumbs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("a.md"), "---\ndate: 2024-05-01\nnoindex: true\n---\n# A\n\nTags: rust\n").unwrap();
+        let config = config.base_url(AbsoluteUrl::new("https://duck.dev").unwrap());
+        Site::new(config).build().unwrap();
+        let post = fs::read_to_string(public.join("posts/a.html")).unwrap();
+        assert!(post.contains(r#"<meta name="robots" content="noindex">"#));
+        assert!(post.contains(r#"<link rel="canonical" href="https://duck.dev/posts/a.html">"#));
+        let tag = fs::read_to_string(public.join("tags/tag_rust.html")).unwrap();
+        assert!(tag.contains(r#"<link rel="canonical" href="https://duck.dev/tags/tag_rust.html">"#));
+        assert!(tag.contains(concat!(
+            r#""itemListElement":[{"@type":"ListItem","item":"https://duck.dev/index.html","name":"Index","position":1},"#,
+            r#"{"@type":"ListItem","item":"https://duck.dev/tags/index.html","name":"Tags","position":2},"#,
+        )));
+        let index = fs::read_to_string(public.join("index.html")).unwrap();
+        assert!(!index.contains("BreadcrumbList"));
+        assert!(index.contains(r#"<body class="page-index">"#));
+        assert!(post.contains(r#"<body class="page-post tag-rust">"#));
+        assert!(tag.contains(r#"<body class="page-tag tag-rust">"#));
+    }
+
+    #[test]
+    fn purge_leaves_out_unused_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        let static_dir = dir.path().join("static");
+        fs::create_dir(&static_dir).unwrap();
+        fs::write(content.join("a.md"), "---\ndate: 2024-05-01\n---\n# A\n\nTags: rust\n").unwrap();
+        fs::write(static_dir.join("style.css"), ".tag-rust { color: red; }\n.page-index .post-list { margin: 0; }\n.unused { color: blue; }\n").unwrap();
+        let config = || config.clone().purge(crate::purge::PurgeConfig { enabled: true, safelist: Vec::new() });
+
+        Site::new(config()).build().unwrap();
+        let post = fs::read_to_string(public.join("posts/a.html")).unwrap();
+        assert!(post.contains("<style>.tag-rust { color: red; }\n</style>"));
+        let index = fs::read_to_string(public.join("index.html")).unwrap();
+        assert!(index.contains("<style>.page-index .post-list { margin: 0; }\n</style>"));
+
+        Site::new(Config { inline_css: false, ..config() }).build().unwrap();
+        let css = fs::read_to_string(public.join("style.css")).unwrap();
+        assert_eq!(css, ".tag-rust { color: red; }\n.page-index .post-list { margin: 0; }\n");
+    }
+
+    #[test]
+    fn fonts_are_preloaded_from_the_site_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        let static_dir = dir.path().join("static");
+        fs::create_dir_all(static_dir.join("fonts")).unwrap();
+        fs::write(content.join("a.md"), "---\ndate: 2024-05-01\n---\n# A\n\nQuack.\n").unwrap();
+        fs::write(static_dir.join("fonts/body.woff2"), "font").unwrap();
+        fs::write(static_dir.join("fonts/body.ttf"), "font").unwrap();
+        let config = config.fonts(crate::fonts::FontsConfig { preload: true, ..Default::default() });
+        Site::new(config).build().unwrap();
+        let post = fs::read_to_string(public.join("posts/a.html")).unwrap();
+        assert!(post.contains(r#"<link rel="preload" as="font" href="../fonts/body.woff2" type="font/woff2" crossorigin>"#));
+        assert!(!post.contains("body.ttf"));
+        assert!(public.join("fonts/body.ttf").exists());
+    }
+
+    #[test]
+    fn front_matter_dates_win_over_git() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("imported.md"), "---\ndate: 2015-06-01\n---\n# Imported\n\nQuack.\n").unwrap();
+        fs::write(content.join("new.md"), "# New\n\nQuack.\n").unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C").arg(dir.path())
+                .args(["-c", "user.name=Duck", "-c", "user.email=duck@example.com"])
+                .args(args)
+                .env("GIT_AUTHOR_DATE", "2024-05-01T10:00:00Z")
+                .status();
+            status.is_ok_and(|s| s.success())
+        };
+        if !(git(&["init", "-q"]) && git(&["add", "."]) && git(&["commit", "-q", "-m", "Import"])) {
+            return; // no git
+        }
+        Site::new(config).build().unwrap();
+        let imported = fs::read_to_string(public.join("posts/imported.html")).unwrap();
+        assert!(imported.contains(r#""datePublished":"2015-06-01"#));
+        let new = fs::read_to_string(public.join("posts/new.html")).unwrap();
+        assert!(new.contains(r#""datePublished":"2024-05-01"#));
+    }
+
+    #[test]
+    fn strip_title_moves_the_title_to_the_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("a.md"), "---\ndate: 2024-05-01\n---\n# Ducks & geese\n\nQuack.\n").unwrap();
+        let config = config.strip_title(true);
+        Site::new(config).build().unwrap();
+        let page = fs::read_to_string(public.join("posts/a.html")).unwrap();
+        assert!(page.contains(r#"<h1 class="p-name">Ducks &amp; geese</h1><div class="meta">"#));
+        assert_eq!(page.matches("<h1").count(), 1);
+        assert!(page.contains(r#"<div class="e-content"><p>Quack.</p>"#));
+    }
+
+    #[test]
+    fn protected_posts_keep_their_body_out_of_pages_and_feeds() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("secret.md"), "---\nprotected: quack\n---\n# Secret\n\nMembers only.\n").unwrap();
+        let config = config.base_url(AbsoluteUrl::new("https://duck.dev").unwrap());
+        Site::new(config).build().unwrap();
+
+        let page = fs::read_to_string(public.join("posts/secret.html")).unwrap();
+        assert!(page.contains(r#"<div class="protected" data-salt=""#));
+        assert!(page.contains(r#"<script src="../protect.js" defer></script>"#));
+        assert!(public.join(PROTECT_SCRIPT).exists());
+        for file in ["posts/secret.html", "index.html", "atom.xml", "rss.xml"] {
+            assert!(!fs::read_to_string(public.join(file)).unwrap().contains("Members only"), "{file}");
+        }
+    }
+
+    #[test]
+    fn feed_ids_survive_renames() {
+        let dir = tempfile::tempdir().unwrap();
+        let (content, public, config) = test_site(dir.path());
+        fs::write(content.join("old-name.md"), "---\ndate: 2024-05-04\n---\n# A\n\nFirst.\n").unwrap();
+        let config = config.base_url(AbsoluteUrl::new("https://duck.dev").unwrap());
+        let guid = r#"<guid isPermaLink="true">https://duck.dev/posts/old-name.html</guid>"#;
+        Site::new(config.clone()).build().unwrap();
+        assert!(fs::read_to_string(public.join("rss.xml")).unwrap().contains(guid));
+
+        // Renamed, with the old permalink as an alias: same id, not updated
+        fs::remove_file(content.join("old-name.md")).unwrap();
+        fs::write(
+            content.join("new-name.md"),
+            "---\ndate: 2024-05-04\naliases: [/old, /posts/old-name.html]\n---\n# A\n\nEdited.\n",
+        ).unwrap();
+        Site::new(config.clone()).build().unwrap();
+        let rss = fs::read_to_string(public.join("rss.xml")).unwrap();
+        assert!(rss.contains(r#"<guid isPermaLink="false">https://duck.dev/posts/old-name.html</guid>"#));
+        assert!(rss.contains("<link>https://duck.dev/posts/new-name.html</link>"));
+        let atom = fs::read_to_string(public.join("atom.xml")).unwrap();
+        assert!(atom.contains("<id>https://duck.dev/posts/old-name.html</id>"));
+        assert!(atom.contains("    <updated>2024-05-04T00:00:00+08:00</updated>"));
+
+        // An explicit id wins
+        fs::write(content.join("new-name.md"), "---\ndate: 2024-05-04\nid: urn:uuid:1234\n---\n# A\n").unwrap();
+        Site::new(config).build().unwrap();
+        assert!(fs::read_to_string(public.join("rss.xml")).unwrap().contains(r#"<guid isPermaLink="false">urn:uuid:1234</guid>"#));
+    }
+}
@@ -0,0 +1,102 @@
+//! `sitemap.xml` and `robots.txt`.
+//!
+//! Both need absolute URLs, so the sitemap (and the `Sitemap:` line in
+//! `robots.txt`) are only written when `Config::base_url` is set. Posts
+//! with `noindex: true` front matter are left out of the sitemap; the
+//! others carry their last-updated date as `<lastmod>`.
+
+use serde::Deserialize;
+
+use crate::types::{AbsoluteUrl, HtmlSafe};
+
+/// `robots.txt` settings (the `[robots]` table).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RobotsConfig {
+    /// Write `robots.txt` (a file in the static directory wins anyway).
+    pub enabled: bool,
+    /// Paths crawlers are asked to skip.
+    pub disallow: Vec<String>,
+}
+
+impl Default for RobotsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disallow: Vec::new(),
+        }
+    }
+}
+
+/// A page listed in the sitemap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SitemapEntry {
+    /// Path relative to the site root (`posts/a.html`).
+    pub path: String,
+    /// When the page content last changed (ISO 8601).
+    pub lastmod: Option<String>,
+}
+
+impl SitemapEntry {
+    pub fn new(path: impl Into<String>, lastmod: Option<String>) -> Self {
+        Self { path: path.into(), lastmod }
+    }
+}
+
+/// `sitemap.xml` listing `entries`.
+pub fn render_sitemap(base_url: &AbsoluteUrl, entries: &[SitemapEntry]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for entry in entries {
+        let loc = HtmlSafe::escape(&base_url.join(&entry.path));
+        let lastmod = entry
+            .lastmod
+            .as_ref()
+            .map(|d| format!("<lastmod>{}</lastmod>", HtmlSafe::escape(d)))
+            .unwrap_or_default();
+        xml.push_str(&format!("  <url><loc>{}</loc>{}</url>\n", loc, lastmod));
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// `robots.txt` allowing everything except `disallow`, with a sitemap
+/// reference when the base URL is known.
+pub fn render_robots(robots: &RobotsConfig, base_url: Option<&AbsoluteUrl>) -> String {
+    let mut txt = String::from("User-agent: *\n");
+    if robots.disallow.is_empty() {
+        txt.push_str("Disallow:\n");
+    }
+    for path in &robots.disallow {
+        txt.push_str(&format!("Disallow: {}\n", path));
+    }
+    if let Some(base) = base_url {
+        txt.push_str(&format!("\nSitemap: {}\n", base.join("sitemap.xml")));
+    }
+    txt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn robots_references_sitemap() {
+        let robots = RobotsConfig { disallow: vec!["/drafts/".to_string()], ..RobotsConfig::default() };
+        assert_eq!(
+            render_robots(&robots, Some(&AbsoluteUrl::new("https://duck.dev/").unwrap())),
+            "User-agent: *\nDisallow: /drafts/\n\nSitemap: https://duck.dev/sitemap.xml\n"
+        );
+        assert_eq!(render_robots(&RobotsConfig::default(), None), "User-agent: *\nDisallow:\n");
+    }
+
+    #[test]
+    fn sitemap_uses_absolute_escaped_urls() {
+        let updated = "2024-05-04T09:30:00+08:00".to_string();
+        let entries = [SitemapEntry::new("index.html", None), SitemapEntry::new("posts/a&b.html", Some(updated))];
+        let xml = render_sitemap(&AbsoluteUrl::new("https://duck.dev").unwrap(), &entries);
+        assert!(xml.contains("<url><loc>https://duck.dev/index.html</loc></url>"));
+        assert!(xml.contains("<loc>https://duck.dev/posts/a&amp;b.html</loc><lastmod>2024-05-04T09:30:00+08:00</lastmod>"));
+    }
+}
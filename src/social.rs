@@ -0,0 +1,163 @@
+//! Social previews: Open Graph tags of post pages, and their check.
+//!
+//! Post pages get `og:title`, `og:description` (the summary) and
+//! `og:image` with its size: front matter `image:`, else the post's first
+//! image. Sites build link cards from these; page and image URLs are
+//! absolute when `base_url` is set.
+//!
+//! `ssg check` reports posts whose card falls short as `SocialPreview`
+//! warnings: a missing tag, a description over 160 characters (cut off
+//! by most sites) or an image smaller than 1200×630, which is shown as a
+//! small thumbnail instead of a large card.
+
+use std::path::Path;
+
+use crate::error::BuildError;
+use crate::types::EscapeHtml;
+
+/// Longest description shown in full, in characters.
+pub const MAX_DESCRIPTION: usize = 160;
+
+/// Smallest image shown as a large card.
+pub const MIN_IMAGE_SIZE: (u32, u32) = (1200, 630);
+
+/// Preview image of a post.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocialImage {
+    pub url: String,
+    /// Size in pixels (0 if unknown).
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Open Graph and Twitter card `<meta>` tags of a post page.
+pub fn head_tags(title: &str, description: Option<&str>, url: &str, image: Option<&SocialImage>) -> String {
+    let meta = |property: &str, content: &str| format!(r#"<meta property="{property}" content="{}">"#, content.escape_html());
+    let mut tags = vec![meta("og:type", "article"), meta("og:title", title), meta("og:url", url)];
+    if let Some(description) = description.filter(|d| !d.is_empty()) {
+        tags.push(meta("og:description", description));
+    }
+    let card = match image {
+        Some(image) => {
+            tags.push(meta("og:image", &image.url));
+            if image.width > 0 && image.height > 0 {
+                tags.push(meta("og:image:width", &image.width.to_string()));
+                tags.push(meta("og:image:height", &image.height.to_string()));
+            }
+            "summary_large_image"
+        }
+        None => "summary",
+    };
+    tags.push(format!(r#"<meta name="twitter:card" content="{card}">"#));
+    tags.join("")
+}
+
+/// Problems with the preview of the page `html` written to `path`. Pages
+/// other than posts (without `og:type` `article`) have none.
+pub fn check_html(html: &str, path: &Path) -> Vec<BuildError> {
+    let tags = og_tags(html);
+    let get = |property: &str| tags.iter().find(|(p, _)| p == property).map(|(_, content)| content.as_str());
+    if get("og:type") != Some("article") {
+        return Vec::new();
+    }
+
+    let mut problems = Vec::new();
+    for property in ["og:title", "og:description", "og:image"] {
+        if get(property).is_none_or(|content| content.trim().is_empty()) {
+            problems.push(format!("no {property}"));
+        }
+    }
+    if let Some(description) = get("og:description") {
+        let length = description.chars().count();
+        if length > MAX_DESCRIPTION {
+            problems.push(format!("description is {length} characters, more than {MAX_DESCRIPTION}"));
+        }
+    }
+    if let Some(url) = get("og:image") {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            problems.push(format!("og:image {url} is not an absolute URL (set base_url)"));
+        }
+        let size = |property| get(property).and_then(|value| value.parse::<u32>().ok());
+        match (size("og:image:width"), size("og:image:height")) {
+            (Some(width), Some(height)) if width < MIN_IMAGE_SIZE.0 || height < MIN_IMAGE_SIZE.1 => problems.push(format!(
+                "image is {width}×{height}, smaller than {}×{}", MIN_IMAGE_SIZE.0, MIN_IMAGE_SIZE.1
+            )),
+            (Some(_), Some(_)) => {}
+            _ => problems.push("og:image has no size".to_string()),
+        }
+    }
+
+    problems.into_iter()
+        .map(|message| BuildError::SocialPreview { path: path.to_path_buf(), message })
+        .collect()
+}
+
+/// `(property, content)` of the page's `<meta property="og:...">` tags,
+/// contents unescaped.
+fn og_tags(html: &str) -> Vec<(String, String)> {
+    let head = html.split("</head>").next().unwrap_or(html);
+    head.split("<meta ")
+        .skip(1)
+        .filter_map(|tag| {
+            let tag = &tag[..tag.find('>')?];
+            let property = attribute(tag, "property")?;
+            property.starts_with("og:").then(|| (property, attribute(tag, "content").unwrap_or_default()))
+        })
+        .collect()
+}
+
+/// Unescaped value of the double-quoted attribute `name` in `tag`.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!(r#"{name}=""#))? + name.len() + 2;
+    let end = start + tag[start..].find('"')?;
+    Some(tag[start..end]
+        .replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(head: &str) -> String {
+        format!("<html><head>{head}</head><body></body></html>")
+    }
+
+    fn messages(html: &str) -> Vec<String> {
+        check_html(html, Path::new("public/posts/a.html"))
+            .into_iter()
+            .map(|e| match e {
+                BuildError::SocialPreview { message, .. } => message,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn complete_previews_pass() {
+        let image = SocialImage { url: "https://duck.dev/images/a.webp".to_string(), width: 1200, height: 630 };
+        let tags = head_tags("A & B", Some("Short"), "https://duck.dev/posts/a.html", Some(&image));
+        assert!(tags.contains(r#"<meta property="og:title" content="A &amp; B">"#));
+        assert!(tags.contains(r#"<meta name="twitter:card" content="summary_large_image">"#));
+        assert!(messages(&page(&tags)).is_empty());
+        assert!(messages(&page("<title>Index</title>")).is_empty());
+    }
+
+    #[test]
+    fn reports_missing_tags_long_descriptions_and_small_images() {
+        let tags = head_tags("A", None, "a.html", None);
+        assert_eq!(messages(&page(&tags)), ["no og:description", "no og:image"]);
+
+        let image = SocialImage { url: "../images/a.webp".to_string(), width: 800, height: 600 };
+        let long = "quack ".repeat(30);
+        let tags = head_tags("A", Some(long.trim()), "a.html", Some(&image));
+        assert_eq!(messages(&page(&tags)), [
+            "description is 179 characters, more than 160",
+            "og:image ../images/a.webp is not an absolute URL (set base_url)",
+            "image is 800×600, smaller than 1200×630",
+        ]);
+    }
+}
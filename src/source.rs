@@ -0,0 +1,89 @@
+//! Markdown siblings of the post pages and the `llms.txt` index.
+//!
+//! With `post_source = "txt"` (or `"md"`), every post page gets its
+//! markdown (front matter removed, includes expanded) written next to it
+//! as `posts/{stem}.txt`, linked from the page with
+//! `<link rel="alternate" type="text/plain">`, for readers and tools that
+//! want the source. `llms.txt` at the site root then lists them.
+
+use serde::Deserialize;
+
+/// `llms.txt` file name at the site root.
+pub const LLMS_FILE: &str = "llms.txt";
+
+/// Format of the post source siblings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostSource {
+    /// `{stem}.txt`, which browsers show inline.
+    Txt,
+    /// `{stem}.md`.
+    Md,
+}
+
+impl PostSource {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Txt => "txt",
+            Self::Md => "md",
+        }
+    }
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Self::Txt => "text/plain",
+            Self::Md => "text/markdown",
+        }
+    }
+}
+
+/// A post listed in `llms.txt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LlmsEntry {
+    pub title: String,
+    /// URL of the post source.
+    pub href: String,
+    pub summary: Option<String>,
+}
+
+/// `llms.txt` with a link list per section (`## Posts`).
+pub fn render_llms_txt(title: &str, description: Option<&str>, sections: &[(String, Vec<LlmsEntry>)]) -> String {
+    let mut txt = format!("# {title}\n");
+    if let Some(description) = description {
+        txt.push_str(&format!("\n> {description}\n"));
+    }
+    for (heading, entries) in sections {
+        txt.push_str(&format!("\n## {heading}\n\n"));
+        for entry in entries {
+            txt.push_str(&format!("- [{}]({})", entry.title.replace(['[', ']'], ""), entry.href));
+            if let Some(summary) = entry.summary.as_deref().filter(|s| !s.is_empty()) {
+                txt.push_str(&format!(": {summary}"));
+            }
+            txt.push('\n');
+        }
+    }
+    txt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn llms_txt_lists_sections() {
+        let entries = vec![
+            LlmsEntry {
+                title: "Hello [world]".to_string(),
+                href: "https://duck.example/posts/hello.txt".to_string(),
+                summary: Some("First post".to_string()),
+            },
+            LlmsEntry { title: "Bye".to_string(), href: "posts/bye.txt".to_string(), summary: None },
+        ];
+        assert_eq!(
+            render_llms_txt("Duck", Some("Notes on Rust"), &[("Posts".to_string(), entries)]),
+            "# Duck\n\n> Notes on Rust\n\n## Posts\n\n\
+             - [Hello world](https://duck.example/posts/hello.txt): First post\n- [Bye](posts/bye.txt)\n"
+        );
+        assert_eq!(PostSource::Md.media_type(), "text/markdown");
+    }
+}
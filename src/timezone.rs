@@ -0,0 +1,52 @@
+//! Site timezone for displayed and machine-readable dates.
+//!
+//! Either a fixed offset (`timezone_offset_hours`) or an IANA zone
+//! (`timezone = "Europe/Berlin"`), which follows daylight saving time:
+//! each date gets the offset that was in effect at that instant.
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Timezone dates are shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SiteTimezone {
+    Offset(FixedOffset),
+    Zone(Tz),
+}
+
+impl SiteTimezone {
+    /// `utc` in this timezone.
+    pub fn localize(&self, utc: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Self::Offset(offset) => utc.with_timezone(offset),
+            Self::Zone(zone) => utc.with_timezone(zone).fixed_offset(),
+        }
+    }
+
+    /// A wall-clock time in this timezone. Times skipped by a DST change
+    /// are None; repeated ones take the earlier instant.
+    pub fn from_local(&self, naive: &NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+        match self {
+            Self::Offset(offset) => offset.from_local_datetime(naive).earliest(),
+            Self::Zone(zone) => zone.from_local_datetime(naive).earliest().map(|d| d.fixed_offset()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zone_follows_daylight_saving() {
+        let berlin = SiteTimezone::Zone(chrono_tz::Europe::Berlin);
+        let winter = "2024-01-15T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let summer = "2024-07-15T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(berlin.localize(winter).to_rfc3339(), "2024-01-15T13:00:00+01:00");
+        assert_eq!(berlin.localize(summer).to_rfc3339(), "2024-07-15T14:00:00+02:00");
+
+        // 02:30 doesn't exist on the spring-forward night
+        let skipped = NaiveDateTime::parse_from_str("2024-03-31 02:30", "%Y-%m-%d %H:%M").unwrap();
+        assert_eq!(berlin.from_local(&skipped), None);
+    }
+}
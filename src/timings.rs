@@ -0,0 +1,101 @@
+//! Per-phase build profiling (`--timings`).
+//!
+//! Every build records wall-clock and process CPU time for each phase.
+//! CPU time covers all rayon workers, so a phase whose CPU time is far
+//! above its wall time is running in parallel. `print_report` adds the
+//! slowest posts and images, which shows whether markdown rendering or
+//! WebP encoding dominates.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use cpu_time::ProcessTime;
+use serde::Serialize;
+
+use crate::error::BuildSummary;
+use crate::report::millis;
+
+/// Number of slowest posts and images listed by default.
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// Timing of one build phase.
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseTiming {
+    pub name: &'static str,
+    pub wall_ms: f64,
+    /// Process CPU time (0 where the platform cannot measure it).
+    pub cpu_ms: f64,
+}
+
+/// Running measurement of a phase.
+pub struct PhaseTimer {
+    name: &'static str,
+    wall: Instant,
+    cpu: Option<ProcessTime>,
+}
+
+impl PhaseTimer {
+    pub fn start(name: &'static str) -> Self {
+        Self {
+            name,
+            wall: Instant::now(),
+            cpu: ProcessTime::try_now().ok(),
+        }
+    }
+
+    pub fn stop(self) -> PhaseTiming {
+        let cpu = self.cpu
+            .and_then(|cpu| cpu.try_elapsed().ok())
+            .unwrap_or_default();
+        PhaseTiming {
+            name: self.name,
+            wall_ms: millis(self.wall.elapsed()),
+            cpu_ms: millis(cpu),
+        }
+    }
+}
+
+/// Print the phase table and the `limit` slowest posts and images.
+pub fn print_report(summary: &BuildSummary, limit: usize) {
+    println!("{:<10} {:>12} {:>12}", "phase", "wall", "cpu");
+    for phase in &summary.phases {
+        println!("{:<10} {:>10.1}ms {:>10.1}ms", phase.name, phase.wall_ms, phase.cpu_ms);
+    }
+    let wall: f64 = summary.phases.iter().map(|p| p.wall_ms).sum();
+    let cpu: f64 = summary.phases.iter().map(|p| p.cpu_ms).sum();
+    println!("{:<10} {:>10.1}ms {:>10.1}ms", "total", wall, cpu);
+
+    // Image encoding runs inside the render phase
+    let encode: Duration = summary.images.timings.iter().map(|(_, d)| *d).sum();
+    let render_ms: f64 = summary.posts.iter().map(|p| p.render_ms).sum();
+    println!(
+        "\nRendering: {:.1}ms across posts, of which {:.1}ms image optimization ({} images)",
+        render_ms,
+        millis(encode),
+        summary.images.timings.len(),
+    );
+
+    let mut posts: Vec<_> = summary.posts.iter()
+        .map(|p| (p.parse_ms + p.render_ms, p))
+        .collect();
+    posts.sort_by(|a, b| b.0.total_cmp(&a.0));
+    println!("\nSlowest posts:");
+    for (total, post) in posts.into_iter().take(limit) {
+        println!(
+            "  {:>10.1}ms  {} (parse {:.1}ms, render {:.1}ms)",
+            total,
+            post.source.display(),
+            post.parse_ms,
+            post.render_ms,
+        );
+    }
+
+    let mut images: Vec<(&Path, Duration)> = summary.images.timings.iter()
+        .map(|(path, elapsed)| (path.as_path(), *elapsed))
+        .collect();
+    images.sort_by_key(|(_, elapsed)| std::cmp::Reverse(*elapsed));
+    println!("\nSlowest images:");
+    for (path, elapsed) in images.into_iter().take(limit) {
+        println!("  {:>10.1}ms  {}", millis(elapsed), path.display());
+    }
+}
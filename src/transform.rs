@@ -0,0 +1,846 @@
+//! Markdown rendering as a pipeline of transform stages.
+//!
+//! A [`Pipeline`] runs every stage's [`EventTransform::markdown`] hook on
+//! the source text, parses the result, runs every stage's
+//! [`EventTransform::events`] hook on the event stream and writes the
+//! HTML. The standard stages, in order:
+//!
+//! - [`Shortcodes`]: inline data values and `{{< ... >}}` shortcodes
+//! - [`Diagrams`]: mermaid and dot code blocks
+//! - [`Images`]: optimized images in figures, with captions
+//! - [`Links`]: broken link warnings, downloads and external link markup
+//! - [`DemoteHeadings`]: `#` sections below the title as `<h2>`
+//! - [`StripTitle`]: the title heading out of the body
+//! - [`HeadingIds`]: `id`s on headings, for anchor links
+//!
+//! `Pipeline::stages` is public: stages can be removed, reordered or
+//! added before the pipeline is handed to `Site::with_pipeline`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, HeadingLevel, Parser, Tag, TagEnd, html};
+
+use crate::attachment::{self, Attachment};
+use crate::chart;
+use crate::config::Config;
+use crate::diagram::{self, DiagramKind};
+use crate::embed;
+use crate::error::BuildError;
+use crate::gallery;
+use crate::image::{dark_variant, optimize_image, ImageQuery, OptimizedImage};
+use crate::image_meta::ImageMeta;
+use crate::lightbox::LightboxPage;
+use crate::parser::{archive_url, is_external, markdown_options, ArchiveLinks, MarkdownEnv};
+use crate::shortcode;
+use crate::types::{EscapeHtml, HtmlSafe};
+use crate::video::{self, Animation, Player};
+
+/// State of one render, shared by the stages.
+pub struct RenderState<'a> {
+    pub config: &'a Config,
+    /// The markdown file, used to attribute warnings.
+    pub source: &'a Path,
+    /// Prefix from the page to the output root.
+    pub relative_root: &'a str,
+    pub env: MarkdownEnv<'a>,
+    pub lightbox: LightboxPage,
+    pub warnings: Vec<BuildError>,
+    /// Every image embedded so far, after optimization.
+    pub images: Vec<OptimizedImage>,
+    /// Scripts the page needs.
+    pub scripts: Vec<String>,
+    /// Per-post image limits (see `ResolvedPostConfig`).
+    pub image_defaults: ImageQuery,
+    /// Publication date of the post.
+    pub published: Option<NaiveDate>,
+}
+
+impl<'a> RenderState<'a> {
+    pub fn new(config: &'a Config, source: &'a Path, relative_root: &'a str, env: MarkdownEnv<'a>) -> Self {
+        Self {
+            config,
+            source,
+            relative_root,
+            env,
+            lightbox: LightboxPage::new(config.lightbox),
+            warnings: Vec::new(),
+            images: Vec::new(),
+            scripts: Vec::new(),
+            image_defaults: ImageQuery::default(),
+            published: None,
+        }
+    }
+}
+
+/// One stage of the pipeline; both hooks default to passing their input
+/// through.
+pub trait EventTransform: Send + Sync {
+    /// Rewrite the markdown before it is parsed.
+    fn markdown(&self, markdown: String, _state: &mut RenderState<'_>) -> String {
+        markdown
+    }
+
+    /// Rewrite the parsed events.
+    fn events<'e>(&self, events: Vec<Event<'e>>, _state: &mut RenderState<'_>) -> Vec<Event<'e>> {
+        events
+    }
+}
+
+/// Stages markdown is rendered through.
+pub struct Pipeline {
+    pub stages: Vec<Box<dyn EventTransform>>,
+}
+
+impl Pipeline {
+    /// The standard stages.
+    pub fn standard() -> Self {
+        Self {
+            stages: vec![
+                Box::new(Shortcodes),
+                Box::new(Diagrams),
+                Box::new(Images),
+                Box::new(Links),
+                Box::new(DemoteHeadings),
+                Box::new(StripTitle),
+                Box::new(HeadingIds),
+            ],
+        }
+    }
+
+    /// Builder: append a stage.
+    pub fn with_stage(mut self, stage: impl EventTransform + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Run `markdown` through every stage and return the HTML.
+    pub fn run(&self, markdown: &str, state: &mut RenderState<'_>) -> String {
+        let mut markdown = markdown.to_string();
+        for stage in &self.stages {
+            markdown = stage.markdown(markdown, state);
+        }
+        let mut events: Vec<Event<'_>> = Parser::new_ext(&markdown, markdown_options(state.config)).collect();
+        for stage in &self.stages {
+            events = stage.events(events, state);
+        }
+        let mut html_output = String::new();
+        html::push_html(&mut html_output, events.into_iter());
+        html_output
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl std::fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pipeline").field("stages", &self.stages.len()).finish()
+    }
+}
+
+/// Inline `{{ data.path }}` values and expand shortcodes.
+pub struct Shortcodes;
+
+impl EventTransform for Shortcodes {
+    fn markdown(&self, markdown: String, state: &mut RenderState<'_>) -> String {
+        let RenderState { config, relative_root, env, lightbox, warnings, images, image_defaults, .. } = state;
+        let (markdown, mut errors) = env.data.substitute(&markdown);
+        let (markdown, expand_errors) = shortcode::expand(&markdown, |code| match code.name.as_str() {
+            "gallery" => {
+                let gallery = gallery::render(code, config, relative_root, lightbox, env, image_defaults)?;
+                warnings.extend(gallery.warnings);
+                images.extend(gallery.images);
+                Ok(gallery.html)
+            }
+            "data" => env.data.render_shortcode(code),
+            "chart" => chart::render_shortcode(code, env.data),
+            _ => embed::render(code),
+        });
+        errors.extend(expand_errors);
+        state.warnings.extend(errors.into_iter().map(|(position, message)| BuildError::ShortcodeFailed {
+            path: state.source.to_path_buf(),
+            position: Some(position),
+            message,
+        }));
+        markdown
+    }
+}
+
+/// Render `mermaid` and `dot` code blocks as diagrams.
+pub struct Diagrams;
+
+impl EventTransform for Diagrams {
+    fn events<'e>(&self, events: Vec<Event<'e>>, state: &mut RenderState<'_>) -> Vec<Event<'e>> {
+        let config = state.config;
+        let mut out = Vec::with_capacity(events.len());
+        let mut diagram: Option<(DiagramKind, String)> = None;
+        for event in events {
+            match event {
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref info))) if DiagramKind::from_info(info).is_some() => {
+                    diagram = DiagramKind::from_info(info).map(|kind| (kind, String::new()));
+                }
+                Event::Text(text) if diagram.is_some() => {
+                    if let Some((_, code)) = diagram.as_mut() {
+                        code.push_str(&text);
+                    }
+                }
+                Event::End(TagEnd::CodeBlock) if diagram.is_some() => {
+                    let Some((kind, code)) = diagram.take() else { continue };
+                    let rendered = diagram::render(kind, &code, &config.diagrams, state.source);
+                    state.warnings.extend(rendered.warning);
+                    if rendered.needs_script
+                        && let Some(script) = &config.diagrams.mermaid_script
+                        && !state.scripts.contains(script)
+                    {
+                        state.scripts.push(script.clone());
+                    }
+                    out.push(Event::Html(rendered.html.into()));
+                }
+                event => out.push(event),
+            }
+        }
+        out
+    }
+}
+
+/// Optimize images and wrap them in figures with captions, download
+/// links and the lightbox.
+pub struct Images;
+
+/// An image whose alt text is being collected.
+struct OpenImage {
+    url: String,
+    title: String,
+    alt: String,
+}
+
+impl EventTransform for Images {
+    fn events<'e>(&self, events: Vec<Event<'e>>, state: &mut RenderState<'_>) -> Vec<Event<'e>> {
+        let (events, captioned) = take_caption_paragraphs(events);
+        let mut out = Vec::with_capacity(events.len());
+        let mut image: Option<OpenImage> = None;
+        let mut image_index = 0;
+        let mut first_image = true;
+        let mut pending_caption_end: Option<String> = None;
+        // Open paragraph, and whether it starts with a figure
+        let mut paragraph: Option<(usize, bool)> = None;
+        for event in events {
+            if let Some(open) = image.as_mut() {
+                match event {
+                    Event::End(TagEnd::Image) => {
+                        let Some(open) = image.take() else { continue };
+                        let caption_follows = captioned.contains(&image_index);
+                        image_index += 1;
+                        let figure = render_figure(&open, caption_follows, first_image, state);
+                        first_image = false;
+                        if caption_follows {
+                            pending_caption_end = Some(figure.caption_end);
+                        }
+                        if let Some((start, starts_with_figure)) = paragraph.as_mut() {
+                            *starts_with_figure |= *start + 1 == out.len();
+                        }
+                        out.push(Event::Html(figure.html.into()));
+                    }
+                    Event::Text(text) | Event::Code(text) => open.alt.push_str(&text),
+                    _ => {}
+                }
+                continue;
+            }
+            match event {
+                Event::Start(Tag::Image { dest_url, title, .. }) => {
+                    image = Some(OpenImage { url: dest_url.to_string(), title: title.to_string(), alt: String::new() });
+                }
+                Event::Start(Tag::Paragraph) => {
+                    paragraph = Some((out.len(), false));
+                    out.push(event);
+                }
+                Event::End(TagEnd::Paragraph) => {
+                    // A figure alone (or with its caption) is block content,
+                    // not part of a paragraph
+                    let captioned = pending_caption_end.is_some();
+                    out.extend(pending_caption_end.take().map(|end| Event::Html(end.into())));
+                    match paragraph.take() {
+                        Some((start, true)) if captioned || out.len() == start + 2 => {
+                            out.remove(start);
+                        }
+                        _ => out.push(event),
+                    }
+                }
+                event => out.push(event),
+            }
+        }
+        out
+    }
+}
+
+/// Markup of one image.
+struct Figure {
+    html: String,
+    /// Closes a caption taken from the following `Caption:` paragraph.
+    caption_end: String,
+}
+
+fn render_figure(image: &OpenImage, caption_follows: bool, first_image: bool, state: &mut RenderState<'_>) -> Figure {
+    let config = state.config;
+    let MarkdownEnv { outputs, decode_limit, image_cache, .. } = state.env;
+    let content_dir = &config.content_dir;
+    let meta = ImageMeta::load(&image.url, content_dir).unwrap_or_else(|e| {
+        state.warnings.push(e);
+        None
+    }).unwrap_or_default();
+    let alt = match meta.alt() {
+        Some(alt) if image.alt.trim().is_empty() => alt,
+        _ => &image.alt,
+    };
+    if config.require_alt_text && alt.trim().is_empty() {
+        state.warnings.push(BuildError::MissingAltText { path: state.source.to_path_buf(), src: image.url.clone() });
+    }
+    let relative_root = state.relative_root;
+    let src = state.image_defaults.apply_to(&image.url);
+    let optimize = |src: &str| {
+        optimize_image(src, content_dir, &config.public_dir, config.image_bounds(), outputs, decode_limit, image_cache)
+    };
+    // Videos and animated GIFs play in a `<video>`, with the image as
+    // its poster
+    let (player, opt) = if video::mime_type(&src).is_some() {
+        match video::copy_video(&src, content_dir, &config.public_dir, outputs, &mut state.warnings) {
+            Ok(file) => {
+                let poster = file.poster.map_or_else(|| Ok(OptimizedImage::missing("")), |frame| optimize(&frame));
+                (Some(Player::controls(file.source)), poster)
+            }
+            Err(e) => (None, Err(e)),
+        }
+    } else {
+        match video::animate_gif(&src, content_dir, &config.public_dir, outputs, &mut state.warnings) {
+            Some(Animation::Video(sources)) => (Some(Player::looping(sources)), optimize(&src)),
+            Some(Animation::Gif(gif)) => (None, Ok(gif)),
+            None => (None, optimize(&src)),
+        }
+    };
+    let opt = opt.unwrap_or_else(|e| {
+        state.warnings.push(e);
+        // Without processing options, which only apply to optimized images
+        OptimizedImage::missing(image.url.split('?').next().unwrap_or(&image.url))
+    });
+    let dark = dark_variant(&src, content_dir)
+        .filter(|_| player.is_none())
+        .and_then(|dark_src| optimize(&dark_src).map_err(|e| state.warnings.push(e)).ok());
+
+    let final_src_escaped = opt.src.href(relative_root).escape_html();
+
+    let (width_attr, height_attr) = parse_dimensions_or_image(&image.title, opt.width, opt.height);
+
+    // Escape alt text for XSS prevention
+    let safe_alt = alt.escape_html();
+
+    // Title (only if not a dimension spec): the caption, unless
+    // a `Caption:` paragraph follows; else the sidecar's caption
+    let title = (!is_dimension_spec(&image.title) && !image.title.is_empty())
+        .then(|| image.title.escape_html());
+    let title = title.or_else(|| meta.caption.as_deref().filter(|_| !caption_follows).map(|caption| caption.escape_html()));
+    let (title_attr, title_caption) = match title {
+        Some(title) if caption_follows => (format!(r#"title="{}""#, title), None),
+        title => (String::new(), title),
+    };
+
+    // Loading strategy
+    let loading_attrs = if first_image {
+        r#"loading="eager" fetchpriority="high" decoding="sync""#
+    } else {
+        r#"loading="lazy" decoding="async""#
+    };
+
+    let opt_has_output = opt.output_path.is_some();
+    state.images.push(opt);
+
+    let mut img = format!(
+        r#"<img src="{}" alt="{}" {} {} {} {} />"#,
+        final_src_escaped,
+        safe_alt,
+        width_attr,
+        height_attr,
+        title_attr,
+        loading_attrs,
+    );
+    if let Some(dark) = dark {
+        img = format!(
+            r#"<picture><source srcset="{}" media="(prefers-color-scheme: dark)">{}</picture>"#,
+            dark.src.href(relative_root).escape_html(),
+            img,
+        );
+        state.images.push(dark);
+    }
+    let wrapped = match &player {
+        Some(player) => {
+            let poster = opt_has_output.then_some(&final_src_escaped);
+            Some(video_markup(player, poster, &safe_alt, &width_attr, &height_attr, relative_root))
+        }
+        None => state.lightbox.wrap(&img, &final_src_escaped, alt),
+    };
+    let (body, mut download) = match wrapped {
+        Some(wrapped) => (wrapped, String::new()),
+        None => (img, format!(
+            r#"<a href="{}" target="_blank" class="download-link">[ {} ]</a>"#,
+            final_src_escaped,
+            config.ui_strings().download_full_size.escape_html(),
+        )),
+    };
+    download.extend(meta.credit_html());
+    let (caption, caption_end) = if caption_follows {
+        // Closed at the end of the caption paragraph
+        ("<figcaption>".to_string(), figure_end(true, &download))
+    } else {
+        let caption = match title_caption {
+            Some(title) => format!("<figcaption>{}{}", title, figure_end(true, &download)),
+            None if download.is_empty() => "</figure>".to_string(),
+            None => format!("<figcaption>{}", figure_end(false, &download)),
+        };
+        (caption, String::new())
+    };
+    Figure { html: format!(r#"<figure class="image-container">{body}{caption}"#), caption_end }
+}
+
+/// A `<video>` element, showing `poster` until it plays.
+fn video_markup(player: &Player, poster: Option<&HtmlSafe>, alt: &HtmlSafe, width: &str, height: &str, relative_root: &str) -> String {
+    let poster = poster.map(|poster| format!(r#" poster="{poster}""#)).unwrap_or_default();
+    let mut html = format!(r#"<video {}{poster} aria-label="{alt}" {width} {height}>"#, player.attributes);
+    for source in &player.sources {
+        html.push_str(&format!(r#"<source src="{}" type="{}">"#, source.src.href(relative_root).escape_html(), source.mime));
+    }
+    html.push_str("</video>");
+    html
+}
+
+/// Point `.md` links at the post pages, warn about broken sibling links,
+/// copy linked files for download and mark up external links.
+pub struct Links;
+
+impl EventTransform for Links {
+    fn events<'e>(&self, events: Vec<Event<'e>>, state: &mut RenderState<'_>) -> Vec<Event<'e>> {
+        let config = state.config;
+        let mut out = Vec::with_capacity(events.len());
+        // Size shown after the open download link
+        let mut file_size: Option<String> = None;
+        // Archived copy linked after the open external link
+        let mut archived: Option<String> = None;
+        let archived_label = (config.external_links.archive == Some(ArchiveLinks::Link))
+            .then(|| config.ui_strings().archived);
+        for event in events {
+            if let Event::End(TagEnd::Link) = event
+                && let Some(size) = file_size.take()
+            {
+                out.push(event);
+                out.push(Event::Html(format!(r#" <span class="file-size">({size})</span>"#).into()));
+                continue;
+            }
+            if let Event::End(TagEnd::Link) = event
+                && let Some(url) = archived.take()
+            {
+                let label = archived_label.as_deref().unwrap_or_default().escape_html();
+                out.push(event);
+                out.push(Event::Html(format!(r#" <a href="{}" class="archived-link" rel="nofollow">({label})</a>"#, url.escape_html()).into()));
+                continue;
+            }
+            let Event::Start(Tag::Link { link_type, ref dest_url, ref title, ref id }) = event else {
+                out.push(event);
+                continue;
+            };
+            if let Some(page) = state.env.registry.resolve_link(dest_url) {
+                let dest_url = page.href(state.relative_root).into();
+                out.push(Event::Start(Tag::Link { link_type, dest_url, title: title.clone(), id: id.clone() }));
+                continue;
+            }
+            let unresolved = dest_url.split(['#', '?']).next().is_some_and(|path| path.ends_with(".md"));
+            if unresolved || !link_exists(dest_url, &config.content_dir) {
+                state.warnings.push(BuildError::BrokenLink {
+                    path: state.source.to_path_buf(),
+                    target: dest_url.to_string(),
+                });
+            }
+            let title_attr = if title.is_empty() {
+                String::new()
+            } else {
+                format!(r#" title="{}""#, title.escape_html())
+            };
+            if let Some((file, rest)) = download(dest_url, state) {
+                file_size = Some(attachment::human_size(file.length));
+                let href = format!("{}{rest}", file.src.href(state.relative_root));
+                out.push(Event::Html(format!(r#"<a href="{}"{title_attr} class="download" download>"#, href.escape_html()).into()));
+                continue;
+            }
+            if !is_external(dest_url, config.base_url.as_deref()) {
+                out.push(event);
+                continue;
+            }
+            let archive = config.external_links.archive.map(|mode| (mode, archive_url(dest_url, state.published)));
+            let archive_attr = match &archive {
+                Some((ArchiveLinks::Attribute, url)) => format!(r#" data-archive="{}""#, url.escape_html()),
+                _ => String::new(),
+            };
+            out.push(Event::Html(format!(
+                r#"<a href="{}"{}{}{}>"#,
+                dest_url.escape_html(),
+                title_attr,
+                config.external_links.attributes(),
+                archive_attr
+            ).into()));
+            if let Some((ArchiveLinks::Link, url)) = archive {
+                archived = Some(url);
+            }
+        }
+        out
+    }
+}
+
+/// The attachment a sibling link to a file other than a page points at,
+/// copied for download, plus the link's query and fragment.
+fn download<'u>(dest_url: &'u str, state: &mut RenderState<'_>) -> Option<(Attachment, &'u str)> {
+    let config = state.config;
+    let split = dest_url.find(['#', '?']).unwrap_or(dest_url.len());
+    let (path, rest) = dest_url.split_at(split);
+    let page = Path::new(path).extension().is_some_and(|e| e == "md" || e == "html");
+    if page || path.is_empty() || path.contains(':') || path.starts_with('/') || path.starts_with("../") {
+        return None;
+    }
+    attachment::attach(path, &config.content_dir, &config.public_dir, state.env.outputs)
+        .unwrap_or_else(|e| {
+            state.warnings.push(e);
+            None
+        })
+        .map(|file| (file, rest))
+}
+
+/// With `demote_headings`, move the headings after the title down a level
+/// if any of them is an `<h1>`; bodies that start at `##` are left alone.
+pub struct DemoteHeadings;
+
+impl EventTransform for DemoteHeadings {
+    fn events<'e>(&self, mut events: Vec<Event<'e>>, state: &mut RenderState<'_>) -> Vec<Event<'e>> {
+        if !state.config.demote_headings {
+            return events;
+        }
+        // The first h1 is the title
+        let title = events.iter().position(|e| matches!(e, Event::Start(Tag::Heading { level: HeadingLevel::H1, .. })));
+        let Some(title) = title else { return events };
+        let is_h1 = |e: &Event<'_>| matches!(e, Event::Start(Tag::Heading { level: HeadingLevel::H1, .. }));
+        if !events[title + 1..].iter().any(is_h1) {
+            return events;
+        }
+        let demote = |level: HeadingLevel| HeadingLevel::try_from(level as usize + 1).unwrap_or(HeadingLevel::H6);
+        let title_end = title + events[title..].iter().position(|e| matches!(e, Event::End(TagEnd::Heading(_)))).unwrap_or(0);
+        for event in &mut events[title_end + 1..] {
+            match event {
+                Event::Start(Tag::Heading { level, .. }) => *level = demote(*level),
+                Event::End(TagEnd::Heading(level)) => *level = demote(*level),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// With `strip_title`, drop the first `<h1>`, the title, which the post
+/// header shows instead.
+pub struct StripTitle;
+
+impl EventTransform for StripTitle {
+    fn events<'e>(&self, mut events: Vec<Event<'e>>, state: &mut RenderState<'_>) -> Vec<Event<'e>> {
+        if !state.config.strip_title {
+            return events;
+        }
+        let start = events.iter().position(|e| matches!(e, Event::Start(Tag::Heading { level: HeadingLevel::H1, .. })));
+        let Some(start) = start else { return events };
+        let Some(end) = events[start..].iter().position(|e| matches!(e, Event::End(TagEnd::Heading(_)))) else { return events };
+        events.drain(start..=start + end);
+        events
+    }
+}
+
+/// Give headings without one an `id` from their text (`## Getting
+/// started` gets `getting-started`), numbered when repeated.
+pub struct HeadingIds;
+
+impl EventTransform for HeadingIds {
+    fn events<'e>(&self, mut events: Vec<Event<'e>>, _state: &mut RenderState<'_>) -> Vec<Event<'e>> {
+        let mut used: HashMap<String, usize> = HashMap::new();
+        let mut open: Option<(usize, String)> = None;
+        for i in 0..events.len() {
+            match &events[i] {
+                Event::Start(Tag::Heading { id: None, .. }) => open = Some((i, String::new())),
+                Event::Text(text) | Event::Code(text) => {
+                    if let Some((_, heading)) = open.as_mut() {
+                        heading.push_str(text);
+                    }
+                }
+                Event::End(TagEnd::Heading(_)) => {
+                    let Some((start, text)) = open.take() else { continue };
+                    let base = slugify(&text);
+                    if base.is_empty() {
+                        continue;
+                    }
+                    let count = used.entry(base.clone()).or_insert(0);
+                    let slug = if *count == 0 { base } else { format!("{base}-{count}") };
+                    *count += 1;
+                    if let Event::Start(Tag::Heading { id, .. }) = &mut events[start] {
+                        *id = Some(CowStr::from(slug));
+                    }
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Lowercase letters and digits of `text`, runs of anything else turned
+/// into single hyphens.
+fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(slug.trim_end_matches('-').len());
+    slug
+}
+
+/// End of a figure caption: the download link (after a space if there is
+/// caption text), `</figcaption>` and `</figure>`.
+fn figure_end(after_text: bool, download: &str) -> String {
+    let space = if after_text && !download.is_empty() { " " } else { "" };
+    format!("{space}{download}</figcaption></figure>")
+}
+
+/// Remove `Caption:` paragraphs that directly follow a paragraph ending
+/// in an image, moving their inline content (without the prefix) into
+/// the image's paragraph. Returns the new events and the indexes, in
+/// document order, of the images that got a caption.
+fn take_caption_paragraphs(events: Vec<Event<'_>>) -> (Vec<Event<'_>>, Vec<usize>) {
+    let mut out = Vec::with_capacity(events.len());
+    let mut captioned = Vec::new();
+    let mut image_index = 0;
+    let mut i = 0;
+    while i < events.len() {
+        let event = events[i].clone();
+        i += 1;
+        if event != Event::End(TagEnd::Image) {
+            out.push(event);
+            continue;
+        }
+        out.push(event);
+        let caption = match &events[i..] {
+            [Event::End(TagEnd::Paragraph), Event::Start(Tag::Paragraph), Event::Text(text), ..] => {
+                text.trim_start().strip_prefix("Caption:")
+            }
+            _ => None,
+        };
+        if let Some(caption) = caption {
+            let caption = caption.trim_start();
+            if !caption.is_empty() {
+                out.push(Event::Text(caption.to_string().into()));
+            }
+            captioned.push(image_index);
+            i += 3;
+        }
+        image_index += 1;
+    }
+    (out, captioned)
+}
+
+/// Check a link target against the content directory.
+///
+/// Only sibling links (relative to the post, no `../` or `/` prefix, no
+/// scheme) are checked: either the file exists in the content directory,
+/// or it is `name.html` and `name.md` exists there.
+fn link_exists(target: &str, content_dir: &Path) -> bool {
+    let path = target.split(['#', '?']).next().unwrap_or_default();
+
+    let unchecked = path.is_empty()
+        || path.contains(':')
+        || path.starts_with('/')
+        || path.starts_with("../");
+    if unchecked {
+        return true;
+    }
+
+    let local = content_dir.join(path);
+    let page = local.extension().and_then(|e| e.to_str()) == Some("html");
+    let file = if page && !local.exists() { local.with_extension("md") } else { local };
+    // `a/../../x` may point out of the content directory
+    attachment::in_content_dir(&file, content_dir)
+}
+
+/// Parse dimension specification from title or use from image.
+fn parse_dimensions_or_image(title: &str, img_w: u32, img_h: u32) -> (String, String) {
+    let clean = title.trim();
+
+    // Try "WxH" format
+    if let Some(x_pos) = clean.find('x') {
+        let (w_str, h_str) = clean.split_at(x_pos);
+        let h_str = &h_str[1..];
+        if let (Ok(w), Ok(h)) = (w_str.parse::<u32>(), h_str.parse::<u32>()) {
+            return (format!(r#"width="{}""#, w), format!(r#"height="{}""#, h));
+        }
+    }
+
+    // Try single width value, with the height from the image's aspect ratio
+    if let Ok(w) = clean.parse::<u32>() {
+        let height = if img_w > 0 && img_h > 0 {
+            let h = (w as f64 * img_h as f64 / img_w as f64).round() as u32;
+            format!(r#"height="{}""#, h.max(1))
+        } else {
+            String::new()
+        };
+        return (format!(r#"width="{}""#, w), height);
+    }
+
+    // Use image dimensions if available
+    if img_w > 0 && img_h > 0 {
+        return (format!(r#"width="{}""#, img_w), format!(r#"height="{}""#, img_h));
+    }
+
+    (String::new(), String::new())
+}
+
+/// Check if title is a dimension specification.
+fn is_dimension_spec(title: &str) -> bool {
+    let clean = title.trim();
+    if clean.is_empty() {
+        return false;
+    }
+
+    // "WxH" format
+    if let Some(x_pos) = clean.find('x') {
+        let (w_str, h_str) = clean.split_at(x_pos);
+        let h_str = &h_str[1..];
+        return w_str.parse::<u32>().is_ok() && h_str.parse::<u32>().is_ok();
+    }
+
+    // Single number
+    clean.parse::<u32>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::SiteData;
+    use crate::image::DecodeLimit;
+    use crate::image_cache::ImageCache;
+    use crate::output::OutputSet;
+    use crate::registry::{PostRef, Registry};
+    use crate::types::AbsoluteUrl;
+
+    fn run(pipeline: &Pipeline, markdown: &str, config: &Config) -> (String, Vec<BuildError>) {
+        let (outputs, limit, cache, data) = (OutputSet::new(), DecodeLimit::default(), ImageCache::default(), SiteData::default());
+        let registry = Registry::new([PostRef::new("setup", "Setup")]);
+        let env = MarkdownEnv { outputs: &outputs, decode_limit: &limit, image_cache: &cache, data: &data, registry: &registry, pipeline };
+        let mut state = RenderState::new(config, Path::new("post.md"), "../", env);
+        let html = pipeline.run(markdown, &mut state);
+        (html, state.warnings)
+    }
+
+    #[test]
+    fn heading_ids_are_unique() {
+        let pipeline = Pipeline { stages: vec![Box::new(HeadingIds)] };
+        let (html, _) = run(&pipeline, "# Hello, World!\n\n## Setup `cargo`\n\n## Setup cargo\n\n## ---\n", &Config::new());
+        assert_eq!(
+            html,
+            "<h1 id=\"hello-world\">Hello, World!</h1>\n<h2 id=\"setup-cargo\">Setup <code>cargo</code></h2>\n\
+             <h2 id=\"setup-cargo-1\">Setup cargo</h2>\n<h2>---</h2>\n"
+        );
+        assert_eq!(slugify("Über Café"), "über-café");
+    }
+
+    #[test]
+    fn demotes_headings_below_the_title() {
+        let pipeline = Pipeline { stages: vec![Box::new(DemoteHeadings)] };
+        let config = Config::new().demote_headings(true);
+        let (html, _) = run(&pipeline, "# Title\n\n# Intro\n\n## Details\n\n###### Deep\n", &config);
+        assert_eq!(html, "<h1>Title</h1>\n<h2>Intro</h2>\n<h3>Details</h3>\n<h6>Deep</h6>\n");
+
+        // Already in order, or not enabled
+        let markdown = "# Title\n\n## Intro\n";
+        assert_eq!(run(&pipeline, markdown, &config).0, "<h1>Title</h1>\n<h2>Intro</h2>\n");
+        assert_eq!(run(&pipeline, "# A\n\n# B\n", &Config::new()).0, "<h1>A</h1>\n<h1>B</h1>\n");
+    }
+
+    #[test]
+    fn strips_the_title_heading() {
+        let pipeline = Pipeline { stages: vec![Box::new(DemoteHeadings), Box::new(StripTitle)] };
+        let config = Config::new().strip_title(true);
+        let (html, _) = run(&pipeline, "Intro\n\n# Title *here*\n\n## Setup\n", &config);
+        assert_eq!(html, "<p>Intro</p>\n<h2>Setup</h2>\n");
+
+        // Sections below the title are demoted first
+        let (html, _) = run(&pipeline, "# Title\n\n# Intro\n", &config.clone().demote_headings(true));
+        assert_eq!(html, "<h2>Intro</h2>\n");
+        assert_eq!(run(&pipeline, "# Title\n", &Config::new()).0, "<h1>Title</h1>\n");
+    }
+
+    #[test]
+    fn links_stage_marks_external_links() {
+        let pipeline = Pipeline { stages: vec![Box::new(Links)] };
+        let config = Config::new().base_url(AbsoluteUrl::new("https://duck.dev").unwrap());
+        let markdown = "[a](https://rust-lang.org) [b](https://duck.dev/x) [c](missing.html) [d](setup.md#cargo) [e](gone.md)";
+        let (html, warnings) = run(&pipeline, markdown, &config);
+        assert_eq!(
+            html,
+            "<p><a href=\"https://rust-lang.org\" rel=\"noopener noreferrer\">a</a> \
+             <a href=\"https://duck.dev/x\">b</a> <a href=\"missing.html\">c</a> \
+             <a href=\"../posts/setup.html#cargo\">d</a> <a href=\"gone.md\">e</a></p>\n"
+        );
+        let broken: Vec<_> = warnings.iter()
+            .filter_map(|w| match w {
+                BuildError::BrokenLink { target, .. } => Some(target.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(broken, ["missing.html", "gone.md"]);
+    }
+
+    #[test]
+    fn required_alt_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let pipeline = Pipeline { stages: vec![Box::new(Images)] };
+        let config = Config::new().content_dir(dir.path()).public_dir(dir.path().join("public"));
+        let missing_alt = |config: &Config| {
+            let (_, warnings) = run(&pipeline, "![](a.png) ![ ](b.png)\n\n![Duck](c.png)\n", config);
+            warnings.into_iter()
+                .filter_map(|w| match w {
+                    BuildError::MissingAltText { src, .. } => Some(src),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert!(missing_alt(&config).is_empty());
+        assert_eq!(missing_alt(&config.require_alt_text(true)), ["a.png", "b.png"]);
+    }
+
+    #[test]
+    fn custom_stage_runs_after_standard_ones() {
+        struct Shout;
+        impl EventTransform for Shout {
+            fn events<'e>(&self, events: Vec<Event<'e>>, _state: &mut RenderState<'_>) -> Vec<Event<'e>> {
+                events
+                    .into_iter()
+                    .map(|event| match event {
+                        Event::Text(text) => Event::Text(text.to_uppercase().into()),
+                        event => event,
+                    })
+                    .collect()
+            }
+        }
+        let pipeline = Pipeline::standard().with_stage(Shout);
+        let (html, _) = run(&pipeline, "## Quack\n\nquack\n", &Config::new());
+        assert_eq!(html, "<h2 id=\"quack\">QUACK</h2>\n<p>QUACK</p>\n");
+        assert_eq!(parse_dimensions_or_image("300", 1200, 800), (r#"width="300""#.to_string(), r#"height="200""#.to_string()));
+    }
+}
@@ -2,6 +2,8 @@
 
 mod tag;
 mod html_safe;
+mod url;
 
 pub use tag::Tag;
 pub use html_safe::{HtmlSafe, EscapeHtml};
+pub use url::{AbsoluteUrl, Href, RelPath};
@@ -13,39 +13,37 @@ use crate::error::BuildError;
 /// - Non-empty
 /// - No characters: `<`, `>`, `&`, `"`, `'`, `/`
 /// - Max 50 characters
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// Tags order by their text, so sets of tags iterate the same way on
+/// every build.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Tag(String);
 
 impl Tag {
     /// Maximum allowed tag length.
     pub const MAX_LENGTH: usize = 50;
 
+    /// Rejection reason of an empty tag (`Tags: a, , b`).
+    pub const EMPTY: &'static str = "tag is empty";
+
     /// Characters not allowed in tags (HTML-unsafe).
     const FORBIDDEN_CHARS: [char; 6] = ['<', '>', '&', '"', '\'', '/'];
 
     /// Attempt to create a validated Tag from raw input.
     pub fn new(raw: &str) -> Result<Self, BuildError> {
+        let invalid = |reason| BuildError::InvalidTag { tag: raw.to_string(), reason, path: None, position: None };
         let trimmed = raw.trim();
 
         if trimmed.is_empty() {
-            return Err(BuildError::InvalidTag {
-                tag: raw.to_string(),
-                reason: "tag is empty",
-            });
+            return Err(invalid(Self::EMPTY));
         }
 
         if trimmed.len() > Self::MAX_LENGTH {
-            return Err(BuildError::InvalidTag {
-                tag: raw.to_string(),
-                reason: "tag exceeds 50 characters",
-            });
+            return Err(invalid("tag exceeds 50 characters"));
         }
 
         if trimmed.chars().any(|c| Self::FORBIDDEN_CHARS.contains(&c)) {
-            return Err(BuildError::InvalidTag {
-                tag: raw.to_string(),
-                reason: "tag contains HTML special characters",
-            });
+            return Err(invalid("tag contains HTML special characters"));
         }
 
         Ok(Self(trimmed.to_string()))
@@ -80,6 +78,13 @@ impl AsRef<str> for Tag {
 mod tests {
     use super::*;
 
+    #[test]
+    fn orders_by_text() {
+        let tags: std::collections::BTreeSet<Tag> = ["web", "Rust", "async"].into_iter().map(|t| Tag::new(t).unwrap()).collect();
+        let order: Vec<&str> = tags.iter().map(Tag::as_str).collect();
+        assert_eq!(order, ["Rust", "async", "web"]);
+    }
+
     #[test]
     fn valid_tag() {
         let tag = Tag::new("Rust").unwrap();
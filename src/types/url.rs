@@ -0,0 +1,251 @@
+//! URL types that join without double or missing slashes.
+//!
+//! - `AbsoluteUrl`: an `http(s)` URL with a host (`base_url`, feed and
+//!   sitemap links), stored without a trailing slash
+//! - `RelPath`: a path relative to the output root (`images/a.webp`),
+//!   normalized on construction
+//! - `Href`: either, as used by `src` and `href` attributes
+
+use std::fmt;
+use std::ops::Deref;
+
+use serde::Deserialize;
+
+use crate::error::BuildError;
+
+/// A validated absolute `http(s)` URL.
+///
+/// Invariants (enforced at construction):
+/// - `http://` or `https://` scheme and a non-empty host
+/// - No whitespace
+/// - No trailing slash
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct AbsoluteUrl(String);
+
+impl AbsoluteUrl {
+    /// Attempt to create a validated URL from raw input.
+    pub fn new(raw: &str) -> Result<Self, BuildError> {
+        let invalid = |reason| BuildError::InvalidUrl { url: raw.to_string(), reason };
+        let trimmed = raw.trim();
+        let rest = trimmed
+            .strip_prefix("https://")
+            .or_else(|| trimmed.strip_prefix("http://"))
+            .ok_or_else(|| invalid("URL must start with http:// or https://"))?;
+        if rest.split(['/', '?', '#']).next().is_none_or(str::is_empty) {
+            return Err(invalid("URL has no host"));
+        }
+        if trimmed.contains(char::is_whitespace) {
+            return Err(invalid("URL contains whitespace"));
+        }
+        Ok(Self(trimmed.trim_end_matches('/').to_string()))
+    }
+
+    /// `path` below this URL, normalized like a `RelPath`
+    /// (`https://duck.dev/blog` + `/posts//a.html` →
+    /// `https://duck.dev/blog/posts/a.html`).
+    pub fn join(&self, path: impl AsRef<str>) -> Self {
+        let path = normalize(path.as_ref());
+        if path.is_empty() {
+            return Self(format!("{}/", self.0));
+        }
+        Self(format!("{}/{}", self.0, path.trim_start_matches("./")))
+    }
+
+    /// Get the URL as a string slice.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for AbsoluteUrl {
+    type Error = BuildError;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        Self::new(&raw)
+    }
+}
+
+impl Deref for AbsoluteUrl {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for AbsoluteUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A path relative to the output root (`posts/a.html`).
+///
+/// Invariants (enforced at construction):
+/// - No scheme, no protocol-relative `//` prefix, no backslashes
+/// - No leading slash, no empty or `.` segments, `x/..` resolved
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RelPath(String);
+
+impl RelPath {
+    /// Attempt to create a normalized path from raw input.
+    pub fn new(raw: &str) -> Result<Self, BuildError> {
+        let invalid = |reason| BuildError::InvalidUrl { url: raw.to_string(), reason };
+        let path = raw.split(['?', '#']).next().unwrap_or_default();
+        if path.starts_with("//") {
+            return Err(invalid("path is protocol-relative"));
+        }
+        if path.split('/').next().is_some_and(|first| first.contains(':')) {
+            return Err(invalid("path has a scheme"));
+        }
+        if path.contains('\\') {
+            return Err(invalid("path contains a backslash"));
+        }
+        Ok(Self(normalize(raw)))
+    }
+
+    /// `path` below this directory (`posts` + `a.html` → `posts/a.html`).
+    pub fn join(&self, path: &str) -> Self {
+        Self(normalize(&format!("{}/{}", self.0, path.replace('\\', "/"))))
+    }
+
+    /// Link to this path from a page `relative_root` below the root
+    /// (`"../"` or `".."` → `../posts/a.html`).
+    pub fn href(&self, relative_root: &str) -> String {
+        match (relative_root, self.0.as_str()) {
+            ("", "") => "./".to_string(),
+            ("", path) => path.to_string(),
+            (root, path) => format!("{}/{}", root.trim_end_matches('/'), path.trim_start_matches("./")),
+        }
+    }
+
+    /// Get the path as a string slice.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for RelPath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RelPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Target of a `src` or `href`: the same on every page, or relative to
+/// the output root.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Href {
+    Absolute(AbsoluteUrl),
+    Relative(RelPath),
+}
+
+impl Href {
+    /// An `http(s)` URL, else a root-relative path.
+    pub fn parse(raw: &str) -> Result<Self, BuildError> {
+        if raw.starts_with("http://") || raw.starts_with("https://") {
+            AbsoluteUrl::new(raw).map(Self::Absolute)
+        } else {
+            RelPath::new(raw).map(Self::Relative)
+        }
+    }
+
+    /// Attribute value on a page `relative_root` below the root.
+    pub fn href(&self, relative_root: &str) -> String {
+        match self {
+            Self::Absolute(url) => url.to_string(),
+            Self::Relative(path) => path.href(relative_root),
+        }
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        matches!(self, Self::Absolute(_))
+    }
+}
+
+impl fmt::Display for Href {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Absolute(url) => url.fmt(f),
+            Self::Relative(path) => path.fmt(f),
+        }
+    }
+}
+
+/// Drop empty and `.` segments (leading and repeated slashes) and resolve
+/// `x/..`; the query and fragment are kept as they are. A first segment
+/// with a colon gets `./` so it doesn't read as a scheme.
+fn normalize(raw: &str) -> String {
+    let split = raw.find(['?', '#']).unwrap_or(raw.len());
+    let (path, suffix) = raw.split_at(split);
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." if segments.last().is_some_and(|last| *last != "..") => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut out = segments.join("/");
+    if path.ends_with('/') && !out.is_empty() {
+        out.push('/');
+    }
+    if segments.first().is_some_and(|first| first.contains(':')) {
+        out.insert_str(0, "./");
+    }
+    out.push_str(suffix);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absolute_urls_join_with_one_slash() {
+        let base = AbsoluteUrl::new("https://duck.dev/blog/").unwrap();
+        assert_eq!(base.as_str(), "https://duck.dev/blog");
+        assert_eq!(base.join("/posts//a.html").as_str(), "https://duck.dev/blog/posts/a.html");
+        assert_eq!(base.join("").as_str(), "https://duck.dev/blog/");
+        assert_eq!(base.join("en/?q=a//b").as_str(), "https://duck.dev/blog/en/?q=a//b");
+
+        assert!(AbsoluteUrl::new("duck.dev").is_err());
+        assert!(AbsoluteUrl::new("https:///posts").is_err());
+        assert!(AbsoluteUrl::new("https://duck dev").is_err());
+    }
+
+    #[test]
+    fn rel_paths_normalize() {
+        let path = RelPath::new("/images//./a.webp").unwrap();
+        assert_eq!(path.as_str(), "images/a.webp");
+        assert_eq!(path.href("../"), "../images/a.webp");
+        assert_eq!(path.href(".."), "../images/a.webp");
+        assert_eq!(path.href(""), "images/a.webp");
+        assert_eq!(RelPath::new("posts/../tags/").unwrap().as_str(), "tags/");
+        assert_eq!(RelPath::new("../up.png").unwrap().href("../"), "../../up.png");
+        assert_eq!(RelPath::default().join("tags").join("tag_c:x.html").as_str(), "tags/tag_c:x.html");
+        assert_eq!(RelPath::default().join("a:b.html").href(""), "./a:b.html");
+
+        assert!(RelPath::new("mailto:me@duck.dev").is_err());
+        assert!(RelPath::new("//cdn.dev/a.png").is_err());
+        assert!(RelPath::new("a\\b.png").is_err());
+    }
+
+    #[test]
+    fn hrefs_by_kind() {
+        let remote = Href::parse("https://cdn.dev/a.png").unwrap();
+        assert!(remote.is_absolute());
+        assert_eq!(remote.href("../"), "https://cdn.dev/a.png");
+        assert_eq!(Href::parse("a.png").unwrap().href("../"), "../a.png");
+    }
+}
@@ -0,0 +1,282 @@
+//! HTML well-formedness check for `ssg check`.
+//!
+//! Not a full HTML5 parser: tags are matched on a stack, which is enough
+//! to catch what template bugs and raw HTML in markdown usually break:
+//!
+//! - unclosed and stray end tags (end tags HTML lets you omit, such as
+//!   `</li>` or `</p>`, are not required)
+//! - duplicate `id` attributes
+//! - block elements inside `<p>` and links inside links, which browsers
+//!   silently restructure
+//!
+//! Problems are recoverable `InvalidHtml` warnings at `page:line:column`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::error::{BuildError, SourcePos};
+
+/// Elements without content or end tag.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// Elements whose content is text up to their end tag.
+const RAW_TEXT: &[&str] = &["script", "style", "textarea", "title"];
+
+/// Elements whose end tag may be omitted.
+const OPTIONAL_END: &[&str] = &[
+    "body", "colgroup", "dd", "dt", "head", "html", "li", "option", "p", "tbody", "td", "tfoot", "th", "thead", "tr",
+];
+
+/// Elements that can't appear inside `<p>`.
+const BLOCK: &[&str] = &[
+    "address", "article", "aside", "blockquote", "details", "div", "dl", "fieldset", "figcaption", "figure",
+    "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "main", "nav", "ol", "pre", "section",
+    "table", "ul",
+];
+
+/// Check every page in `pages`, in parallel. Unreadable files are skipped.
+pub fn check_pages(pages: &[PathBuf]) -> Vec<BuildError> {
+    pages
+        .par_iter()
+        .filter_map(|page| fs::read_to_string(page).ok().map(|html| check_html(&html, page)))
+        .flatten()
+        .collect()
+}
+
+/// Problems in the HTML page `html` written to `path`.
+pub fn check_html(html: &str, path: &Path) -> Vec<BuildError> {
+    let mut issues = Vec::new();
+    let mut issue = |position: SourcePos, message: String| {
+        issues.push(BuildError::InvalidHtml { path: path.to_path_buf(), position, message });
+    };
+    let mut positions = PositionCounter::default();
+    // Open elements and where they were opened
+    let mut open: Vec<(String, SourcePos)> = Vec::new();
+    let mut ids: HashMap<String, SourcePos> = HashMap::new();
+
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &html[start..];
+        let position = positions.at(html, start);
+        if rest.starts_with("<!--") {
+            pos = rest.find("-->").map_or(html.len(), |end| start + end + 3);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            pos = rest.find('>').map_or(html.len(), |end| start + end + 1);
+            continue;
+        }
+        let Some(tag) = Tag::parse(rest) else {
+            // A literal `<`
+            pos = start + 1;
+            continue;
+        };
+        pos = start + tag.len;
+
+        if tag.end {
+            if VOID.contains(&tag.name.as_str()) {
+                continue;
+            }
+            let Some(index) = open.iter().rposition(|(name, _)| *name == tag.name) else {
+                issue(position, format!("stray </{}>", tag.name));
+                continue;
+            };
+            for (name, opened) in open.drain(index..).skip(1) {
+                if !OPTIONAL_END.contains(&name.as_str()) {
+                    issue(opened, format!("<{name}> is never closed (</{}> at {position})", tag.name));
+                }
+            }
+            continue;
+        }
+
+        for (name, value) in &tag.attributes {
+            if name == "id" {
+                if let Some(first) = ids.get(value) {
+                    issue(position, format!("duplicate id \"{value}\" (first at {first})"));
+                } else {
+                    ids.insert(value.clone(), position);
+                }
+            }
+        }
+        let name = tag.name.as_str();
+        if BLOCK.contains(&name) && let Some((_, opened)) = open.iter().rev().find(|(open, _)| open == "p") {
+            issue(position, format!("<{name}> inside <p> (opened at {opened})"));
+        }
+        if name == "a" && let Some((_, opened)) = open.iter().find(|(open, _)| open == "a") {
+            issue(position, format!("<a> inside <a> (opened at {opened})"));
+        }
+        // A new list item or cell ends the previous one
+        if let Some((top, _)) = open.last() && implied_end(top, name) {
+            open.pop();
+        }
+        if VOID.contains(&name) || tag.self_closing {
+            continue;
+        }
+        if RAW_TEXT.contains(&name) {
+            let close = format!("</{name}");
+            pos = find_ignore_case(&html[pos..], &close).map_or(html.len(), |end| pos + end);
+        }
+        open.push((tag.name, position));
+    }
+
+    for (name, opened) in open {
+        if !OPTIONAL_END.contains(&name.as_str()) {
+            issue(opened, format!("<{name}> is never closed"));
+        }
+    }
+    issues
+}
+
+/// Whether opening `next` implicitly closes the open element `top`.
+fn implied_end(top: &str, next: &str) -> bool {
+    match top {
+        "li" | "option" | "tr" => top == next,
+        "p" => next == "p",
+        "dt" | "dd" => matches!(next, "dt" | "dd"),
+        "td" | "th" => matches!(next, "td" | "th" | "tr"),
+        _ => false,
+    }
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.to_ascii_lowercase().find(needle)
+}
+
+/// A start or end tag.
+struct Tag {
+    /// Lowercased element name.
+    name: String,
+    end: bool,
+    self_closing: bool,
+    /// Lowercased names with their values.
+    attributes: Vec<(String, String)>,
+    /// Bytes from `<` through `>`.
+    len: usize,
+}
+
+impl Tag {
+    /// The tag `input` starts with, if it starts with one.
+    fn parse(input: &str) -> Option<Self> {
+        let (end, body) = match input[1..].strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, &input[1..]),
+        };
+        let name_len = body.find(|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != ':').unwrap_or(body.len());
+        if name_len == 0 || !body.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let name = body[..name_len].to_ascii_lowercase();
+
+        let mut attributes = Vec::new();
+        let mut rest = &body[name_len..];
+        let mut self_closing = false;
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix('>') {
+                rest = after;
+                break;
+            }
+            if let Some(after) = rest.strip_prefix("/>") {
+                self_closing = true;
+                rest = after;
+                break;
+            }
+            if let Some(after) = rest.strip_prefix('/') {
+                rest = after;
+                continue;
+            }
+            let attr_len = rest.find(|c: char| c.is_whitespace() || "=>/".contains(c)).unwrap_or(rest.len());
+            if attr_len == 0 && !rest.starts_with('=') {
+                // Unterminated tag
+                return None;
+            }
+            let attr = rest[..attr_len].to_ascii_lowercase();
+            rest = rest[attr_len..].trim_start();
+            let mut value = String::new();
+            if let Some(after) = rest.strip_prefix('=') {
+                let after = after.trim_start();
+                let (raw, remaining) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let close = after[1..].find(quote)? + 1;
+                        (&after[1..close], &after[close + 1..])
+                    }
+                    _ => {
+                        let end = after.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(after.len());
+                        after.split_at(end)
+                    }
+                };
+                value = raw.to_string();
+                rest = remaining;
+            }
+            attributes.push((attr, value));
+        }
+        Some(Self { name, end, self_closing, attributes, len: input.len() - rest.len() })
+    }
+}
+
+/// Positions of increasing byte offsets, without rescanning the page.
+#[derive(Default)]
+struct PositionCounter {
+    offset: usize,
+    /// Newlines before `offset`.
+    lines: usize,
+    /// Byte offset of the line `offset` is on.
+    line_start: usize,
+}
+
+impl PositionCounter {
+    fn at(&mut self, text: &str, offset: usize) -> SourcePos {
+        let skipped = &text[self.offset..offset];
+        self.lines += skipped.matches('\n').count();
+        if let Some(newline) = skipped.rfind('\n') {
+            self.line_start = self.offset + newline + 1;
+        }
+        self.offset = offset;
+        SourcePos::new(self.lines + 1, text[self.line_start..offset].chars().count() + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(html: &str) -> Vec<(usize, String)> {
+        check_html(html, Path::new("page.html"))
+            .into_iter()
+            .map(|issue| match issue {
+                BuildError::InvalidHtml { position, message, .. } => (position.line, message),
+                other => panic!("unexpected {other}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn accepts_valid_pages() {
+        let page = "<!DOCTYPE html>\n<html lang=\"en\">\n<head><meta charset=\"utf-8\"><title>a < b</title>\
+            <script>if (a < b && '</div>') {}</script></head>\n<body><!-- <div> -->\
+            <ul><li>one<li>two</ul><p>one<p>two<img src=\"a.png\" alt=\"a > b\"><br/>\
+            <svg><path d=\"M0\"/></svg></p></body>\n</html>";
+        assert_eq!(check(page), []);
+    }
+
+    #[test]
+    fn flags_unclosed_stray_duplicate_and_nested() {
+        let page = "<main>\n<div id=\"a\"><span>\n</div>\n<p>text<div id=\"a\"></div></p>\n</em>\n<a href=\"/\"><a href=\"/x\">x</a></a></main>\n<section>";
+        assert_eq!(check(page), [
+            (2, "<span> is never closed (</div> at 3:1)".to_string()),
+            (4, "duplicate id \"a\" (first at 2:1)".to_string()),
+            (4, "<div> inside <p> (opened at 4:1)".to_string()),
+            (5, "stray </em>".to_string()),
+            (6, "<a> inside <a> (opened at 6:1)".to_string()),
+            (7, "<section> is never closed".to_string()),
+        ]);
+        let issue = check_html("<p>\n  <div></div></p>", Path::new("index.html")).remove(0);
+        assert_eq!(issue.to_string(), "Invalid HTML in index.html:2:3: <div> inside <p> (opened at 1:1)");
+    }
+}
@@ -0,0 +1,356 @@
+//! Videos and animated GIFs embedded like images.
+//!
+//! A video file (`![Demo](demo.mp4)`) is copied to `public/video/` and
+//! emitted as a `<video controls>` that loads nothing until played. Its
+//! poster is a frame `ffmpeg` (when installed) picks from the video,
+//! optimized like any image.
+//!
+//! An animated GIF is converted with `ffmpeg` into
+//! `images/<stem>.<hash>.webm` and `.mp4`, and emitted as a muted, looping
+//! `<video>` whose poster is the first frame. Without `ffmpeg`, or if it
+//! fails, the GIF is copied as is: re-encoding it as an image would keep
+//! only its first frame. Outputs are content-addressed, so unchanged GIFs
+//! are never converted twice.
+
+use std::fs::{self, File};
+use std::io::{BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use image::AnimationDecoder;
+use image::codecs::gif::GifDecoder;
+use tracing::debug;
+
+use crate::error::BuildError;
+use crate::image::{hashed_name, OptimizedImage};
+use crate::output::{content_hash, Change, OutputSet};
+use crate::types::{Href, RelPath};
+
+/// A video file and its MIME type, for a `<source>` element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoSource {
+    pub src: Href,
+    pub mime: &'static str,
+}
+
+/// A `<video>` element: its sources and attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Player {
+    pub sources: Vec<VideoSource>,
+    pub attributes: &'static str,
+}
+
+impl Player {
+    /// A video file, played on demand.
+    pub fn controls(source: VideoSource) -> Self {
+        Self { sources: vec![source], attributes: r#"controls preload="none""# }
+    }
+
+    /// A converted GIF, playing muted and looping like the GIF did.
+    pub fn looping(sources: Vec<VideoSource>) -> Self {
+        Self { sources, attributes: "autoplay loop muted playsinline" }
+    }
+}
+
+/// A video file copied into the public directory.
+#[derive(Debug, Clone)]
+pub struct VideoFile {
+    pub source: VideoSource,
+    /// Extracted poster frame, to optimize as an image (None without
+    /// `ffmpeg`).
+    pub poster: Option<String>,
+}
+
+/// MIME type of a video file by extension (a query is ignored); None for
+/// anything else.
+pub fn mime_type(src: &str) -> Option<&'static str> {
+    let path = src.split('?').next().unwrap_or(src);
+    let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "mp4" | "m4v" => Some("video/mp4"),
+        "webm" => Some("video/webm"),
+        "ogv" => Some("video/ogg"),
+        "mov" => Some("video/quicktime"),
+        _ => None,
+    }
+}
+
+/// Copy the video `original_src` (relative to `content_dir`, or an
+/// external URL) into `public_dir/video/` and extract its poster frame.
+/// Extraction failures are added to `warnings`.
+pub fn copy_video(
+    original_src: &str,
+    content_dir: &Path,
+    public_dir: &Path,
+    outputs: &OutputSet,
+    warnings: &mut Vec<BuildError>,
+) -> Result<VideoFile, BuildError> {
+    let src = original_src.split('?').next().unwrap_or(original_src);
+    let mime = mime_type(src).unwrap_or("video/mp4");
+    if src.starts_with("http://") || src.starts_with("https://") {
+        let source = VideoSource { src: Href::parse(src)?, mime };
+        return Ok(VideoFile { source, poster: None });
+    }
+
+    let path = content_dir.join(src);
+    let (Some(file_name), Ok(metadata)) = (path.file_name(), fs::metadata(&path)) else {
+        return Err(BuildError::MediaNotFound { path });
+    };
+    if !metadata.is_file() {
+        return Err(BuildError::MediaNotFound { path });
+    }
+    let dir = public_dir.join("video");
+    outputs.create_dir(&dir)?;
+    let dest = dir.join(file_name);
+    let unchanged = fs::metadata(&dest).is_ok_and(|existing| {
+        existing.len() == metadata.len() && existing.modified().ok() >= metadata.modified().ok()
+    });
+    if unchanged {
+        outputs.record(&dest);
+    } else {
+        outputs.copy(&path, &dest)?;
+    }
+
+    let poster = poster_frame(&path, &metadata, outputs).unwrap_or_else(|message| {
+        warnings.push(BuildError::VideoFailed { path: path.clone(), message });
+        None
+    });
+    Ok(VideoFile {
+        source: VideoSource { src: Href::Relative(RelPath::default().join("video").join(&file_name.to_string_lossy())), mime },
+        poster: poster.map(|frame| frame.to_string_lossy().into_owned()),
+    })
+}
+
+/// A representative frame of `video` as PNG, kept in the temporary
+/// directory per source stamp so that unchanged videos aren't decoded
+/// again; None without `ffmpeg`.
+fn poster_frame(video: &Path, metadata: &fs::Metadata, outputs: &OutputSet) -> Result<Option<PathBuf>, String> {
+    let modified = metadata.modified().ok()
+        .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    let stem = video.file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+    let dir = std::env::temp_dir().join("ssg-posters").join(format!("{:x}-{modified:x}", metadata.len()));
+    let frame = dir.join(format!("{stem}-poster.png"));
+    if frame.exists() {
+        return Ok(Some(frame));
+    }
+    if outputs.is_dry_run() {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-y", "-loglevel", "error", "-i"])
+        .arg(video)
+        // The most typical of the first frames, not a black fade-in
+        .args(["-vf", "thumbnail", "-frames:v", "1"])
+        .arg(&frame);
+    run(cmd, &frame, video)
+        .map(|ran| ran.then_some(frame))
+}
+
+/// How an animated GIF is embedded.
+#[derive(Debug, Clone)]
+pub enum Animation {
+    /// Converted by `ffmpeg`, smallest first.
+    Video(Vec<VideoSource>),
+    /// Copied as is.
+    Gif(OptimizedImage),
+}
+
+/// Video encodings: extension, MIME type and `ffmpeg` arguments.
+const ENCODINGS: [(&str, &str, &[&str]); 2] = [
+    ("webm", "video/webm", &["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "40"]),
+    ("mp4", "video/mp4", &["-c:v", "libx264", "-crf", "28", "-movflags", "+faststart"]),
+];
+
+/// Convert the image `original_src` (relative to `content_dir`, a query is
+/// ignored) if it is an animated GIF; None for anything else. Conversion
+/// failures are added to `warnings` and fall back to the GIF.
+pub fn animate_gif(
+    original_src: &str,
+    content_dir: &Path,
+    public_dir: &Path,
+    outputs: &OutputSet,
+    warnings: &mut Vec<BuildError>,
+) -> Option<Animation> {
+    let src = original_src.split('?').next().unwrap_or(original_src);
+    let src_path = content_dir.join(src);
+    if !is_animated_gif(&src_path) {
+        return None;
+    }
+    let stem = Path::new(src).file_stem()?.to_str()?;
+    let hash = content_hash(&src_path)?;
+    let output = |extension: &str| {
+        let name = hashed_name(stem, &hash, extension);
+        (public_dir.join("images").join(&name), Href::Relative(RelPath::default().join("images").join(&name)))
+    };
+
+    let mut sources = Vec::new();
+    for (extension, mime, args) in ENCODINGS {
+        let (dest, href) = output(extension);
+        match encode(&src_path, &dest, args, outputs) {
+            Ok(true) => sources.push(VideoSource { src: href, mime }),
+            Ok(false) => break,
+            Err(message) => {
+                warnings.push(BuildError::VideoFailed { path: src_path.clone(), message });
+                break;
+            }
+        }
+    }
+    if sources.len() == ENCODINGS.len() {
+        return Some(Animation::Video(sources));
+    }
+
+    let (dest, src) = output("gif");
+    if let Err(e) = copy(&src_path, &dest, outputs) {
+        warnings.push(e);
+    }
+    let (width, height) = image::ImageReader::open(&src_path)
+        .ok()
+        .and_then(|reader| reader.with_guessed_format().ok())
+        .and_then(|reader| reader.into_dimensions().ok())
+        .unwrap_or((0, 0));
+    let bytes = src_path.metadata().map(|m| m.len()).unwrap_or(0);
+    Some(Animation::Gif(OptimizedImage {
+        src,
+        width,
+        height,
+        source_bytes: bytes,
+        output_bytes: bytes,
+        output_path: Some(dest),
+        elapsed: Duration::ZERO,
+    }))
+}
+
+/// Whether `path` is a GIF with more than one frame.
+fn is_animated_gif(path: &Path) -> bool {
+    let is_gif = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("gif"));
+    if !is_gif {
+        return false;
+    }
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    GifDecoder::new(BufReader::new(file)).is_ok_and(|decoder| decoder.into_frames().take(2).filter(Result::is_ok).count() == 2)
+}
+
+/// Encode `src` into `dest` with `ffmpeg` unless it exists; false if
+/// `ffmpeg` isn't installed.
+fn encode(src: &Path, dest: &Path, args: &[&str], outputs: &OutputSet) -> Result<bool, String> {
+    if dest.exists() {
+        outputs.record(dest);
+        return Ok(true);
+    }
+    if outputs.is_dry_run() {
+        outputs.record_change(dest, Change::Create);
+        return Ok(true);
+    }
+
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-y", "-loglevel", "error", "-i"])
+        .arg(src)
+        // Muted, and even dimensions for the yuv420p encoders
+        .args(["-an", "-pix_fmt", "yuv420p", "-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2"])
+        .args(args)
+        .arg(dest);
+    let ran = run(cmd, dest, src)?;
+    if ran {
+        outputs.record_change(dest, Change::Create);
+    }
+    Ok(ran)
+}
+
+/// Run `ffmpeg` writing `dest` from `src`; false if it isn't installed.
+fn run(mut cmd: Command, dest: &Path, src: &Path) -> Result<bool, String> {
+    let output = match cmd.output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            debug!("ffmpeg not installed, skipping {}", src.display());
+            return Ok(false);
+        }
+        Err(e) => return Err(format!("failed to run ffmpeg: {e}")),
+    };
+    if !output.status.success() {
+        // Don't leave a partial file behind to be reused
+        let _ = fs::remove_file(dest);
+        return Err(format!("ffmpeg exited with {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(true)
+}
+
+/// Copy `src` to `dest` unless it exists (the name is content-addressed).
+fn copy(src: &Path, dest: &Path, outputs: &OutputSet) -> Result<(), BuildError> {
+    if dest.exists() {
+        outputs.record(dest);
+        return Ok(());
+    }
+    outputs.copy(src, dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Delay, Frame, RgbaImage};
+    use image::codecs::gif::GifEncoder;
+
+    fn write_gif(path: &Path, frames: usize) {
+        let mut encoder = GifEncoder::new(File::create(path).unwrap());
+        let frames = (0..frames).map(|_| Frame::from_parts(RgbaImage::new(4, 2), 0, 0, Delay::from_numer_denom_ms(100, 1)));
+        encoder.encode_frames(frames).unwrap();
+    }
+
+    #[test]
+    fn only_animated_gifs_are_converted() {
+        let dir = tempfile::tempdir().unwrap();
+        let public = dir.path().join("public");
+        std::fs::create_dir_all(public.join("images")).unwrap();
+        write_gif(&dir.path().join("still.gif"), 1);
+        write_gif(&dir.path().join("loop.gif"), 3);
+        let (outputs, mut warnings) = (OutputSet::new(), Vec::new());
+
+        assert!(animate_gif("still.gif", dir.path(), &public, &outputs, &mut warnings).is_none());
+        assert!(animate_gif("missing.gif", dir.path(), &public, &outputs, &mut warnings).is_none());
+        match animate_gif("loop.gif?width=2", dir.path(), &public, &outputs, &mut warnings).unwrap() {
+            Animation::Video(sources) => {
+                assert_eq!(sources.iter().map(|s| s.mime).collect::<Vec<_>>(), ["video/webm", "video/mp4"]);
+                assert!(sources.iter().all(|s| public.join(s.src.to_string()).exists()));
+            }
+            // Without ffmpeg
+            Animation::Gif(gif) => {
+                assert!(gif.src.to_string().starts_with("images/loop.") && gif.src.to_string().ends_with(".gif"));
+                assert_eq!((gif.width, gif.height), (4, 2));
+                assert!(gif.output_path.unwrap().exists());
+            }
+        }
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn video_files_are_copied() {
+        assert_eq!(mime_type("clips/demo.MP4?width=600"), Some("video/mp4"));
+        assert_eq!(mime_type("demo.webm"), Some("video/webm"));
+        assert_eq!(mime_type("demo.gif"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let public = dir.path().join("public");
+        std::fs::create_dir_all(dir.path().join("clips")).unwrap();
+        fs::write(dir.path().join("clips/demo.mp4"), b"not really a video").unwrap();
+        let (outputs, mut warnings) = (OutputSet::new(), Vec::new());
+
+        let file = copy_video("clips/demo.mp4", dir.path(), &public, &outputs, &mut warnings).unwrap();
+        assert_eq!(file.source, VideoSource { src: Href::parse("video/demo.mp4").unwrap(), mime: "video/mp4" });
+        assert!(public.join("video/demo.mp4").exists());
+        // ffmpeg, if installed, can't read it
+        assert!(file.poster.is_none());
+        assert!(warnings.iter().all(|w| matches!(w, BuildError::VideoFailed { .. })));
+
+        let remote = copy_video("https://cdn.dev/demo.webm", dir.path(), &public, &outputs, &mut warnings).unwrap();
+        assert_eq!((remote.source.src.to_string().as_str(), remote.source.mime), ("https://cdn.dev/demo.webm", "video/webm"));
+        assert!(matches!(
+            copy_video("missing.mp4", dir.path(), &public, &outputs, &mut warnings),
+            Err(BuildError::MediaNotFound { .. })
+        ));
+    }
+}
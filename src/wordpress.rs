@@ -0,0 +1,816 @@
+//! `ssg import wordpress`: posts from a WordPress export (WXR) file.
+//!
+//! Each published post becomes a markdown post: the HTML content is
+//! converted to markdown (tables, frames and media stay HTML), categories
+//! and tags become the `Tags:` line, the post's old URL an alias, and the
+//! excerpt the summary. `[caption]` shortcodes become `Caption:`
+//! paragraphs.
+//!
+//! Images from the blog's uploads are downloaded into `uploads/` in the
+//! content directory, full size rather than the resized copies WordPress
+//! links, and the posts point at the local files, so the build optimizes
+//! them like any other image. Images from other sites stay remote.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+
+use crate::csp::parse_attributes;
+use crate::error::BuildError;
+use crate::import::{self, Header, ImportSummary};
+use crate::sri;
+
+/// Directory in the content directory downloaded images go to.
+const UPLOADS_DIR: &str = "uploads";
+
+/// Import the published posts of the WordPress export at `source` into
+/// `content_dir`. With `dry_run`, nothing is downloaded or written.
+pub fn import(source: &Path, content_dir: &Path, dry_run: bool) -> Result<ImportSummary, BuildError> {
+    let xml = fs::read_to_string(source)
+        .map_err(|e| BuildError::ContentNotReadable { path: source.to_path_buf(), source: e })?;
+    let issue = |message: String| BuildError::ImportFailed { path: source.to_path_buf(), message };
+    let channel = elements(&xml)
+        .into_iter()
+        .find(|e| e.name == "rss")
+        .and_then(|rss| elements(rss.inner).into_iter().find(|e| e.name == "channel"))
+        .ok_or_else(|| issue("not a WordPress export (no <rss><channel>)".to_string()))?;
+    let channel = elements(channel.inner);
+    let site = ["wp:base_blog_url", "wp:base_site_url", "link"]
+        .iter()
+        .find_map(|name| channel.iter().find(|e| e.name == *name).map(|e| e.text()))
+        .unwrap_or_default();
+
+    let mut summary = ImportSummary::default();
+    let mut targets = HashSet::new();
+    let mut copied = HashMap::new();
+    for item in channel.iter().filter(|e| e.name == "item") {
+        let Some(post) = Item::new(item.inner) else { continue };
+        if post.kind != "post" || post.status == "trash" {
+            continue;
+        }
+        if post.status != "publish" {
+            summary.drafts += 1;
+            continue;
+        }
+        let label = if post.link.is_empty() { &post.title } else { &post.link };
+        let Some(slug) = post.slug() else {
+            summary.warnings.push(issue(format!("skipped {label:?}: no usable slug")));
+            continue;
+        };
+        let target = content_dir.join(format!("{slug}.md"));
+        if target.exists() || !targets.insert(target.clone()) {
+            summary.warnings.push(issue(format!("skipped {label:?}: {} already exists", target.display())));
+            continue;
+        }
+
+        // Only an image that arrived is linked locally; the others keep
+        // their URL
+        let body = markdown(&captions(&post.content), |src| {
+            let Some((url, rel)) = upload(src, &site) else {
+                return src.to_string();
+            };
+            let to = content_dir.join(&rel);
+            let arrived = match copied.get(&to) {
+                Some(&arrived) => arrived,
+                None if to.exists() => true,
+                None => {
+                    let arrived = if dry_run { Ok(()) } else { download(&url, &to) };
+                    if let Err(e) = &arrived {
+                        summary.warnings.push(issue(format!("cannot download {url}: {e}")));
+                    } else {
+                        summary.images += 1;
+                    }
+                    copied.insert(to, arrived.is_ok());
+                    arrived.is_ok()
+                }
+            };
+            if arrived { rel } else { src.to_string() }
+        });
+
+        let header = post.header(source, &mut summary.warnings);
+        if !dry_run {
+            import::write(&target, &header.markdown(&body))?;
+        }
+        summary.posts.push((source.to_path_buf(), target));
+    }
+    Ok(summary)
+}
+
+/// Fetch `url` (its full-size original first) into `to`.
+fn download(url: &str, to: &Path) -> Result<(), String> {
+    let original = full_size(url);
+    let body = match original.as_deref().map(sri::fetch) {
+        Some(Ok(body)) => body,
+        _ => sri::fetch(url)?,
+    };
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(to, body).map_err(|e| e.to_string())
+}
+
+/// `photo.jpg` for a resized `photo-300x200.jpg` WordPress generated.
+fn full_size(url: &str) -> Option<String> {
+    let (stem, ext) = url.rsplit_once('.')?;
+    let (name, size) = stem.rsplit_once('-')?;
+    let (width, height) = size.split_once('x')?;
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    (digits(width) && digits(height)).then(|| format!("{name}.{ext}"))
+}
+
+/// The absolute URL of an image `src` from the blog's uploads and its
+/// path in the content directory.
+fn upload(src: &str, site: &str) -> Option<(String, String)> {
+    let url = match src.strip_prefix('/') {
+        Some(_) if src.starts_with("//") => format!("https:{src}"),
+        Some(_) => format!("{}{src}", site.trim_end_matches('/')),
+        None => src.to_string(),
+    };
+    let without_query = url.split(['?', '#']).next().unwrap_or_default();
+    let (_, rel) = without_query.split_once("/wp-content/uploads/")?;
+    let rel = import::normalize(&percent_decode(rel))?;
+    let rel = full_size(&rel).unwrap_or(rel);
+    Some((without_query.to_string(), format!("{UPLOADS_DIR}/{rel}")))
+}
+
+/// An `<item>` of the export.
+#[derive(Debug, Default)]
+struct Item {
+    title: String,
+    link: String,
+    name: String,
+    id: String,
+    status: String,
+    kind: String,
+    date_gmt: String,
+    date: String,
+    modified_gmt: String,
+    content: String,
+    excerpt: String,
+    /// `(domain, name)` of its categories and tags.
+    categories: Vec<(String, String)>,
+}
+
+impl Item {
+    fn new(xml: &str) -> Option<Self> {
+        let mut item = Self::default();
+        for element in elements(xml) {
+            let field = match element.name {
+                "title" => &mut item.title,
+                "link" => &mut item.link,
+                "wp:post_name" => &mut item.name,
+                "wp:post_id" => &mut item.id,
+                "wp:status" => &mut item.status,
+                "wp:post_type" => &mut item.kind,
+                "wp:post_date_gmt" => &mut item.date_gmt,
+                "wp:post_date" => &mut item.date,
+                "wp:post_modified_gmt" => &mut item.modified_gmt,
+                "content:encoded" => &mut item.content,
+                "excerpt:encoded" => &mut item.excerpt,
+                "category" => {
+                    let (attrs, _) = parse_attributes(element.attrs);
+                    let domain = attrs.into_iter().find(|(k, _)| k == "domain").map(|(_, v)| v).unwrap_or_default();
+                    item.categories.push((domain, element.text()));
+                    continue;
+                }
+                _ => continue,
+            };
+            *field = element.text().trim().to_string();
+        }
+        (!item.kind.is_empty()).then_some(item)
+    }
+
+    /// File stem: the post name, else `post-{id}`.
+    fn slug(&self) -> Option<String> {
+        let name = percent_decode(&self.name).replace(['/', '\\'], "-");
+        let slug = if name.is_empty() { format!("post-{}", self.id) } else { name };
+        (slug != "post-" && !slug.starts_with('.')).then_some(slug)
+    }
+
+    fn header(&self, source: &Path, warnings: &mut Vec<BuildError>) -> Header {
+        let raw_tags: Vec<String> = self
+            .categories
+            .iter()
+            .filter(|(domain, name)| matches!(domain.as_str(), "category" | "post_tag") && name != "Uncategorized")
+            .map(|(_, name)| name.clone())
+            .collect();
+        let excerpt = markdown(&self.excerpt, str::to_string);
+        let alias = self.link.split_once("://").and_then(|(_, rest)| rest.find('/').map(|i| rest[i..].to_string()));
+        Header {
+            title: (!self.title.is_empty()).then(|| self.title.clone()),
+            date: gmt(&self.date_gmt).or_else(|| import::normalize_date(&self.date)),
+            updated: gmt(&self.modified_gmt),
+            summary: (!excerpt.is_empty()).then_some(excerpt),
+            tags: import::valid_tags(&raw_tags, source, warnings),
+            aliases: alias.filter(|path| path != "/" && !path.contains("?p=")).into_iter().collect(),
+        }
+    }
+}
+
+/// RFC 3339 date of a `2019-05-04 10:30:00` UTC timestamp; drafts have
+/// `0000-00-00 00:00:00`.
+fn gmt(raw: &str) -> Option<String> {
+    NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|date| date.format("%Y-%m-%dT%H:%M:%S+00:00").to_string())
+}
+
+/// An XML element: its qualified name, raw attributes and content.
+#[derive(Debug)]
+struct Element<'a> {
+    name: &'a str,
+    attrs: &'a str,
+    inner: &'a str,
+}
+
+impl Element<'_> {
+    /// Text content: CDATA sections as is, the rest entity-decoded.
+    fn text(&self) -> String {
+        let mut text = String::new();
+        let mut rest = self.inner;
+        while let Some(start) = rest.find("<![CDATA[") {
+            text.push_str(&decode_entities(&rest[..start]));
+            let cdata = &rest[start + "<![CDATA[".len()..];
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            text.push_str(&cdata[..end]);
+            rest = cdata.get(end + 3..).unwrap_or_default();
+        }
+        text.push_str(&decode_entities(rest));
+        text
+    }
+}
+
+/// The child elements of `xml`, skipping comments, CDATA and processing
+/// instructions.
+fn elements(xml: &str) -> Vec<Element<'_>> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = xml[pos..].find('<').map(|i| pos + i) {
+        let rest = &xml[start..];
+        if let Some(skip) = skip_markup(rest) {
+            pos = start + skip;
+            continue;
+        }
+        let Some(tag_end) = rest.find('>') else { break };
+        let tag = &rest[1..tag_end];
+        let name_len = tag.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(tag.len());
+        let name = &tag[..name_len];
+        if name.is_empty() || tag.starts_with('/') {
+            pos = start + tag_end + 1;
+            continue;
+        }
+        let open = start + tag_end + 1;
+        if tag.ends_with('/') {
+            found.push(Element { name, attrs: tag[name_len..].trim_end_matches('/'), inner: "" });
+            pos = open;
+            continue;
+        }
+        let (inner_end, after) = closing(xml, open, name);
+        found.push(Element { name, attrs: &tag[name_len..], inner: &xml[open..inner_end] });
+        pos = after;
+    }
+    found
+}
+
+/// Length of the comment, CDATA section or declaration `rest` starts with.
+fn skip_markup(rest: &str) -> Option<usize> {
+    let (open, close) = [("<!--", "-->"), ("<![CDATA[", "]]>"), ("<?", "?>"), ("<!", ">")]
+        .into_iter()
+        .find(|(open, _)| rest.starts_with(open))?;
+    Some(rest[open.len()..].find(close).map_or(rest.len(), |end| open.len() + end + close.len()))
+}
+
+/// Where the content of the `name` element opened before `from` ends, and
+/// the position after its closing tag.
+fn closing(xml: &str, from: usize, name: &str) -> (usize, usize) {
+    let mut depth = 0;
+    let mut pos = from;
+    while let Some(start) = xml[pos..].find('<').map(|i| pos + i) {
+        let rest = &xml[start..];
+        if let Some(skip) = skip_markup(rest) {
+            pos = start + skip;
+            continue;
+        }
+        let tag_end = rest.find('>').unwrap_or(rest.len() - 1);
+        let tag = &rest[1..tag_end];
+        let tag_name = tag.trim_start_matches('/').split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or_default();
+        if tag_name == name {
+            if tag.starts_with('/') {
+                if depth == 0 {
+                    return (start, start + tag_end + 1);
+                }
+                depth -= 1;
+            } else if !tag.ends_with('/') {
+                depth += 1;
+            }
+        }
+        pos = start + tag_end + 1;
+    }
+    (xml.len(), xml.len())
+}
+
+/// `&amp;`, `&#8217;` and the other entities in exported text.
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                "hellip" => '…',
+                "ndash" => '–',
+                "mdash" => '—',
+                "lsquo" => '‘',
+                "rsquo" => '’',
+                "ldquo" => '“',
+                "rdquo" => '”',
+                entity => {
+                    let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                    };
+                    code.and_then(char::from_u32)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// `[caption]…[/caption]` shortcodes as `<figure>`s with a
+/// `<figcaption>`: the image (or the link around it), then the caption.
+fn captions(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("[caption") {
+        out.push_str(&rest[..start]);
+        let shortcode = &rest[start..];
+        let (Some(open_end), Some(close)) = (shortcode.find(']'), shortcode.find("[/caption]")) else {
+            out.push_str(shortcode);
+            return out;
+        };
+        let inner = shortcode[open_end + 1..close.max(open_end + 1)].trim();
+        let image_end = if inner.starts_with("<a") { inner.find("</a>").map(|end| end + 4) } else { inner.find('>').map(|end| end + 1) };
+        let (image, caption) = inner.split_at(image_end.unwrap_or(0));
+        out.push_str(&format!("<figure>{image}<figcaption>{}</figcaption></figure>", caption.trim()));
+        rest = &shortcode[close + "[/caption]".len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Elements copied into the markdown as HTML.
+const RAW_ELEMENTS: &[&str] = &["table", "iframe", "video", "audio", "object", "dl"];
+
+/// What an open element collects its content for.
+#[derive(Debug)]
+enum Frame {
+    Quote,
+    Item,
+    Link(String),
+    Code(String),
+    Caption,
+}
+
+/// Markdown of WordPress post `html`; `image` maps each image source to
+/// the one to use.
+fn markdown(html: &str, mut image: impl FnMut(&str) -> String) -> String {
+    // Open frames and the markdown of each; the first is the post
+    let mut stack: Vec<(Option<Frame>, String)> = vec![(None, String::new())];
+    let mut lists: Vec<Option<usize>> = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        let (text, after) = rest.split_at(text_end);
+        let (frame, out) = stack.last_mut().expect("the post frame is never popped");
+        match frame {
+            Some(Frame::Code(_)) => out.push_str(&decode_entities(text)),
+            _ => push_text(out, text),
+        }
+        rest = after;
+        let Some(tag) = rest.strip_prefix('<') else { break };
+        if let Some(skip) = skip_markup(rest) {
+            rest = &rest[skip..];
+            continue;
+        }
+        let closing_tag = tag.starts_with('/');
+        let tag = tag.trim_start_matches('/');
+        let name_len = tag.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(tag.len());
+        if name_len == 0 {
+            stack.last_mut().expect("the post frame is never popped").1.push_str("&lt;");
+            rest = tag;
+            continue;
+        }
+        let name = tag[..name_len].to_ascii_lowercase();
+        let (attrs, after) = parse_attributes(&tag[name_len..]);
+        let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+        let source = rest;
+        rest = after;
+
+        if !closing_tag && RAW_ELEMENTS.contains(&name.as_str()) {
+            let end_tag = format!("</{name}>");
+            let end = find_ignore_case(rest, &end_tag).map_or(rest.len(), |end| end + end_tag.len());
+            let out = &mut stack.last_mut().expect("the post frame is never popped").1;
+            block(out);
+            out.push_str(&source[..source.len() - rest.len() + end]);
+            block(out);
+            rest = &rest[end..];
+            continue;
+        }
+        if !closing_tag && matches!(name.as_str(), "script" | "style") {
+            let end_tag = format!("</{name}>");
+            rest = find_ignore_case(rest, &end_tag).map_or("", |end| &rest[end + end_tag.len()..]);
+            continue;
+        }
+
+        let in_code = matches!(stack.last(), Some((Some(Frame::Code(_)), _)));
+        let out = &mut stack.last_mut().expect("the post frame is never popped").1;
+        match (name.as_str(), closing_tag) {
+            ("p" | "div" | "figure" | "section" | "article" | "header" | "footer", _) => block(out),
+            ("br", _) => out.push_str("\\\n"),
+            ("hr", false) => {
+                block(out);
+                out.push_str("---");
+                block(out);
+            }
+            (h @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6"), closing) => {
+                block(out);
+                if !closing {
+                    out.push_str(&"#".repeat(usize::from(h.as_bytes()[1] - b'0')));
+                    out.push(' ');
+                }
+            }
+            ("strong" | "b", _) => out.push_str("**"),
+            ("em" | "i", _) => out.push('*'),
+            ("del" | "s" | "strike", _) => out.push_str("~~"),
+            ("code", false) if in_code => {
+                let lang = attr("class").and_then(|class| class.split_whitespace().find_map(|c| c.strip_prefix("language-")));
+                if let (Some(lang), Some((Some(Frame::Code(fence)), _))) = (lang, stack.last_mut()) {
+                    *fence = lang.to_string();
+                }
+            }
+            ("code", true) if in_code => {}
+            ("code", _) => out.push('`'),
+            ("img", false) => {
+                let src = image(attr("src").unwrap_or_default());
+                let alt = attr("alt").unwrap_or_default().replace(['[', ']'], "");
+                out.push_str(&format!("![{alt}]({src})"));
+            }
+            ("ul" | "ol", false) => {
+                block(out);
+                lists.push((name == "ol").then(|| attr("start").and_then(|s| s.parse().ok()).unwrap_or(1)));
+            }
+            ("ul" | "ol", true) => {
+                lists.pop();
+                block(out);
+            }
+            ("blockquote", false) => stack.push((Some(Frame::Quote), String::new())),
+            ("li", false) => stack.push((Some(Frame::Item), String::new())),
+            ("a", false) => stack.push((Some(Frame::Link(attr("href").unwrap_or_default().to_string())), String::new())),
+            ("pre", false) => {
+                let lang = attr("class").and_then(|class| class.split_whitespace().find_map(|c| c.strip_prefix("language-")));
+                stack.push((Some(Frame::Code(lang.unwrap_or_default().to_string())), String::new()));
+            }
+            ("figcaption", false) => stack.push((Some(Frame::Caption), String::new())),
+            ("blockquote" | "li" | "a" | "pre" | "figcaption", true) => close_frame(&mut stack, &mut lists, &name),
+            _ => {}
+        }
+    }
+    while stack.len() > 1 {
+        let name = match stack.last() {
+            Some((Some(Frame::Quote), _)) => "blockquote",
+            Some((Some(Frame::Item), _)) => "li",
+            Some((Some(Frame::Link(_)), _)) => "a",
+            Some((Some(Frame::Code(_)), _)) => "pre",
+            _ => "figcaption",
+        };
+        close_frame(&mut stack, &mut lists, name);
+    }
+
+    let markdown = stack.pop().map(|(_, out)| out).unwrap_or_default();
+    let mut tidy = String::with_capacity(markdown.len());
+    let mut blank = false;
+    for line in markdown.lines() {
+        let line = if line.ends_with('\\') { line } else { line.trim_end() };
+        if line.is_empty() {
+            blank = !tidy.is_empty();
+            continue;
+        }
+        if blank {
+            tidy.push('\n');
+            blank = false;
+        }
+        tidy.push_str(line);
+        tidy.push('\n');
+    }
+    tidy
+}
+
+/// Close the innermost frame if it is for `name` and add its markdown to
+/// the frame around it.
+fn close_frame(stack: &mut Vec<(Option<Frame>, String)>, lists: &mut [Option<usize>], name: &str) {
+    let open = match stack.last() {
+        Some((Some(Frame::Quote), _)) => "blockquote",
+        Some((Some(Frame::Item), _)) => "li",
+        Some((Some(Frame::Link(_)), _)) => "a",
+        Some((Some(Frame::Code(_)), _)) => "pre",
+        Some((Some(Frame::Caption), _)) => "figcaption",
+        _ => return,
+    };
+    if open != name {
+        return;
+    }
+    let Some((Some(frame), inner)) = stack.pop() else { return };
+    let out = &mut stack.last_mut().expect("the post frame is never popped").1;
+    match frame {
+        Frame::Quote => {
+            block(out);
+            for line in inner.trim().lines() {
+                out.push_str(if line.is_empty() { ">\n" } else { "> " });
+                if !line.is_empty() {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+            block(out);
+        }
+        Frame::Item => {
+            let marker = match lists.last_mut() {
+                Some(Some(n)) => {
+                    *n += 1;
+                    format!("{}. ", *n - 1)
+                }
+                _ => "- ".to_string(),
+            };
+            if !out.is_empty() && !out.ends_with('\n') {
+                out.push('\n');
+            }
+            out.push_str(&marker);
+            let indent = " ".repeat(marker.len());
+            for (i, line) in inner.trim().lines().enumerate() {
+                if i > 0 && !line.is_empty() {
+                    out.push_str(&indent);
+                }
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        Frame::Link(href) => {
+            let text = inner.trim();
+            // A link to the full-size image around the image itself
+            if href.is_empty() || text.starts_with("![") && text.ends_with(')') && !text.contains("](#") {
+                out.push_str(text);
+            } else if !text.is_empty() {
+                out.push_str(&format!("[{text}]({})", href.replace(' ', "%20")));
+            }
+        }
+        Frame::Code(lang) => {
+            block(out);
+            let code = inner.trim_matches('\n');
+            let fence = if code.contains("```") { "~~~" } else { "```" };
+            out.push_str(&format!("{fence}{lang}\n{code}\n{fence}"));
+            block(out);
+        }
+        Frame::Caption => {
+            let text = inner.trim();
+            if !text.is_empty() {
+                block(out);
+                out.push_str(&format!("Caption: {text}"));
+                block(out);
+            }
+        }
+    }
+}
+
+/// End the paragraph `out` ends with, if any.
+fn block(out: &mut String) {
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push_str(if out.ends_with('\n') { "\n" } else { "\n\n" });
+    }
+}
+
+/// Add HTML text to `out`: whitespace collapsed, blank lines ending
+/// paragraphs and single line breaks kept, as WordPress shows them.
+fn push_text(out: &mut String, text: &str) {
+    if text.trim().is_empty() {
+        if text.contains("\n\n") {
+            block(out);
+        } else if !text.is_empty() && !out.is_empty() && !out.ends_with(char::is_whitespace) {
+            out.push(' ');
+        }
+        return;
+    }
+    let text = text.replace("\r\n", "\n");
+    for (i, paragraph) in text.split("\n\n").enumerate() {
+        if i > 0 {
+            block(out);
+        }
+        let paragraph = if out.is_empty() || out.ends_with('\n') { paragraph.trim_start() } else { paragraph };
+        let lines: Vec<String> = paragraph.split('\n').map(|line| escape(&line.split_whitespace().collect::<Vec<_>>().join(" "))).collect();
+        let leading = paragraph.starts_with(char::is_whitespace) && !out.is_empty() && !out.ends_with(char::is_whitespace);
+        let trailing = paragraph.ends_with(char::is_whitespace);
+        if leading {
+            out.push(' ');
+        }
+        out.push_str(&lines.iter().filter(|line| !line.is_empty()).cloned().collect::<Vec<_>>().join("\\\n"));
+        if trailing {
+            out.push(' ');
+        }
+    }
+}
+
+/// Decoded `text` with markdown's special characters escaped.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in decode_entities(text).chars() {
+        match c {
+            '\\' | '*' | '_' | '`' | '[' | ']' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '<' => out.push_str("&lt;"),
+            '\u{a0}' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.to_ascii_lowercase().find(needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_post_html() {
+        let html = "<!-- wp:paragraph -->\n<p>Hello <strong>world</strong> &amp; <a href=\"https://example.com\">friends</a>.</p>\n<!-- /wp:paragraph -->\n\n\
+            <h2>Steps</h2>\n<ol><li>One</li><li>Two <em>2*2</em></li></ol>\n\
+            <blockquote><p>Quoted</p></blockquote>\n\
+            <pre class=\"wp-block-code\"><code class=\"language-rust\">let a = 1 &lt; 2;</code></pre>\n\
+            Classic line\nnext line\n\n<table><tr><td>x</td></tr></table>";
+        assert_eq!(
+            markdown(html, str::to_string),
+            "Hello **world** & [friends](https://example.com).\n\n## Steps\n\n1. One\n2. Two *2\\*2*\n\n> Quoted\n\n\
+             ```rust\nlet a = 1 < 2;\n```\n\nClassic line\\\nnext line\n\n<table><tr><td>x</td></tr></table>\n"
+        );
+    }
+
+    #[test]
+    fn relinks_uploaded_images() {
+        let html = captions(
+            "[caption id=\"attachment_7\" align=\"alignnone\" width=\"300\"]<a href=\"https://old.example/wp-content/uploads/2019/05/duck.jpg\">\
+             <img src=\"https://old.example/wp-content/uploads/2019/05/duck-300x200.jpg\" alt=\"A duck\" /></a> The duck[/caption]\n\n\
+             <img src=\"https://elsewhere.example/x.png\" alt=\"\">",
+        );
+        let mut images = Vec::new();
+        let markdown = markdown(&html, |src| match upload(src, "https://old.example") {
+            Some((url, rel)) => {
+                images.push(url);
+                rel
+            }
+            None => src.to_string(),
+        });
+        assert_eq!(markdown, "![A duck](uploads/2019/05/duck.jpg)\n\nCaption: The duck\n\n![](https://elsewhere.example/x.png)\n");
+        assert_eq!(images, ["https://old.example/wp-content/uploads/2019/05/duck-300x200.jpg"]);
+        assert_eq!(full_size(&images[0]).as_deref(), Some("https://old.example/wp-content/uploads/2019/05/duck.jpg"));
+        assert_eq!(upload("/wp-content/uploads/a%20b.png", "https://old.example/").unwrap().1, "uploads/a b.png");
+    }
+
+    #[test]
+    fn failed_downloads_keep_their_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploads = dir.path().join("old/wp-content/uploads");
+        fs::create_dir_all(&uploads).unwrap();
+        fs::write(uploads.join("duck.jpg"), "jpeg").unwrap();
+        let base = format!("file://{}", dir.path().join("old").display());
+        let xml = format!(
+            r#"<rss><channel><link>{base}</link><item>
+    <title>Ducks</title>
+    <wp:post_name>ducks</wp:post_name>
+    <wp:post_date_gmt>2019-05-04 10:30:00</wp:post_date_gmt>
+    <wp:status>publish</wp:status>
+    <wp:post_type>post</wp:post_type>
+    <content:encoded><![CDATA[<img src="{base}/wp-content/uploads/duck.jpg" alt="Here">
+<img src="{base}/wp-content/uploads/gone.jpg" alt="Gone">]]></content:encoded>
+  </item></channel></rss>"#
+        );
+        let source = dir.path().join("export.xml");
+        fs::write(&source, xml).unwrap();
+        let content = dir.path().join("content");
+
+        let summary = import(&source, &content, false).unwrap();
+        assert_eq!(summary.images, 1);
+        assert_eq!(summary.warnings.len(), 1);
+        assert!(summary.warnings[0].to_string().contains("gone.jpg"));
+        assert_eq!(fs::read_to_string(content.join("uploads/duck.jpg")).unwrap(), "jpeg");
+        let markdown = fs::read_to_string(content.join("ducks.md")).unwrap();
+        assert!(markdown.contains("![Here](uploads/duck.jpg)"));
+        assert!(markdown.contains(&format!("![Gone]({base}/wp-content/uploads/gone.jpg)")));
+    }
+
+    #[test]
+    fn imports_published_posts() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+  <title>Old blog</title>
+  <link>https://old.example</link>
+  <wp:base_blog_url>https://old.example</wp:base_blog_url>
+  <item>
+    <title>Hello &amp; welcome</title>
+    <link>https://old.example/2019/05/hello/</link>
+    <wp:post_id>12</wp:post_id>
+    <wp:post_date><![CDATA[2019-05-04 12:30:00]]></wp:post_date>
+    <wp:post_date_gmt><![CDATA[2019-05-04 10:30:00]]></wp:post_date_gmt>
+    <wp:post_name><![CDATA[hello]]></wp:post_name>
+    <wp:status><![CDATA[publish]]></wp:status>
+    <wp:post_type><![CDATA[post]]></wp:post_type>
+    <category domain="category" nicename="uncategorized"><![CDATA[Uncategorized]]></category>
+    <category domain="post_tag" nicename="rust"><![CDATA[Rust]]></category>
+    <category domain="post_tag" nicename="a-b"><![CDATA[a<b]]></category>
+    <content:encoded><![CDATA[<p>First post.</p>
+<p>The </item> tag is in CDATA.</p>]]></content:encoded>
+    <excerpt:encoded><![CDATA[A <em>first</em> post.]]></excerpt:encoded>
+  </item>
+  <item>
+    <title>Unfinished</title>
+    <wp:post_date_gmt>0000-00-00 00:00:00</wp:post_date_gmt>
+    <wp:status>draft</wp:status>
+    <wp:post_type>post</wp:post_type>
+  </item>
+  <item>
+    <title>duck.jpg</title>
+    <wp:status>inherit</wp:status>
+    <wp:post_type>attachment</wp:post_type>
+  </item>
+</channel>
+</rss>"#;
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("export.xml");
+        fs::write(&source, xml).unwrap();
+        let content = dir.path().join("content");
+
+        let summary = import(&source, &content, false).unwrap();
+        let target = content.join("hello.md");
+        assert_eq!(summary.posts, [(source.clone(), target.clone())]);
+        assert_eq!(summary.drafts, 1);
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "---\ndate: 2019-05-04T10:30:00+00:00\naliases: [/2019/05/hello/]\nsummary: A *first* post.\n---\n\
+             # Hello & welcome\n\nTags: Rust\n\nFirst post.\n\nThe tag is in CDATA.\n"
+        );
+        let categories: Vec<&str> = summary.warnings.iter().map(BuildError::category).collect();
+        assert_eq!(categories, ["invalid_tag"]);
+
+        let again = import(&source, &content, true).unwrap();
+        assert!(again.posts.is_empty());
+        assert_eq!(again.warnings[0].category(), "import_failed");
+
+        fs::write(&source, "<html></html>").unwrap();
+        assert!(import(&source, &content, true).is_err());
+    }
+}